@@ -11,10 +11,12 @@ pub mod clickhouse;
 pub mod db;
 pub mod guard;
 pub mod logs;
+pub mod pegboard;
 pub mod pubsub;
 pub mod telemetry;
 pub mod topology;
 pub mod vector;
+pub mod workflow;
 
 pub use api_peer::*;
 pub use api_public::*;
@@ -24,10 +26,12 @@ pub use clickhouse::*;
 pub use db::Database;
 pub use guard::*;
 pub use logs::*;
+pub use pegboard::*;
 pub use pubsub::PubSub;
 pub use telemetry::*;
 pub use topology::*;
 pub use vector::*;
+pub use workflow::*;
 
 // IMPORTANT:
 //
@@ -75,6 +79,9 @@ pub struct Root {
 	#[serde(default)]
 	pub logs: Option<Logs>,
 
+	#[serde(default)]
+	pub pegboard: Option<Pegboard>,
+
 	#[serde(default)]
 	pub topology: Option<Topology>,
 
@@ -93,6 +100,9 @@ pub struct Root {
 	#[serde(default)]
 	pub vector_http: Option<VectorHttp>,
 
+	#[serde(default)]
+	pub workflow: Option<Workflow>,
+
 	#[serde(default)]
 	pub telemetry: Telemetry,
 }
@@ -105,12 +115,14 @@ impl Default for Root {
 			api_public: None,
 			api_peer: None,
 			logs: None,
+			pegboard: None,
 			topology: None,
 			database: None,
 			pubsub: None,
 			cache: None,
 			clickhouse: None,
 			vector_http: None,
+			workflow: None,
 			telemetry: Default::default(),
 		}
 	}
@@ -137,6 +149,11 @@ impl Root {
 		self.logs.as_ref().unwrap_or(&DEFAULT)
 	}
 
+	pub fn pegboard(&self) -> &Pegboard {
+		static DEFAULT: LazyLock<Pegboard> = LazyLock::new(Pegboard::default);
+		self.pegboard.as_ref().unwrap_or(&DEFAULT)
+	}
+
 	pub fn topology(&self) -> &Topology {
 		static DEFAULT: LazyLock<Topology> = LazyLock::new(Topology::default);
 		self.topology.as_ref().unwrap_or(&DEFAULT)
@@ -165,6 +182,11 @@ impl Root {
 		self.vector_http.as_ref()
 	}
 
+	pub fn workflow(&self) -> &Workflow {
+		static DEFAULT: LazyLock<Workflow> = LazyLock::new(Workflow::default);
+		self.workflow.as_ref().unwrap_or(&DEFAULT)
+	}
+
 	pub fn validate_and_set_defaults(&mut self) -> Result<()> {
 		// Set default pubsub to Postgres if configured for database
 		if self.pubsub.is_none()
@@ -0,0 +1,24 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Configuration for actors.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Pegboard {
+	/// Maximum amount of KV storage in bytes each actor can use. Defaults to 1 GiB.
+	pub actor_kv_storage_quota: Option<u64>,
+	/// Overrides `actor_kv_storage_quota` for actors in specific namespaces, keyed by namespace name.
+	#[serde(default)]
+	pub actor_kv_storage_quota_by_namespace: HashMap<String, u64>,
+}
+
+impl Pegboard {
+	pub fn actor_kv_storage_quota(&self, namespace_name: &str) -> u64 {
+		self.actor_kv_storage_quota_by_namespace
+			.get(namespace_name)
+			.copied()
+			.or(self.actor_kv_storage_quota)
+			.unwrap_or(crate::defaults::pegboard::ACTOR_KV_STORAGE_QUOTA)
+	}
+}
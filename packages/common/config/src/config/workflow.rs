@@ -0,0 +1,53 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::PathBuf, time::Duration};
+
+/// Configuration for Gasoline workflows.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct Workflow {
+	/// Retention policies of completed and silenced workflows, keyed by workflow name. Workflows
+	/// without a policy are kept forever.
+	#[serde(default)]
+	pub retention: HashMap<String, WorkflowRetention>,
+	/// Directory to store archived workflows in.
+	///
+	/// Defaults to a `workflow-archive` directory in the engine's data directory.
+	pub archive_path: Option<PathBuf>,
+	/// Maximum amount of workflows a single worker runs at once. Workflows past this limit are left
+	/// for other workers.
+	///
+	/// Unlimited if not set.
+	pub max_concurrent_workflows: Option<usize>,
+	/// Maximum amount of workflows with a given name a single worker runs at once, keyed by workflow
+	/// name. Workflows without a limit are only bound by `max_concurrent_workflows`.
+	#[serde(default)]
+	pub max_concurrent_workflows_per_name: HashMap<String, usize>,
+}
+
+impl Workflow {
+	pub fn archive_path(&self) -> PathBuf {
+		self.archive_path.clone().unwrap_or_else(|| {
+			dirs::data_local_dir()
+				.map(|dir| dir.join("rivet-engine").join("workflow-archive"))
+				.unwrap_or_else(|| PathBuf::from("./data/workflow-archive"))
+		})
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct WorkflowRetention {
+	/// Days after completion or silencing before a workflow is purged.
+	pub days: u64,
+	/// Whether to write purged workflows to `archive_path` as compressed files before deleting
+	/// them.
+	#[serde(default)]
+	pub archive: bool,
+}
+
+impl WorkflowRetention {
+	pub fn duration(&self) -> Duration {
+		Duration::from_secs(self.days * 24 * 60 * 60)
+	}
+}
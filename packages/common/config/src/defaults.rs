@@ -9,3 +9,12 @@ pub mod ports {
 	pub const GUARD: u16 = 6420;
 	pub const API_PEER: u16 = 6421;
 }
+
+pub mod pegboard {
+	pub const ACTOR_KV_STORAGE_QUOTA: u64 = 1024 * 1024 * 1024; // 1 GiB
+}
+
+pub mod db {
+	pub const BACKUP_INTERVAL_SECS: u64 = 60 * 60; // 1 hour
+	pub const BACKUP_KEEP_LAST: usize = 24;
+}
@@ -1,10 +1,12 @@
-use std::{fmt::Debug, hash::Hash};
+use std::{fmt::Debug, hash::Hash, time::Duration};
 
 use anyhow::Result;
 use async_trait::async_trait;
+use rand::Rng;
+use rivet_error::RivetError;
 use serde::{Serialize, de::DeserializeOwned};
 
-use crate::ctx::ActivityCtx;
+use crate::ctx::{ActivityCtx, common::RETRY_TIMEOUT_MS};
 
 #[async_trait]
 pub trait Activity {
@@ -14,6 +16,7 @@ pub trait Activity {
 	const NAME: &'static str;
 	const MAX_RETRIES: usize;
 	const TIMEOUT: std::time::Duration;
+	const RETRY_POLICY: RetryPolicy;
 
 	async fn run(ctx: &ActivityCtx, input: &Self::Input) -> Result<Self::Output>;
 }
@@ -21,3 +24,74 @@ pub trait Activity {
 pub trait ActivityInput: Serialize + DeserializeOwned + Debug + Hash + Send {
 	type Activity: Activity;
 }
+
+/// Backoff between retries of a failed activity and which errors are never retried.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+	/// Backoff after the first failure.
+	pub initial_interval: Duration,
+	/// Factor the backoff grows by after each failure.
+	pub multiplier: f64,
+	/// Upper bound of the backoff, not including jitter.
+	pub max_interval: Duration,
+	/// Maximum random duration added to each backoff.
+	pub jitter: Duration,
+	/// `RivetError` groups that fail the workflow immediately instead of being retried.
+	pub non_retryable_groups: &'static [&'static str],
+}
+
+impl RetryPolicy {
+	pub const DEFAULT: RetryPolicy = RetryPolicy {
+		initial_interval: Duration::from_millis(RETRY_TIMEOUT_MS as u64),
+		multiplier: 2.0,
+		max_interval: Duration::from_millis((RETRY_TIMEOUT_MS as u64) << 8),
+		jitter: Duration::from_millis(500),
+		non_retryable_groups: &[],
+	};
+
+	/// Returns how long to wait before retrying an activity that has failed `error_count` times before.
+	pub fn interval(&self, error_count: usize) -> Duration {
+		let exponent = i32::try_from(error_count).unwrap_or(i32::MAX);
+		let interval = Duration::try_from_secs_f64(
+			self.initial_interval.as_secs_f64() * self.multiplier.powi(exponent),
+		)
+		.unwrap_or(self.max_interval)
+		.min(self.max_interval);
+
+		let jitter_ms = u64::try_from(self.jitter.as_millis()).unwrap_or(u64::MAX);
+		let jitter = Duration::from_millis(rand::thread_rng().gen_range(0..=jitter_ms));
+
+		interval + jitter
+	}
+
+	/// Whether an activity error should be retried according to this policy.
+	pub fn is_retryable(&self, err: &anyhow::Error) -> bool {
+		if err.downcast_ref::<NonRetryable>().is_some() {
+			return false;
+		}
+
+		!err.chain()
+			.filter_map(|x| x.downcast_ref::<RivetError>())
+			.any(|x| self.non_retryable_groups.contains(&x.group()))
+	}
+}
+
+impl Default for RetryPolicy {
+	fn default() -> Self {
+		RetryPolicy::DEFAULT
+	}
+}
+
+/// Marks an activity error as non-retryable, failing the workflow without using up the remaining retries.
+///
+/// ```ignore
+/// validate(&input.config).context(NonRetryable)?;
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct NonRetryable;
+
+impl std::fmt::Display for NonRetryable {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "non-retryable error")
+	}
+}
@@ -1,5 +1,6 @@
 //! This module contains builders used by all ctx's besides the workflow ctx.
 
 pub mod message;
+pub mod schedule;
 pub mod signal;
 pub mod workflow;
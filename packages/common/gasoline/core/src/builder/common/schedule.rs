@@ -0,0 +1,145 @@
+use std::fmt::Display;
+
+use anyhow::Result;
+use rivet_util::Id;
+use serde::Serialize;
+
+use crate::{
+	builder::BuilderError,
+	db::DatabaseHandle,
+	error::WorkflowError,
+	schedule::CatchUp,
+	workflow::{Workflow, WorkflowInput},
+};
+
+pub struct ScheduleBuilder<I: WorkflowInput> {
+	db: DatabaseHandle,
+	config: rivet_config::Config,
+	cron: String,
+	input: I,
+	tags: serde_json::Map<String, serde_json::Value>,
+	catch_up: CatchUp,
+	unique: bool,
+	error: Option<BuilderError>,
+}
+
+impl<I> ScheduleBuilder<I>
+where
+	I: WorkflowInput,
+	<I as WorkflowInput>::Workflow: Workflow<Input = I>,
+{
+	pub(crate) fn new(
+		db: DatabaseHandle,
+		config: rivet_config::Config,
+		cron: impl Display,
+		input: I,
+		from_workflow: bool,
+	) -> Self {
+		ScheduleBuilder {
+			db,
+			config,
+			cron: cron.to_string(),
+			input,
+			tags: serde_json::Map::new(),
+			catch_up: CatchUp::default(),
+			unique: false,
+			error: from_workflow.then_some(BuilderError::CannotDispatchFromOpInWorkflow),
+		}
+	}
+
+	/// Tags given to every workflow dispatched by this schedule.
+	pub fn tags(mut self, tags: serde_json::Value) -> Self {
+		if self.error.is_some() {
+			return self;
+		}
+
+		match tags {
+			serde_json::Value::Object(map) => {
+				self.tags.extend(map);
+			}
+			_ => self.error = Some(BuilderError::TagsNotMap),
+		}
+
+		self
+	}
+
+	/// Tag given to every workflow dispatched by this schedule.
+	pub fn tag(mut self, k: impl Display, v: impl Serialize) -> Self {
+		if self.error.is_some() {
+			return self;
+		}
+
+		match serde_json::to_value(&v) {
+			Ok(v) => {
+				self.tags.insert(k.to_string(), v);
+			}
+			Err(err) => self.error = Some(err.into()),
+		}
+
+		self
+	}
+
+	/// How runs missed while no workers were running are handled. Defaults to `CatchUp::Latest`.
+	pub fn catch_up(mut self, catch_up: CatchUp) -> Self {
+		if self.error.is_some() {
+			return self;
+		}
+
+		self.catch_up = catch_up;
+
+		self
+	}
+
+	/// Does not create a schedule if one already exists for the same workflow with the same tags. Useful
+	/// for schedules that are created every time a service starts.
+	pub fn unique(mut self) -> Self {
+		if self.error.is_some() {
+			return self;
+		}
+
+		self.unique = true;
+
+		self
+	}
+
+	#[tracing::instrument(skip_all, fields(workflow_name=I::Workflow::NAME, cron=%self.cron, schedule_id, unique=self.unique))]
+	pub async fn create(self) -> Result<Id> {
+		if let Some(err) = self.error {
+			return Err(err.into());
+		}
+
+		let workflow_name = I::Workflow::NAME;
+		let schedule_id = Id::new_v1(self.config.dc_label());
+
+		let no_tags = self.tags.is_empty();
+		let tags = serde_json::Value::Object(self.tags);
+		let tags = if no_tags { None } else { Some(&tags) };
+
+		tracing::debug!(?tags, input=?self.input, "creating schedule");
+
+		// Serialize input
+		let input_val = serde_json::value::to_raw_value(&self.input)
+			.map_err(WorkflowError::SerializeWorkflowInput)?;
+
+		let actual_schedule_id = self
+			.db
+			.create_schedule(
+				schedule_id,
+				workflow_name,
+				&self.cron,
+				self.catch_up,
+				tags,
+				&input_val,
+				self.unique,
+			)
+			.await?;
+
+		tracing::Span::current().record("schedule_id", actual_schedule_id.to_string());
+
+		if schedule_id != actual_schedule_id {
+			tracing::debug!(?tags, "unique schedule already exists");
+		}
+
+		Ok(actual_schedule_id)
+	}
+}
@@ -22,6 +22,7 @@ pub struct WorkflowBuilder<T, I: WorkflowInput> {
 	repr: T,
 	tags: serde_json::Map<String, serde_json::Value>,
 	unique: bool,
+	priority: i32,
 	error: Option<BuilderError>,
 	_marker: PhantomData<I>,
 }
@@ -46,6 +47,7 @@ where
 			repr,
 			tags: serde_json::Map::new(),
 			unique: false,
+			priority: 0,
 			error: from_workflow.then_some(BuilderError::CannotDispatchFromOpInWorkflow),
 			_marker: PhantomData,
 		}
@@ -93,7 +95,19 @@ where
 		self
 	}
 
-	#[tracing::instrument(skip_all, fields(workflow_name=I::Workflow::NAME, workflow_id, unique=self.unique))]
+	/// Workflows with a higher priority are pulled by workers before workflows with a lower priority
+	/// when there is a backlog. Defaults to 0.
+	pub fn priority(mut self, priority: i32) -> Self {
+		if self.error.is_some() {
+			return self;
+		}
+
+		self.priority = priority;
+
+		self
+	}
+
+	#[tracing::instrument(skip_all, fields(workflow_name=I::Workflow::NAME, workflow_id, unique=self.unique, priority=self.priority))]
 	pub async fn dispatch(self) -> Result<Id> {
 		if let Some(err) = self.error {
 			return Err(err.into());
@@ -127,6 +141,7 @@ where
 				tags,
 				&input_val,
 				self.unique,
+				self.priority,
 			)
 			.await?;
 
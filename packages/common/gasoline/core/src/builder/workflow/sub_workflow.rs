@@ -23,6 +23,7 @@ pub struct SubWorkflowBuilder<'a, T, I: WorkflowInput> {
 	repr: T,
 	tags: serde_json::Map<String, serde_json::Value>,
 	unique: bool,
+	priority: i32,
 	error: Option<BuilderError>,
 	_marker: PhantomData<I>,
 }
@@ -41,6 +42,7 @@ where
 			repr,
 			tags: serde_json::Map::new(),
 			unique: false,
+			priority: 0,
 			error: None,
 			_marker: PhantomData,
 		}
@@ -88,6 +90,18 @@ where
 		self
 	}
 
+	/// Workflows with a higher priority are pulled by workers before workflows with a lower priority
+	/// when there is a backlog. Defaults to 0.
+	pub fn priority(mut self, priority: i32) -> Self {
+		if self.error.is_some() {
+			return self;
+		}
+
+		self.priority = priority;
+
+		self
+	}
+
 	#[tracing::instrument(skip_all)]
 	pub async fn dispatch(self) -> Result<Id> {
 		self.ctx.check_stop()?;
@@ -112,6 +126,7 @@ where
 			self.repr.as_input()?,
 			tags,
 			self.unique,
+			self.priority,
 		)
 		.in_current_span()
 		.await
@@ -119,13 +134,14 @@ where
 	}
 
 	// This doesn't have a self parameter because self.tags was already moved (see above)
-	#[tracing::instrument(skip_all, fields(sub_workflow_name=I::Workflow::NAME, sub_workflow_id, unique))]
+	#[tracing::instrument(skip_all, fields(sub_workflow_name=I::Workflow::NAME, sub_workflow_id, unique, priority))]
 	async fn dispatch_workflow_inner(
 		ctx: &mut WorkflowCtx,
 		version: usize,
 		input: &I,
 		tags: Option<serde_json::Value>,
 		unique: bool,
+		priority: i32,
 	) -> WorkflowResult<Id>
 	where
 		I: WorkflowInput,
@@ -171,6 +187,7 @@ where
 					&input_val,
 					ctx.loop_location(),
 					unique,
+					priority,
 				)
 				.await?;
 
@@ -212,7 +229,7 @@ where
 	}
 
 	#[tracing::instrument(name="sub_workflow", skip_all, fields(sub_workflow_name=I::Workflow::NAME))]
-	pub async fn output(self) -> Result<<<I as WorkflowInput>::Workflow as Workflow>::Output> {
+	pub async fn output(mut self) -> Result<<<I as WorkflowInput>::Workflow as Workflow>::Output> {
 		self.ctx.check_stop()?;
 
 		if let Some(err) = self.error {
@@ -259,10 +276,11 @@ where
 	/// workflow will go to sleep until the sub workflow completes.
 	#[tracing::instrument(skip_all, fields(sub_workflow_name=I::Workflow::NAME))]
 	async fn wait_for_workflow(
-		&self,
+		&mut self,
 		sub_workflow_id: Id,
 	) -> Result<<<I as WorkflowInput>::Workflow as Workflow>::Output> {
 		self.ctx.check_stop()?;
+		self.ctx.check_cancel().await?;
 
 		tracing::debug!("waiting for sub workflow");
 
@@ -285,6 +303,8 @@ where
 
 			if let Some(output) = workflow.parse_output::<<I as WorkflowInput>::Workflow>()? {
 				return Ok(output);
+			} else if workflow.cancelled {
+				return Err(WorkflowError::SubWorkflowCancelled(sub_workflow_id).into());
 			} else {
 				if retries == 0 {
 					return Err(WorkflowError::SubWorkflowIncomplete(sub_workflow_id).into());
@@ -10,10 +10,12 @@ pub const RETRY_TIMEOUT_MS: usize = 1000;
 pub const WORKFLOW_TIMEOUT: Duration = Duration::from_secs(60);
 
 use crate::{
+	activity::RetryPolicy,
 	ctx::OperationCtx,
 	db::{DatabaseHandle, WorkflowData},
 	error::WorkflowError,
 	operation::{Operation, OperationInput},
+	query::Query,
 	utils::tags::AsTags,
 	workflow::Workflow,
 };
@@ -43,6 +45,8 @@ pub async fn wait_for_workflow_output<W: Workflow>(
 				.ok_or(WorkflowError::WorkflowNotFound)?;
 			if let Some(output) = workflow.parse_output::<W>()? {
 				return Ok(output);
+			} else if workflow.cancelled {
+				return Err(WorkflowError::SubWorkflowCancelled(workflow_id).into());
 			}
 
 			// Poll and wait for a wake at the same time
@@ -73,6 +77,29 @@ pub async fn get_workflows(
 	db.get_workflows(workflow_ids).await.map_err(Into::into)
 }
 
+/// Runs a query against the last committed state of a workflow.
+pub async fn query_workflow<Q: Query>(db: &DatabaseHandle, workflow_id: Id) -> Result<Q::Output> {
+	let workflow = db
+		.get_workflows(vec![workflow_id])
+		.await?
+		.into_iter()
+		.next()
+		.ok_or(WorkflowError::WorkflowNotFound)?;
+	let state = workflow.parse_state::<Q::State>()?;
+
+	Q::run(&state).map_err(|err| WorkflowError::QueryFailure(err).into())
+}
+
+/// Cancels a workflow and the sub workflows it dispatched.
+pub async fn cancel_workflow(db: &DatabaseHandle, workflow_id: Id) -> Result<()> {
+	db.cancel_workflow(workflow_id).await.map_err(Into::into)
+}
+
+/// Deletes a schedule. Workflows it already dispatched are not affected.
+pub async fn delete_schedule(db: &DatabaseHandle, schedule_id: Id) -> Result<()> {
+	db.delete_schedule(schedule_id).await.map_err(Into::into)
+}
+
 pub async fn op<I>(
 	db: &DatabaseHandle,
 	config: &rivet_config::Config,
@@ -107,7 +134,7 @@ where
 
 	let res = tokio::time::timeout(I::Operation::TIMEOUT, I::Operation::run(&ctx, &input))
 		.await
-		.map_err(|_| WorkflowError::OperationTimeout(0))
+		.map_err(|_| WorkflowError::OperationTimeout(0, RetryPolicy::DEFAULT))
 		.map(|res| res.map_err(WorkflowError::OperationFailure));
 
 	// Record metrics
@@ -1,4 +1,4 @@
-use std::ops::Deref;
+use std::{fmt::Display, ops::Deref};
 
 use anyhow::Result;
 use rivet_util::Id;
@@ -12,6 +12,7 @@ use crate::{
 	error::WorkflowResult,
 	message::Message,
 	operation::{Operation, OperationInput},
+	query::Query,
 	signal::Signal,
 	utils::tags::AsTags,
 	workflow::{Workflow, WorkflowInput},
@@ -99,6 +100,52 @@ impl OperationCtx {
 			.await
 	}
 
+	/// Creates a schedule builder. The schedule durably dispatches the workflow on every run of the given
+	/// cron expression, which may include a leading seconds field.
+	pub fn schedule_workflow<I>(
+		&self,
+		cron: impl Display,
+		input: I,
+	) -> builder::schedule::ScheduleBuilder<I>
+	where
+		I: WorkflowInput,
+		<I as WorkflowInput>::Workflow: Workflow<Input = I>,
+	{
+		builder::schedule::ScheduleBuilder::new(
+			self.db.clone(),
+			self.config.clone(),
+			cron,
+			input,
+			self.from_workflow,
+		)
+	}
+
+	/// Deletes a schedule. Workflows it already dispatched are not affected.
+	#[tracing::instrument(skip_all, fields(%schedule_id))]
+	pub async fn delete_schedule(&self, schedule_id: Id) -> Result<()> {
+		common::delete_schedule(&self.db, schedule_id)
+			.in_current_span()
+			.await
+	}
+
+	/// Runs a query against the last committed state of a workflow. Does not affect the workflow's
+	/// history.
+	#[tracing::instrument(skip_all, fields(%workflow_id, query_name=Q::NAME))]
+	pub async fn query<Q: Query>(&self, workflow_id: Id) -> Result<Q::Output> {
+		common::query_workflow::<Q>(&self.db, workflow_id)
+			.in_current_span()
+			.await
+	}
+
+	/// Cancels a workflow and the sub workflows it dispatched. The cancellation surfaces in the
+	/// workflow as `WorkflowError::Cancelled` at its next `listen`, `sleep` or `activity` call.
+	#[tracing::instrument(skip_all, fields(%workflow_id))]
+	pub async fn cancel_workflow(&self, workflow_id: Id) -> Result<()> {
+		common::cancel_workflow(&self.db, workflow_id)
+			.in_current_span()
+			.await
+	}
+
 	/// Creates a signal builder.
 	pub fn signal<T: Signal + Serialize>(&self, body: T) -> builder::signal::SignalBuilder<T> {
 		// TODO: Add check for from_workflow so you cant dispatch a signal
@@ -1,4 +1,4 @@
-use std::{ops::Deref, sync::Arc, time::Duration};
+use std::{fmt::Display, ops::Deref, sync::Arc, time::Duration};
 use tokio::{sync::watch, task::JoinHandle};
 
 use anyhow::Result;
@@ -13,6 +13,8 @@ use crate::{
 	message::Message,
 	operation::{Operation, OperationInput},
 	prelude::*,
+	query::Query,
+	replay::{ReplayReport, Replayer},
 	signal::Signal,
 	utils::tags::AsTags,
 	workflow::{Workflow, WorkflowInput},
@@ -156,6 +158,73 @@ impl TestCtx {
 			.await
 	}
 
+	/// Creates a schedule builder. The schedule durably dispatches the workflow on every run of the given
+	/// cron expression, which may include a leading seconds field.
+	pub fn schedule_workflow<I>(
+		&self,
+		cron: impl Display,
+		input: I,
+	) -> builder::schedule::ScheduleBuilder<I>
+	where
+		I: WorkflowInput,
+		<I as WorkflowInput>::Workflow: Workflow<Input = I>,
+	{
+		builder::schedule::ScheduleBuilder::new(
+			self.db.clone(),
+			self.config.clone(),
+			cron,
+			input,
+			false,
+		)
+	}
+
+	/// Deletes a schedule. Workflows it already dispatched are not affected.
+	#[tracing::instrument(skip_all, fields(%schedule_id))]
+	pub async fn delete_schedule(&self, schedule_id: Id) -> Result<()> {
+		common::delete_schedule(&self.db, schedule_id)
+			.in_current_span()
+			.await
+	}
+
+	/// Runs a query against the last committed state of a workflow. Does not affect the workflow's
+	/// history.
+	#[tracing::instrument(skip_all, fields(%workflow_id, query_name=Q::NAME))]
+	pub async fn query<Q: Query>(&self, workflow_id: Id) -> Result<Q::Output> {
+		common::query_workflow::<Q>(&self.db, workflow_id)
+			.in_current_span()
+			.await
+	}
+
+	/// Replays the recorded history of a workflow against the workflows in the given registry, which may
+	/// differ from the ones the worker of this test ctx runs. Nothing is written to the database.
+	#[tracing::instrument(skip_all, fields(%workflow_id))]
+	pub async fn replay(&self, reg: Registry, workflow_id: Id) -> Result<ReplayReport> {
+		let history = self
+			.debug_db
+			.get_workflow_history(workflow_id, false)
+			.await?
+			.ok_or(WorkflowError::WorkflowNotFound)?;
+
+		Replayer::new(
+			reg.handle(),
+			self.db.clone(),
+			self.config.clone(),
+			self.pools.clone(),
+		)?
+		.replay(history)
+		.in_current_span()
+		.await
+	}
+
+	/// Cancels a workflow and the sub workflows it dispatched. The cancellation surfaces in the
+	/// workflow as `WorkflowError::Cancelled` at its next `listen`, `sleep` or `activity` call.
+	#[tracing::instrument(skip_all, fields(%workflow_id))]
+	pub async fn cancel_workflow(&self, workflow_id: Id) -> Result<()> {
+		common::cancel_workflow(&self.db, workflow_id)
+			.in_current_span()
+			.await
+	}
+
 	/// Creates a signal builder.
 	pub fn signal<T: Signal + Serialize>(&self, body: T) -> builder::signal::SignalBuilder<T> {
 		builder::signal::SignalBuilder::new(
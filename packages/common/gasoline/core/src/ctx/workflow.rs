@@ -25,7 +25,7 @@ use crate::{
 	history::{
 		History,
 		cursor::{Cursor, HistoryResult},
-		event::SleepState,
+		event::{Event, EventData, SleepState},
 		location::{Coordinate, Location},
 		removed::Removed,
 	},
@@ -76,9 +76,13 @@ pub struct WorkflowCtx {
 	msg_ctx: MessageCtx,
 	/// Used to stop workflow execution by the worker.
 	stop: watch::Receiver<()>,
+	/// Whether or not this workflow has been cancelled. Surfaces at the next step that is not a replay.
+	cancelled: bool,
 
 	/// Whether or not this ctx is used as part of a .join
 	parallelized: bool,
+	/// Set when replaying a recorded history with `Replayer`. Activities are never run.
+	dry_run: bool,
 }
 
 impl WorkflowCtx {
@@ -119,8 +123,10 @@ impl WorkflowCtx {
 
 			msg_ctx,
 			stop,
+			cancelled: data.cancelled,
 
 			parallelized: false,
+			dry_run: false,
 		})
 	}
 
@@ -196,6 +202,33 @@ impl WorkflowCtx {
 					}
 				}
 			}
+			Err(WorkflowError::Cancelled) => {
+				tracing::debug!("workflow cancelled");
+
+				let err_str = WorkflowError::Cancelled.to_string();
+
+				let mut retries = 0;
+				let mut interval = tokio::time::interval(DB_ACTION_RETRY);
+				interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+				// Retry loop
+				loop {
+					interval.tick().await;
+
+					if let Err(err) = self
+						.db
+						.complete_cancelled_workflow(self.workflow_id, &self.name, &err_str)
+						.await
+					{
+						if retries > MAX_DB_ACTION_RETRIES {
+							return Err(err);
+						}
+						retries += 1;
+					} else {
+						break;
+					}
+				}
+			}
 			Err(err) => {
 				let wake_immediate = err.wake_immediate();
 
@@ -276,6 +309,19 @@ impl WorkflowCtx {
 		Ok(())
 	}
 
+	/// Runs the workflow against its recorded history without writing anything. Errors with
+	/// `WorkflowError::ReplayEnd` once a step is reached that would have to run for the first time.
+	#[tracing::instrument(name="workflow_replay", skip_all, fields(workflow_id=%self.workflow_id, workflow_name=%self.name))]
+	pub(crate) async fn replay(mut self) -> WorkflowResult<()> {
+		self.dry_run = true;
+
+		let workflow = self.registry.get_workflow(&self.name)?;
+		(workflow.run)(&mut self).await?;
+
+		// Validate no leftover events
+		self.cursor.check_clear()
+	}
+
 	/// Run then handle the result of an activity.
 	#[tracing::instrument(skip_all, fields(activity_name=%A::NAME, %location))]
 	async fn run_activity<A: Activity>(
@@ -284,6 +330,11 @@ impl WorkflowCtx {
 		location: &Location,
 		create_ts: i64,
 	) -> WorkflowResult<A::Output> {
+		// Recorded outputs are replayed by the caller, anything else would have side effects
+		if self.dry_run {
+			return Err(WorkflowError::ReplayEnd);
+		}
+
 		tracing::debug!("running activity");
 
 		let ctx = ActivityCtx::new(
@@ -308,7 +359,7 @@ impl WorkflowCtx {
 
 		let res = tokio::time::timeout(A::TIMEOUT, A::run(&ctx, input).in_current_span())
 			.await
-			.map_err(|_| WorkflowError::ActivityTimeout(0));
+			.map_err(|_| WorkflowError::ActivityTimeout(0, A::RETRY_POLICY));
 
 		let dt = start_instant.elapsed().as_secs_f64();
 
@@ -408,7 +459,12 @@ impl WorkflowCtx {
 					],
 				);
 
-				Err(WorkflowError::ActivityFailure(err, 0))
+				// Fail fast on errors that can never succeed
+				if !A::RETRY_POLICY.is_retryable(&err) {
+					return Err(WorkflowError::ActivityNonRetryable(err));
+				}
+
+				Err(WorkflowError::ActivityFailure(err, 0, A::RETRY_POLICY))
 			}
 			Err(err) => {
 				tracing::debug!("activity timeout");
@@ -520,8 +576,10 @@ impl WorkflowCtx {
 
 			msg_ctx: self.msg_ctx.clone(),
 			stop: self.stop.clone(),
+			cancelled: self.cancelled,
 
 			parallelized: self.parallelized,
+			dry_run: self.dry_run,
 		}
 	}
 
@@ -548,6 +606,41 @@ impl WorkflowCtx {
 		let _ = self.stop.clone().changed().await;
 		Err(WorkflowError::WorkflowStopped)
 	}
+
+	/// Surfaces cancellation if this workflow was cancelled and the current step is not a replay. A
+	/// branch event at the current location is the marker left by a previous run of `cancel`.
+	pub(crate) async fn check_cancel(&mut self) -> WorkflowResult<()> {
+		if !self.cancelled {
+			return Ok(());
+		}
+
+		match self.cursor.current_event() {
+			None
+			| Some(Event {
+				data: EventData::Branch,
+				..
+			}) => self.cancel().await,
+			_ => Ok(()),
+		}
+	}
+
+	/// Runs the workflow's cleanup hook in a new branch then errors with `WorkflowError::Cancelled`.
+	async fn cancel(&mut self) -> WorkflowResult<()> {
+		tracing::debug!("cancelling workflow");
+
+		let mut branch = self.branch().await?;
+
+		// Steps in the cleanup hook should not surface cancellation again
+		branch.cancelled = false;
+
+		let workflow = self.registry.get_workflow(&self.name)?;
+		(workflow.on_cancel)(&mut branch).await?;
+
+		// Validate no leftover events
+		branch.cursor.check_clear()?;
+
+		Err(WorkflowError::Cancelled)
+	}
 }
 
 impl WorkflowCtx {
@@ -563,6 +656,18 @@ impl WorkflowCtx {
 		builder::sub_workflow::SubWorkflowBuilder::new(self, self.version, input)
 	}
 
+	/// Cancels a workflow and the sub workflows it dispatched. Not recorded in history; cancelling is
+	/// idempotent so replaying this is harmless.
+	#[tracing::instrument(skip_all, fields(%workflow_id))]
+	pub async fn cancel_workflow(&mut self, workflow_id: Id) -> Result<()> {
+		self.check_stop()?;
+
+		self.db
+			.cancel_workflow(workflow_id)
+			.await
+			.map_err(Into::into)
+	}
+
 	/// Run activity. Will replay on failure.
 	#[tracing::instrument(skip_all, fields(activity_name=%I::Activity::NAME))]
 	pub async fn activity<I>(
@@ -574,6 +679,7 @@ impl WorkflowCtx {
 		<I as ActivityInput>::Activity: Activity<Input = I>,
 	{
 		self.check_stop()?;
+		self.check_cancel().await?;
 
 		let history_res = self
 			.cursor
@@ -611,28 +717,31 @@ impl WorkflowCtx {
 						// Convert error in the case of max retries exceeded. This will only act on retryable
 						// errors
 						let err = match err {
-							WorkflowError::ActivityFailure(err, _) => {
+							WorkflowError::ActivityFailure(err, _, retry_policy) => {
 								if error_count + 1 >= I::Activity::MAX_RETRIES {
 									WorkflowError::ActivityMaxFailuresReached(err)
 								} else {
 									// Add error count to the error for backoff calculation
-									WorkflowError::ActivityFailure(err, error_count)
+									WorkflowError::ActivityFailure(err, error_count, retry_policy)
 								}
 							}
-							WorkflowError::ActivityTimeout(_) => {
+							WorkflowError::ActivityTimeout(_, retry_policy) => {
 								if error_count + 1 >= I::Activity::MAX_RETRIES {
 									WorkflowError::ActivityMaxFailuresReached(err.into())
 								} else {
 									// Add error count to the error for backoff calculation
-									WorkflowError::ActivityTimeout(error_count)
+									WorkflowError::ActivityTimeout(error_count, retry_policy)
 								}
 							}
-							WorkflowError::OperationTimeout(_) => {
+							WorkflowError::OperationTimeout(_, _) => {
 								if error_count + 1 >= I::Activity::MAX_RETRIES {
 									WorkflowError::ActivityMaxFailuresReached(err.into())
 								} else {
 									// Add error count to the error for backoff calculation
-									WorkflowError::OperationTimeout(error_count)
+									WorkflowError::OperationTimeout(
+										error_count,
+										I::Activity::RETRY_POLICY,
+									)
 								}
 							}
 							_ => err,
@@ -705,6 +814,7 @@ impl WorkflowCtx {
 	#[tracing::instrument(skip_all, fields(t=std::any::type_name::<T>()))]
 	pub async fn listen<T: Listen>(&mut self) -> Result<T> {
 		self.check_stop()?;
+		self.check_cancel().await?;
 
 		let history_res = self.cursor.compare_signal(self.version)?;
 		let location = self.cursor.current_location_for(&history_res);
@@ -768,6 +878,7 @@ impl WorkflowCtx {
 		listener: &T,
 	) -> Result<<T as CustomListener>::Output> {
 		self.check_stop()?;
+		self.check_cancel().await?;
 
 		let history_res = self.cursor.compare_signal(self.version)?;
 		let location = self.cursor.current_location_for(&history_res);
@@ -859,6 +970,7 @@ impl WorkflowCtx {
 		T: Serialize + DeserializeOwned,
 	{
 		self.check_stop()?;
+		self.check_cancel().await?;
 
 		let history_res = self.cursor.compare_loop(self.version)?;
 		let loop_location = self.cursor.current_location_for(&history_res);
@@ -1025,6 +1137,7 @@ impl WorkflowCtx {
 	#[tracing::instrument(skip_all, fields(duration))]
 	pub async fn sleep_until(&mut self, time: impl TsToMillis) -> Result<()> {
 		self.check_stop()?;
+		self.check_cancel().await?;
 
 		let history_res = self.cursor.compare_sleep(self.version)?;
 		let location = self.cursor.current_location_for(&history_res);
@@ -1055,6 +1168,13 @@ impl WorkflowCtx {
 		let duration = deadline_ts.saturating_sub(rivet_util::timestamp::now());
 		tracing::Span::current().record("duration", &duration);
 
+		// Cancelled during this sleep, skip the rest of it
+		if self.cancelled && replay && duration > 0 {
+			self.cursor.update(&location);
+
+			return self.cancel().await.map_err(Into::into);
+		}
+
 		// No-op
 		if duration <= 0 {
 			if !replay && duration < -50 {
@@ -1105,6 +1225,7 @@ impl WorkflowCtx {
 	#[tracing::instrument(skip_all, fields(t=std::any::type_name::<T>(), duration))]
 	pub async fn listen_until<T: Listen>(&mut self, time: impl TsToMillis) -> Result<Option<T>> {
 		self.check_stop()?;
+		self.check_cancel().await?;
 
 		let history_res = self.cursor.compare_sleep(self.version)?;
 		let history_res2 = history_res.equivalent();
@@ -1,5 +1,8 @@
+use std::path::Path;
+
 use anyhow::*;
 use rivet_util::Id;
+use serde::{Deserialize, Serialize};
 
 use super::Database;
 use crate::history::{
@@ -22,12 +25,39 @@ pub trait DatabaseDebug: Database {
 
 	async fn wake_workflows(&self, workflow_ids: Vec<Id>) -> Result<()>;
 
+	/// Deletes the given workflows and the signals they received. Only workflows that completed or
+	/// were silenced are purged. If `archive_path` is set, workflows are written to it before being
+	/// deleted. Returns the ids of the purged workflows.
+	async fn purge_workflows(
+		&self,
+		workflow_ids: Vec<Id>,
+		archive_path: Option<&Path>,
+	) -> Result<Vec<Id>>;
+
+	/// Purges workflows with the given name that completed or were silenced before `before_ts`.
+	/// Returns the amount of purged workflows.
+	async fn purge_expired_workflows(
+		&self,
+		workflow_name: &str,
+		before_ts: i64,
+		archive_path: Option<&Path>,
+	) -> Result<usize>;
+
 	async fn get_workflow_history(
 		&self,
 		workflow_id: Id,
 		include_forgotten: bool,
 	) -> Result<Option<HistoryData>>;
 
+	/// Exports a workflow with its entire history (including forgotten events), the signals it received and
+	/// its sub workflows. Returns `None` if the workflow does not exist.
+	async fn export_workflow(&self, workflow_id: Id) -> Result<Option<WorkflowExport>>;
+
+	/// Writes exported or archived workflows to the database, each in its own transaction. Workflows that did
+	/// not complete and were not silenced are woken so they run again. Returns the ids of the imported
+	/// workflows.
+	async fn import_workflows(&self, workflows: &[WorkflowArchive]) -> Result<Vec<Id>>;
+
 	async fn get_signals(&self, signal_ids: Vec<Id>) -> Result<Vec<SignalData>>;
 
 	async fn find_signals(
@@ -62,6 +92,29 @@ pub enum WorkflowState {
 	Sleeping,
 	Dead,
 	Silenced,
+	Cancelled,
+}
+
+/// A workflow and everything related to it, written by `DatabaseDebug::export_workflow`.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkflowExport {
+	/// The exported workflow comes first, followed by its sub workflows.
+	pub workflows: Vec<WorkflowArchive>,
+}
+
+/// Every key of a workflow and of the signals it received. Also the format of archived workflows.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkflowArchive {
+	pub workflow_id: Id,
+	pub workflow_name: String,
+	pub entries: Vec<ArchiveEntry>,
+}
+
+/// Hex encoded key (relative to the gasoline subspace) and value.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+	pub key: String,
+	pub value: String,
 }
 
 #[derive(Debug)]
@@ -1,6 +1,7 @@
 use std::{
 	collections::HashMap,
 	ops::Deref,
+	path::Path,
 	result::Result::{Err, Ok},
 };
 
@@ -16,12 +17,12 @@ use universaldb::{
 	value::Value,
 };
 
-use super::{DatabaseKv, keys, update_metric};
+use super::{DatabaseKv, keys, retention::PurgeResult, update_metric};
 use crate::{
 	db::debug::{
 		ActivityError, ActivityEvent, DatabaseDebug, Event, EventData, HistoryData, LoopEvent,
 		MessageSendEvent, SignalData, SignalEvent, SignalSendEvent, SignalState, SubWorkflowEvent,
-		WorkflowData, WorkflowState,
+		WorkflowArchive, WorkflowData, WorkflowExport, WorkflowState,
 	},
 	error::{WorkflowError, WorkflowResult},
 	history::{
@@ -56,6 +57,7 @@ impl DatabaseKv {
 			let has_wake_condition_key = keys::workflow::HasWakeConditionKey::new(workflow_id);
 			let worker_instance_id_key = keys::workflow::WorkerInstanceIdKey::new(workflow_id);
 			let silence_ts_key = keys::workflow::SilenceTsKey::new(workflow_id);
+			let complete_ts_key = keys::workflow::CompleteTsKey::new(workflow_id);
 
 			let (
 				tags,
@@ -68,6 +70,7 @@ impl DatabaseKv {
 				has_wake_condition_entry,
 				worker_instance_id_entry,
 				silence_ts_entry,
+				complete_ts_entry,
 			) = tokio::try_join!(
 				tx.get_ranges_keyvalues(
 					RangeOption {
@@ -113,6 +116,7 @@ impl DatabaseKv {
 				tx.get(&self.subspace.pack(&has_wake_condition_key), Snapshot),
 				tx.get(&self.subspace.pack(&worker_instance_id_key), Snapshot),
 				tx.get(&self.subspace.pack(&silence_ts_key), Snapshot),
+				tx.get(&self.subspace.pack(&complete_ts_key), Snapshot),
 			)?;
 
 			let Some(create_ts_entry) = &create_ts_entry else {
@@ -148,6 +152,9 @@ impl DatabaseKv {
 				WorkflowState::Silenced
 			} else if output.is_some() {
 				WorkflowState::Complete
+			} else if complete_ts_entry.is_some() {
+				// Cancelled workflows stop with a complete ts but no output
+				WorkflowState::Cancelled
 			} else if worker_instance_id_entry.is_some() {
 				WorkflowState::Running
 			} else if has_wake_condition_entry.is_some() {
@@ -310,6 +317,8 @@ impl DatabaseDebug for DatabaseKv {
 					let mut matching_tags = 0;
 					let mut name_matches = name.is_none();
 					let mut state_matches = state.is_none() || state == Some(WorkflowState::Dead);
+					let mut has_output = false;
+					let mut is_silenced = false;
 
 					while let Some(entry) = stream.try_next().await? {
 						let workflow_id = *self.subspace.unpack::<JustId>(entry.key())?;
@@ -331,6 +340,8 @@ impl DatabaseDebug for DatabaseKv {
 								name_matches = name.is_none();
 								state_matches =
 									state.is_none() || state == Some(WorkflowState::Dead);
+								has_output = false;
+								is_silenced = false;
 							}
 						}
 
@@ -355,6 +366,8 @@ impl DatabaseDebug for DatabaseKv {
 							.unpack::<keys::workflow::OutputChunkKey>(entry.key())
 						{
 							// Has output
+							has_output = true;
+
 							match state {
 								Some(WorkflowState::Complete) => state_matches = true,
 								Some(_) => state_matches = false,
@@ -384,10 +397,24 @@ impl DatabaseDebug for DatabaseKv {
 							.subspace
 							.unpack::<keys::workflow::SilenceTsKey>(entry.key())
 						{
+							is_silenced = true;
+
 							match state {
 								Some(WorkflowState::Silenced) => state_matches = true,
 								_ => state_matches = false,
 							}
+						} else if let Ok(_) = self
+							.subspace
+							.unpack::<keys::workflow::CompleteTsKey>(entry.key())
+						{
+							// Cancelled workflows stop with a complete ts but no output
+							match state {
+								Some(WorkflowState::Cancelled) => {
+									state_matches = !has_output && !is_silenced
+								}
+								Some(WorkflowState::Dead) => state_matches = false,
+								_ => {}
+							}
 						}
 					}
 
@@ -435,6 +462,7 @@ impl DatabaseDebug for DatabaseKv {
 						let wake_sub_workflow_key =
 							keys::workflow::WakeSubWorkflowKey::new(workflow_id);
 						let error_key = keys::workflow::ErrorKey::new(workflow_id);
+						let complete_ts_key = keys::workflow::CompleteTsKey::new(workflow_id);
 
 						let Some(name_entry) =
 							tx.get(&self.subspace.pack(&name_key), Serializable).await?
@@ -459,6 +487,7 @@ impl DatabaseDebug for DatabaseKv {
 							has_output,
 							has_wake_condition,
 							is_silenced,
+							is_complete,
 							wake_sub_workflow_entry,
 							error_entry,
 						) = tokio::try_join!(
@@ -536,6 +565,11 @@ impl DatabaseDebug for DatabaseKv {
 									.await
 									.map(|x| x.is_some())
 							},
+							async {
+								tx.get(&self.subspace.pack(&complete_ts_key), Serializable)
+									.await
+									.map(|x| x.is_some())
+							},
 							tx.get(&self.subspace.pack(&wake_sub_workflow_key), Serializable),
 							tx.get(&self.subspace.pack(&error_key), Serializable),
 						)?;
@@ -607,13 +641,25 @@ impl DatabaseDebug for DatabaseKv {
 						// Clear "has wake condition"
 						tx.clear(&self.subspace.pack(&has_wake_condition_key));
 
+						let silence_ts = rivet_util::timestamp::now();
 						tx.set(
 							&self.subspace.pack(&silence_ts_key),
-							&silence_ts_key.serialize(rivet_util::timestamp::now())?,
+							&silence_ts_key.serialize(silence_ts)?,
+						);
+
+						// Add to retention index
+						let retention_key = keys::workflow::RetentionKey::new(
+							workflow_name.clone(),
+							silence_ts,
+							workflow_id,
+						);
+						tx.set(
+							&self.subspace.pack(&retention_key),
+							&retention_key.serialize(())?,
 						);
 
-						// Clear metric
-						let metric = if has_output {
+						// Clear metric. Cancelled workflows have a complete ts but no output
+						let metric = if has_output || is_complete {
 							keys::metric::GaugeMetric::WorkflowComplete(workflow_name.clone())
 						} else if has_wake_condition {
 							let error =
@@ -723,6 +769,40 @@ impl DatabaseDebug for DatabaseKv {
 		Ok(())
 	}
 
+	#[tracing::instrument(skip_all)]
+	async fn purge_workflows(
+		&self,
+		workflow_ids: Vec<Id>,
+		archive_path: Option<&Path>,
+	) -> Result<Vec<Id>> {
+		let mut purged = Vec::new();
+
+		for workflow_id in workflow_ids {
+			match self.purge_workflow(workflow_id, archive_path).await? {
+				PurgeResult::Purged => purged.push(workflow_id),
+				PurgeResult::Skipped => {
+					tracing::warn!(?workflow_id, "workflow not complete or silenced, skipping");
+				}
+				PurgeResult::NotFound => {
+					tracing::warn!(?workflow_id, "workflow not found");
+				}
+			}
+		}
+
+		Ok(purged)
+	}
+
+	#[tracing::instrument(skip_all)]
+	async fn purge_expired_workflows(
+		&self,
+		workflow_name: &str,
+		before_ts: i64,
+		archive_path: Option<&Path>,
+	) -> Result<usize> {
+		self.purge_expired_workflows_inner(workflow_name, before_ts, archive_path)
+			.await
+	}
+
 	#[tracing::instrument(skip_all)]
 	async fn get_workflow_history(
 		&self,
@@ -929,6 +1009,16 @@ impl DatabaseDebug for DatabaseKv {
 			.map_err(Into::into)
 	}
 
+	#[tracing::instrument(skip_all)]
+	async fn export_workflow(&self, workflow_id: Id) -> Result<Option<WorkflowExport>> {
+		self.export_workflow_inner(workflow_id).await
+	}
+
+	#[tracing::instrument(skip_all)]
+	async fn import_workflows(&self, workflows: &[WorkflowArchive]) -> Result<Vec<Id>> {
+		self.import_workflows_inner(workflows).await
+	}
+
 	#[tracing::instrument(skip_all)]
 	async fn get_signals(&self, signal_ids: Vec<Id>) -> Result<Vec<SignalData>> {
 		self.pools
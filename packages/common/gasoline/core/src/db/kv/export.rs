@@ -0,0 +1,355 @@
+//! Export and import of workflows, used to reproduce a workflow in another database.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{Result, ensure};
+use rivet_util::Id;
+use universaldb::utils::{FormalKey, IsolationLevel::*};
+
+use super::{DatabaseKv, keys, retention::read_entries, update_metric};
+use crate::{
+	db::debug::{WorkflowArchive, WorkflowExport},
+	history::event::EventType,
+};
+
+impl DatabaseKv {
+	#[tracing::instrument(skip_all, fields(%workflow_id))]
+	pub(super) async fn export_workflow_inner(
+		&self,
+		workflow_id: Id,
+	) -> Result<Option<WorkflowExport>> {
+		let mut workflows = Vec::new();
+		let mut seen = HashSet::new();
+		let mut queue = vec![workflow_id];
+
+		while let Some(workflow_id) = queue.pop() {
+			if !seen.insert(workflow_id) {
+				continue;
+			}
+
+			let Some((archive, sub_workflow_ids)) = self.read_export(workflow_id).await? else {
+				if workflows.is_empty() {
+					return Ok(None);
+				}
+
+				// Sub workflows may have been purged already
+				tracing::debug!(?workflow_id, "sub workflow not found");
+				continue;
+			};
+
+			workflows.push(archive);
+			queue.extend(sub_workflow_ids);
+		}
+
+		Ok(Some(WorkflowExport { workflows }))
+	}
+
+	/// Returns the workflow and the ids of its sub workflows.
+	async fn read_export(&self, workflow_id: Id) -> Result<Option<(WorkflowArchive, Vec<Id>)>> {
+		self.pools
+			.udb()?
+			.run(|tx| async move {
+				let name_key = keys::workflow::NameKey::new(workflow_id);
+				let workflow_subspace = self
+					.subspace
+					.subspace(&keys::workflow::EntireSubspaceKey::new(workflow_id));
+				let pending_signals_subspace =
+					self.subspace
+						.subspace(&keys::workflow::PendingSignalKey::entire_subspace(
+							workflow_id,
+						));
+
+				let (name_entry, workflow_entries, pending_signal_entries) = tokio::try_join!(
+					tx.get(&self.subspace.pack(&name_key), Snapshot),
+					read_entries(&tx, &workflow_subspace),
+					read_entries(&tx, &pending_signals_subspace),
+				)?;
+
+				let Some(name_entry) = name_entry else {
+					return Ok(None);
+				};
+				let workflow_name = name_key.deserialize(&name_entry)?;
+
+				let mut event_types = HashMap::new();
+				let mut sub_workflow_ids = Vec::new();
+				let mut history_signal_ids = Vec::new();
+
+				for (key, value) in &workflow_entries {
+					if let Ok(event_type_key) =
+						self.subspace.unpack::<keys::history::EventTypeKey>(key)
+					{
+						event_types.insert(
+							event_type_key.location().clone(),
+							event_type_key.deserialize(value)?,
+						);
+					} else if let Ok(sub_workflow_id_key) =
+						self.subspace.unpack::<keys::history::SubWorkflowIdKey>(key)
+					{
+						sub_workflow_ids.push((
+							sub_workflow_id_key.location().clone(),
+							sub_workflow_id_key.deserialize(value)?,
+						));
+					} else if let Ok(signal_id_key) =
+						self.subspace.unpack::<keys::history::SignalIdKey>(key)
+					{
+						history_signal_ids.push(signal_id_key.deserialize(value)?);
+					}
+				}
+
+				// Signal send events also record the id of the workflow they were sent to
+				let sub_workflow_ids = sub_workflow_ids
+					.into_iter()
+					.filter(|(location, _)| {
+						event_types.get(location) == Some(&EventType::SubWorkflow)
+					})
+					.map(|(_, sub_workflow_id)| sub_workflow_id)
+					.collect();
+
+				let pending_signal_keys = pending_signal_entries
+					.iter()
+					.map(|(key, _)| {
+						self.subspace
+							.unpack::<keys::workflow::PendingSignalKey>(key)
+					})
+					.collect::<std::result::Result<Vec<_>, _>>()?;
+
+				let signal_ids = self
+					.received_signal_ids(&tx, workflow_id, &pending_signal_keys, history_signal_ids)
+					.await?;
+
+				let mut entries = workflow_entries;
+				entries.extend(pending_signal_entries);
+				entries.extend(self.read_signal_entries(&tx, &signal_ids).await?);
+
+				Ok(Some((
+					self.to_archive(workflow_id, &workflow_name, &entries),
+					sub_workflow_ids,
+				)))
+			})
+			.await
+	}
+
+	#[tracing::instrument(skip_all)]
+	pub(super) async fn import_workflows_inner(
+		&self,
+		workflows: &[WorkflowArchive],
+	) -> Result<Vec<Id>> {
+		let workflows = workflows
+			.iter()
+			.map(|archive| {
+				let entries = archive
+					.entries
+					.iter()
+					.map(|entry| {
+						let key =
+							[self.subspace.bytes(), hex::decode(&entry.key)?.as_slice()].concat();
+						let value = hex::decode(&entry.value)?;
+
+						Ok((key, value))
+					})
+					.collect::<Result<Vec<_>>>()?;
+
+				Ok((archive, entries))
+			})
+			.collect::<Result<Vec<_>>>()?;
+
+		// Fail before writing anything if any of the workflows exist already
+		let workflow_ids = workflows
+			.iter()
+			.map(|(archive, _)| archive.workflow_id)
+			.collect::<Vec<_>>();
+		let workflow_ids = &workflow_ids;
+		self.pools
+			.udb()?
+			.run(|tx| async move {
+				let tx = tx.with_subspace(self.subspace.clone());
+
+				for workflow_id in workflow_ids {
+					ensure!(
+						!tx.exists(&keys::workflow::NameKey::new(*workflow_id), Snapshot)
+							.await?,
+						"workflow {workflow_id} already exists"
+					);
+				}
+
+				Ok(())
+			})
+			.await?;
+
+		// Each workflow is imported in its own transaction, importing many workflows at once would
+		// exceed the transaction size limit
+		let mut imported = Vec::new();
+		for (archive, entries) in &workflows {
+			self.pools
+				.udb()?
+				.run(|tx| async move {
+					self.import_workflow(archive.workflow_id, &archive.workflow_name, entries, &tx)
+						.await
+				})
+				.await?;
+
+			imported.push(archive.workflow_id);
+		}
+
+		self.wake_worker();
+
+		Ok(imported)
+	}
+
+	async fn import_workflow(
+		&self,
+		workflow_id: Id,
+		workflow_name: &str,
+		entries: &[(Vec<u8>, Vec<u8>)],
+		tx: &universaldb::Transaction,
+	) -> Result<()> {
+		let tx = tx.with_subspace(self.subspace.clone());
+
+		ensure!(
+			!tx.exists(&keys::workflow::NameKey::new(workflow_id), Serializable)
+				.await?,
+			"workflow {workflow_id} already exists"
+		);
+
+		let workflow_subspace = self
+			.subspace
+			.subspace(&keys::workflow::EntireSubspaceKey::new(workflow_id));
+		let pending_signals_subspace =
+			self.subspace
+				.subspace(&keys::workflow::PendingSignalKey::entire_subspace(
+					workflow_id,
+				));
+		let signals_subspace = self
+			.subspace
+			.subspace(&keys::signal::DataSubspaceKey::new());
+
+		let worker_instance_id_key = self
+			.subspace
+			.pack(&keys::workflow::WorkerInstanceIdKey::new(workflow_id));
+		let has_wake_condition_key = self
+			.subspace
+			.pack(&keys::workflow::HasWakeConditionKey::new(workflow_id));
+		let complete_ts_key = keys::workflow::CompleteTsKey::new(workflow_id);
+		let packed_complete_ts_key = self.subspace.pack(&complete_ts_key);
+		let silence_ts_key = keys::workflow::SilenceTsKey::new(workflow_id);
+		let packed_silence_ts_key = self.subspace.pack(&silence_ts_key);
+		let output_subspace = self
+			.subspace
+			.subspace(&keys::workflow::OutputKey::new(workflow_id));
+		let tags_subspace = self
+			.subspace
+			.subspace(&keys::workflow::TagKey::subspace(workflow_id));
+
+		let mut has_output = false;
+		let mut complete_ts = None;
+		let mut silence_ts = None;
+		let mut tags = Vec::new();
+		let mut pending_signal_names = Vec::new();
+
+		for (key, value) in entries {
+			ensure!(
+				workflow_subspace.is_start_of(key)
+					|| pending_signals_subspace.is_start_of(key)
+					|| signals_subspace.is_start_of(key),
+				"entry does not belong to workflow {workflow_id}"
+			);
+
+			// The worker that was running the workflow does not exist in this database. Wake conditions are
+			// written below.
+			if key == &worker_instance_id_key || key == &has_wake_condition_key {
+				continue;
+			}
+
+			if key == &packed_complete_ts_key {
+				complete_ts = Some(complete_ts_key.deserialize(value)?);
+			} else if key == &packed_silence_ts_key {
+				silence_ts = Some(silence_ts_key.deserialize(value)?);
+			} else if output_subspace.is_start_of(key) {
+				has_output = true;
+			} else if tags_subspace.is_start_of(key) {
+				let tag_key = self.subspace.unpack::<keys::workflow::TagKey>(key)?;
+				tags.push((tag_key.k, tag_key.v));
+			} else if pending_signals_subspace.is_start_of(key) {
+				let pending_signal_key = self
+					.subspace
+					.unpack::<keys::workflow::PendingSignalKey>(key)?;
+				pending_signal_names.push(pending_signal_key.signal_name);
+			}
+
+			tx.set(key, value);
+		}
+
+		// Rebuild the "by name and first tag" secondary index
+		for (k, v) in &tags {
+			let rest_of_tags = tags
+				.iter()
+				.filter(|(k2, _)| k2 != k)
+				.map(|(k, v)| (k.clone(), v.clone()))
+				.collect();
+
+			tx.write(
+				&keys::workflow::ByNameAndTagKey::new(
+					workflow_name.to_string(),
+					k.clone(),
+					v.clone(),
+					workflow_id,
+				),
+				rest_of_tags,
+			)?;
+		}
+
+		tx.write(
+			&keys::workflow::ByNameAndTagKey::null(workflow_name.to_string(), workflow_id),
+			tags,
+		)?;
+
+		for ts in complete_ts.iter().chain(silence_ts.iter()) {
+			tx.write(
+				&keys::workflow::RetentionKey::new(workflow_name.to_string(), *ts, workflow_id),
+				(),
+			)?;
+		}
+
+		if silence_ts.is_none() {
+			if has_output {
+				update_metric(
+					&tx,
+					None,
+					Some(keys::metric::GaugeMetric::WorkflowComplete(
+						workflow_name.to_string(),
+					)),
+				);
+			} else {
+				// Wake conditions are not exported, running the workflow again recreates them
+				tx.write(
+					&keys::wake::WorkflowWakeConditionKey::new(
+						workflow_name.to_string(),
+						workflow_id,
+						keys::wake::WakeCondition::Immediate,
+					),
+					(),
+				)?;
+
+				tx.write(&keys::workflow::HasWakeConditionKey::new(workflow_id), ())?;
+
+				update_metric(
+					&tx,
+					None,
+					Some(keys::metric::GaugeMetric::WorkflowSleeping(
+						workflow_name.to_string(),
+					)),
+				);
+			}
+		}
+
+		for signal_name in pending_signal_names {
+			update_metric(
+				&tx,
+				None,
+				Some(keys::metric::GaugeMetric::SignalPending(signal_name)),
+			);
+		}
+
+		Ok(())
+	}
+}
@@ -173,6 +173,10 @@ impl EventTypeKey {
 			forgotten: false,
 		}
 	}
+
+	pub fn location(&self) -> &Location {
+		&self.location
+	}
 }
 
 impl FormalKey for EventTypeKey {
@@ -484,6 +488,10 @@ impl SubWorkflowIdKey {
 			forgotten: false,
 		}
 	}
+
+	pub fn location(&self) -> &Location {
+		&self.location
+	}
 }
 
 impl FormalKey for SubWorkflowIdKey {
@@ -602,6 +610,22 @@ impl TuplePack for InputKey {
 	}
 }
 
+impl<'de> TupleUnpack<'de> for InputKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, (workflow_id, location, forgotten)) =
+			unpack_history_key(input, tuple_depth, INPUT, "INPUT")?;
+
+		Ok((
+			input,
+			InputKey {
+				workflow_id,
+				location,
+				forgotten,
+			},
+		))
+	}
+}
+
 pub struct InputChunkKey {
 	workflow_id: Id,
 	location: Location,
@@ -718,6 +742,22 @@ impl TuplePack for OutputKey {
 	}
 }
 
+impl<'de> TupleUnpack<'de> for OutputKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, (workflow_id, location, forgotten)) =
+			unpack_history_key(input, tuple_depth, OUTPUT, "OUTPUT")?;
+
+		Ok((
+			input,
+			OutputKey {
+				workflow_id,
+				location,
+				forgotten,
+			},
+		))
+	}
+}
+
 pub struct OutputChunkKey {
 	workflow_id: Id,
 	location: Location,
@@ -1,6 +1,96 @@
+use anyhow::Result;
+use universaldb::{
+	prelude::*,
+	utils::{
+		FormalChunkedKeyDecoder, FormalKeyDecoder, Subspace, decode_formal_chunked_key,
+		decode_formal_key,
+	},
+};
+
 pub mod history;
 pub mod metric;
+pub mod schedule;
 pub mod signal;
 pub mod wake;
 pub mod worker_instance;
 pub mod workflow;
+
+pub fn subspace() -> Subspace {
+	Subspace::new(&(RIVET, GASOLINE, KV))
+}
+
+/// Decodes the value of a workflow key as JSON. Returns `None` if the key is not a known key.
+pub fn decode_value(key: &[u8], value: &[u8]) -> Option<Result<serde_json::Value>> {
+	const DECODERS: &[FormalKeyDecoder] = &[
+		decode_formal_key::<workflow::LeaseKey>,
+		decode_formal_key::<workflow::TagKey>,
+		decode_formal_key::<workflow::WakeSignalKey>,
+		decode_formal_key::<workflow::WakeDeadlineKey>,
+		decode_formal_key::<workflow::NameKey>,
+		decode_formal_key::<workflow::CreateTsKey>,
+		decode_formal_key::<workflow::RayIdKey>,
+		decode_formal_key::<workflow::ErrorKey>,
+		decode_formal_key::<workflow::WakeSubWorkflowKey>,
+		decode_formal_key::<workflow::PendingSignalKey>,
+		decode_formal_key::<workflow::ByNameAndTagKey>,
+		decode_formal_key::<workflow::HasWakeConditionKey>,
+		decode_formal_key::<workflow::WorkerInstanceIdKey>,
+		decode_formal_key::<workflow::SilenceTsKey>,
+		decode_formal_key::<workflow::CompleteTsKey>,
+		decode_formal_key::<workflow::RetentionKey>,
+		decode_formal_key::<workflow::RetentionBackfilledKey>,
+		decode_formal_key::<workflow::CancelTsKey>,
+		decode_formal_key::<workflow::PriorityKey>,
+		decode_formal_key::<history::EventTypeKey>,
+		decode_formal_key::<history::VersionKey>,
+		decode_formal_key::<history::CreateTsKey>,
+		decode_formal_key::<history::NameKey>,
+		decode_formal_key::<history::SignalIdKey>,
+		decode_formal_key::<history::SubWorkflowIdKey>,
+		decode_formal_key::<history::ErrorKey>,
+		decode_formal_key::<history::IterationKey>,
+		decode_formal_key::<history::DeadlineTsKey>,
+		decode_formal_key::<history::SleepStateKey>,
+		decode_formal_key::<history::InnerEventTypeKey>,
+		decode_formal_key::<history::TagKey>,
+		decode_formal_key::<signal::AckTsKey>,
+		decode_formal_key::<signal::CreateTsKey>,
+		decode_formal_key::<signal::RayIdKey>,
+		decode_formal_key::<signal::NameKey>,
+		decode_formal_key::<signal::WorkflowIdKey>,
+		decode_formal_key::<signal::SilenceTsKey>,
+		decode_formal_key::<wake::WorkflowWakeConditionKey>,
+		decode_formal_key::<wake::SubWorkflowWakeKey>,
+		decode_formal_key::<schedule::ConfigKey>,
+		decode_formal_key::<schedule::NextTsKey>,
+		decode_formal_key::<schedule::ByNextTsKey>,
+		decode_formal_key::<schedule::ByNameKey>,
+		decode_formal_key::<worker_instance::LastPingTsKey>,
+		decode_formal_key::<worker_instance::MetricsLockKey>,
+		decode_formal_key::<metric::GaugeMetricKey>,
+	];
+
+	let subspace = subspace();
+	DECODERS
+		.iter()
+		.find_map(|decode| decode(&subspace, key, value))
+}
+
+/// Combines and decodes the chunks of a chunked workflow key as JSON. Returns `None` if the key is
+/// not a known chunked key.
+pub fn decode_chunked_value(key: &[u8], chunks: &[Value]) -> Option<Result<serde_json::Value>> {
+	const DECODERS: &[FormalChunkedKeyDecoder] = &[
+		decode_formal_chunked_key::<workflow::InputKey>,
+		decode_formal_chunked_key::<workflow::OutputKey>,
+		decode_formal_chunked_key::<workflow::StateKey>,
+		decode_formal_chunked_key::<history::InputKey>,
+		decode_formal_chunked_key::<history::OutputKey>,
+		decode_formal_chunked_key::<signal::BodyKey>,
+		decode_formal_chunked_key::<schedule::InputKey>,
+	];
+
+	let subspace = subspace();
+	DECODERS
+		.iter()
+		.find_map(|decode| decode(&subspace, key, chunks))
+}
@@ -0,0 +1,402 @@
+use std::result::Result::Ok;
+
+use anyhow::*;
+use rivet_util::Id;
+use serde::{Deserialize, Serialize};
+use universaldb::prelude::*;
+
+use crate::schedule::CatchUp;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScheduleConfig {
+	pub workflow_name: String,
+	pub cron: String,
+	pub catch_up: CatchUp,
+	pub tags: Option<serde_json::Value>,
+	pub create_ts: i64,
+}
+
+pub struct ConfigKey {
+	schedule_id: Id,
+}
+
+impl ConfigKey {
+	pub fn new(schedule_id: Id) -> Self {
+		ConfigKey { schedule_id }
+	}
+}
+
+impl FormalKey for ConfigKey {
+	type Value = ScheduleConfig;
+
+	fn deserialize(&self, raw: &[u8]) -> Result<Self::Value> {
+		serde_json::from_slice(raw).map_err(Into::into)
+	}
+
+	fn serialize(&self, value: Self::Value) -> Result<Vec<u8>> {
+		serde_json::to_vec(&value).map_err(Into::into)
+	}
+}
+
+impl TuplePack for ConfigKey {
+	fn pack<W: std::io::Write>(
+		&self,
+		w: &mut W,
+		tuple_depth: TupleDepth,
+	) -> std::io::Result<VersionstampOffset> {
+		let t = (SCHEDULE, DATA, self.schedule_id, CONFIG);
+		t.pack(w, tuple_depth)
+	}
+}
+
+impl<'de> TupleUnpack<'de> for ConfigKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, (_, _, schedule_id, data)) =
+			<(usize, usize, Id, usize)>::unpack(input, tuple_depth)?;
+		if data != CONFIG {
+			return Err(PackError::Message("expected CONFIG data".into()));
+		}
+
+		let v = ConfigKey { schedule_id };
+
+		Ok((input, v))
+	}
+}
+
+pub struct InputKey {
+	schedule_id: Id,
+}
+
+impl InputKey {
+	pub fn new(schedule_id: Id) -> Self {
+		InputKey { schedule_id }
+	}
+
+	pub fn split_ref(&self, value: &serde_json::value::RawValue) -> Result<Vec<Vec<u8>>> {
+		Ok(value
+			.get()
+			.as_bytes()
+			.chunks(universaldb::utils::CHUNK_SIZE)
+			.map(|x| x.to_vec())
+			.collect())
+	}
+}
+
+impl FormalChunkedKey for InputKey {
+	type ChunkKey = InputChunkKey;
+	type Value = Box<serde_json::value::RawValue>;
+
+	fn chunk(&self, chunk: usize) -> Self::ChunkKey {
+		InputChunkKey {
+			schedule_id: self.schedule_id,
+			chunk,
+		}
+	}
+
+	fn combine(&self, chunks: Vec<Value>) -> Result<Self::Value> {
+		serde_json::value::RawValue::from_string(String::from_utf8(
+			chunks
+				.iter()
+				.map(|x| x.value().iter().map(|x| *x))
+				.flatten()
+				.collect(),
+		)?)
+		.context("failed to combine `InputKey`")
+	}
+
+	fn split(&self, value: Self::Value) -> Result<Vec<Vec<u8>>> {
+		self.split_ref(value.as_ref())
+	}
+}
+
+impl TuplePack for InputKey {
+	fn pack<W: std::io::Write>(
+		&self,
+		w: &mut W,
+		tuple_depth: TupleDepth,
+	) -> std::io::Result<VersionstampOffset> {
+		let t = (SCHEDULE, DATA, self.schedule_id, INPUT);
+		t.pack(w, tuple_depth)
+	}
+}
+
+impl<'de> TupleUnpack<'de> for InputKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, (_, _, schedule_id, data)) =
+			<(usize, usize, Id, usize)>::unpack(input, tuple_depth)?;
+		if data != INPUT {
+			return Err(PackError::Message("expected INPUT data".into()));
+		}
+
+		let v = InputKey { schedule_id };
+
+		Ok((input, v))
+	}
+}
+
+pub struct InputChunkKey {
+	schedule_id: Id,
+	chunk: usize,
+}
+
+impl TuplePack for InputChunkKey {
+	fn pack<W: std::io::Write>(
+		&self,
+		w: &mut W,
+		tuple_depth: TupleDepth,
+	) -> std::io::Result<VersionstampOffset> {
+		let t = (SCHEDULE, DATA, self.schedule_id, INPUT, self.chunk);
+		t.pack(w, tuple_depth)
+	}
+}
+
+impl<'de> TupleUnpack<'de> for InputChunkKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, (_, _, schedule_id, data, chunk)) =
+			<(usize, usize, Id, usize, usize)>::unpack(input, tuple_depth)?;
+		if data != INPUT {
+			return Err(PackError::Message("expected INPUT data".into()));
+		}
+
+		let v = InputChunkKey { schedule_id, chunk };
+
+		Ok((input, v))
+	}
+}
+
+pub struct NextTsKey {
+	schedule_id: Id,
+}
+
+impl NextTsKey {
+	pub fn new(schedule_id: Id) -> Self {
+		NextTsKey { schedule_id }
+	}
+}
+
+impl FormalKey for NextTsKey {
+	// Timestamp.
+	type Value = i64;
+
+	fn deserialize(&self, raw: &[u8]) -> Result<Self::Value> {
+		Ok(i64::from_be_bytes(raw.try_into()?))
+	}
+
+	fn serialize(&self, value: Self::Value) -> Result<Vec<u8>> {
+		Ok(value.to_be_bytes().to_vec())
+	}
+}
+
+impl TuplePack for NextTsKey {
+	fn pack<W: std::io::Write>(
+		&self,
+		w: &mut W,
+		tuple_depth: TupleDepth,
+	) -> std::io::Result<VersionstampOffset> {
+		let t = (SCHEDULE, DATA, self.schedule_id, NEXT_TS);
+		t.pack(w, tuple_depth)
+	}
+}
+
+impl<'de> TupleUnpack<'de> for NextTsKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, (_, _, schedule_id, data)) =
+			<(usize, usize, Id, usize)>::unpack(input, tuple_depth)?;
+		if data != NEXT_TS {
+			return Err(PackError::Message("expected NEXT_TS data".into()));
+		}
+
+		let v = NextTsKey { schedule_id };
+
+		Ok((input, v))
+	}
+}
+
+pub struct EntireSubspaceKey {
+	schedule_id: Id,
+}
+
+impl EntireSubspaceKey {
+	pub fn new(schedule_id: Id) -> Self {
+		EntireSubspaceKey { schedule_id }
+	}
+}
+
+impl TuplePack for EntireSubspaceKey {
+	fn pack<W: std::io::Write>(
+		&self,
+		w: &mut W,
+		tuple_depth: TupleDepth,
+	) -> std::io::Result<VersionstampOffset> {
+		let t = (SCHEDULE, DATA, self.schedule_id);
+		t.pack(w, tuple_depth)
+	}
+}
+
+/// Index of schedules by their next run, read by workers to find due schedules.
+pub struct ByNextTsKey {
+	pub next_ts: i64,
+	pub schedule_id: Id,
+}
+
+impl ByNextTsKey {
+	pub fn new(next_ts: i64, schedule_id: Id) -> Self {
+		ByNextTsKey {
+			next_ts,
+			schedule_id,
+		}
+	}
+
+	pub fn subspace() -> ByNextTsSubspaceKey {
+		ByNextTsSubspaceKey::new()
+	}
+}
+
+impl FormalKey for ByNextTsKey {
+	type Value = ();
+
+	fn deserialize(&self, _raw: &[u8]) -> Result<Self::Value> {
+		Ok(())
+	}
+
+	fn serialize(&self, _value: Self::Value) -> Result<Vec<u8>> {
+		Ok(Vec::new())
+	}
+}
+
+impl TuplePack for ByNextTsKey {
+	fn pack<W: std::io::Write>(
+		&self,
+		w: &mut W,
+		tuple_depth: TupleDepth,
+	) -> std::io::Result<VersionstampOffset> {
+		let t = (SCHEDULE, BY_NEXT_TS, self.next_ts, self.schedule_id);
+		t.pack(w, tuple_depth)
+	}
+}
+
+impl<'de> TupleUnpack<'de> for ByNextTsKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, (_, data, next_ts, schedule_id)) =
+			<(usize, usize, i64, Id)>::unpack(input, tuple_depth)?;
+		if data != BY_NEXT_TS {
+			return Err(PackError::Message("expected BY_NEXT_TS data".into()));
+		}
+
+		let v = ByNextTsKey {
+			next_ts,
+			schedule_id,
+		};
+
+		Ok((input, v))
+	}
+}
+
+pub struct ByNextTsSubspaceKey {}
+
+impl ByNextTsSubspaceKey {
+	pub fn new() -> Self {
+		ByNextTsSubspaceKey {}
+	}
+}
+
+impl TuplePack for ByNextTsSubspaceKey {
+	fn pack<W: std::io::Write>(
+		&self,
+		w: &mut W,
+		tuple_depth: TupleDepth,
+	) -> std::io::Result<VersionstampOffset> {
+		let t = (SCHEDULE, BY_NEXT_TS);
+		t.pack(w, tuple_depth)
+	}
+}
+
+/// Index of schedules by the name of the workflow they dispatch. Used for listing schedules and for
+/// finding existing schedules when creating a unique schedule.
+pub struct ByNameKey {
+	workflow_name: String,
+	pub schedule_id: Id,
+}
+
+impl ByNameKey {
+	pub fn new(workflow_name: String, schedule_id: Id) -> Self {
+		ByNameKey {
+			workflow_name,
+			schedule_id,
+		}
+	}
+
+	pub fn subspace(workflow_name: Option<String>) -> ByNameSubspaceKey {
+		ByNameSubspaceKey::new(workflow_name)
+	}
+}
+
+impl FormalKey for ByNameKey {
+	/// Tags of the schedule.
+	type Value = Option<serde_json::Value>;
+
+	fn deserialize(&self, raw: &[u8]) -> Result<Self::Value> {
+		serde_json::from_slice(raw).map_err(Into::into)
+	}
+
+	fn serialize(&self, value: Self::Value) -> Result<Vec<u8>> {
+		serde_json::to_vec(&value).map_err(Into::into)
+	}
+}
+
+impl TuplePack for ByNameKey {
+	fn pack<W: std::io::Write>(
+		&self,
+		w: &mut W,
+		tuple_depth: TupleDepth,
+	) -> std::io::Result<VersionstampOffset> {
+		let t = (SCHEDULE, BY_NAME, &self.workflow_name, self.schedule_id);
+		t.pack(w, tuple_depth)
+	}
+}
+
+impl<'de> TupleUnpack<'de> for ByNameKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, (_, data, workflow_name, schedule_id)) =
+			<(usize, usize, String, Id)>::unpack(input, tuple_depth)?;
+		if data != BY_NAME {
+			return Err(PackError::Message("expected BY_NAME data".into()));
+		}
+
+		let v = ByNameKey {
+			workflow_name,
+			schedule_id,
+		};
+
+		Ok((input, v))
+	}
+}
+
+pub struct ByNameSubspaceKey {
+	workflow_name: Option<String>,
+}
+
+impl ByNameSubspaceKey {
+	pub fn new(workflow_name: Option<String>) -> Self {
+		ByNameSubspaceKey { workflow_name }
+	}
+}
+
+impl TuplePack for ByNameSubspaceKey {
+	fn pack<W: std::io::Write>(
+		&self,
+		w: &mut W,
+		tuple_depth: TupleDepth,
+	) -> std::io::Result<VersionstampOffset> {
+		let mut offset = VersionstampOffset::None { size: 0 };
+
+		let t = (SCHEDULE, BY_NAME);
+		offset += t.pack(w, tuple_depth)?;
+
+		if let Some(workflow_name) = &self.workflow_name {
+			offset += workflow_name.pack(w, tuple_depth)?;
+		}
+
+		Ok(offset)
+	}
+}
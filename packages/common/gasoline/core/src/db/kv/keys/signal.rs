@@ -61,6 +61,20 @@ impl TuplePack for BodyKey {
 	}
 }
 
+impl<'de> TupleUnpack<'de> for BodyKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, (_, _, signal_id, data)) =
+			<(usize, usize, Id, usize)>::unpack(input, tuple_depth)?;
+		if data != BODY {
+			return Err(PackError::Message("expected BODY data".into()));
+		}
+
+		let v = BodyKey { signal_id };
+
+		Ok((input, v))
+	}
+}
+
 pub struct BodyChunkKey {
 	signal_id: Id,
 	chunk: usize,
@@ -332,6 +346,28 @@ impl<'de> TupleUnpack<'de> for WorkflowIdKey {
 	}
 }
 
+/// Subspace of all data of a single signal.
+pub struct EntireSubspaceKey {
+	signal_id: Id,
+}
+
+impl EntireSubspaceKey {
+	pub fn new(signal_id: Id) -> Self {
+		EntireSubspaceKey { signal_id }
+	}
+}
+
+impl TuplePack for EntireSubspaceKey {
+	fn pack<W: std::io::Write>(
+		&self,
+		w: &mut W,
+		tuple_depth: TupleDepth,
+	) -> std::io::Result<VersionstampOffset> {
+		let t = (SIGNAL, DATA, self.signal_id);
+		t.pack(w, tuple_depth)
+	}
+}
+
 pub struct DataSubspaceKey {}
 
 impl DataSubspaceKey {
@@ -203,6 +203,20 @@ impl TuplePack for InputKey {
 	}
 }
 
+impl<'de> TupleUnpack<'de> for InputKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, (_, _, workflow_id, data)) =
+			<(usize, usize, Id, usize)>::unpack(input, tuple_depth)?;
+		if data != INPUT {
+			return Err(PackError::Message("expected INPUT data".into()));
+		}
+
+		let v = InputKey { workflow_id };
+
+		Ok((input, v))
+	}
+}
+
 pub struct InputChunkKey {
 	workflow_id: Id,
 	chunk: usize,
@@ -290,6 +304,20 @@ impl TuplePack for OutputKey {
 	}
 }
 
+impl<'de> TupleUnpack<'de> for OutputKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, (_, _, workflow_id, data)) =
+			<(usize, usize, Id, usize)>::unpack(input, tuple_depth)?;
+		if data != OUTPUT {
+			return Err(PackError::Message("expected OUTPUT data".into()));
+		}
+
+		let v = OutputKey { workflow_id };
+
+		Ok((input, v))
+	}
+}
+
 pub struct OutputChunkKey {
 	workflow_id: Id,
 	chunk: usize,
@@ -377,6 +405,20 @@ impl TuplePack for StateKey {
 	}
 }
 
+impl<'de> TupleUnpack<'de> for StateKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, (_, _, workflow_id, data)) =
+			<(usize, usize, Id, usize)>::unpack(input, tuple_depth)?;
+		if data != STATE {
+			return Err(PackError::Message("expected STATE data".into()));
+		}
+
+		let v = StateKey { workflow_id };
+
+		Ok((input, v))
+	}
+}
+
 pub struct StateChunkKey {
 	workflow_id: Id,
 	chunk: usize,
@@ -804,6 +846,14 @@ impl PendingSignalKey {
 	pub fn subspace(workflow_id: Id, signal_name: String) -> PendingSignalSubspaceKey {
 		PendingSignalSubspaceKey::new(workflow_id, signal_name)
 	}
+
+	/// Subspace of the pending signals of all names.
+	pub fn entire_subspace(workflow_id: Id) -> PendingSignalSubspaceKey {
+		PendingSignalSubspaceKey {
+			workflow_id,
+			signal_name: None,
+		}
+	}
 }
 
 impl FormalKey for PendingSignalKey {
@@ -854,14 +904,14 @@ impl<'de> TupleUnpack<'de> for PendingSignalKey {
 
 pub struct PendingSignalSubspaceKey {
 	workflow_id: Id,
-	signal_name: String,
+	signal_name: Option<String>,
 }
 
 impl PendingSignalSubspaceKey {
 	pub fn new(workflow_id: Id, signal_name: String) -> Self {
 		PendingSignalSubspaceKey {
 			workflow_id,
-			signal_name,
+			signal_name: Some(signal_name),
 		}
 	}
 }
@@ -872,14 +922,16 @@ impl TuplePack for PendingSignalSubspaceKey {
 		w: &mut W,
 		tuple_depth: TupleDepth,
 	) -> std::io::Result<VersionstampOffset> {
-		let t = (
-			WORKFLOW,
-			SIGNAL,
-			self.workflow_id,
-			PENDING,
-			&self.signal_name,
-		);
-		t.pack(w, tuple_depth)
+		let mut offset = VersionstampOffset::None { size: 0 };
+
+		let t = (WORKFLOW, SIGNAL, self.workflow_id, PENDING);
+		offset += t.pack(w, tuple_depth)?;
+
+		if let Some(signal_name) = &self.signal_name {
+			offset += signal_name.pack(w, tuple_depth)?;
+		}
+
+		Ok(offset)
 	}
 }
 
@@ -1108,6 +1160,28 @@ impl<'de> TupleUnpack<'de> for WorkerInstanceIdKey {
 	}
 }
 
+/// Subspace of all data of a single workflow, including its history.
+pub struct EntireSubspaceKey {
+	workflow_id: Id,
+}
+
+impl EntireSubspaceKey {
+	pub fn new(workflow_id: Id) -> Self {
+		EntireSubspaceKey { workflow_id }
+	}
+}
+
+impl TuplePack for EntireSubspaceKey {
+	fn pack<W: std::io::Write>(
+		&self,
+		w: &mut W,
+		tuple_depth: TupleDepth,
+	) -> std::io::Result<VersionstampOffset> {
+		let t = (WORKFLOW, DATA, self.workflow_id);
+		t.pack(w, tuple_depth)
+	}
+}
+
 pub struct DataSubspaceKey {}
 
 impl DataSubspaceKey {
@@ -1175,3 +1249,294 @@ impl<'de> TupleUnpack<'de> for SilenceTsKey {
 		Ok((input, v))
 	}
 }
+
+#[derive(Debug)]
+pub struct CompleteTsKey {
+	workflow_id: Id,
+}
+
+impl CompleteTsKey {
+	pub fn new(workflow_id: Id) -> Self {
+		CompleteTsKey { workflow_id }
+	}
+}
+
+impl FormalKey for CompleteTsKey {
+	// Timestamp.
+	type Value = i64;
+
+	fn deserialize(&self, raw: &[u8]) -> Result<Self::Value> {
+		Ok(i64::from_be_bytes(raw.try_into()?))
+	}
+
+	fn serialize(&self, value: Self::Value) -> Result<Vec<u8>> {
+		Ok(value.to_be_bytes().to_vec())
+	}
+}
+
+impl TuplePack for CompleteTsKey {
+	fn pack<W: std::io::Write>(
+		&self,
+		w: &mut W,
+		tuple_depth: TupleDepth,
+	) -> std::io::Result<VersionstampOffset> {
+		let t = (WORKFLOW, DATA, self.workflow_id, COMPLETE_TS);
+		t.pack(w, tuple_depth)
+	}
+}
+
+impl<'de> TupleUnpack<'de> for CompleteTsKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, (_, _, workflow_id, data)) =
+			<(usize, usize, Id, usize)>::unpack(input, tuple_depth)?;
+		if data != COMPLETE_TS {
+			return Err(PackError::Message("expected COMPLETE_TS data".into()));
+		}
+
+		let v = CompleteTsKey { workflow_id };
+
+		Ok((input, v))
+	}
+}
+
+#[derive(Debug)]
+pub struct CancelTsKey {
+	workflow_id: Id,
+}
+
+impl CancelTsKey {
+	pub fn new(workflow_id: Id) -> Self {
+		CancelTsKey { workflow_id }
+	}
+}
+
+impl FormalKey for CancelTsKey {
+	// Timestamp.
+	type Value = i64;
+
+	fn deserialize(&self, raw: &[u8]) -> Result<Self::Value> {
+		Ok(i64::from_be_bytes(raw.try_into()?))
+	}
+
+	fn serialize(&self, value: Self::Value) -> Result<Vec<u8>> {
+		Ok(value.to_be_bytes().to_vec())
+	}
+}
+
+impl TuplePack for CancelTsKey {
+	fn pack<W: std::io::Write>(
+		&self,
+		w: &mut W,
+		tuple_depth: TupleDepth,
+	) -> std::io::Result<VersionstampOffset> {
+		let t = (WORKFLOW, DATA, self.workflow_id, CANCEL_TS);
+		t.pack(w, tuple_depth)
+	}
+}
+
+impl<'de> TupleUnpack<'de> for CancelTsKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, (_, _, workflow_id, data)) =
+			<(usize, usize, Id, usize)>::unpack(input, tuple_depth)?;
+		if data != CANCEL_TS {
+			return Err(PackError::Message("expected CANCEL_TS data".into()));
+		}
+
+		let v = CancelTsKey { workflow_id };
+
+		Ok((input, v))
+	}
+}
+
+/// Set at dispatch time for workflows with a non-default priority.
+#[derive(Debug)]
+pub struct PriorityKey {
+	workflow_id: Id,
+}
+
+impl PriorityKey {
+	pub fn new(workflow_id: Id) -> Self {
+		PriorityKey { workflow_id }
+	}
+}
+
+impl FormalKey for PriorityKey {
+	type Value = i32;
+
+	fn deserialize(&self, raw: &[u8]) -> Result<Self::Value> {
+		Ok(i32::from_be_bytes(raw.try_into()?))
+	}
+
+	fn serialize(&self, value: Self::Value) -> Result<Vec<u8>> {
+		Ok(value.to_be_bytes().to_vec())
+	}
+}
+
+impl TuplePack for PriorityKey {
+	fn pack<W: std::io::Write>(
+		&self,
+		w: &mut W,
+		tuple_depth: TupleDepth,
+	) -> std::io::Result<VersionstampOffset> {
+		let t = (WORKFLOW, DATA, self.workflow_id, PRIORITY);
+		t.pack(w, tuple_depth)
+	}
+}
+
+impl<'de> TupleUnpack<'de> for PriorityKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, (_, _, workflow_id, data)) =
+			<(usize, usize, Id, usize)>::unpack(input, tuple_depth)?;
+		if data != PRIORITY {
+			return Err(PackError::Message("expected PRIORITY data".into()));
+		}
+
+		let v = PriorityKey { workflow_id };
+
+		Ok((input, v))
+	}
+}
+
+/// Index of workflows that completed or were silenced, ordered by when that happened. Used to purge
+/// workflows according to their retention policy. A workflow that completed and was silenced has
+/// two entries.
+#[derive(Debug)]
+pub struct RetentionKey {
+	workflow_name: String,
+	pub ts: i64,
+	pub workflow_id: Id,
+}
+
+impl RetentionKey {
+	pub fn new(workflow_name: String, ts: i64, workflow_id: Id) -> Self {
+		RetentionKey {
+			workflow_name,
+			ts,
+			workflow_id,
+		}
+	}
+
+	pub fn subspace(workflow_name: String) -> RetentionSubspaceKey {
+		RetentionSubspaceKey::new(workflow_name)
+	}
+}
+
+impl FormalKey for RetentionKey {
+	type Value = ();
+
+	fn deserialize(&self, _raw: &[u8]) -> Result<Self::Value> {
+		Ok(())
+	}
+
+	fn serialize(&self, _value: Self::Value) -> Result<Vec<u8>> {
+		Ok(Vec::new())
+	}
+}
+
+impl TuplePack for RetentionKey {
+	fn pack<W: std::io::Write>(
+		&self,
+		w: &mut W,
+		tuple_depth: TupleDepth,
+	) -> std::io::Result<VersionstampOffset> {
+		let t = (
+			WORKFLOW,
+			RETENTION,
+			&self.workflow_name,
+			self.ts,
+			self.workflow_id,
+		);
+		t.pack(w, tuple_depth)
+	}
+}
+
+impl<'de> TupleUnpack<'de> for RetentionKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, (_, data, workflow_name, ts, workflow_id)) =
+			<(usize, usize, String, i64, Id)>::unpack(input, tuple_depth)?;
+		if data != RETENTION {
+			return Err(PackError::Message("expected RETENTION data".into()));
+		}
+
+		let v = RetentionKey {
+			workflow_name,
+			ts,
+			workflow_id,
+		};
+
+		Ok((input, v))
+	}
+}
+
+pub struct RetentionSubspaceKey {
+	workflow_name: String,
+}
+
+impl RetentionSubspaceKey {
+	pub fn new(workflow_name: String) -> Self {
+		RetentionSubspaceKey { workflow_name }
+	}
+}
+
+impl TuplePack for RetentionSubspaceKey {
+	fn pack<W: std::io::Write>(
+		&self,
+		w: &mut W,
+		tuple_depth: TupleDepth,
+	) -> std::io::Result<VersionstampOffset> {
+		let t = (WORKFLOW, RETENTION, &self.workflow_name);
+		t.pack(w, tuple_depth)
+	}
+}
+
+/// Set once workflows of this name that predate the retention index were added to it.
+#[derive(Debug)]
+pub struct RetentionBackfilledKey {
+	workflow_name: String,
+}
+
+impl RetentionBackfilledKey {
+	pub fn new(workflow_name: String) -> Self {
+		RetentionBackfilledKey { workflow_name }
+	}
+}
+
+impl FormalKey for RetentionBackfilledKey {
+	// Timestamp.
+	type Value = i64;
+
+	fn deserialize(&self, raw: &[u8]) -> Result<Self::Value> {
+		Ok(i64::from_be_bytes(raw.try_into()?))
+	}
+
+	fn serialize(&self, value: Self::Value) -> Result<Vec<u8>> {
+		Ok(value.to_be_bytes().to_vec())
+	}
+}
+
+impl TuplePack for RetentionBackfilledKey {
+	fn pack<W: std::io::Write>(
+		&self,
+		w: &mut W,
+		tuple_depth: TupleDepth,
+	) -> std::io::Result<VersionstampOffset> {
+		let t = (WORKFLOW, RETENTION_BACKFILLED, &self.workflow_name);
+		t.pack(w, tuple_depth)
+	}
+}
+
+impl<'de> TupleUnpack<'de> for RetentionBackfilledKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, (_, data, workflow_name)) =
+			<(usize, usize, String)>::unpack(input, tuple_depth)?;
+		if data != RETENTION_BACKFILLED {
+			return Err(PackError::Message(
+				"expected RETENTION_BACKFILLED data".into(),
+			));
+		}
+
+		let v = RetentionBackfilledKey { workflow_name };
+
+		Ok((input, v))
+	}
+}
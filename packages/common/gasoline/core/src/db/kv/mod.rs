@@ -23,7 +23,7 @@ use universaldb::{
 
 use rivet_metrics::KeyValue;
 
-use super::{Database, PulledWorkflowData, SignalData, WorkflowData};
+use super::{Database, PullLimits, PulledWorkflowData, ScheduleData, SignalData, WorkflowData};
 use crate::{
 	error::{WorkflowError, WorkflowResult},
 	history::{
@@ -34,10 +34,14 @@ use crate::{
 		location::Location,
 	},
 	metrics,
+	schedule::CatchUp,
 };
 
 mod debug;
-mod keys;
+mod export;
+pub(crate) mod keys;
+mod retention;
+mod schedule;
 
 /// How long before considering the leases of a given worker instance expired.
 const WORKER_INSTANCE_LOST_THRESHOLD_MS: i64 = rivet_util::duration::seconds(30);
@@ -237,6 +241,7 @@ impl DatabaseKv {
 		tags: Option<&serde_json::Value>,
 		input: &serde_json::value::RawValue,
 		unique: bool,
+		priority: i32,
 		tx: &universaldb::Transaction,
 	) -> Result<Id> {
 		let tx = tx.with_subspace(self.subspace.clone());
@@ -265,6 +270,11 @@ impl DatabaseKv {
 
 		tx.write(&keys::workflow::RayIdKey::new(workflow_id), ray_id)?;
 
+		// Default priority is implied by the key not existing
+		if priority != 0 {
+			tx.write(&keys::workflow::PriorityKey::new(workflow_id), priority)?;
+		}
+
 		// Write tags
 		let tags = tags
 			.map(|x| {
@@ -407,6 +417,175 @@ impl DatabaseKv {
 			}
 		}
 	}
+
+	/// Stops a workflow for good, either with an output or after it was cancelled. Returns whether or
+	/// not other workflows waiting on this one were woken.
+	async fn complete_workflow_inner(
+		&self,
+		workflow_id: Id,
+		workflow_name: &str,
+		output: Option<&serde_json::value::RawValue>,
+		error: Option<&str>,
+		tx: &universaldb::Transaction,
+	) -> Result<bool> {
+		let sub_workflow_wake_subspace = self
+			.subspace
+			.subspace(&keys::wake::SubWorkflowWakeKey::subspace(workflow_id));
+		let tags_subspace = self
+			.subspace
+			.subspace(&keys::workflow::TagKey::subspace(workflow_id));
+		let wake_deadline_key = keys::workflow::WakeDeadlineKey::new(workflow_id);
+
+		let mut stream = tx.get_ranges_keyvalues(
+			universaldb::RangeOption {
+				mode: StreamingMode::WantAll,
+				..(&sub_workflow_wake_subspace).into()
+			},
+			// NOTE: Must be Serializable to conflict with `get_sub_workflow`
+			Serializable,
+		);
+
+		let (wrote_to_wake_idx, tag_keys, wake_deadline_entry) = tokio::try_join!(
+			// Check for other workflows waiting on this one, wake all
+			async {
+				let mut wrote_to_wake_idx = false;
+
+				while let Some(entry) = stream.try_next().await? {
+					let sub_workflow_wake_key = self
+						.subspace
+						.unpack::<keys::wake::SubWorkflowWakeKey>(&entry.key())?;
+					let workflow_name = sub_workflow_wake_key.deserialize(entry.value())?;
+
+					let wake_condition_key = keys::wake::WorkflowWakeConditionKey::new(
+						workflow_name,
+						sub_workflow_wake_key.workflow_id,
+						keys::wake::WakeCondition::SubWorkflow {
+							sub_workflow_id: workflow_id,
+						},
+					);
+
+					// Add wake condition for workflow
+					tx.set(
+						&self.subspace.pack(&wake_condition_key),
+						&wake_condition_key.serialize(())?,
+					);
+
+					// Clear secondary index
+					tx.clear(entry.key());
+
+					wrote_to_wake_idx = true;
+				}
+
+				Ok(wrote_to_wake_idx)
+			},
+			// Read tags
+			tx.get_ranges_keyvalues(
+				universaldb::RangeOption {
+					mode: StreamingMode::WantAll,
+					..(&tags_subspace).into()
+				},
+				Serializable,
+			)
+			.map(|res| {
+				self.subspace
+					.unpack::<keys::workflow::TagKey>(res?.key())
+					.map_err(anyhow::Error::from)
+			})
+			.try_collect::<Vec<_>>(),
+			tx.get(&self.subspace.pack(&wake_deadline_key), Serializable),
+		)?;
+
+		for key in tag_keys {
+			let by_name_and_tag_key = keys::workflow::ByNameAndTagKey::new(
+				workflow_name.to_string(),
+				key.k,
+				key.v,
+				workflow_id,
+			);
+			tx.clear(&self.subspace.pack(&by_name_and_tag_key));
+		}
+
+		// Clear null key
+		{
+			let by_name_and_tag_key =
+				keys::workflow::ByNameAndTagKey::null(workflow_name.to_string(), workflow_id);
+			tx.clear(&self.subspace.pack(&by_name_and_tag_key));
+		}
+
+		// Get and clear the pending deadline wake condition, if any. This could be put in the
+		// `pull_workflows` function (where we clear secondary indexes) but we chose to clear it
+		// here and in `commit_workflow` because its not a secondary index so theres no worry of
+		// it inserting more wake conditions. This reduces the load on `pull_workflows`. The
+		// reason this isn't immediately cleared in `pull_workflows` along with the rest of the
+		// wake conditions is because it might be in the future.
+		if let Some(raw) = wake_deadline_entry {
+			let deadline_ts = wake_deadline_key.deserialize(&raw)?;
+
+			let wake_condition_key = keys::wake::WorkflowWakeConditionKey::new(
+				workflow_name.to_string(),
+				workflow_id,
+				keys::wake::WakeCondition::Deadline { deadline_ts },
+			);
+
+			tx.clear(&self.subspace.pack(&wake_condition_key));
+		}
+
+		// Clear "has wake condition"
+		let has_wake_condition_key = keys::workflow::HasWakeConditionKey::new(workflow_id);
+		tx.clear(&self.subspace.pack(&has_wake_condition_key));
+
+		// Write output
+		if let Some(output) = output {
+			let output_key = keys::workflow::OutputKey::new(workflow_id);
+
+			for (i, chunk) in output_key.split_ref(output)?.into_iter().enumerate() {
+				let chunk_key = output_key.chunk(i);
+
+				tx.set(&self.subspace.pack(&chunk_key), &chunk);
+			}
+		}
+
+		// Write error
+		if let Some(error) = error {
+			let error_key = keys::workflow::ErrorKey::new(workflow_id);
+			tx.set(
+				&self.subspace.pack(&error_key),
+				&error_key.serialize(error.to_string())?,
+			);
+		}
+
+		// Clear lease
+		let lease_key = keys::workflow::LeaseKey::new(workflow_id);
+		tx.clear(&self.subspace.pack(&lease_key));
+		let worker_instance_id_key = keys::workflow::WorkerInstanceIdKey::new(workflow_id);
+		tx.clear(&self.subspace.pack(&worker_instance_id_key));
+
+		// Write complete ts and add to retention index
+		let complete_ts = rivet_util::timestamp::now();
+		let complete_ts_key = keys::workflow::CompleteTsKey::new(workflow_id);
+		tx.set(
+			&self.subspace.pack(&complete_ts_key),
+			&complete_ts_key.serialize(complete_ts)?,
+		);
+		let retention_key =
+			keys::workflow::RetentionKey::new(workflow_name.to_string(), complete_ts, workflow_id);
+		tx.set(
+			&self.subspace.pack(&retention_key),
+			&retention_key.serialize(())?,
+		);
+
+		update_metric(
+			&tx.with_subspace(self.subspace.clone()),
+			Some(keys::metric::GaugeMetric::WorkflowActive(
+				workflow_name.to_string(),
+			)),
+			Some(keys::metric::GaugeMetric::WorkflowComplete(
+				workflow_name.to_string(),
+			)),
+		);
+
+		Ok(wrote_to_wake_idx)
+	}
 }
 
 #[async_trait::async_trait]
@@ -418,7 +597,7 @@ impl Database for DatabaseKv {
 	async fn from_pools(pools: rivet_pools::Pools) -> anyhow::Result<Arc<Self>> {
 		Ok(Arc::new(DatabaseKv {
 			pools,
-			subspace: universaldb::utils::Subspace::new(&(RIVET, GASOLINE, KV)),
+			subspace: keys::subspace(),
 		}))
 	}
 
@@ -751,6 +930,14 @@ impl Database for DatabaseKv {
 		Ok(())
 	}
 
+	async fn dispatch_due_schedules(&self, worker_instance_id: Id) -> WorkflowResult<()> {
+		self.dispatch_due_schedules_inner(worker_instance_id)
+			.await
+			.map_err(WorkflowError::Udb)?;
+
+		Ok(())
+	}
+
 	#[tracing::instrument(skip_all)]
 	async fn update_worker_ping(&self, worker_instance_id: Id) -> WorkflowResult<()> {
 		metrics::WORKER_LAST_PING.record(
@@ -784,7 +971,7 @@ impl Database for DatabaseKv {
 		Ok(())
 	}
 
-	#[tracing::instrument(skip_all, fields(%workflow_id, %workflow_name, unique))]
+	#[tracing::instrument(skip_all, fields(%workflow_id, %workflow_name, unique, priority))]
 	async fn dispatch_workflow(
 		&self,
 		ray_id: Id,
@@ -793,6 +980,7 @@ impl Database for DatabaseKv {
 		tags: Option<&serde_json::Value>,
 		input: &serde_json::value::RawValue,
 		unique: bool,
+		priority: i32,
 	) -> WorkflowResult<Id> {
 		let workflow_id = self
 			.pools
@@ -806,6 +994,7 @@ impl Database for DatabaseKv {
 					tags,
 					input,
 					unique,
+					priority,
 					&tx,
 				)
 				.await
@@ -839,6 +1028,8 @@ impl Database for DatabaseKv {
 								let output_subspace = self.subspace.subspace(&output_key);
 								let has_wake_condition_key =
 									keys::workflow::HasWakeConditionKey::new(workflow_id);
+								let complete_ts_key =
+									keys::workflow::CompleteTsKey::new(workflow_id);
 
 								// Read input and output
 								let (
@@ -846,6 +1037,7 @@ impl Database for DatabaseKv {
 									state_chunks,
 									output_chunks,
 									has_wake_condition_entry,
+									complete_ts_entry,
 								) = tokio::try_join!(
 									tx.get_ranges_keyvalues(
 										universaldb::RangeOption {
@@ -875,6 +1067,7 @@ impl Database for DatabaseKv {
 										&self.subspace.pack(&has_wake_condition_key),
 										Serializable
 									),
+									tx.get(&self.subspace.pack(&complete_ts_key), Serializable),
 								)?;
 
 								if input_chunks.is_empty() {
@@ -894,12 +1087,16 @@ impl Database for DatabaseKv {
 										Some(output_key.combine(output_chunks)?)
 									};
 
+									// Cancelled workflows stop with a complete ts but no output
+									let cancelled = output.is_none() && complete_ts_entry.is_some();
+
 									Ok(Some(WorkflowData {
 										workflow_id,
 										input,
 										state,
 										output,
 										has_wake_condition: has_wake_condition_entry.is_some(),
+										cancelled,
 									}))
 								}
 							}
@@ -950,6 +1147,7 @@ impl Database for DatabaseKv {
 		&self,
 		worker_instance_id: Id,
 		filter: &[&str],
+		limits: &PullLimits,
 	) -> WorkflowResult<Vec<PulledWorkflowData>> {
 		let start_instant = Instant::now();
 		let owned_filter = filter
@@ -957,7 +1155,7 @@ impl Database for DatabaseKv {
 			.map(|x| x.to_string())
 			.collect::<Vec<_>>();
 
-		let leased_workflows = self
+		let (leased_workflows, priorities) = self
 			.pools
 			.udb()
 			.map_err(WorkflowError::PoolsGeneric)?
@@ -971,7 +1169,7 @@ impl Database for DatabaseKv {
 					let pull_before = now + i64::try_from(self.worker_poll_interval().as_millis())?;
 
 					// Pull all available wake conditions from all registered wf names
-					let entries = futures_util::stream::iter(owned_filter)
+					let mut entries = futures_util::stream::iter(owned_filter)
 						.map(|wf_name| {
 							let wake_subspace_start = self
 								.subspace
@@ -1018,6 +1216,10 @@ impl Database for DatabaseKv {
 						.try_collect::<Vec<_>>()
 						.await?;
 
+					// Oldest wake conditions first so that workflows left over by the limits below are
+					// the most recently woken, regardless of name
+					entries.sort_by_key(|(_, key)| key.ts);
+
 					// Collect name and deadline ts for each wf id
 					let mut dedup_workflows: Vec<(Id, String, Option<i64>)> = Vec::new();
 					for (_, key) in &entries {
@@ -1044,55 +1246,117 @@ impl Database for DatabaseKv {
 						));
 					}
 
+					// Read priorities of all candidates. Must be a Snapshot, priorities never change after
+					// dispatch
+					let priorities = futures_util::stream::iter(
+						dedup_workflows
+							.iter()
+							.map(|(workflow_id, _, _)| *workflow_id),
+					)
+					.map(|workflow_id| {
+						let tx = tx.clone();
+						async move {
+							let priority_key = keys::workflow::PriorityKey::new(workflow_id);
+							let priority = if let Some(raw) =
+								tx.get(&self.subspace.pack(&priority_key), Snapshot).await?
+							{
+								priority_key.deserialize(&raw)?
+							} else {
+								0
+							};
+
+							anyhow::Ok((workflow_id, priority))
+						}
+					})
+					.buffer_unordered(1024)
+					.try_collect::<HashMap<_, _>>()
+					.instrument(tracing::trace_span!("read_priorities"))
+					.await?;
+
+					// Highest priority first. The sort is stable so workflows with the same priority stay in
+					// the order they were woken in
+					dedup_workflows.sort_by_key(|(workflow_id, _, _)| {
+						std::cmp::Reverse(priorities.get(workflow_id).copied().unwrap_or_default())
+					});
+
 					// Check leases
-					let leased_workflows = futures_util::stream::iter(dedup_workflows)
-						.map(|(workflow_id, workflow_name, wake_deadline_ts)| {
-							let tx = tx.clone();
-							async move {
-								let lease_key = keys::workflow::LeaseKey::new(workflow_id);
-								let lease_key_buf = self.subspace.pack(&lease_key);
+					let already_leased = futures_util::stream::iter(
+						dedup_workflows
+							.iter()
+							.map(|(workflow_id, _, _)| *workflow_id),
+					)
+					.map(|workflow_id| {
+						let tx = tx.clone();
+						async move {
+							let lease_key = keys::workflow::LeaseKey::new(workflow_id);
+							let is_leased = tx
+								.get(&self.subspace.pack(&lease_key), Serializable)
+								.await?
+								.is_some();
 
-								// Check lease
-								if tx.get(&lease_key_buf, Serializable).await?.is_some() {
-									Result::<_>::Ok(None)
-								} else {
-									// Write lease
-									tx.set(
-										&lease_key_buf,
-										&lease_key.serialize((
-											workflow_name.clone(),
-											worker_instance_id,
-										))?,
-									);
+							anyhow::Ok(is_leased.then_some(workflow_id))
+						}
+					})
+					// TODO: How to get rid of this buffer?
+					.buffer_unordered(1024)
+					.try_filter_map(|x| std::future::ready(Ok(x)))
+					.try_collect::<HashSet<_>>()
+					.instrument(tracing::trace_span!("check_leases"))
+					.await?;
 
-									// Write worker instance id
-									let worker_instance_id_key =
-										keys::workflow::WorkerInstanceIdKey::new(workflow_id);
-									tx.set(
-										&self.subspace.pack(&worker_instance_id_key),
-										&worker_instance_id_key.serialize(worker_instance_id)?,
-									);
+					// Only lease as many workflows as the worker has capacity for, the rest are left for
+					// other workers. Workflows leased by other workers don't count towards the limits.
+					let mut leased_workflows = Vec::new();
+					let mut name_counts = HashMap::<String, usize>::new();
+					for (workflow_id, workflow_name, wake_deadline_ts) in dedup_workflows {
+						if limits
+							.total
+							.is_some_and(|max| leased_workflows.len() >= max)
+						{
+							break;
+						}
 
-									update_metric(
-										&tx.with_subspace(self.subspace.clone()),
-										Some(keys::metric::GaugeMetric::WorkflowSleeping(
-											workflow_name.clone(),
-										)),
-										Some(keys::metric::GaugeMetric::WorkflowActive(
-											workflow_name.clone(),
-										)),
-									);
+						if already_leased.contains(&workflow_id) {
+							continue;
+						}
 
-									Ok(Some((workflow_id, workflow_name, wake_deadline_ts)))
-								}
-							}
-						})
-						// TODO: How to get rid of this buffer?
-						.buffer_unordered(1024)
-						.try_filter_map(|x| std::future::ready(Ok(x)))
-						.try_collect::<Vec<_>>()
-						.instrument(tracing::trace_span!("map_to_leased_workflows"))
-						.await?;
+						let count = name_counts.entry(workflow_name.clone()).or_default();
+						if limits
+							.per_name
+							.get(&workflow_name)
+							.is_some_and(|max| *count >= *max)
+						{
+							continue;
+						}
+						*count += 1;
+
+						// Write lease
+						let lease_key = keys::workflow::LeaseKey::new(workflow_id);
+						tx.set(
+							&self.subspace.pack(&lease_key),
+							&lease_key.serialize((workflow_name.clone(), worker_instance_id))?,
+						);
+
+						// Write worker instance id
+						let worker_instance_id_key =
+							keys::workflow::WorkerInstanceIdKey::new(workflow_id);
+						tx.set(
+							&self.subspace.pack(&worker_instance_id_key),
+							&worker_instance_id_key.serialize(worker_instance_id)?,
+						);
+
+						update_metric(
+							&tx.with_subspace(self.subspace.clone()),
+							Some(keys::metric::GaugeMetric::WorkflowSleeping(
+								workflow_name.clone(),
+							)),
+							Some(keys::metric::GaugeMetric::WorkflowActive(
+								workflow_name.clone(),
+							)),
+						);
+
+						leased_workflows.push((workflow_id, workflow_name, wake_deadline_ts));
+					}
 
 					for (raw_key, key) in &entries {
 						// Filter unleased entries
@@ -1135,7 +1399,7 @@ impl Database for DatabaseKv {
 
 					// NOTE: We don't read any workflow data in this txn since its only for acquiring leases.
 					// The less operations we do in this txn the less contention there is with other workers.
-					Ok(leased_workflows)
+					Ok((leased_workflows, priorities))
 				}
 			})
 			.custom_instrument(tracing::info_span!("pull_workflows_tx"))
@@ -1165,7 +1429,7 @@ impl Database for DatabaseKv {
 
 		let start_instant2 = Instant::now();
 
-		let pulled_workflows = self
+		let mut pulled_workflows = self
 			.pools
 			.udb()
 			.map_err(WorkflowError::PoolsGeneric)?
@@ -1182,6 +1446,7 @@ impl Database for DatabaseKv {
 								let ray_id_key = keys::workflow::RayIdKey::new(workflow_id);
 								let input_key = keys::workflow::InputKey::new(workflow_id);
 								let state_key = keys::workflow::StateKey::new(workflow_id);
+								let cancel_ts_key = keys::workflow::CancelTsKey::new(workflow_id);
 								let input_subspace = self.subspace.subspace(&input_key);
 								let state_subspace = self.subspace.subspace(&state_key);
 								let active_history_subspace = self.subspace.subspace(
@@ -1196,6 +1461,7 @@ impl Database for DatabaseKv {
 									ray_id_entry,
 									input_chunks,
 									state_chunks,
+									cancel_ts_entry,
 									events,
 								) = tokio::try_join!(
 									async {
@@ -1227,6 +1493,10 @@ impl Database for DatabaseKv {
 										.try_collect::<Vec<_>>()
 										.await
 									},
+									async {
+										tx.get(&self.subspace.pack(&cancel_ts_key), Serializable)
+											.await
+									},
 									async {
 										let mut events_by_location: HashMap<Location, Vec<Event>> =
 											HashMap::new();
@@ -1406,6 +1676,7 @@ impl Database for DatabaseKv {
 									input,
 									state,
 									wake_deadline_ts,
+									cancelled: cancel_ts_entry.is_some(),
 									events,
 								})
 							}
@@ -1421,6 +1692,11 @@ impl Database for DatabaseKv {
 			.await
 			.map_err(WorkflowError::Udb)?;
 
+		// Highest priority first so they are started first
+		pulled_workflows.sort_by_key(|wf| {
+			std::cmp::Reverse(priorities.get(&wf.workflow_id).copied().unwrap_or_default())
+		});
+
 		let dt2 = start_instant2.elapsed().as_secs_f64();
 		let dt = start_instant.elapsed().as_secs_f64();
 		metrics::LAST_PULL_WORKFLOWS_FULL_DURATION.record(
@@ -1455,159 +1731,211 @@ impl Database for DatabaseKv {
 		Ok(pulled_workflows)
 	}
 
-	#[tracing::instrument(skip_all)]
-	async fn complete_workflow(
+	async fn create_schedule(
 		&self,
-		workflow_id: Id,
+		schedule_id: Id,
 		workflow_name: &str,
-		output: &serde_json::value::RawValue,
-	) -> WorkflowResult<()> {
-		let start_instant = Instant::now();
-
-		let wrote_to_wake_idx = self
-			.pools
-			.udb()
-			.map_err(WorkflowError::PoolsGeneric)?
-			.run(|tx| {
-				async move {
-					let sub_workflow_wake_subspace = self
-						.subspace
-						.subspace(&keys::wake::SubWorkflowWakeKey::subspace(workflow_id));
-					let tags_subspace = self
-						.subspace
-						.subspace(&keys::workflow::TagKey::subspace(workflow_id));
-					let wake_deadline_key = keys::workflow::WakeDeadlineKey::new(workflow_id);
-
-					let mut stream = tx.get_ranges_keyvalues(
-						universaldb::RangeOption {
-							mode: StreamingMode::WantAll,
-							..(&sub_workflow_wake_subspace).into()
-						},
-						// NOTE: Must be Serializable to conflict with `get_sub_workflow`
-						Serializable,
-					);
+		cron: &str,
+		catch_up: CatchUp,
+		tags: Option<&serde_json::Value>,
+		input: &serde_json::value::RawValue,
+		unique: bool,
+	) -> WorkflowResult<Id> {
+		// Validate before writing anything
+		crate::schedule::parse_cron(cron)?;
+
+		self.create_schedule_inner(
+			schedule_id,
+			workflow_name,
+			cron,
+			catch_up,
+			tags,
+			input,
+			unique,
+		)
+		.await
+		.map_err(WorkflowError::Udb)
+	}
 
-					let (wrote_to_wake_idx, tag_keys, wake_deadline_entry) = tokio::try_join!(
-						// Check for other workflows waiting on this one, wake all
-						async {
-							let mut wrote_to_wake_idx = false;
+	async fn list_schedules(
+		&self,
+		workflow_name: Option<&str>,
+	) -> WorkflowResult<Vec<ScheduleData>> {
+		self.list_schedules_inner(workflow_name)
+			.await
+			.map_err(WorkflowError::Udb)
+	}
 
-							while let Some(entry) = stream.try_next().await? {
-								let sub_workflow_wake_key =
-									self.subspace
-										.unpack::<keys::wake::SubWorkflowWakeKey>(&entry.key())?;
-								let workflow_name =
-									sub_workflow_wake_key.deserialize(entry.value())?;
+	async fn delete_schedule(&self, schedule_id: Id) -> WorkflowResult<()> {
+		if self
+			.delete_schedule_inner(schedule_id)
+			.await
+			.map_err(WorkflowError::Udb)?
+		{
+			Ok(())
+		} else {
+			Err(WorkflowError::ScheduleNotFound)
+		}
+	}
 
-								let wake_condition_key = keys::wake::WorkflowWakeConditionKey::new(
-									workflow_name,
-									sub_workflow_wake_key.workflow_id,
-									keys::wake::WakeCondition::SubWorkflow {
-										sub_workflow_id: workflow_id,
-									},
-								);
+	#[tracing::instrument(skip_all, fields(%workflow_id))]
+	async fn cancel_workflow(&self, workflow_id: Id) -> WorkflowResult<()> {
+		let mut queue = vec![workflow_id];
+		let mut visited = HashSet::new();
 
-								// Add wake condition for workflow
-								tx.set(
-									&self.subspace.pack(&wake_condition_key),
-									&wake_condition_key.serialize(())?,
-								);
+		while let Some(workflow_id) = queue.pop() {
+			if !visited.insert(workflow_id) {
+				continue;
+			}
 
-								// Clear secondary index
-								tx.clear(entry.key());
+			let sub_workflow_ids = self
+				.pools
+				.udb()
+				.map_err(WorkflowError::PoolsGeneric)?
+				.run(|tx| async move {
+					let tx = tx.with_subspace(self.subspace.clone());
 
-								wrote_to_wake_idx = true;
-							}
+					let name_key = keys::workflow::NameKey::new(workflow_id);
+					let worker_instance_id_key =
+						keys::workflow::WorkerInstanceIdKey::new(workflow_id);
+					let has_wake_condition_key =
+						keys::workflow::HasWakeConditionKey::new(workflow_id);
+					let silence_ts_key = keys::workflow::SilenceTsKey::new(workflow_id);
+					let cancel_ts_key = keys::workflow::CancelTsKey::new(workflow_id);
+					let error_key = keys::workflow::ErrorKey::new(workflow_id);
+					let output_subspace = self
+						.subspace
+						.subspace(&keys::workflow::OutputKey::new(workflow_id));
+					let history_subspace =
+						self.subspace
+							.subspace(&keys::history::HistorySubspaceKey::new(
+								workflow_id,
+								keys::history::HistorySubspaceVariant::All,
+							));
 
-							Ok(wrote_to_wake_idx)
+					let (
+						workflow_name,
+						is_running,
+						has_wake_condition,
+						is_silenced,
+						is_cancelled,
+						has_output,
+						error,
+					) = tokio::try_join!(
+						tx.read_opt(&name_key, Serializable),
+						tx.exists(&worker_instance_id_key, Serializable),
+						tx.exists(&has_wake_condition_key, Serializable),
+						tx.exists(&silence_ts_key, Serializable),
+						tx.exists(&cancel_ts_key, Serializable),
+						async {
+							tx.get_ranges_keyvalues(
+								universaldb::RangeOption {
+									mode: StreamingMode::WantAll,
+									limit: Some(1),
+									..(&output_subspace).into()
+								},
+								Serializable,
+							)
+							.try_next()
+							.await
+							.map(|x| x.is_some())
 						},
-						// Read tags
-						tx.get_ranges_keyvalues(
-							universaldb::RangeOption {
-								mode: StreamingMode::WantAll,
-								..(&tags_subspace).into()
-							},
-							Serializable,
-						)
-						.map(|res| {
-							self.subspace
-								.unpack::<keys::workflow::TagKey>(res?.key())
-								.map_err(anyhow::Error::from)
-						})
-						.try_collect::<Vec<_>>(),
-						tx.get(&self.subspace.pack(&wake_deadline_key), Serializable),
+						tx.read_opt(&error_key, Serializable),
 					)?;
 
-					for key in tag_keys {
-						let by_name_and_tag_key = keys::workflow::ByNameAndTagKey::new(
-							workflow_name.to_string(),
-							key.k,
-							key.v,
-							workflow_id,
-						);
-						tx.clear(&self.subspace.pack(&by_name_and_tag_key));
-					}
+					let Some(workflow_name) = workflow_name else {
+						return Ok(None);
+					};
 
-					// Clear null key
-					{
-						let by_name_and_tag_key = keys::workflow::ByNameAndTagKey::null(
-							workflow_name.to_string(),
-							workflow_id,
-						);
-						tx.clear(&self.subspace.pack(&by_name_and_tag_key));
+					// Sub workflows were already cancelled along with the workflow
+					if is_silenced || is_cancelled || has_output {
+						return Ok(Some(Vec::new()));
 					}
 
-					// Get and clear the pending deadline wake condition, if any. This could be put in the
-					// `pull_workflows` function (where we clear secondary indexes) but we chose to clear it
-					// here and in `commit_workflow` because its not a secondary index so theres no worry of
-					// it inserting more wake conditions. This reduces the load on `pull_workflows`. The
-					// reason this isn't immediately cleared in `pull_workflows` along with the rest of the
-					// wake conditions is because it might be in the future.
-					if let Some(raw) = wake_deadline_entry {
-						let deadline_ts = wake_deadline_key.deserialize(&raw)?;
+					tx.write(&cancel_ts_key, rivet_util::timestamp::now())?;
 
-						let wake_condition_key = keys::wake::WorkflowWakeConditionKey::new(
-							workflow_name.to_string(),
+					// Wake the workflow so the cancellation surfaces. Running workflows are pulled again
+					// right after they commit.
+					tx.write(
+						&keys::wake::WorkflowWakeConditionKey::new(
+							workflow_name.clone(),
 							workflow_id,
-							keys::wake::WakeCondition::Deadline { deadline_ts },
-						);
+							keys::wake::WakeCondition::Immediate,
+						),
+						(),
+					)?;
 
-						tx.clear(&self.subspace.pack(&wake_condition_key));
+					// Dead workflows are woken too, cancellation surfaces before the step that failed.
+					// Workflows that were never pulled yet have no error.
+					if !is_running
+						&& !has_wake_condition
+						&& let Some(error) = error
+					{
+						tx.write(&has_wake_condition_key, ())?;
+
+						update_metric(
+							&tx,
+							Some(keys::metric::GaugeMetric::WorkflowDead(
+								workflow_name.clone(),
+								error,
+							)),
+							Some(keys::metric::GaugeMetric::WorkflowSleeping(workflow_name)),
+						);
 					}
 
-					// Clear "has wake condition"
-					let has_wake_condition_key =
-						keys::workflow::HasWakeConditionKey::new(workflow_id);
-					tx.clear(&self.subspace.pack(&has_wake_condition_key));
+					// Find sub workflows dispatched by this workflow, including forgotten history
+					let mut stream = tx.get_ranges_keyvalues(
+						universaldb::RangeOption {
+							mode: StreamingMode::WantAll,
+							..(&history_subspace).into()
+						},
+						Snapshot,
+					);
 
-					// Write output
-					let output_key = keys::workflow::OutputKey::new(workflow_id);
+					let mut sub_workflow_ids = Vec::new();
+					while let Some(entry) = stream.try_next().await? {
+						if let Ok(key) = self
+							.subspace
+							.unpack::<keys::history::SubWorkflowIdKey>(entry.key())
+						{
+							sub_workflow_ids.push(key.deserialize(entry.value())?);
+						}
+					}
 
-					for (i, chunk) in output_key.split_ref(output)?.into_iter().enumerate() {
-						let chunk_key = output_key.chunk(i);
+					Ok(Some(sub_workflow_ids))
+				})
+				.custom_instrument(tracing::info_span!("cancel_workflow_tx"))
+				.await
+				.map_err(WorkflowError::Udb)?;
 
-						tx.set(&self.subspace.pack(&chunk_key), &chunk);
-					}
+			match sub_workflow_ids {
+				Some(sub_workflow_ids) => queue.extend(sub_workflow_ids),
+				None if visited.len() == 1 => return Err(WorkflowError::WorkflowNotFound),
+				None => tracing::warn!(?workflow_id, "sub workflow not found"),
+			}
+		}
 
-					// Clear lease
-					let lease_key = keys::workflow::LeaseKey::new(workflow_id);
-					tx.clear(&self.subspace.pack(&lease_key));
-					let worker_instance_id_key =
-						keys::workflow::WorkerInstanceIdKey::new(workflow_id);
-					tx.clear(&self.subspace.pack(&worker_instance_id_key));
+		self.wake_worker();
 
-					update_metric(
-						&tx.with_subspace(self.subspace.clone()),
-						Some(keys::metric::GaugeMetric::WorkflowActive(
-							workflow_name.to_string(),
-						)),
-						Some(keys::metric::GaugeMetric::WorkflowComplete(
-							workflow_name.to_string(),
-						)),
-					);
+		Ok(())
+	}
 
-					Ok(wrote_to_wake_idx)
-				}
+	#[tracing::instrument(skip_all)]
+	async fn complete_workflow(
+		&self,
+		workflow_id: Id,
+		workflow_name: &str,
+		output: &serde_json::value::RawValue,
+	) -> WorkflowResult<()> {
+		let start_instant = Instant::now();
+
+		let wrote_to_wake_idx = self
+			.pools
+			.udb()
+			.map_err(WorkflowError::PoolsGeneric)?
+			.run(|tx| async move {
+				self.complete_workflow_inner(workflow_id, workflow_name, Some(output), None, &tx)
+					.await
 			})
 			.custom_instrument(tracing::info_span!("complete_workflows_tx"))
 			.await
@@ -1627,6 +1955,41 @@ impl Database for DatabaseKv {
 		Ok(())
 	}
 
+	#[tracing::instrument(skip_all)]
+	async fn complete_cancelled_workflow(
+		&self,
+		workflow_id: Id,
+		workflow_name: &str,
+		error: &str,
+	) -> WorkflowResult<()> {
+		let start_instant = Instant::now();
+
+		let wrote_to_wake_idx = self
+			.pools
+			.udb()
+			.map_err(WorkflowError::PoolsGeneric)?
+			.run(|tx| async move {
+				self.complete_workflow_inner(workflow_id, workflow_name, None, Some(error), &tx)
+					.await
+			})
+			.custom_instrument(tracing::info_span!("complete_cancelled_workflow_tx"))
+			.await
+			.map_err(WorkflowError::Udb)?;
+
+		// Wake worker again in case some other workflow was waiting for this one to complete
+		if wrote_to_wake_idx {
+			self.wake_worker();
+		}
+
+		let dt = start_instant.elapsed().as_secs_f64();
+		metrics::COMPLETE_WORKFLOW_DURATION.record(
+			dt,
+			&[KeyValue::new("workflow_name", workflow_name.to_string())],
+		);
+
+		Ok(())
+	}
+
 	#[tracing::instrument(skip_all)]
 	async fn commit_workflow(
 		&self,
@@ -1996,9 +2359,16 @@ impl Database for DatabaseKv {
 					let output_subspace = self.subspace.subspace(&output_key);
 					let has_wake_condition_key =
 						keys::workflow::HasWakeConditionKey::new(sub_workflow_id);
+					let complete_ts_key = keys::workflow::CompleteTsKey::new(sub_workflow_id);
 
 					// Read input and output
-					let (input_chunks, state_chunks, output_chunks, has_wake_condition_entry) = tokio::try_join!(
+					let (
+						input_chunks,
+						state_chunks,
+						output_chunks,
+						has_wake_condition_entry,
+						complete_ts_entry,
+					) = tokio::try_join!(
 						tx.get_ranges_keyvalues(
 							universaldb::RangeOption {
 								mode: StreamingMode::WantAll,
@@ -2024,6 +2394,8 @@ impl Database for DatabaseKv {
 						)
 						.try_collect::<Vec<_>>(),
 						tx.get(&self.subspace.pack(&has_wake_condition_key), Serializable),
+						// NOTE: Must be Serializable to conflict with `complete_cancelled_workflow`
+						tx.get(&self.subspace.pack(&complete_ts_key), Serializable),
 					)?;
 
 					if input_chunks.is_empty() {
@@ -2037,7 +2409,12 @@ impl Database for DatabaseKv {
 							state_key.combine(state_chunks)?
 						};
 
-						let output = if output_chunks.is_empty() {
+						// Cancelled workflows stop with a complete ts but no output
+						let cancelled = output_chunks.is_empty() && complete_ts_entry.is_some();
+
+						let output = if cancelled {
+							None
+						} else if output_chunks.is_empty() {
 							// Write sub workflow wake index if the sub workflow is not complete yet. Normally
 							// this is done in `commit_workflow` but without this code there would be a race
 							// condition if the sub workflow completes between after this transaction and
@@ -2064,6 +2441,7 @@ impl Database for DatabaseKv {
 							state,
 							output,
 							has_wake_condition: has_wake_condition_entry.is_some(),
+							cancelled,
 						}))
 					}
 				}
@@ -2150,7 +2528,7 @@ impl Database for DatabaseKv {
 		Ok(())
 	}
 
-	#[tracing::instrument(skip_all, fields(%sub_workflow_id, %sub_workflow_name, unique))]
+	#[tracing::instrument(skip_all, fields(%sub_workflow_id, %sub_workflow_name, unique, priority))]
 	async fn dispatch_sub_workflow(
 		&self,
 		ray_id: Id,
@@ -2163,6 +2541,7 @@ impl Database for DatabaseKv {
 		input: &serde_json::value::RawValue,
 		_loop_location: Option<&Location>,
 		unique: bool,
+		priority: i32,
 	) -> WorkflowResult<Id> {
 		let sub_workflow_id = self
 			.pools
@@ -2177,6 +2556,7 @@ impl Database for DatabaseKv {
 						tags,
 						input,
 						unique,
+						priority,
 						&tx,
 					)
 					.await?;
@@ -0,0 +1,665 @@
+//! Purging and archival of workflows that completed or were silenced.
+
+use std::{
+	io::Write,
+	path::Path,
+	result::Result::{Err, Ok},
+};
+
+use anyhow::{Context, Result};
+use futures_util::{StreamExt, TryStreamExt};
+use rivet_util::Id;
+use universaldb::utils::{FormalKey, IsolationLevel::*, Subspace};
+use universaldb::{RangeOption, options::StreamingMode};
+
+use super::{DatabaseKv, keys, update_metric};
+use crate::db::debug::{ArchiveEntry, WorkflowArchive};
+
+/// Max amount of retention index entries read per transaction.
+const EXPIRED_BATCH_SIZE: usize = 100;
+/// Max amount of workflows checked per transaction when backfilling the retention index.
+const BACKFILL_BATCH_SIZE: usize = 100;
+
+/// Snapshot of a purgeable workflow.
+struct PurgeTarget {
+	workflow_name: String,
+	complete_ts: Option<i64>,
+	silence_ts: Option<i64>,
+	tag_keys: Vec<keys::workflow::TagKey>,
+	pending_signal_keys: Vec<keys::workflow::PendingSignalKey>,
+	signal_ids: Vec<Id>,
+	/// Raw keys and values of the workflow and its signals.
+	entries: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+pub(super) enum PurgeResult {
+	Purged,
+	/// The workflow is still running or sleeping, or changed while being purged.
+	Skipped,
+	NotFound,
+}
+
+impl DatabaseKv {
+	/// Deletes a workflow that completed or was silenced along with the signals it received. The
+	/// workflow is written to `{archive_path}/{workflow_name}/{workflow_id}.json.lz4` first if an
+	/// archive path is given.
+	#[tracing::instrument(skip_all, fields(%workflow_id))]
+	pub(super) async fn purge_workflow(
+		&self,
+		workflow_id: Id,
+		archive_path: Option<&Path>,
+	) -> Result<PurgeResult> {
+		let target = match self.read_purge_target(workflow_id).await? {
+			Ok(target) => target,
+			Err(res) => return Ok(res),
+		};
+
+		if let Some(archive_path) = archive_path {
+			self.write_archive(workflow_id, &target, archive_path)
+				.await?;
+		}
+
+		let target = &target;
+		let purged = self
+			.pools
+			.udb()?
+			.run(|tx| async move {
+				let pending_signals_subspace =
+					self.subspace
+						.subspace(&keys::workflow::PendingSignalKey::entire_subspace(
+							workflow_id,
+						));
+				let lease_key = keys::workflow::LeaseKey::new(workflow_id);
+				let worker_instance_id_key = keys::workflow::WorkerInstanceIdKey::new(workflow_id);
+				let silence_ts_key = keys::workflow::SilenceTsKey::new(workflow_id);
+
+				// Make sure nothing changed since the target was read. Publishing a signal or waking
+				// the workflow conflicts with these reads.
+				let (pending_signal_keys, lease_entry, worker_instance_id_entry, silence_ts_entry) =
+					tokio::try_join!(
+						tx.get_ranges_keyvalues(
+							RangeOption {
+								mode: StreamingMode::WantAll,
+								..(&pending_signals_subspace).into()
+							},
+							Serializable,
+						)
+						.map_ok(|entry| entry.key().to_vec())
+						.try_collect::<Vec<_>>(),
+						tx.get(&self.subspace.pack(&lease_key), Serializable),
+						tx.get(&self.subspace.pack(&worker_instance_id_key), Serializable),
+						tx.get(&self.subspace.pack(&silence_ts_key), Serializable),
+					)?;
+
+				if pending_signal_keys.len() != target.pending_signal_keys.len()
+					|| lease_entry.is_some()
+					|| worker_instance_id_entry.is_some()
+					|| silence_ts_entry.is_some() != target.silence_ts.is_some()
+				{
+					return Ok(false);
+				}
+
+				for key in &target.tag_keys {
+					let by_name_and_tag_key = keys::workflow::ByNameAndTagKey::new(
+						target.workflow_name.clone(),
+						key.k.clone(),
+						key.v.clone(),
+						workflow_id,
+					);
+					tx.clear(&self.subspace.pack(&by_name_and_tag_key));
+				}
+
+				// Clear null key
+				{
+					let by_name_and_tag_key = keys::workflow::ByNameAndTagKey::null(
+						target.workflow_name.clone(),
+						workflow_id,
+					);
+					tx.clear(&self.subspace.pack(&by_name_and_tag_key));
+				}
+
+				// Clear retention index
+				for ts in target.complete_ts.iter().chain(target.silence_ts.iter()) {
+					let retention_key = keys::workflow::RetentionKey::new(
+						target.workflow_name.clone(),
+						*ts,
+						workflow_id,
+					);
+					tx.clear(&self.subspace.pack(&retention_key));
+				}
+
+				// Clear pending signals and their wake conditions
+				for key in &target.pending_signal_keys {
+					let mut wake_condition_key = keys::wake::WorkflowWakeConditionKey::new(
+						target.workflow_name.clone(),
+						workflow_id,
+						keys::wake::WakeCondition::Signal {
+							signal_id: key.signal_id,
+						},
+					);
+					wake_condition_key.ts = key.ts;
+					tx.clear(&self.subspace.pack(&wake_condition_key));
+
+					update_metric(
+						&tx.with_subspace(self.subspace.clone()),
+						Some(keys::metric::GaugeMetric::SignalPending(
+							key.signal_name.clone(),
+						)),
+						None,
+					);
+				}
+				tx.clear_subspace_range(&pending_signals_subspace);
+
+				for signal_id in &target.signal_ids {
+					tx.clear_subspace_range(
+						&self
+							.subspace
+							.subspace(&keys::signal::EntireSubspaceKey::new(*signal_id)),
+					);
+				}
+
+				// Clear workflows waiting on this one, if any
+				tx.clear_subspace_range(
+					&self
+						.subspace
+						.subspace(&keys::wake::SubWorkflowWakeKey::subspace(workflow_id)),
+				);
+
+				tx.clear_subspace_range(
+					&self
+						.subspace
+						.subspace(&keys::workflow::EntireSubspaceKey::new(workflow_id)),
+				);
+
+				// Silenced workflows already had their metric cleared
+				if target.silence_ts.is_none() {
+					update_metric(
+						&tx.with_subspace(self.subspace.clone()),
+						Some(keys::metric::GaugeMetric::WorkflowComplete(
+							target.workflow_name.clone(),
+						)),
+						None,
+					);
+				}
+
+				Ok(true)
+			})
+			.await?;
+
+		if purged {
+			Ok(PurgeResult::Purged)
+		} else {
+			Ok(PurgeResult::Skipped)
+		}
+	}
+
+	/// Purges all workflows with the given name that completed or were silenced before `before_ts`.
+	/// Returns the amount of purged workflows.
+	#[tracing::instrument(skip_all, fields(%workflow_name))]
+	pub(super) async fn purge_expired_workflows_inner(
+		&self,
+		workflow_name: &str,
+		before_ts: i64,
+		archive_path: Option<&Path>,
+	) -> Result<usize> {
+		let backfilled = self.backfill_retention_index(workflow_name).await?;
+		if backfilled != 0 {
+			tracing::debug!(?backfilled, "backfilled retention index");
+		}
+
+		let retention_subspace = self
+			.subspace
+			.subspace(&keys::workflow::RetentionKey::subspace(
+				workflow_name.to_string(),
+			));
+		let (mut cursor, end) = retention_subspace.range();
+		let mut purged = 0;
+
+		loop {
+			let batch = self
+				.pools
+				.udb()?
+				.run(|tx| {
+					let (begin, end) = (cursor.clone(), end.clone());
+
+					async move {
+						tx.get_ranges_keyvalues(
+							RangeOption {
+								mode: StreamingMode::WantAll,
+								limit: Some(EXPIRED_BATCH_SIZE),
+								..(begin, end).into()
+							},
+							Snapshot,
+						)
+						.map(|res| {
+							let entry = res?;
+							let key = self
+								.subspace
+								.unpack::<keys::workflow::RetentionKey>(entry.key())?;
+
+							Ok((entry.key().to_vec(), key))
+						})
+						.try_collect::<Vec<_>>()
+						.await
+					}
+				})
+				.await?;
+
+			let batch_len = batch.len();
+			for (raw_key, key) in batch {
+				if key.ts >= before_ts {
+					return Ok(purged);
+				}
+
+				match self.purge_workflow(key.workflow_id, archive_path).await? {
+					PurgeResult::Purged => purged += 1,
+					PurgeResult::Skipped => {}
+					PurgeResult::NotFound => {
+						// Dangling index entry
+						let raw_key = &raw_key;
+						self.pools
+							.udb()?
+							.run(|tx| async move {
+								tx.clear(raw_key);
+								Ok(())
+							})
+							.await?;
+					}
+				}
+
+				// Continue after the last key, entries of skipped workflows stay in the index
+				cursor = [raw_key.as_slice(), &[0]].concat();
+			}
+
+			if batch_len < EXPIRED_BATCH_SIZE {
+				return Ok(purged);
+			}
+		}
+	}
+
+	/// Adds workflows that completed or were silenced before the retention index existed to the
+	/// index. Workflows without a complete ts are treated as completing now so they are kept for
+	/// the entire retention period. Only runs once per workflow name, workflows completed since then
+	/// are added to the index when they complete. Returns the amount of backfilled workflows.
+	async fn backfill_retention_index(&self, workflow_name: &str) -> Result<usize> {
+		let backfilled_key = keys::workflow::RetentionBackfilledKey::new(workflow_name.to_string());
+
+		let already_backfilled = self
+			.pools
+			.udb()?
+			.run(|tx| {
+				let backfilled_key = &backfilled_key;
+				async move {
+					tx.with_subspace(self.subspace.clone())
+						.exists(backfilled_key, Snapshot)
+						.await
+				}
+			})
+			.await?;
+		if already_backfilled {
+			return Ok(0);
+		}
+
+		let by_name_subspace =
+			self.subspace
+				.subspace(&keys::workflow::ByNameAndTagKey::null_subspace(
+					workflow_name.to_string(),
+				));
+		let (mut cursor, end) = by_name_subspace.range();
+		let mut backfilled = 0;
+
+		loop {
+			let (batch_len, batch_backfilled, last_key) = self
+				.pools
+				.udb()?
+				.run(|tx| {
+					let (begin, end) = (cursor.clone(), end.clone());
+
+					async move {
+						let workflow_keys = tx
+							.get_ranges_keyvalues(
+								RangeOption {
+									mode: StreamingMode::WantAll,
+									limit: Some(BACKFILL_BATCH_SIZE),
+									..(begin, end).into()
+								},
+								Snapshot,
+							)
+							.map(|res| {
+								let entry = res?;
+								let key = self
+									.subspace
+									.unpack::<keys::workflow::ByNameAndTagKey>(entry.key())?;
+
+								Ok((entry.key().to_vec(), key.workflow_id))
+							})
+							.try_collect::<Vec<_>>()
+							.await?;
+
+						let tx = tx.with_subspace(self.subspace.clone());
+						let mut batch_backfilled = 0;
+
+						for (_, workflow_id) in &workflow_keys {
+							let workflow_id = *workflow_id;
+							let complete_ts_key = keys::workflow::CompleteTsKey::new(workflow_id);
+							let silence_ts_key = keys::workflow::SilenceTsKey::new(workflow_id);
+							let output_subspace = self
+								.subspace
+								.subspace(&keys::workflow::OutputKey::new(workflow_id));
+
+							let (complete_ts, silence_ts, has_output) = tokio::try_join!(
+								tx.read_opt(&complete_ts_key, Serializable),
+								tx.read_opt(&silence_ts_key, Serializable),
+								async {
+									tx.get_ranges_keyvalues(
+										RangeOption {
+											mode: StreamingMode::WantAll,
+											limit: Some(1),
+											..(&output_subspace).into()
+										},
+										Serializable,
+									)
+									.try_next()
+									.await
+									.map_err(Into::into)
+									.map(|x| x.is_some())
+								},
+							)?;
+
+							let complete_ts = match complete_ts {
+								Some(complete_ts) => Some(complete_ts),
+								None if has_output => {
+									let now = rivet_util::timestamp::now();
+									tx.write(&complete_ts_key, now)?;
+
+									Some(now)
+								}
+								None => None,
+							};
+
+							let mut missing = false;
+							for ts in complete_ts.iter().chain(silence_ts.iter()) {
+								let retention_key = keys::workflow::RetentionKey::new(
+									workflow_name.to_string(),
+									*ts,
+									workflow_id,
+								);
+
+								if !tx.exists(&retention_key, Serializable).await? {
+									tx.write(&retention_key, ())?;
+									missing = true;
+								}
+							}
+
+							if missing {
+								batch_backfilled += 1;
+							}
+						}
+
+						Ok((
+							workflow_keys.len(),
+							batch_backfilled,
+							workflow_keys.last().map(|(key, _)| key.clone()),
+						))
+					}
+				})
+				.await?;
+
+			backfilled += batch_backfilled;
+
+			if let Some(last_key) = last_key {
+				cursor = [last_key.as_slice(), &[0]].concat();
+			}
+
+			if batch_len < BACKFILL_BATCH_SIZE {
+				break;
+			}
+		}
+
+		self.pools
+			.udb()?
+			.run(|tx| {
+				let backfilled_key = &backfilled_key;
+				async move {
+					tx.with_subspace(self.subspace.clone())
+						.write(backfilled_key, rivet_util::timestamp::now())
+				}
+			})
+			.await?;
+
+		Ok(backfilled)
+	}
+
+	/// Returns the result of the purge early if the workflow cannot be purged.
+	async fn read_purge_target(
+		&self,
+		workflow_id: Id,
+	) -> Result<std::result::Result<PurgeTarget, PurgeResult>> {
+		self.pools
+			.udb()?
+			.run(|tx| async move {
+				let name_key = keys::workflow::NameKey::new(workflow_id);
+				let lease_key = keys::workflow::LeaseKey::new(workflow_id);
+				let workflow_subspace = self
+					.subspace
+					.subspace(&keys::workflow::EntireSubspaceKey::new(workflow_id));
+				let pending_signals_subspace =
+					self.subspace
+						.subspace(&keys::workflow::PendingSignalKey::entire_subspace(
+							workflow_id,
+						));
+
+				let (name_entry, lease_entry, workflow_entries, pending_signal_entries) = tokio::try_join!(
+					tx.get(&self.subspace.pack(&name_key), Serializable),
+					tx.get(&self.subspace.pack(&lease_key), Serializable),
+					read_entries(&tx, &workflow_subspace),
+					read_entries(&tx, &pending_signals_subspace),
+				)?;
+
+				let Some(name_entry) = name_entry else {
+					return Ok(Err(PurgeResult::NotFound));
+				};
+				let workflow_name = name_key.deserialize(&name_entry)?;
+
+				let worker_instance_id_key = self
+					.subspace
+					.pack(&keys::workflow::WorkerInstanceIdKey::new(workflow_id));
+				let complete_ts_key = keys::workflow::CompleteTsKey::new(workflow_id);
+				let packed_complete_ts_key = self.subspace.pack(&complete_ts_key);
+				let silence_ts_key = keys::workflow::SilenceTsKey::new(workflow_id);
+				let packed_silence_ts_key = self.subspace.pack(&silence_ts_key);
+				let output_subspace = self
+					.subspace
+					.subspace(&keys::workflow::OutputKey::new(workflow_id));
+				let tags_subspace = self
+					.subspace
+					.subspace(&keys::workflow::TagKey::subspace(workflow_id));
+
+				let mut is_running = lease_entry.is_some();
+				let mut has_output = false;
+				let mut complete_ts = None;
+				let mut silence_ts = None;
+				let mut tag_keys = Vec::new();
+				let mut candidate_signal_ids = Vec::new();
+
+				for (key, value) in &workflow_entries {
+					if key == &worker_instance_id_key {
+						is_running = true;
+					} else if key == &packed_complete_ts_key {
+						complete_ts = Some(complete_ts_key.deserialize(value)?);
+					} else if key == &packed_silence_ts_key {
+						silence_ts = Some(silence_ts_key.deserialize(value)?);
+					} else if output_subspace.is_start_of(key) {
+						has_output = true;
+					} else if tags_subspace.is_start_of(key) {
+						tag_keys.push(self.subspace.unpack::<keys::workflow::TagKey>(key)?);
+					} else if let Ok(signal_id_key) =
+						self.subspace.unpack::<keys::history::SignalIdKey>(key)
+					{
+						// Sent and received signals both have a signal id event, the signal's
+						// workflow id tells them apart
+						candidate_signal_ids.push(signal_id_key.deserialize(value)?);
+					}
+				}
+
+				// Cancelled workflows have a complete ts without an output
+				if is_running || (!has_output && complete_ts.is_none() && silence_ts.is_none()) {
+					return Ok(Err(PurgeResult::Skipped));
+				}
+
+				// Workflows completed before the complete ts was recorded have none, use any value so
+				// they are still purgeable
+				if has_output && complete_ts.is_none() && silence_ts.is_none() {
+					complete_ts = Some(0);
+				}
+
+				let pending_signal_keys = pending_signal_entries
+					.iter()
+					.map(|(key, _)| {
+						self.subspace
+							.unpack::<keys::workflow::PendingSignalKey>(key)
+					})
+					.collect::<std::result::Result<Vec<_>, _>>()?;
+
+				let signal_ids = self
+					.received_signal_ids(
+						&tx,
+						workflow_id,
+						&pending_signal_keys,
+						candidate_signal_ids,
+					)
+					.await?;
+
+				let mut entries = workflow_entries;
+				entries.extend(pending_signal_entries);
+				entries.extend(self.read_signal_entries(&tx, &signal_ids).await?);
+
+				Ok(Ok(PurgeTarget {
+					workflow_name,
+					complete_ts,
+					silence_ts,
+					tag_keys,
+					pending_signal_keys,
+					signal_ids,
+					entries,
+				}))
+			})
+			.await
+	}
+
+	async fn write_archive(
+		&self,
+		workflow_id: Id,
+		target: &PurgeTarget,
+		archive_path: &Path,
+	) -> Result<()> {
+		let archive = self.to_archive(workflow_id, &target.workflow_name, &target.entries);
+
+		let mut encoder = lz4_flex::frame::FrameEncoder::new(Vec::new());
+		serde_json::to_writer(&mut encoder, &archive)?;
+		encoder.flush()?;
+		let buf = encoder.finish()?;
+
+		let dir = archive_path.join(&target.workflow_name);
+		tokio::fs::create_dir_all(&dir)
+			.await
+			.with_context(|| format!("failed to create archive dir {}", dir.display()))?;
+
+		// Write to a temporary file first so a crash never leaves a partial archive behind
+		let path = dir.join(format!("{workflow_id}.json.lz4"));
+		let tmp_path = dir.join(format!("{workflow_id}.json.lz4.tmp"));
+		tokio::fs::write(&tmp_path, buf).await?;
+		tokio::fs::rename(&tmp_path, &path).await?;
+
+		tracing::debug!(path=%path.display(), "archived workflow");
+
+		Ok(())
+	}
+
+	/// Ids of the pending signals and of the signals in history that were sent to this workflow.
+	pub(super) async fn received_signal_ids(
+		&self,
+		tx: &universaldb::RetryableTransaction,
+		workflow_id: Id,
+		pending_signal_keys: &[keys::workflow::PendingSignalKey],
+		history_signal_ids: Vec<Id>,
+	) -> Result<Vec<Id>> {
+		let mut signal_ids = pending_signal_keys
+			.iter()
+			.map(|key| key.signal_id)
+			.collect::<Vec<_>>();
+
+		for signal_id in history_signal_ids {
+			let workflow_id_key = keys::signal::WorkflowIdKey::new(signal_id);
+			let Some(entry) = tx
+				.get(&self.subspace.pack(&workflow_id_key), Snapshot)
+				.await?
+			else {
+				continue;
+			};
+
+			if workflow_id_key.deserialize(&entry)? == workflow_id
+				&& !signal_ids.contains(&signal_id)
+			{
+				signal_ids.push(signal_id);
+			}
+		}
+
+		Ok(signal_ids)
+	}
+
+	pub(super) async fn read_signal_entries(
+		&self,
+		tx: &universaldb::RetryableTransaction,
+		signal_ids: &[Id],
+	) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+		let mut entries = Vec::new();
+
+		for signal_id in signal_ids {
+			let signal_subspace = self
+				.subspace
+				.subspace(&keys::signal::EntireSubspaceKey::new(*signal_id));
+			entries.extend(read_entries(tx, &signal_subspace).await?);
+		}
+
+		Ok(entries)
+	}
+
+	pub(super) fn to_archive(
+		&self,
+		workflow_id: Id,
+		workflow_name: &str,
+		entries: &[(Vec<u8>, Vec<u8>)],
+	) -> WorkflowArchive {
+		let prefix_len = self.subspace.bytes().len();
+
+		WorkflowArchive {
+			workflow_id,
+			workflow_name: workflow_name.to_string(),
+			entries: entries
+				.iter()
+				.map(|(key, value)| ArchiveEntry {
+					key: hex::encode(&key[prefix_len..]),
+					value: hex::encode(value),
+				})
+				.collect(),
+		}
+	}
+}
+
+pub(super) async fn read_entries(
+	tx: &universaldb::RetryableTransaction,
+	subspace: &Subspace,
+) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+	tx.get_ranges_keyvalues(
+		RangeOption {
+			mode: StreamingMode::WantAll,
+			..subspace.into()
+		},
+		Snapshot,
+	)
+	.map_ok(|entry| (entry.key().to_vec(), entry.value().to_vec()))
+	.try_collect::<Vec<_>>()
+	.await
+	.map_err(Into::into)
+}
@@ -0,0 +1,370 @@
+//! Durable cron schedules that dispatch workflows.
+
+use std::result::Result::Ok;
+
+use anyhow::Result;
+use futures_util::{StreamExt, TryStreamExt};
+use rivet_util::Id;
+use universaldb::utils::{FormalChunkedKey, FormalKey, IsolationLevel::*};
+use universaldb::{RangeOption, options::StreamingMode};
+
+use super::{DatabaseKv, keys};
+use crate::{
+	db::ScheduleData,
+	schedule::{self, CatchUp, MAX_CATCH_UP_RUNS, MISSED_RUN_THRESHOLD_MS},
+};
+
+/// Max amount of due schedules dispatched per tick.
+const DUE_BATCH_SIZE: usize = 100;
+
+impl DatabaseKv {
+	#[tracing::instrument(skip_all, fields(%schedule_id, %workflow_name, %cron))]
+	pub(super) async fn create_schedule_inner(
+		&self,
+		schedule_id: Id,
+		workflow_name: &str,
+		cron: &str,
+		catch_up: CatchUp,
+		tags: Option<&serde_json::Value>,
+		input: &serde_json::value::RawValue,
+		unique: bool,
+	) -> Result<Id> {
+		let next_ts =
+			schedule::next_run_ts(&schedule::parse_cron(cron)?, rivet_util::timestamp::now())?;
+
+		self.pools
+			.udb()?
+			.run(|tx| async move {
+				let tx = tx.with_subspace(self.subspace.clone());
+
+				if unique {
+					let by_name_subspace =
+						self.subspace
+							.subspace(&keys::schedule::ByNameKey::subspace(Some(
+								workflow_name.to_string(),
+							)));
+
+					let mut stream = tx.get_ranges_keyvalues(
+						RangeOption {
+							mode: StreamingMode::WantAll,
+							..(&by_name_subspace).into()
+						},
+						Serializable,
+					);
+
+					while let Some(entry) = stream.try_next().await? {
+						let (key, existing_tags) =
+							tx.read_entry::<keys::schedule::ByNameKey>(&entry)?;
+
+						if existing_tags.as_ref() == tags {
+							tracing::debug!(existing_schedule_id=?key.schedule_id, "found existing schedule");
+							return Ok(key.schedule_id);
+						}
+					}
+				}
+
+				tx.write(
+					&keys::schedule::ConfigKey::new(schedule_id),
+					keys::schedule::ScheduleConfig {
+						workflow_name: workflow_name.to_string(),
+						cron: cron.to_string(),
+						catch_up,
+						tags: tags.cloned(),
+						create_ts: rivet_util::timestamp::now(),
+					},
+				)?;
+
+				// Write input
+				let input_key = keys::schedule::InputKey::new(schedule_id);
+
+				for (i, chunk) in input_key.split_ref(input)?.into_iter().enumerate() {
+					let chunk_key = input_key.chunk(i);
+
+					tx.set(&self.subspace.pack(&chunk_key), &chunk);
+				}
+
+				tx.write(&keys::schedule::NextTsKey::new(schedule_id), next_ts)?;
+				tx.write(&keys::schedule::ByNextTsKey::new(next_ts, schedule_id), ())?;
+				tx.write(
+					&keys::schedule::ByNameKey::new(workflow_name.to_string(), schedule_id),
+					tags.cloned(),
+				)?;
+
+				Ok(schedule_id)
+			})
+			.await
+	}
+
+	#[tracing::instrument(skip_all)]
+	pub(super) async fn list_schedules_inner(
+		&self,
+		workflow_name: Option<&str>,
+	) -> Result<Vec<ScheduleData>> {
+		self.pools
+			.udb()?
+			.run(|tx| async move {
+				let tx = tx.with_subspace(self.subspace.clone());
+
+				let by_name_subspace = self.subspace.subspace(
+					&keys::schedule::ByNameKey::subspace(workflow_name.map(ToString::to_string)),
+				);
+
+				let schedule_ids = tx
+					.get_ranges_keyvalues(
+						RangeOption {
+							mode: StreamingMode::WantAll,
+							..(&by_name_subspace).into()
+						},
+						Snapshot,
+					)
+					.map(|res| {
+						let entry = res?;
+						let key = tx.unpack::<keys::schedule::ByNameKey>(entry.key())?;
+
+						anyhow::Ok(key.schedule_id)
+					})
+					.try_collect::<Vec<_>>()
+					.await?;
+
+				futures_util::stream::iter(schedule_ids)
+					.map(|schedule_id| {
+						let tx = tx.clone();
+						async move {
+							let config_key = keys::schedule::ConfigKey::new(schedule_id);
+							let next_ts_key = keys::schedule::NextTsKey::new(schedule_id);
+							let input_key = keys::schedule::InputKey::new(schedule_id);
+							let input_subspace = self.subspace.subspace(&input_key);
+
+							let (config, next_ts, input_chunks) = tokio::try_join!(
+								tx.read_opt(&config_key, Snapshot),
+								tx.read_opt(&next_ts_key, Snapshot),
+								tx.get_ranges_keyvalues(
+									RangeOption {
+										mode: StreamingMode::WantAll,
+										..(&input_subspace).into()
+									},
+									Snapshot,
+								)
+								.try_collect::<Vec<_>>(),
+							)?;
+
+							// Deleted in the meantime
+							let (Some(config), Some(next_ts)) = (config, next_ts) else {
+								return Ok(None);
+							};
+
+							Ok(Some(ScheduleData {
+								schedule_id,
+								workflow_name: config.workflow_name,
+								cron: config.cron,
+								catch_up: config.catch_up,
+								tags: config.tags,
+								input: input_key.combine(input_chunks)?,
+								create_ts: config.create_ts,
+								next_ts,
+							}))
+						}
+					})
+					.buffer_unordered(512)
+					.try_filter_map(|x| std::future::ready(Ok(x)))
+					.try_collect::<Vec<_>>()
+					.await
+			})
+			.await
+	}
+
+	/// Returns false if the schedule does not exist.
+	#[tracing::instrument(skip_all, fields(%schedule_id))]
+	pub(super) async fn delete_schedule_inner(&self, schedule_id: Id) -> Result<bool> {
+		self.pools
+			.udb()?
+			.run(|tx| async move {
+				let tx = tx.with_subspace(self.subspace.clone());
+
+				let config_key = keys::schedule::ConfigKey::new(schedule_id);
+				let next_ts_key = keys::schedule::NextTsKey::new(schedule_id);
+
+				let (config, next_ts) = tokio::try_join!(
+					tx.read_opt(&config_key, Serializable),
+					tx.read_opt(&next_ts_key, Serializable),
+				)?;
+
+				let Some(config) = config else {
+					return Ok(false);
+				};
+
+				if let Some(next_ts) = next_ts {
+					tx.delete(&keys::schedule::ByNextTsKey::new(next_ts, schedule_id));
+				}
+				tx.delete(&keys::schedule::ByNameKey::new(
+					config.workflow_name,
+					schedule_id,
+				));
+				tx.clear_subspace_range(
+					&self
+						.subspace
+						.subspace(&keys::schedule::EntireSubspaceKey::new(schedule_id)),
+				);
+
+				Ok(true)
+			})
+			.await
+	}
+
+	/// Returns the amount of dispatched workflows.
+	#[tracing::instrument(skip_all)]
+	pub(super) async fn dispatch_due_schedules_inner(
+		&self,
+		worker_instance_id: Id,
+	) -> Result<usize> {
+		let now = rivet_util::timestamp::now();
+		let by_next_ts_subspace = self
+			.subspace
+			.subspace(&keys::schedule::ByNextTsKey::subspace());
+		let (begin, _) = by_next_ts_subspace.range();
+		// Every key with a next ts up to and including now sorts before this key
+		let end = by_next_ts_subspace.pack(&(now + 1));
+
+		let due = self
+			.pools
+			.udb()?
+			.run(|tx| {
+				let (begin, end) = (begin.clone(), end.clone());
+
+				async move {
+					tx.get_ranges_keyvalues(
+						RangeOption {
+							mode: StreamingMode::WantAll,
+							limit: Some(DUE_BATCH_SIZE),
+							..(begin, end).into()
+						},
+						Snapshot,
+					)
+					.map(|res| {
+						let entry = res?;
+						let key = self
+							.subspace
+							.unpack::<keys::schedule::ByNextTsKey>(entry.key())?;
+
+						Ok(key)
+					})
+					.try_collect::<Vec<_>>()
+					.await
+				}
+			})
+			.await?;
+
+		let mut dispatched = 0;
+		for key in due {
+			dispatched += self
+				.dispatch_schedule(key.schedule_id, key.next_ts, now, worker_instance_id)
+				.await?;
+		}
+
+		if dispatched != 0 {
+			self.wake_worker();
+		}
+
+		Ok(dispatched)
+	}
+
+	/// Dispatches the due runs of a single schedule and advances it to its next run in the same
+	/// transaction, so a run is never lost or dispatched twice across worker restarts.
+	#[tracing::instrument(skip_all, fields(%schedule_id))]
+	async fn dispatch_schedule(
+		&self,
+		schedule_id: Id,
+		indexed_next_ts: i64,
+		now: i64,
+		worker_instance_id: Id,
+	) -> Result<usize> {
+		self.pools
+			.udb()?
+			.run(|tx| async move {
+				let tx = tx.with_subspace(self.subspace.clone());
+
+				let config_key = keys::schedule::ConfigKey::new(schedule_id);
+				let next_ts_key = keys::schedule::NextTsKey::new(schedule_id);
+				let input_key = keys::schedule::InputKey::new(schedule_id);
+				let input_subspace = self.subspace.subspace(&input_key);
+
+				let (config, next_ts, input_chunks) = tokio::try_join!(
+					tx.read_opt(&config_key, Serializable),
+					tx.read_opt(&next_ts_key, Serializable),
+					tx.get_ranges_keyvalues(
+						RangeOption {
+							mode: StreamingMode::WantAll,
+							..(&input_subspace).into()
+						},
+						Serializable,
+					)
+					.try_collect::<Vec<_>>(),
+				)?;
+
+				// Deleted or already dispatched by another worker
+				let (Some(config), Some(next_ts)) = (config, next_ts) else {
+					return Ok(0);
+				};
+				if next_ts != indexed_next_ts || next_ts > now {
+					return Ok(0);
+				}
+
+				let cron = schedule::parse_cron(&config.cron)?;
+				let input = input_key.combine(input_chunks)?;
+
+				let (run_count, new_next_ts) = match config.catch_up {
+					CatchUp::Latest => (1, schedule::next_run_ts(&cron, now)?),
+					CatchUp::All => {
+						let mut run_count = 0;
+						let mut run_ts = next_ts;
+
+						// Remaining runs are dispatched on the next tick
+						while run_ts <= now && run_count < MAX_CATCH_UP_RUNS {
+							run_count += 1;
+							run_ts = schedule::next_run_ts(&cron, run_ts)?;
+						}
+
+						(run_count, run_ts)
+					}
+					CatchUp::Skip => {
+						// First run that is not too late to dispatch
+						let recent_ts = schedule::next_run_ts(
+							&cron,
+							(now - MISSED_RUN_THRESHOLD_MS).max(next_ts) - 1,
+						)?;
+
+						(
+							if recent_ts <= now { 1 } else { 0 },
+							schedule::next_run_ts(&cron, now)?,
+						)
+					}
+				};
+
+				for _ in 0..run_count {
+					let ray_id = Id::new_v1(worker_instance_id.label());
+					let workflow_id = Id::new_v1(worker_instance_id.label());
+
+					self.dispatch_workflow_inner(
+						ray_id,
+						workflow_id,
+						&config.workflow_name,
+						config.tags.as_ref(),
+						&input,
+						false,
+						0,
+						&tx,
+					)
+					.await?;
+
+					tracing::debug!(?workflow_id, workflow_name=%config.workflow_name, "dispatched scheduled workflow");
+				}
+
+				tx.delete(&keys::schedule::ByNextTsKey::new(next_ts, schedule_id));
+				tx.write(&keys::schedule::ByNextTsKey::new(new_next_ts, schedule_id), ())?;
+				tx.write(&next_ts_key, new_next_ts)?;
+
+				Ok(run_count)
+			})
+			.await
+	}
+}
@@ -11,12 +11,16 @@ use crate::{
 		event::{Event, EventType, SleepState},
 		location::Location,
 	},
+	schedule::CatchUp,
 	workflow::Workflow,
 };
 
 pub mod debug;
 mod kv;
-pub use kv::DatabaseKv;
+pub use kv::{
+	DatabaseKv,
+	keys::{decode_chunked_value, decode_value},
+};
 
 pub type DatabaseHandle = Arc<dyn Database + Sync>;
 
@@ -71,6 +75,10 @@ pub trait Database: Send {
 	/// Function to publish metrics. Called periodically.
 	async fn publish_metrics(&self, worker_instance_id: Id) -> WorkflowResult<()>;
 
+	/// Dispatches workflows for all schedules whose next run has passed, then advances them to their
+	/// following run. Called periodically.
+	async fn dispatch_due_schedules(&self, worker_instance_id: Id) -> WorkflowResult<()>;
+
 	// MARK: Workflows/signals
 
 	/// Writes a new workflow to the database. If unique is set, this should return the existing workflow ID
@@ -83,6 +91,7 @@ pub trait Database: Send {
 		tags: Option<&serde_json::Value>,
 		input: &serde_json::value::RawValue,
 		unique: bool,
+		priority: i32,
 	) -> WorkflowResult<Id>;
 
 	/// Retrieves workflows with the given IDs.
@@ -95,14 +104,43 @@ pub trait Database: Send {
 		tags: &serde_json::Value,
 	) -> WorkflowResult<Option<Id>>;
 
-	/// Pulls workflows for processing by the worker. Will only pull workflows with names matching the filter.
-	/// Should also update the ping of this worker instance.
+	/// Pulls workflows for processing by the worker. Will only pull workflows with names matching the filter
+	/// and no more than the given limits allow, leaving the rest for other workers. Should also update the
+	/// ping of this worker instance.
 	async fn pull_workflows(
 		&self,
 		worker_instance_id: Id,
 		filter: &[&str],
+		limits: &PullLimits,
 	) -> WorkflowResult<Vec<PulledWorkflowData>>;
 
+	/// Writes a new schedule that dispatches the given workflow on every run of the cron expression. If
+	/// unique is set, this should return the existing schedule ID (if one exists with the same workflow
+	/// name and tags) instead of the given schedule ID.
+	async fn create_schedule(
+		&self,
+		schedule_id: Id,
+		workflow_name: &str,
+		cron: &str,
+		catch_up: CatchUp,
+		tags: Option<&serde_json::Value>,
+		input: &serde_json::value::RawValue,
+		unique: bool,
+	) -> WorkflowResult<Id>;
+
+	/// Retrieves all schedules, or only the schedules that dispatch the given workflow.
+	async fn list_schedules(
+		&self,
+		workflow_name: Option<&str>,
+	) -> WorkflowResult<Vec<ScheduleData>>;
+
+	/// Deletes a schedule. Workflows it already dispatched are not affected.
+	async fn delete_schedule(&self, schedule_id: Id) -> WorkflowResult<()>;
+
+	/// Flags a workflow and all of the sub workflows it dispatched as cancelled, waking them so they
+	/// run their cleanup hook. Workflows that already completed or were silenced are skipped.
+	async fn cancel_workflow(&self, workflow_id: Id) -> WorkflowResult<()>;
+
 	/// Mark a workflow as completed.
 	async fn complete_workflow(
 		&self,
@@ -111,6 +149,15 @@ pub trait Database: Send {
 		output: &serde_json::value::RawValue,
 	) -> WorkflowResult<()>;
 
+	/// Mark a cancelled workflow as stopped after its cleanup hook ran. Like `complete_workflow` but
+	/// without an output.
+	async fn complete_cancelled_workflow(
+		&self,
+		workflow_id: Id,
+		workflow_name: &str,
+		error: &str,
+	) -> WorkflowResult<()>;
+
 	/// Write a workflow sleep/failure to the database.
 	async fn commit_workflow(
 		&self,
@@ -180,6 +227,7 @@ pub trait Database: Send {
 		input: &serde_json::value::RawValue,
 		loop_location: Option<&Location>,
 		unique: bool,
+		priority: i32,
 	) -> WorkflowResult<Id>;
 
 	/// Updates workflow tags.
@@ -291,6 +339,8 @@ pub struct WorkflowData {
 	state: Box<serde_json::value::RawValue>,
 	output: Option<Box<serde_json::value::RawValue>>,
 	pub has_wake_condition: bool,
+	/// Whether or not the workflow was cancelled and stopped without an output.
+	pub cancelled: bool,
 }
 
 impl WorkflowData {
@@ -320,10 +370,32 @@ pub struct PulledWorkflowData {
 	pub input: Box<serde_json::value::RawValue>,
 	pub state: Box<serde_json::value::RawValue>,
 	pub wake_deadline_ts: Option<i64>,
+	pub cancelled: bool,
 
 	pub events: HashMap<Location, Vec<Event>>,
 }
 
+/// Caps the amount of workflows leased by a single `Database::pull_workflows` call.
+#[derive(Debug, Default)]
+pub struct PullLimits {
+	/// Max amount of workflows to pull in total.
+	pub total: Option<usize>,
+	/// Max amount of workflows to pull per workflow name. Names not in this map are only limited by `total`.
+	pub per_name: HashMap<String, usize>,
+}
+
+#[derive(Debug)]
+pub struct ScheduleData {
+	pub schedule_id: Id,
+	pub workflow_name: String,
+	pub cron: String,
+	pub catch_up: CatchUp,
+	pub tags: Option<serde_json::Value>,
+	pub input: Box<serde_json::value::RawValue>,
+	pub create_ts: i64,
+	pub next_ts: i64,
+}
+
 pub struct SignalData {
 	pub signal_id: Id,
 	pub signal_name: String,
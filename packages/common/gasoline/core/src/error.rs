@@ -1,9 +1,6 @@
-use std::time::{SystemTime, UNIX_EPOCH};
-
 use rivet_util::Id;
-use tokio::time::Instant;
 
-use crate::ctx::common::RETRY_TIMEOUT_MS;
+use crate::activity::RetryPolicy;
 
 pub type WorkflowResult<T> = Result<T, WorkflowError>;
 
@@ -12,13 +9,16 @@ pub enum WorkflowError {
 	#[error("workflow failure: {0:?}")]
 	WorkflowFailure(#[source] anyhow::Error),
 
-	// Includes error count
+	// Includes error count and the retry policy of the activity
 	#[error("activity failure: {0:?}")]
-	ActivityFailure(#[source] anyhow::Error, usize),
+	ActivityFailure(#[source] anyhow::Error, usize, RetryPolicy),
 
 	#[error("activity failure, max retries reached: {0:?}")]
 	ActivityMaxFailuresReached(#[source] anyhow::Error),
 
+	#[error("activity failure, not retryable: {0:?}")]
+	ActivityNonRetryable(#[source] anyhow::Error),
+
 	#[error("operation failure: {0:?}")]
 	OperationFailure(#[source] anyhow::Error),
 
@@ -28,9 +28,24 @@ pub enum WorkflowError {
 	#[error("workflow not found")]
 	WorkflowNotFound,
 
+	#[error("query missing from registry: {0}")]
+	QueryMissingFromRegistry(String),
+
+	#[error("query failure: {0:?}")]
+	QueryFailure(#[source] anyhow::Error),
+
+	#[error("serialize query output: {0}")]
+	SerializeQueryOutput(#[source] serde_json::Error),
+
 	#[error("workflow stopped")]
 	WorkflowStopped,
 
+	#[error("workflow cancelled")]
+	Cancelled,
+
+	#[error("replay reached a step that is not in the recorded history")]
+	ReplayEnd,
+
 	#[error("history diverged: {0}")]
 	HistoryDiverged(String),
 
@@ -94,6 +109,12 @@ pub enum WorkflowError {
 	#[error("invalid tags: {0}")]
 	InvalidTags(String),
 
+	#[error("invalid cron expression: {0}")]
+	InvalidCron(String),
+
+	#[error("schedule not found")]
+	ScheduleNotFound,
+
 	#[error("failed to serialize loop state: {0}")]
 	SerializeLoopState(#[source] serde_json::Error),
 
@@ -124,6 +145,9 @@ pub enum WorkflowError {
 	#[error("sub workflow incomplete: {0:?}")]
 	SubWorkflowIncomplete(Id),
 
+	#[error("sub workflow cancelled: {0:?}")]
+	SubWorkflowCancelled(Id),
+
 	#[error("integer conversion failed")]
 	IntegerConversion,
 
@@ -145,13 +169,13 @@ pub enum WorkflowError {
 	#[error("config error: {0}")]
 	Config(#[source] anyhow::Error),
 
-	// Includes error count
+	// Includes error count and the retry policy of the activity
 	#[error("activity timed out")]
-	ActivityTimeout(usize),
+	ActivityTimeout(usize, RetryPolicy),
 
-	// Includes error count
+	// Includes error count and the retry policy of the activity
 	#[error("operation timed out")]
-	OperationTimeout(usize),
+	OperationTimeout(usize, RetryPolicy),
 
 	#[error("duplicate registered workflow: {0}")]
 	DuplicateRegisteredWorkflow(String),
@@ -186,29 +210,14 @@ impl WorkflowError {
 	/// Returns the next deadline for a workflow to be woken up again based on the error.
 	pub(crate) fn deadline_ts(&self) -> Option<i64> {
 		match self {
-			WorkflowError::ActivityFailure(_, error_count)
-			| WorkflowError::ActivityTimeout(error_count)
-			| WorkflowError::OperationTimeout(error_count) => {
+			WorkflowError::ActivityFailure(_, error_count, retry_policy)
+			| WorkflowError::ActivityTimeout(error_count, retry_policy)
+			| WorkflowError::OperationTimeout(error_count, retry_policy) => {
 				// NOTE: Max retry is handled in `WorkflowCtx::activity`
-				let mut backoff = rivet_util::backoff::Backoff::new_at(
-					8,
-					None,
-					RETRY_TIMEOUT_MS,
-					500,
-					*error_count,
-				);
-				let next = backoff.step().expect("should not have max retry");
-
-				// Calculate timestamp based on the backoff
-				let duration_until = next.duration_since(Instant::now());
-				let deadline_ts = (SystemTime::now() + duration_until)
-					.duration_since(UNIX_EPOCH)
-					.unwrap_or_else(|err| unreachable!("time is broken: {}", err))
-					.as_millis()
-					.try_into()
+				let interval = i64::try_from(retry_policy.interval(*error_count).as_millis())
 					.expect("doesn't fit in i64");
 
-				Some(deadline_ts)
+				Some(rivet_util::timestamp::now() + interval)
 			}
 			WorkflowError::Sleep(ts) | WorkflowError::NoSignalFoundAndSleep(_, ts) => Some(*ts),
 			_ => None,
@@ -218,9 +227,9 @@ impl WorkflowError {
 	/// Any error that the workflow can continue on with its execution from.
 	pub(crate) fn is_recoverable(&self) -> bool {
 		match self {
-			WorkflowError::ActivityFailure(_, _)
-			| WorkflowError::ActivityTimeout(_)
-			| WorkflowError::OperationTimeout(_)
+			WorkflowError::ActivityFailure(_, _, _)
+			| WorkflowError::ActivityTimeout(_, _)
+			| WorkflowError::OperationTimeout(_, _)
 			| WorkflowError::NoSignalFound(_)
 			| WorkflowError::NoSignalFoundAndSleep(_, _)
 			| WorkflowError::SubWorkflowIncomplete(_)
@@ -233,9 +242,9 @@ impl WorkflowError {
 	/// Any error that the workflow can try again on a fixed number of times. Only used for printing.
 	pub(crate) fn is_retryable(&self) -> bool {
 		match self {
-			WorkflowError::ActivityFailure(_, _)
-			| WorkflowError::ActivityTimeout(_)
-			| WorkflowError::OperationTimeout(_) => true,
+			WorkflowError::ActivityFailure(_, _, _)
+			| WorkflowError::ActivityTimeout(_, _)
+			| WorkflowError::OperationTimeout(_, _) => true,
 			_ => false,
 		}
 	}
@@ -1,7 +1,7 @@
 use std::ops::Deref;
 
 use rivet_util::Id;
-use serde::de::DeserializeOwned;
+use serde::{Serialize, de::DeserializeOwned};
 use strum::FromRepr;
 
 use super::location::Coordinate;
@@ -84,7 +84,8 @@ impl std::fmt::Display for EventData {
 	}
 }
 
-#[derive(Hash, Debug, Clone, Copy, PartialEq, Eq, FromRepr)]
+#[derive(Hash, Debug, Clone, Copy, PartialEq, Eq, FromRepr, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum EventType {
 	Activity = 0,
 	Signal = 1,
@@ -186,7 +187,8 @@ pub struct SleepEvent {
 	pub state: SleepState,
 }
 
-#[derive(Debug, Clone, Hash, Copy, PartialEq, Eq, FromRepr)]
+#[derive(Debug, Clone, Hash, Copy, PartialEq, Eq, FromRepr, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum SleepState {
 	Normal = 0,
 	Uninterrupted = 1,
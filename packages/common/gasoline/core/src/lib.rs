@@ -10,7 +10,10 @@ pub mod message;
 pub mod metrics;
 pub mod operation;
 pub mod prelude;
+pub mod query;
 pub mod registry;
+pub mod replay;
+pub mod schedule;
 pub mod signal;
 mod stub;
 pub mod utils;
@@ -10,7 +10,7 @@ pub mod util {
 }
 
 pub use crate::{
-	activity::Activity as ActivityTrait,
+	activity::{Activity as ActivityTrait, NonRetryable, RetryPolicy},
 	ctx::workflow::Loop,
 	ctx::*,
 	db::{self, Database},
@@ -20,7 +20,9 @@ pub use crate::{
 	listen::{CustomListener, Listen},
 	message::Message as MessageTrait,
 	operation::Operation as OperationTrait,
+	query::Query as QueryTrait,
 	registry::Registry,
+	schedule::CatchUp,
 	signal::{Signal as SignalTrait, join_signal},
 	stub::{activity, closure, removed, v},
 	worker::Worker,
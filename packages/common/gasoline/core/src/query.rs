@@ -0,0 +1,44 @@
+use std::fmt::Debug;
+
+use anyhow::Result;
+use serde::{Serialize, de::DeserializeOwned};
+
+use crate::error::{WorkflowError, WorkflowResult};
+
+/// A read-only projection of a workflow's state. Queries read the state last committed by the
+/// workflow and never touch its history, so they can be run against running, sleeping and completed
+/// workflows alike.
+pub trait Query {
+	type State: DeserializeOwned;
+	type Output: Serialize + DeserializeOwned + Debug + Send;
+
+	const NAME: &'static str;
+
+	fn run(state: &Self::State) -> Result<Self::Output>;
+}
+
+/// Type-erased query handler stored in the registry, used to run queries by name (i.e. from the CLI).
+#[derive(Clone, Copy)]
+pub struct RegistryQuery {
+	pub name: &'static str,
+	pub run: fn(&serde_json::value::RawValue) -> WorkflowResult<Box<serde_json::value::RawValue>>,
+}
+
+impl RegistryQuery {
+	pub const fn new<Q: Query>() -> Self {
+		RegistryQuery {
+			name: Q::NAME,
+			run: run_raw::<Q>,
+		}
+	}
+}
+
+fn run_raw<Q: Query>(
+	state: &serde_json::value::RawValue,
+) -> WorkflowResult<Box<serde_json::value::RawValue>> {
+	let state = serde_json::from_str::<Q::State>(state.get())
+		.map_err(WorkflowError::DeserializeWorkflowState)?;
+	let output = Q::run(&state).map_err(WorkflowError::QueryFailure)?;
+
+	serde_json::value::to_raw_value(&output).map_err(WorkflowError::SerializeQueryOutput)
+}
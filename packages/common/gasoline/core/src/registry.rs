@@ -6,6 +6,7 @@ use tracing::Instrument;
 use crate::{
 	ctx::WorkflowCtx,
 	error::{WorkflowError, WorkflowResult},
+	query::RegistryQuery,
 	workflow::Workflow,
 };
 
@@ -85,6 +86,24 @@ impl Registry {
 					.in_current_span()
 					.boxed()
 				},
+				on_cancel: |ctx| {
+					async move {
+						// Deserialize input
+						let input = serde_json::from_str(ctx.input().get())
+							.map_err(WorkflowError::DeserializeWorkflowInput)?;
+
+						W::on_cancel(ctx, &input).await.map_err(|err| {
+							// Differentiate between WorkflowError and user error
+							match err.downcast::<WorkflowError>() {
+								Ok(inner_err) => inner_err,
+								Err(err) => WorkflowError::WorkflowFailure(err),
+							}
+						})
+					}
+					.in_current_span()
+					.boxed()
+				},
+				queries: W::QUERIES,
 			}),
 		);
 
@@ -97,6 +116,20 @@ impl Registry {
 			.ok_or(WorkflowError::WorkflowMissingFromRegistry(name.to_string()))
 	}
 
+	pub fn get_query(
+		&self,
+		workflow_name: &str,
+		query_name: &str,
+	) -> WorkflowResult<&RegistryQuery> {
+		self.get_workflow(workflow_name)?
+			.queries
+			.iter()
+			.find(|query| query.name == query_name)
+			.ok_or_else(|| {
+				WorkflowError::QueryMissingFromRegistry(format!("{workflow_name}.{query_name}"))
+			})
+	}
+
 	pub fn size(&self) -> usize {
 		self.workflows.len()
 	}
@@ -108,4 +141,9 @@ pub struct RegistryWorkflow {
 	) -> Pin<
 		Box<dyn Future<Output = WorkflowResult<Box<serde_json::value::RawValue>>> + Send + 'a>,
 	>,
+	/// Runs the workflow's cancellation cleanup hook.
+	pub on_cancel: for<'a> fn(
+		&'a mut WorkflowCtx,
+	) -> Pin<Box<dyn Future<Output = WorkflowResult<()>> + Send + 'a>>,
+	pub queries: &'static [RegistryQuery],
 }
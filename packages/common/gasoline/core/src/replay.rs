@@ -0,0 +1,552 @@
+//! Replays recorded workflow histories against the current workflow code without running activities or
+//! writing to the database. Used to find changes that would break in-flight workflows before deploying.
+
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use anyhow::Result;
+use futures_util::stream::BoxStream;
+use rivet_util::Id;
+use tokio::sync::watch;
+
+use crate::{
+	ctx::WorkflowCtx,
+	db::{
+		Database, DatabaseHandle, PullLimits, PulledWorkflowData, ScheduleData, SignalData,
+		WorkflowData, debug,
+	},
+	error::{WorkflowError, WorkflowResult},
+	history::{
+		event::{
+			ActivityEvent, Event, EventData, EventType, LoopEvent, MessageSendEvent, SignalEvent,
+			SignalSendEvent, SleepState, SubWorkflowEvent,
+		},
+		location::Location,
+	},
+	registry::RegistryHandle,
+	schedule::CatchUp,
+};
+
+/// Replays workflow histories fetched with `DatabaseDebug::get_workflow_history`.
+pub struct Replayer {
+	registry: RegistryHandle,
+	db: DatabaseHandle,
+
+	config: rivet_config::Config,
+	pools: rivet_pools::Pools,
+	cache: rivet_cache::Cache,
+}
+
+impl Replayer {
+	/// `db` is only read from, to resolve the output of finished sub workflows.
+	pub fn new(
+		registry: RegistryHandle,
+		db: DatabaseHandle,
+		config: rivet_config::Config,
+		pools: rivet_pools::Pools,
+	) -> Result<Self> {
+		let cache = rivet_cache::CacheInner::from_env(&config, pools.clone())?;
+
+		Ok(Replayer {
+			registry,
+			db: Arc::new(ReplayDatabase { inner: db }),
+			config,
+			pools,
+			cache,
+		})
+	}
+
+	/// Runs the workflow in the registry against the given history. Recorded activity outputs, signals and
+	/// loop states are replayed as is; replay stops at the first step that is not in the history.
+	#[tracing::instrument(skip_all, fields(workflow_id=%history.wf.workflow_id, workflow_name=%history.wf.workflow_name))]
+	pub async fn replay(&self, history: debug::HistoryData) -> Result<ReplayReport> {
+		let workflow_id = history.wf.workflow_id;
+		let workflow_name = history.wf.workflow_name.clone();
+
+		let data = pulled_workflow_data(history)?;
+
+		// Never sent to, only used to keep `check_stop` from tripping
+		let (_stop_tx, stop_rx) = watch::channel(());
+
+		let ctx = WorkflowCtx::new(
+			self.registry.clone(),
+			self.db.clone(),
+			self.config.clone(),
+			self.pools.clone(),
+			self.cache.clone(),
+			data,
+			stop_rx,
+		)?;
+
+		let outcome = match ctx.replay().await {
+			Ok(()) => ReplayOutcome::Complete,
+			Err(err) => ReplayOutcome::from_err(err),
+		};
+
+		Ok(ReplayReport {
+			workflow_id,
+			workflow_name,
+			outcome,
+		})
+	}
+}
+
+#[derive(Debug)]
+pub struct ReplayReport {
+	pub workflow_id: Id,
+	pub workflow_name: String,
+	pub outcome: ReplayOutcome,
+}
+
+#[derive(Debug)]
+pub enum ReplayOutcome {
+	/// The workflow ran to completion using only recorded history.
+	Complete,
+	/// The recorded history is consistent with the workflow but the workflow has steps left to run. This is
+	/// the expected outcome for in-flight workflows.
+	Incomplete(String),
+	/// The workflow code no longer matches the recorded history. Deploying it would break this workflow.
+	Diverged(String),
+	/// The workflow errored for a reason unrelated to its history.
+	Failed(String),
+}
+
+impl ReplayOutcome {
+	fn from_err(err: WorkflowError) -> Self {
+		// User errors may wrap the workflow error that caused them
+		let inner = match &err {
+			WorkflowError::WorkflowFailure(err) => {
+				err.chain().find_map(|x| x.downcast_ref::<WorkflowError>())
+			}
+			_ => None,
+		}
+		.unwrap_or(&err);
+
+		match inner {
+			WorkflowError::HistoryDiverged(_) | WorkflowError::LatentHistoryFound(_) => {
+				ReplayOutcome::Diverged(inner.to_string())
+			}
+			WorkflowError::ReplayEnd => ReplayOutcome::Incomplete(inner.to_string()),
+			_ if inner.is_recoverable() => ReplayOutcome::Incomplete(inner.to_string()),
+			_ => ReplayOutcome::Failed(err.to_string()),
+		}
+	}
+
+	pub fn is_diverged(&self) -> bool {
+		matches!(self, ReplayOutcome::Diverged(_))
+	}
+}
+
+impl std::fmt::Display for ReplayOutcome {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		match self {
+			ReplayOutcome::Complete => write!(f, "complete"),
+			ReplayOutcome::Incomplete(reason) => write!(f, "incomplete ({reason})"),
+			ReplayOutcome::Diverged(reason) => write!(f, "diverged ({reason})"),
+			ReplayOutcome::Failed(reason) => write!(f, "failed ({reason})"),
+		}
+	}
+}
+
+/// Converts a debug history into the form the worker would have pulled it in.
+fn pulled_workflow_data(history: debug::HistoryData) -> WorkflowResult<PulledWorkflowData> {
+	let mut events = HashMap::<Location, Vec<Event>>::new();
+
+	for event in history.events {
+		// Forgotten events belong to previous loop iterations and are never replayed
+		if event.forgotten {
+			continue;
+		}
+
+		let root = event.location.root();
+		events.entry(root).or_default().push(convert_event(event)?);
+	}
+
+	for branch in events.values_mut() {
+		branch.sort_by(|a, b| a.coordinate.cmp(&b.coordinate));
+	}
+
+	let wf = history.wf;
+
+	Ok(PulledWorkflowData {
+		workflow_id: wf.workflow_id,
+		workflow_name: wf.workflow_name,
+		create_ts: wf.create_ts,
+		// Not recorded, only used for tracing
+		ray_id: wf.workflow_id,
+		input: to_raw_value(&wf.input)?,
+		state: to_raw_value(&wf.data)?,
+		wake_deadline_ts: None,
+		cancelled: wf.state == debug::WorkflowState::Cancelled,
+		events,
+	})
+}
+
+fn convert_event(event: debug::Event) -> WorkflowResult<Event> {
+	let coordinate = event
+		.location
+		.tail()
+		.cloned()
+		.ok_or(WorkflowError::MissingEventData("location"))?;
+
+	let data = match event.data {
+		debug::EventData::Activity(activity) => EventData::Activity(ActivityEvent {
+			name: activity.name,
+			create_ts: event.create_ts,
+			output: activity.output.as_ref().map(to_raw_value).transpose()?,
+			error_count: activity.errors.iter().map(|err| err.count).sum(),
+		}),
+		debug::EventData::Signal(signal) => EventData::Signal(SignalEvent {
+			name: signal.name,
+			body: to_raw_value(&signal.body)?,
+		}),
+		debug::EventData::SignalSend(signal_send) => EventData::SignalSend(SignalSendEvent {
+			signal_id: signal_send.signal_id,
+			name: signal_send.name,
+		}),
+		debug::EventData::MessageSend(message_send) => EventData::MessageSend(MessageSendEvent {
+			name: message_send.name,
+		}),
+		debug::EventData::SubWorkflow(sub_workflow) => EventData::SubWorkflow(SubWorkflowEvent {
+			sub_workflow_id: sub_workflow.sub_workflow_id,
+			name: sub_workflow.name,
+		}),
+		debug::EventData::Loop(loop_event) => EventData::Loop(LoopEvent {
+			state: to_raw_value(&loop_event.state)?,
+			output: loop_event.output.as_ref().map(to_raw_value).transpose()?,
+			iteration: loop_event.iteration,
+		}),
+		debug::EventData::Sleep(sleep) => EventData::Sleep(sleep),
+		debug::EventData::Removed(removed) => EventData::Removed(removed),
+		debug::EventData::VersionCheck => EventData::VersionCheck,
+		debug::EventData::Branch => EventData::Branch,
+		debug::EventData::Empty => EventData::Empty,
+	};
+
+	Ok(Event {
+		coordinate,
+		version: event.version,
+		data,
+	})
+}
+
+fn to_raw_value(value: &serde_json::Value) -> WorkflowResult<Box<serde_json::value::RawValue>> {
+	serde_json::value::to_raw_value(value)
+		.map_err(Into::into)
+		.map_err(WorkflowError::DeserializeEventData)
+}
+
+/// Database used while replaying. Reads are forwarded to the real database, everything that would write
+/// (or wait on something new to happen) errors with `WorkflowError::ReplayEnd`.
+struct ReplayDatabase {
+	inner: DatabaseHandle,
+}
+
+#[async_trait::async_trait]
+impl Database for ReplayDatabase {
+	async fn from_pools(_pools: rivet_pools::Pools) -> Result<Arc<Self>> {
+		anyhow::bail!("replay database must be created with `Replayer::new`")
+	}
+
+	// Sleeping in memory would only delay the `ReplayEnd` error
+	fn worker_poll_interval(&self) -> Duration {
+		Duration::ZERO
+	}
+
+	fn max_signal_poll_retries(&self) -> usize {
+		0
+	}
+
+	fn max_sub_workflow_poll_retries(&self) -> usize {
+		0
+	}
+
+	async fn wake_sub<'a, 'b>(&'a self) -> WorkflowResult<BoxStream<'b, ()>> {
+		Ok(Box::pin(futures_util::stream::pending()))
+	}
+
+	async fn update_worker_ping(&self, _worker_instance_id: Id) -> WorkflowResult<()> {
+		Err(WorkflowError::ReplayEnd)
+	}
+
+	async fn clear_expired_leases(&self, _worker_instance_id: Id) -> WorkflowResult<()> {
+		Err(WorkflowError::ReplayEnd)
+	}
+
+	async fn publish_metrics(&self, _worker_instance_id: Id) -> WorkflowResult<()> {
+		Err(WorkflowError::ReplayEnd)
+	}
+
+	async fn dispatch_due_schedules(&self, _worker_instance_id: Id) -> WorkflowResult<()> {
+		Err(WorkflowError::ReplayEnd)
+	}
+
+	async fn dispatch_workflow(
+		&self,
+		_ray_id: Id,
+		_workflow_id: Id,
+		_workflow_name: &str,
+		_tags: Option<&serde_json::Value>,
+		_input: &serde_json::value::RawValue,
+		_unique: bool,
+		_priority: i32,
+	) -> WorkflowResult<Id> {
+		Err(WorkflowError::ReplayEnd)
+	}
+
+	async fn get_workflows(&self, workflow_ids: Vec<Id>) -> WorkflowResult<Vec<WorkflowData>> {
+		self.inner.get_workflows(workflow_ids).await
+	}
+
+	async fn find_workflow(
+		&self,
+		workflow_name: &str,
+		tags: &serde_json::Value,
+	) -> WorkflowResult<Option<Id>> {
+		self.inner.find_workflow(workflow_name, tags).await
+	}
+
+	async fn pull_workflows(
+		&self,
+		_worker_instance_id: Id,
+		_filter: &[&str],
+		_limits: &PullLimits,
+	) -> WorkflowResult<Vec<PulledWorkflowData>> {
+		Err(WorkflowError::ReplayEnd)
+	}
+
+	async fn create_schedule(
+		&self,
+		_schedule_id: Id,
+		_workflow_name: &str,
+		_cron: &str,
+		_catch_up: CatchUp,
+		_tags: Option<&serde_json::Value>,
+		_input: &serde_json::value::RawValue,
+		_unique: bool,
+	) -> WorkflowResult<Id> {
+		Err(WorkflowError::ReplayEnd)
+	}
+
+	async fn list_schedules(
+		&self,
+		workflow_name: Option<&str>,
+	) -> WorkflowResult<Vec<ScheduleData>> {
+		self.inner.list_schedules(workflow_name).await
+	}
+
+	async fn delete_schedule(&self, _schedule_id: Id) -> WorkflowResult<()> {
+		Err(WorkflowError::ReplayEnd)
+	}
+
+	// Not recorded in history, so a workflow calls this again on every run
+	async fn cancel_workflow(&self, _workflow_id: Id) -> WorkflowResult<()> {
+		Ok(())
+	}
+
+	async fn complete_workflow(
+		&self,
+		_workflow_id: Id,
+		_workflow_name: &str,
+		_output: &serde_json::value::RawValue,
+	) -> WorkflowResult<()> {
+		Err(WorkflowError::ReplayEnd)
+	}
+
+	async fn complete_cancelled_workflow(
+		&self,
+		_workflow_id: Id,
+		_workflow_name: &str,
+		_error: &str,
+	) -> WorkflowResult<()> {
+		Err(WorkflowError::ReplayEnd)
+	}
+
+	async fn commit_workflow(
+		&self,
+		_workflow_id: Id,
+		_workflow_name: &str,
+		_wake_immediate: bool,
+		_wake_deadline_ts: Option<i64>,
+		_wake_signals: &[&str],
+		_wake_sub_workflow_id: Option<Id>,
+		_error: &str,
+	) -> WorkflowResult<()> {
+		Err(WorkflowError::ReplayEnd)
+	}
+
+	async fn pull_next_signal(
+		&self,
+		_workflow_id: Id,
+		_workflow_name: &str,
+		_filter: &[&str],
+		_location: &Location,
+		_version: usize,
+		_loop_location: Option<&Location>,
+		_last_try: bool,
+	) -> WorkflowResult<Option<SignalData>> {
+		Err(WorkflowError::ReplayEnd)
+	}
+
+	async fn get_sub_workflow(
+		&self,
+		workflow_id: Id,
+		workflow_name: &str,
+		sub_workflow_id: Id,
+	) -> WorkflowResult<Option<WorkflowData>> {
+		self.inner
+			.get_sub_workflow(workflow_id, workflow_name, sub_workflow_id)
+			.await
+	}
+
+	async fn publish_signal(
+		&self,
+		_ray_id: Id,
+		_workflow_id: Id,
+		_signal_id: Id,
+		_signal_name: &str,
+		_body: &serde_json::value::RawValue,
+	) -> WorkflowResult<()> {
+		Err(WorkflowError::ReplayEnd)
+	}
+
+	async fn publish_signal_from_workflow(
+		&self,
+		_from_workflow_id: Id,
+		_location: &Location,
+		_version: usize,
+		_ray_id: Id,
+		_workflow_id: Id,
+		_signal_id: Id,
+		_signal_name: &str,
+		_body: &serde_json::value::RawValue,
+		_loop_location: Option<&Location>,
+	) -> WorkflowResult<()> {
+		Err(WorkflowError::ReplayEnd)
+	}
+
+	async fn dispatch_sub_workflow(
+		&self,
+		_ray_id: Id,
+		_workflow_id: Id,
+		_location: &Location,
+		_version: usize,
+		_sub_workflow_id: Id,
+		_sub_workflow_name: &str,
+		_tags: Option<&serde_json::Value>,
+		_input: &serde_json::value::RawValue,
+		_loop_location: Option<&Location>,
+		_unique: bool,
+		_priority: i32,
+	) -> WorkflowResult<Id> {
+		Err(WorkflowError::ReplayEnd)
+	}
+
+	async fn update_workflow_tags(
+		&self,
+		_workflow_id: Id,
+		_workflow_name: &str,
+		_tags: &serde_json::Value,
+	) -> WorkflowResult<()> {
+		Err(WorkflowError::ReplayEnd)
+	}
+
+	async fn update_workflow_state(
+		&self,
+		_workflow_id: Id,
+		_state: &serde_json::value::RawValue,
+	) -> WorkflowResult<()> {
+		Err(WorkflowError::ReplayEnd)
+	}
+
+	async fn commit_workflow_activity_event(
+		&self,
+		_workflow_id: Id,
+		_location: &Location,
+		_version: usize,
+		_name: &str,
+		_create_ts: i64,
+		_input: &serde_json::value::RawValue,
+		_output: Result<&serde_json::value::RawValue, &str>,
+		_loop_location: Option<&Location>,
+	) -> WorkflowResult<()> {
+		Err(WorkflowError::ReplayEnd)
+	}
+
+	async fn commit_workflow_message_send_event(
+		&self,
+		_from_workflow_id: Id,
+		_location: &Location,
+		_version: usize,
+		_tags: &serde_json::Value,
+		_message_name: &str,
+		_body: &serde_json::value::RawValue,
+		_loop_location: Option<&Location>,
+	) -> WorkflowResult<()> {
+		Err(WorkflowError::ReplayEnd)
+	}
+
+	async fn upsert_workflow_loop_event(
+		&self,
+		_workflow_id: Id,
+		_workflow_name: &str,
+		_location: &Location,
+		_version: usize,
+		_iteration: usize,
+		_state: &serde_json::value::RawValue,
+		_output: Option<&serde_json::value::RawValue>,
+		_loop_location: Option<&Location>,
+	) -> WorkflowResult<()> {
+		Err(WorkflowError::ReplayEnd)
+	}
+
+	async fn commit_workflow_sleep_event(
+		&self,
+		_from_workflow_id: Id,
+		_location: &Location,
+		_version: usize,
+		_deadline_ts: i64,
+		_loop_location: Option<&Location>,
+	) -> WorkflowResult<()> {
+		Err(WorkflowError::ReplayEnd)
+	}
+
+	async fn update_workflow_sleep_event_state(
+		&self,
+		_from_workflow_id: Id,
+		_location: &Location,
+		_state: SleepState,
+	) -> WorkflowResult<()> {
+		Err(WorkflowError::ReplayEnd)
+	}
+
+	async fn commit_workflow_branch_event(
+		&self,
+		_from_workflow_id: Id,
+		_location: &Location,
+		_version: usize,
+		_loop_location: Option<&Location>,
+	) -> WorkflowResult<()> {
+		Err(WorkflowError::ReplayEnd)
+	}
+
+	async fn commit_workflow_removed_event(
+		&self,
+		_from_workflow_id: Id,
+		_location: &Location,
+		_event_type: EventType,
+		_event_name: Option<&str>,
+		_loop_location: Option<&Location>,
+	) -> WorkflowResult<()> {
+		Err(WorkflowError::ReplayEnd)
+	}
+
+	async fn commit_workflow_version_check_event(
+		&self,
+		_from_workflow_id: Id,
+		_location: &Location,
+		_version: usize,
+		_loop_location: Option<&Location>,
+	) -> WorkflowResult<()> {
+		Err(WorkflowError::ReplayEnd)
+	}
+}
@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+use crate::error::{WorkflowError, WorkflowResult};
+
+/// How long a run can be late before `CatchUp::Skip` drops it.
+pub(crate) const MISSED_RUN_THRESHOLD_MS: i64 = rivet_util::duration::minutes(1);
+/// Most runs dispatched for a single schedule at once with `CatchUp::All`. Remaining runs are dispatched
+/// on the next tick.
+pub(crate) const MAX_CATCH_UP_RUNS: usize = 100;
+
+/// How a schedule handles runs that were missed, for example while no workers were running.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, strum::Display)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum CatchUp {
+	/// Dispatches a single workflow for all of the missed runs.
+	#[default]
+	Latest,
+	/// Dispatches a workflow for every missed run.
+	All,
+	/// Drops runs that are more than a minute late.
+	Skip,
+}
+
+/// Parses a cron expression. Supports an optional leading seconds field, like the service manager's cron
+/// schedules.
+pub(crate) fn parse_cron(cron: &str) -> WorkflowResult<croner::Cron> {
+	croner::Cron::new(cron)
+		.with_seconds_optional()
+		.parse()
+		.map_err(|err| WorkflowError::InvalidCron(err.to_string()))
+}
+
+/// Returns the first run of the given cron after `ts`.
+pub(crate) fn next_run_ts(cron: &croner::Cron, ts: i64) -> WorkflowResult<i64> {
+	let dt = chrono::DateTime::from_timestamp_millis(ts)
+		.ok_or_else(|| WorkflowError::InvalidCron(format!("timestamp out of range: {ts}")))?;
+
+	cron.find_next_occurrence(&dt, false)
+		.map(|dt| dt.timestamp_millis())
+		.map_err(|err| WorkflowError::InvalidCron(err.to_string()))
+}
@@ -11,12 +11,19 @@ use tokio::{signal::ctrl_c, sync::watch, task::JoinHandle};
 use tracing::Instrument;
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
-use crate::{ctx::WorkflowCtx, db::DatabaseHandle, error::WorkflowError, registry::RegistryHandle};
+use crate::{
+	ctx::WorkflowCtx,
+	db::{DatabaseHandle, PullLimits},
+	error::WorkflowError,
+	registry::RegistryHandle,
+};
 
 /// How often to run gc and update ping.
 const PING_INTERVAL: Duration = Duration::from_secs(20);
 /// How often to publish metrics.
 const METRICS_INTERVAL: Duration = Duration::from_secs(20);
+/// How often to check for due schedules.
+const SCHEDULE_INTERVAL: Duration = Duration::from_secs(1);
 /// Time to allow running workflows to shutdown after receiving a SIGINT or SIGTERM.
 const SHUTDOWN_DURATION: Duration = Duration::from_secs(30);
 
@@ -75,6 +82,7 @@ impl Worker {
 
 		let mut gc_handle = self.gc();
 		let mut metrics_handle = self.publish_metrics();
+		let mut schedules_handle = self.dispatch_schedules();
 
 		let res = loop {
 			let shutdown_fut = async {
@@ -103,6 +111,10 @@ impl Worker {
 					tracing::error!(?res, "metrics task unexpectedly stopped");
 					break Ok(());
 				},
+				res = &mut schedules_handle => {
+					tracing::error!(?res, "schedules task unexpectedly stopped");
+					break Ok(());
+				},
 				res = shutdown_fut => {
 					if res.is_err() {
 						tracing::debug!("shutdown channel dropped, ignoring");
@@ -119,6 +131,7 @@ impl Worker {
 				// Cancel background tasks
 				gc_handle.abort();
 				metrics_handle.abort();
+				schedules_handle.abort();
 
 				break Err(err);
 			}
@@ -127,6 +140,7 @@ impl Worker {
 		// Cancel background tasks
 		gc_handle.abort();
 		metrics_handle.abort();
+		schedules_handle.abort();
 
 		res?;
 
@@ -208,19 +222,27 @@ impl Worker {
 	/// Query the database for new workflows and run them.
 	#[tracing::instrument(skip_all)]
 	async fn tick(&mut self, cache: &rivet_cache::Cache) -> Result<()> {
-		// Create filter from registered workflow names
+		let limits = self.pull_limits();
+
+		// Create filter from registered workflow names, excluding names already at their limit
 		let filter = self
 			.registry
 			.workflows
 			.keys()
 			.map(|k| k.as_str())
+			.filter(|name| limits.per_name.get(*name) != Some(&0))
 			.collect::<Vec<_>>();
 
 		// Query awake workflows
-		let workflows = self
-			.db
-			.pull_workflows(self.worker_instance_id, &filter)
-			.await?;
+		let workflows = if filter.is_empty() || limits.total == Some(0) {
+			tracing::debug!("worker at capacity, not pulling workflows");
+
+			Vec::new()
+		} else {
+			self.db
+				.pull_workflows(self.worker_instance_id, &filter, &limits)
+				.await?
+		};
 
 		// Remove join handles for completed workflows. This must happen after we pull workflows to ensure an
 		// accurate state of the current workflows
@@ -235,6 +257,7 @@ impl Worker {
 				continue;
 			}
 
+			let workflow_name = workflow.workflow_name.clone();
 			let (stop_tx, stop_rx) = watch::channel(());
 
 			let ctx = WorkflowCtx::new(
@@ -278,6 +301,7 @@ impl Worker {
 			self.running_workflows.insert(
 				workflow_id,
 				WorkflowHandle {
+					workflow_name,
 					stop: stop_tx,
 					handle,
 				},
@@ -287,6 +311,40 @@ impl Worker {
 		Ok(())
 	}
 
+	/// Remaining capacity of this worker based on the configured concurrency limits.
+	fn pull_limits(&self) -> PullLimits {
+		let workflow_config = self.config.workflow();
+
+		let mut running_total = 0;
+		let mut running_by_name = HashMap::<&str, usize>::new();
+		for wf in self.running_workflows.values() {
+			if !wf.handle.is_finished() {
+				running_total += 1;
+				*running_by_name
+					.entry(wf.workflow_name.as_str())
+					.or_default() += 1;
+			}
+		}
+
+		PullLimits {
+			total: workflow_config
+				.max_concurrent_workflows
+				.map(|max| max.saturating_sub(running_total)),
+			per_name: workflow_config
+				.max_concurrent_workflows_per_name
+				.iter()
+				.map(|(workflow_name, max)| {
+					let running = running_by_name
+						.get(workflow_name.as_str())
+						.copied()
+						.unwrap_or_default();
+
+					(workflow_name.clone(), max.saturating_sub(running))
+				})
+				.collect(),
+		}
+	}
+
 	fn gc(&self) -> JoinHandle<()> {
 		let db = self.db.clone();
 		let worker_instance_id = self.worker_instance_id;
@@ -332,9 +390,31 @@ impl Worker {
 			.instrument(tracing::info_span!("worker_metrics_task")),
 		)
 	}
+
+	fn dispatch_schedules(&self) -> JoinHandle<()> {
+		let db = self.db.clone();
+		let worker_instance_id = self.worker_instance_id;
+
+		tokio::task::spawn(
+			async move {
+				let mut schedule_interval = tokio::time::interval(SCHEDULE_INTERVAL);
+				schedule_interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+
+				loop {
+					schedule_interval.tick().await;
+
+					if let Err(err) = db.dispatch_due_schedules(worker_instance_id).await {
+						tracing::error!(?err, "unhandled schedules error");
+					}
+				}
+			}
+			.instrument(tracing::info_span!("worker_schedules_task")),
+		)
+	}
 }
 
 struct WorkflowHandle {
+	workflow_name: String,
 	stop: watch::Sender<()>,
 	handle: JoinHandle<()>,
 }
@@ -5,7 +5,7 @@ use async_trait::async_trait;
 use serde::{Serialize, de::DeserializeOwned};
 use tokio::sync::MutexGuard;
 
-use crate::ctx::WorkflowCtx;
+use crate::{ctx::WorkflowCtx, query::RegistryQuery};
 
 #[async_trait]
 pub trait Workflow {
@@ -13,8 +13,16 @@ pub trait Workflow {
 	type Output: Serialize + DeserializeOwned + Debug + Send;
 
 	const NAME: &'static str;
+	/// Queries that can be run against this workflow's state by name.
+	const QUERIES: &'static [RegistryQuery] = &[];
 
 	async fn run(ctx: &mut WorkflowCtx, input: &Self::Input) -> Result<Self::Output>;
+
+	/// Cleanup hook that runs once the workflow is cancelled, before it stops. Steps taken here are
+	/// recorded in history like any other.
+	async fn on_cancel(_ctx: &mut WorkflowCtx, _input: &Self::Input) -> Result<()> {
+		Ok(())
+	}
 }
 
 pub trait WorkflowInput: Serialize + DeserializeOwned + Debug + Send {
@@ -1,16 +1,21 @@
 use std::time::Duration;
 
+use gas::db::debug::WorkflowState;
 use gas::prelude::*;
 use gasoline as gas;
 
 mod workflows;
 use workflows::activity_test::*;
 use workflows::basic::*;
+use workflows::cancel_test::*;
 use workflows::eviction_test::*;
 use workflows::listen_timeout::*;
 use workflows::loop_test::*;
+use workflows::replay_test::*;
+use workflows::retry_test::*;
 use workflows::signal_test::*;
 use workflows::sleep_test::*;
+use workflows::state_test::*;
 use workflows::sub_test::*;
 
 #[tokio::test]
@@ -200,6 +205,482 @@ async fn test_workflow_listen_with_timeout() {
 	assert!(res); // Should have timed out since we didn't send a signal
 }
 
+#[tokio::test]
+async fn test_workflow_query() {
+	let mut reg = Registry::new();
+	reg.register_workflow::<StateTestWorkflow>().unwrap();
+	let test_ctx = TestCtx::new(reg).await.unwrap();
+
+	let workflow_id = test_ctx
+		.workflow(StateTestInput { initial_value: 42 })
+		.dispatch()
+		.await
+		.unwrap();
+
+	// Wait for workflow to complete with timeout
+	tokio::time::timeout(
+		Duration::from_secs(5),
+		test_ctx.workflow::<StateTestInput>(workflow_id).output(),
+	)
+	.await
+	.unwrap()
+	.unwrap();
+
+	let value = test_ctx
+		.query::<StateValueQuery>(workflow_id)
+		.await
+		.unwrap();
+	assert_eq!(value, Some(42));
+
+	// Queries are also runnable by name through the registry
+	let mut reg = Registry::new();
+	reg.register_workflow::<StateTestWorkflow>().unwrap();
+	let query = reg
+		.get_query(StateTestWorkflow::NAME, StateValueQuery::NAME)
+		.unwrap();
+	let output =
+		(query.run)(&serde_json::value::to_raw_value(&Some(TestState { value: 7 })).unwrap())
+			.unwrap();
+	assert_eq!(output.get(), "7");
+
+	assert!(reg.get_query(StateTestWorkflow::NAME, "missing").is_err());
+}
+
+#[tokio::test]
+async fn test_workflow_export_import() {
+	let mut reg = Registry::new();
+	reg.register_workflow::<SubTestWorkflow>().unwrap();
+	reg.register_workflow::<BasicWorkflow>().unwrap();
+	let test_ctx = TestCtx::new(reg).await.unwrap();
+
+	let workflow_id = test_ctx
+		.workflow(SubWorkflowInput {
+			parent_value: "parent".to_string(),
+		})
+		.dispatch()
+		.await
+		.unwrap();
+
+	tokio::time::timeout(
+		Duration::from_secs(5),
+		test_ctx.workflow::<SubWorkflowInput>(workflow_id).output(),
+	)
+	.await
+	.unwrap()
+	.unwrap();
+
+	let history = test_ctx
+		.debug_db()
+		.get_workflow_history(workflow_id, true)
+		.await
+		.unwrap()
+		.unwrap();
+
+	// The sub workflow is exported along with its parent
+	let export = test_ctx
+		.debug_db()
+		.export_workflow(workflow_id)
+		.await
+		.unwrap()
+		.unwrap();
+	assert_eq!(export.workflows.len(), 2);
+	assert_eq!(export.workflows[0].workflow_id, workflow_id);
+
+	let workflow_ids = export
+		.workflows
+		.iter()
+		.map(|wf| wf.workflow_id)
+		.collect::<Vec<_>>();
+
+	// Cannot import over existing workflows
+	assert!(
+		test_ctx
+			.debug_db()
+			.import_workflows(&export.workflows)
+			.await
+			.is_err()
+	);
+
+	let purged = test_ctx
+		.debug_db()
+		.purge_workflows(workflow_ids.clone(), None)
+		.await
+		.unwrap();
+	assert_eq!(purged.len(), 2);
+
+	// Round trip through JSON like the CLI does
+	let export = serde_json::from_slice::<gas::db::debug::WorkflowExport>(
+		&serde_json::to_vec(&export).unwrap(),
+	)
+	.unwrap();
+	let imported = test_ctx
+		.debug_db()
+		.import_workflows(&export.workflows)
+		.await
+		.unwrap();
+	assert_eq!(imported, workflow_ids);
+
+	let imported_history = test_ctx
+		.debug_db()
+		.get_workflow_history(workflow_id, true)
+		.await
+		.unwrap()
+		.unwrap();
+	assert_eq!(imported_history.events.len(), history.events.len());
+	assert_eq!(imported_history.wf.output, history.wf.output);
+
+	let workflows = test_ctx.get_workflows(vec![workflow_id]).await.unwrap();
+	let output = workflows[0].parse_output::<SubTestWorkflow>().unwrap();
+	assert_eq!(output.as_deref(), Some("parent_sub"));
+}
+
+#[tokio::test]
+async fn test_workflow_purge_expired() {
+	let mut reg = Registry::new();
+	reg.register_workflow::<SignalTestWorkflow>().unwrap();
+	reg.register_workflow::<SleepTestWorkflow>().unwrap();
+	let test_ctx = TestCtx::new(reg).await.unwrap();
+
+	let workflow_id = test_ctx
+		.workflow(SignalTestInput {})
+		.tag("purge", "complete")
+		.dispatch()
+		.await
+		.unwrap();
+	let running_workflow_id = test_ctx
+		.workflow(SleepTestInput {
+			duration_ms: 60_000,
+		})
+		.tag("purge", "running")
+		.dispatch()
+		.await
+		.unwrap();
+
+	// Give workflow time to start listening
+	tokio::time::sleep(Duration::from_millis(100)).await;
+
+	let signal_id = test_ctx
+		.signal(TestSignal {
+			value: "signal_value".to_string(),
+		})
+		.to_workflow_id(workflow_id)
+		.send()
+		.await
+		.unwrap();
+
+	tokio::time::timeout(
+		Duration::from_secs(5),
+		test_ctx.workflow::<SignalTestInput>(workflow_id).output(),
+	)
+	.await
+	.unwrap()
+	.unwrap();
+
+	assert_eq!(
+		test_ctx
+			.find_workflow::<SignalTestWorkflow>(("purge", "complete"))
+			.await
+			.unwrap(),
+		Some(workflow_id)
+	);
+
+	let before_ts = util::timestamp::now() + 1;
+
+	let purged = test_ctx
+		.debug_db()
+		.purge_expired_workflows(SignalTestWorkflow::NAME, before_ts, None)
+		.await
+		.unwrap();
+	assert_eq!(purged, 1);
+
+	assert!(
+		test_ctx
+			.find_workflow::<SignalTestWorkflow>(("purge", "complete"))
+			.await
+			.unwrap()
+			.is_none()
+	);
+	assert!(
+		test_ctx
+			.debug_db()
+			.get_signals(vec![signal_id])
+			.await
+			.unwrap()
+			.is_empty()
+	);
+
+	// Running workflows are not purged
+	let purged = test_ctx
+		.debug_db()
+		.purge_expired_workflows(SleepTestWorkflow::NAME, before_ts, None)
+		.await
+		.unwrap();
+	assert_eq!(purged, 0);
+
+	assert_eq!(
+		test_ctx
+			.find_workflow::<SleepTestWorkflow>(("purge", "running"))
+			.await
+			.unwrap(),
+		Some(running_workflow_id)
+	);
+}
+
+#[tokio::test]
+async fn test_workflow_replay() {
+	use std::sync::atomic::Ordering;
+
+	use gas::replay::ReplayOutcome;
+
+	let mut reg = Registry::new();
+	reg.register_workflow::<ReplayTestWorkflow>().unwrap();
+	let test_ctx = TestCtx::new(reg).await.unwrap();
+
+	let workflow_id = test_ctx
+		.workflow(ReplayTestInput { value: 21 })
+		.dispatch()
+		.await
+		.unwrap();
+
+	let output = tokio::time::timeout(
+		Duration::from_secs(5),
+		test_ctx.workflow::<ReplayTestInput>(workflow_id).output(),
+	)
+	.await
+	.unwrap()
+	.unwrap();
+	assert_eq!(output, 42);
+	assert_eq!(DOUBLE_RUNS.load(Ordering::SeqCst), 1);
+
+	// Same code replays the whole history
+	let mut reg = Registry::new();
+	reg.register_workflow::<ReplayTestWorkflow>().unwrap();
+	let report = test_ctx.replay(reg, workflow_id).await.unwrap();
+	assert!(
+		matches!(report.outcome, ReplayOutcome::Complete),
+		"{}",
+		report.outcome
+	);
+
+	// Activities are replayed from their recorded output, never run
+	assert_eq!(DOUBLE_RUNS.load(Ordering::SeqCst), 1);
+
+	// Appending a step stops replay at the new step
+	let mut reg = Registry::new();
+	reg.register_workflow::<extended::ExtendedReplayTestWorkflow>()
+		.unwrap();
+	let report = test_ctx.replay(reg, workflow_id).await.unwrap();
+	assert!(
+		matches!(report.outcome, ReplayOutcome::Incomplete(_)),
+		"{}",
+		report.outcome
+	);
+
+	// Replacing a recorded step diverges
+	let mut reg = Registry::new();
+	reg.register_workflow::<changed::ChangedReplayTestWorkflow>()
+		.unwrap();
+	let report = test_ctx.replay(reg, workflow_id).await.unwrap();
+	assert!(report.outcome.is_diverged(), "{}", report.outcome);
+}
+
+#[tokio::test]
+async fn test_workflow_cancel() {
+	let mut reg = Registry::new();
+	reg.register_workflow::<CancelTestWorkflow>().unwrap();
+	reg.register_workflow::<SleepTestWorkflow>().unwrap();
+	let test_ctx = TestCtx::new(reg).await.unwrap();
+
+	let workflow_id = test_ctx
+		.workflow(CancelTestInput {
+			duration_ms: 60_000,
+		})
+		.dispatch()
+		.await
+		.unwrap();
+
+	// Give workflow time to start sleeping
+	tokio::time::sleep(Duration::from_millis(500)).await;
+
+	test_ctx.cancel_workflow(workflow_id).await.unwrap();
+
+	// Wait for the workflow to stop
+	let res = wait_for_state(&test_ctx, workflow_id, WorkflowState::Cancelled).await;
+	assert_eq!(res.error.as_deref(), Some("workflow cancelled"));
+
+	// Cleanup hook ran
+	let state = serde_json::from_value::<CancelTestState>(res.data).unwrap();
+	assert!(state.cleaned_up);
+
+	// Sub workflow was cancelled too
+	let sub_workflow_id = state.sub_workflow_id.unwrap();
+	let res = wait_for_state(&test_ctx, sub_workflow_id, WorkflowState::Cancelled).await;
+	assert_eq!(res.error.as_deref(), Some("workflow cancelled"));
+
+	// Cancelled workflows are purgeable
+	let purged = test_ctx
+		.debug_db()
+		.purge_expired_workflows(CancelTestWorkflow::NAME, util::timestamp::now() + 1, None)
+		.await
+		.unwrap();
+	assert_eq!(purged, 1);
+
+	assert!(
+		gas::db::debug::DatabaseDebug::get_workflows(test_ctx.debug_db(), vec![workflow_id])
+			.await
+			.unwrap()
+			.is_empty()
+	);
+}
+
+#[tokio::test]
+async fn test_workflow_cancel_wakes_waiter() {
+	let mut reg = Registry::new();
+	reg.register_workflow::<CancelWaitTestWorkflow>().unwrap();
+	reg.register_workflow::<SleepTestWorkflow>().unwrap();
+	let test_ctx = TestCtx::new(reg).await.unwrap();
+
+	let sleep_workflow_id = test_ctx
+		.workflow(SleepTestInput {
+			duration_ms: 60_000,
+		})
+		.dispatch()
+		.await
+		.unwrap();
+
+	let workflow_id = test_ctx
+		.workflow(CancelWaitTestInput {
+			workflow_id: sleep_workflow_id,
+		})
+		.dispatch()
+		.await
+		.unwrap();
+
+	// Give the waiter time to start waiting
+	tokio::time::sleep(Duration::from_millis(500)).await;
+
+	test_ctx.cancel_workflow(sleep_workflow_id).await.unwrap();
+
+	wait_for_state(&test_ctx, sleep_workflow_id, WorkflowState::Cancelled).await;
+
+	// The waiter is woken and fails instead of waiting forever
+	let res = wait_for_state(&test_ctx, workflow_id, WorkflowState::Dead).await;
+	assert!(
+		res.error
+			.as_deref()
+			.unwrap()
+			.starts_with("sub workflow cancelled"),
+		"unexpected error: {:?}",
+		res.error
+	);
+}
+
+#[tokio::test]
+async fn test_activity_retry_policy() {
+	let mut reg = Registry::new();
+	reg.register_workflow::<RetryTestWorkflow>().unwrap();
+	let test_ctx = TestCtx::new(reg).await.unwrap();
+
+	let cases = [
+		// Retried with a short backoff until max retries is reached
+		(
+			RetryTestInput::Flaky,
+			&FLAKY_RUNS,
+			3,
+			"activity failure, max retries reached",
+		),
+		// `RivetError` group marked as non-retryable
+		(
+			RetryTestInput::Validate,
+			&VALIDATE_RUNS,
+			1,
+			"activity failure, not retryable",
+		),
+		// Error marked with `NonRetryable`
+		(
+			RetryTestInput::Reject,
+			&REJECT_RUNS,
+			1,
+			"activity failure, not retryable",
+		),
+	];
+
+	for (input, runs, expected_runs, expected_error) in cases {
+		let workflow_id = test_ctx.workflow(input).dispatch().await.unwrap();
+
+		// Wait for the workflow to die
+		let res = wait_for_state(&test_ctx, workflow_id, WorkflowState::Dead).await;
+
+		assert!(
+			res.error.as_deref().unwrap().starts_with(expected_error),
+			"unexpected error: {:?}",
+			res.error
+		);
+		assert_eq!(
+			runs.load(std::sync::atomic::Ordering::SeqCst),
+			expected_runs
+		);
+	}
+}
+
+#[tokio::test]
+async fn test_workflow_schedule() {
+	let mut reg = Registry::new();
+	reg.register_workflow::<SleepTestWorkflow>().unwrap();
+	let test_ctx = TestCtx::new(reg).await.unwrap();
+
+	let schedule_id = test_ctx
+		.schedule_workflow(
+			"* * * * * *",
+			SleepTestInput {
+				duration_ms: 60_000,
+			},
+		)
+		.tag("schedule", "test")
+		.unique()
+		.create()
+		.await
+		.unwrap();
+
+	// Creating the same unique schedule again returns the existing one
+	let existing_schedule_id = test_ctx
+		.schedule_workflow("0 0 * * * *", SleepTestInput { duration_ms: 0 })
+		.tag("schedule", "test")
+		.unique()
+		.create()
+		.await
+		.unwrap();
+	assert_eq!(schedule_id, existing_schedule_id);
+
+	// Wait for the schedule to dispatch a workflow
+	tokio::time::timeout(Duration::from_secs(5), async {
+		loop {
+			if test_ctx
+				.find_workflow::<SleepTestWorkflow>(("schedule", "test"))
+				.await
+				.unwrap()
+				.is_some()
+			{
+				break;
+			}
+
+			tokio::time::sleep(Duration::from_millis(100)).await;
+		}
+	})
+	.await
+	.unwrap();
+
+	let schedules = test_ctx.debug_db().list_schedules(None).await.unwrap();
+	assert_eq!(schedules.len(), 1);
+	assert_eq!(schedules[0].schedule_id, schedule_id);
+	assert_eq!(schedules[0].workflow_name, "sleep_test_workflow");
+
+	test_ctx.delete_schedule(schedule_id).await.unwrap();
+
+	let schedules = test_ctx.debug_db().list_schedules(None).await.unwrap();
+	assert!(schedules.is_empty());
+}
+
 #[tokio::test]
 async fn test_workflow_eviction() {
 	fn build_reg() -> Registry {
@@ -243,7 +724,7 @@ async fn test_workflow_eviction() {
 				.into_iter()
 				.next()
 				.unwrap();
-		assert_eq!(res.state, gas::db::debug::WorkflowState::Sleeping,);
+		assert_eq!(res.state, WorkflowState::Sleeping,);
 
 		(
 			workflow_id,
@@ -286,3 +767,130 @@ async fn test_workflow_eviction() {
 		sub.next().await.unwrap();
 	}
 }
+
+#[tokio::test]
+async fn test_pull_workflows_limits() {
+	// Nothing is registered so the test worker leaves all workflows to be pulled manually
+	let test_ctx = TestCtx::new(Registry::new()).await.unwrap();
+
+	for i in 0..3 {
+		test_ctx
+			.workflow(BasicWorkflowInput {
+				value: i.to_string(),
+			})
+			.dispatch()
+			.await
+			.unwrap();
+		test_ctx
+			.workflow(SleepTestInput { duration_ms: 100 })
+			.dispatch()
+			.await
+			.unwrap();
+	}
+
+	let db = db::DatabaseKv::from_pools(test_ctx.pools().clone())
+		.await
+		.unwrap();
+	let filter = [BasicWorkflow::NAME, SleepTestWorkflow::NAME];
+
+	let limits = db::PullLimits {
+		total: Some(3),
+		per_name: [(BasicWorkflow::NAME.to_string(), 1)].into(),
+	};
+	let pulled = db
+		.pull_workflows(Id::new_v1(test_ctx.config().dc_label()), &filter, &limits)
+		.await
+		.unwrap();
+	assert_eq!(pulled.len(), 3);
+	assert_eq!(
+		pulled
+			.iter()
+			.filter(|wf| wf.workflow_name == BasicWorkflow::NAME)
+			.count(),
+		1
+	);
+
+	// Workflows past the limits are left for other workers
+	let pulled = db
+		.pull_workflows(
+			Id::new_v1(test_ctx.config().dc_label()),
+			&filter,
+			&db::PullLimits::default(),
+		)
+		.await
+		.unwrap();
+	assert_eq!(pulled.len(), 3);
+}
+
+#[tokio::test]
+async fn test_pull_workflows_priority() {
+	// Nothing is registered so the test worker leaves all workflows to be pulled manually
+	let test_ctx = TestCtx::new(Registry::new()).await.unwrap();
+
+	for _ in 0..2 {
+		test_ctx
+			.workflow(BasicWorkflowInput {
+				value: "low".to_string(),
+			})
+			.dispatch()
+			.await
+			.unwrap();
+	}
+	let high_priority_workflow_id = test_ctx
+		.workflow(BasicWorkflowInput {
+			value: "high".to_string(),
+		})
+		.priority(10)
+		.dispatch()
+		.await
+		.unwrap();
+
+	let db = db::DatabaseKv::from_pools(test_ctx.pools().clone())
+		.await
+		.unwrap();
+
+	// Dispatched last but pulled first
+	let limits = db::PullLimits {
+		total: Some(1),
+		..Default::default()
+	};
+	let pulled = db
+		.pull_workflows(
+			Id::new_v1(test_ctx.config().dc_label()),
+			&[BasicWorkflow::NAME],
+			&limits,
+		)
+		.await
+		.unwrap();
+	assert_eq!(pulled.len(), 1);
+	assert_eq!(pulled[0].workflow_id, high_priority_workflow_id);
+}
+
+/// Polls the workflow until it reaches the given state, failing after 5 seconds.
+async fn wait_for_state(
+	test_ctx: &TestCtx,
+	workflow_id: Id,
+	state: WorkflowState,
+) -> gas::db::debug::WorkflowData {
+	tokio::time::timeout(Duration::from_secs(5), async {
+		loop {
+			let res = gas::db::debug::DatabaseDebug::get_workflows(
+				test_ctx.debug_db(),
+				vec![workflow_id],
+			)
+			.await
+			.unwrap()
+			.into_iter()
+			.next()
+			.unwrap();
+
+			if res.state == state {
+				break res;
+			}
+
+			tokio::time::sleep(Duration::from_millis(100)).await;
+		}
+	})
+	.await
+	.unwrap()
+}
@@ -0,0 +1,79 @@
+use super::sleep_test::SleepTestInput;
+use gas::prelude::*;
+use gasoline as gas;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CancelTestInput {
+	pub duration_ms: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CancelTestState {
+	pub sub_workflow_id: Option<Id>,
+	pub cleaned_up: bool,
+}
+
+#[workflow(CancelTestWorkflow)]
+#[on_cancel = cancel_test_cleanup]
+pub async fn cancel_test_workflow(ctx: &mut WorkflowCtx, input: &CancelTestInput) -> Result<()> {
+	let sub_workflow_id = ctx
+		.workflow(SleepTestInput {
+			duration_ms: input.duration_ms,
+		})
+		.dispatch()
+		.await?;
+
+	ctx.activity(SetSubWorkflowIdInput { sub_workflow_id })
+		.await?;
+
+	ctx.sleep(input.duration_ms).await?;
+
+	Ok(())
+}
+
+async fn cancel_test_cleanup(ctx: &mut WorkflowCtx, _input: &CancelTestInput) -> Result<()> {
+	ctx.activity(CleanupActivityInput {}).await?;
+
+	Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize, Hash)]
+pub struct SetSubWorkflowIdInput {
+	pub sub_workflow_id: Id,
+}
+
+#[activity(SetSubWorkflowId)]
+pub async fn set_sub_workflow_id(ctx: &ActivityCtx, input: &SetSubWorkflowIdInput) -> Result<()> {
+	let mut state = ctx.state::<Option<CancelTestState>>()?;
+	state.get_or_insert_default().sub_workflow_id = Some(input.sub_workflow_id);
+
+	Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize, Hash)]
+pub struct CleanupActivityInput {}
+
+#[activity(CleanupActivity)]
+pub async fn cleanup_activity(ctx: &ActivityCtx, _input: &CleanupActivityInput) -> Result<()> {
+	let mut state = ctx.state::<Option<CancelTestState>>()?;
+	state.get_or_insert_default().cleaned_up = true;
+
+	Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CancelWaitTestInput {
+	pub workflow_id: Id,
+}
+
+#[workflow(CancelWaitTestWorkflow)]
+pub async fn cancel_wait_test_workflow(
+	ctx: &mut WorkflowCtx,
+	input: &CancelWaitTestInput,
+) -> Result<()> {
+	ctx.workflow::<SleepTestInput>(input.workflow_id)
+		.output()
+		.await?;
+
+	Ok(())
+}
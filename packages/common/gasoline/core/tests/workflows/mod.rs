@@ -1,9 +1,12 @@
 pub mod activity_test;
 pub mod basic;
+pub mod cancel_test;
 pub mod eviction_test;
 pub mod listen_timeout;
 pub mod loop_test;
 pub mod properties_test;
+pub mod replay_test;
+pub mod retry_test;
 pub mod signal_test;
 pub mod sleep_test;
 pub mod state_test;
@@ -0,0 +1,88 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use gas::prelude::*;
+use gasoline as gas;
+
+/// Amount of times `DoubleActivity` actually ran.
+pub static DOUBLE_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReplayTestInput {
+	pub value: i32,
+}
+
+#[workflow(ReplayTestWorkflow)]
+pub async fn replay_test_workflow(ctx: &mut WorkflowCtx, input: &ReplayTestInput) -> Result<i32> {
+	let doubled = ctx
+		.activity(DoubleActivityInput { value: input.value })
+		.await?;
+
+	Ok(doubled)
+}
+
+#[derive(Debug, Serialize, Deserialize, Hash)]
+pub struct DoubleActivityInput {
+	pub value: i32,
+}
+
+#[activity(DoubleActivity)]
+pub async fn double_activity(_ctx: &ActivityCtx, input: &DoubleActivityInput) -> Result<i32> {
+	DOUBLE_RUNS.fetch_add(1, Ordering::SeqCst);
+
+	Ok(input.value * 2)
+}
+
+#[derive(Debug, Serialize, Deserialize, Hash)]
+pub struct NegateActivityInput {
+	pub value: i32,
+}
+
+#[activity(NegateActivity)]
+pub async fn negate_activity(_ctx: &ActivityCtx, input: &NegateActivityInput) -> Result<i32> {
+	Ok(-input.value)
+}
+
+/// New version of `replay_test_workflow` that appends a step, which in-flight histories can replay.
+pub mod extended {
+	use super::*;
+
+	#[derive(Debug, Serialize, Deserialize)]
+	pub struct ReplayTestInput {
+		pub value: i32,
+	}
+
+	#[workflow(ExtendedReplayTestWorkflow)]
+	pub async fn replay_test_workflow(
+		ctx: &mut WorkflowCtx,
+		input: &ReplayTestInput,
+	) -> Result<i32> {
+		let doubled = ctx
+			.activity(DoubleActivityInput { value: input.value })
+			.await?;
+		let negated = ctx.activity(NegateActivityInput { value: doubled }).await?;
+
+		Ok(negated)
+	}
+}
+
+/// New version of `replay_test_workflow` that replaces its first step, which breaks recorded histories.
+pub mod changed {
+	use super::*;
+
+	#[derive(Debug, Serialize, Deserialize)]
+	pub struct ReplayTestInput {
+		pub value: i32,
+	}
+
+	#[workflow(ChangedReplayTestWorkflow)]
+	pub async fn replay_test_workflow(
+		ctx: &mut WorkflowCtx,
+		input: &ReplayTestInput,
+	) -> Result<i32> {
+		let negated = ctx
+			.activity(NegateActivityInput { value: input.value })
+			.await?;
+
+		Ok(negated)
+	}
+}
@@ -0,0 +1,73 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use gas::prelude::*;
+use gasoline as gas;
+use rivet_error::*;
+
+/// Amount of times `FlakyActivity` ran.
+pub static FLAKY_RUNS: AtomicUsize = AtomicUsize::new(0);
+/// Amount of times `ValidateActivity` ran.
+pub static VALIDATE_RUNS: AtomicUsize = AtomicUsize::new(0);
+/// Amount of times `RejectActivity` ran.
+pub static REJECT_RUNS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(RivetError, Debug, Deserialize, Serialize)]
+#[error("validation")]
+pub enum Validation {
+	#[error("invalid_value", "The value is invalid.")]
+	InvalidValue,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum RetryTestInput {
+	Flaky,
+	Validate,
+	Reject,
+}
+
+#[workflow(RetryTestWorkflow)]
+pub async fn retry_test_workflow(ctx: &mut WorkflowCtx, input: &RetryTestInput) -> Result<()> {
+	match input {
+		RetryTestInput::Flaky => ctx.activity(FlakyActivityInput {}).await?,
+		RetryTestInput::Validate => ctx.activity(ValidateActivityInput {}).await?,
+		RetryTestInput::Reject => ctx.activity(RejectActivityInput {}).await?,
+	}
+
+	Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize, Hash)]
+pub struct FlakyActivityInput {}
+
+#[activity(FlakyActivity)]
+#[max_retries = 3]
+#[initial_interval = 10]
+#[multiplier = 1.5]
+#[max_interval = 50]
+#[jitter = 0]
+pub async fn flaky_activity(_ctx: &ActivityCtx, _input: &FlakyActivityInput) -> Result<()> {
+	FLAKY_RUNS.fetch_add(1, Ordering::SeqCst);
+
+	bail!("flaky failure");
+}
+
+#[derive(Debug, Serialize, Deserialize, Hash)]
+pub struct ValidateActivityInput {}
+
+#[activity(ValidateActivity)]
+#[non_retryable = "validation"]
+pub async fn validate_activity(_ctx: &ActivityCtx, _input: &ValidateActivityInput) -> Result<()> {
+	VALIDATE_RUNS.fetch_add(1, Ordering::SeqCst);
+
+	Err(Validation::InvalidValue.build()).context("failed to validate input")
+}
+
+#[derive(Debug, Serialize, Deserialize, Hash)]
+pub struct RejectActivityInput {}
+
+#[activity(RejectActivity)]
+pub async fn reject_activity(_ctx: &ActivityCtx, _input: &RejectActivityInput) -> Result<()> {
+	REJECT_RUNS.fetch_add(1, Ordering::SeqCst);
+
+	Err(anyhow!("rejected")).context(NonRetryable)
+}
@@ -7,6 +7,7 @@ pub struct StateTestInput {
 }
 
 #[workflow(StateTestWorkflow)]
+#[query = StateValueQuery]
 pub async fn state_test_workflow(ctx: &mut WorkflowCtx, input: &StateTestInput) -> Result<i32> {
 	// First activity sets state
 	ctx.activity(SetStateActivityInput {
@@ -44,6 +45,11 @@ pub async fn set_state_activity(ctx: &ActivityCtx, input: &SetStateActivityInput
 	Ok(())
 }
 
+#[query(StateValueQuery)]
+pub fn state_value(state: &Option<TestState>) -> Result<Option<i32>> {
+	Ok(state.as_ref().map(|s| s.value))
+}
+
 #[derive(Debug, Serialize, Deserialize, Hash)]
 pub struct GetStateActivityInput {}
 
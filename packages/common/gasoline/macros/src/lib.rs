@@ -10,6 +10,11 @@ use syn::{
 struct Config {
 	max_retries: usize,
 	timeout: u64,
+	initial_interval: Option<u64>,
+	multiplier: Option<f64>,
+	max_interval: Option<u64>,
+	jitter: Option<u64>,
+	non_retryable: Vec<syn::LitStr>,
 }
 
 impl Default for Config {
@@ -17,10 +22,21 @@ impl Default for Config {
 		Config {
 			max_retries: 5,
 			timeout: 30,
+			initial_interval: None,
+			multiplier: None,
+			max_interval: None,
+			jitter: None,
+			non_retryable: Vec::new(),
 		}
 	}
 }
 
+#[derive(Default)]
+struct WorkflowConfig {
+	on_cancel: Option<syn::Path>,
+	queries: Vec<syn::Path>,
+}
+
 struct MessageConfig {
 	tail_ttl: u64,
 }
@@ -39,9 +55,10 @@ pub fn workflow(attr: TokenStream, item: TokenStream) -> TokenStream {
 		.unwrap_or_else(|| "Workflow".to_string());
 	let item_fn = parse_macro_input!(item as ItemFn);
 
-	if let Err(err) = parse_empty_config(&item_fn.attrs) {
-		return err.into_compile_error().into();
-	}
+	let config = match parse_workflow_config(&item_fn.attrs) {
+		Ok(x) => x,
+		Err(err) => return err.into_compile_error().into(),
+	};
 
 	let ctx_ty = syn::parse_str("&mut WorkflowCtx").unwrap();
 	let TraitFnOutput {
@@ -69,6 +86,24 @@ pub fn workflow(attr: TokenStream, item: TokenStream) -> TokenStream {
 	let fn_body = item_fn.block;
 	let vis = item_fn.vis;
 
+	let on_cancel = config.on_cancel.map(|on_cancel| {
+		quote! {
+			async fn on_cancel(#ctx_ident: #ctx_ty, #input_ident: &Self::Input) -> Result<()> {
+				#on_cancel(#ctx_ident, #input_ident).await
+			}
+		}
+	});
+
+	let queries = (!config.queries.is_empty()).then(|| {
+		let queries = config.queries;
+
+		quote! {
+			const QUERIES: &'static [gas::query::RegistryQuery] = &[
+				#(gas::query::RegistryQuery::new::<#queries>(),)*
+			];
+		}
+	});
+
 	let expanded = quote! {
 		#vis struct #struct_ident;
 
@@ -82,10 +117,13 @@ pub fn workflow(attr: TokenStream, item: TokenStream) -> TokenStream {
 			type Output = #output_type;
 
 			const NAME: &'static str = #fn_name;
+			#queries
 
 			async fn run(#ctx_ident: #ctx_ty, #input_ident: &Self::Input) -> Result<Self::Output> {
 				#fn_body
 			}
+
+			#on_cancel
 		}
 	};
 
@@ -133,6 +171,28 @@ pub fn activity(attr: TokenStream, item: TokenStream) -> TokenStream {
 	let max_retries = config.max_retries;
 	let timeout = config.timeout;
 
+	// Unset properties fall back to the default retry policy
+	let mut retry_policy_fields = Vec::new();
+	if let Some(initial_interval) = config.initial_interval {
+		retry_policy_fields.push(
+			quote! { initial_interval: std::time::Duration::from_millis(#initial_interval), },
+		);
+	}
+	if let Some(multiplier) = config.multiplier {
+		retry_policy_fields.push(quote! { multiplier: #multiplier, });
+	}
+	if let Some(max_interval) = config.max_interval {
+		retry_policy_fields
+			.push(quote! { max_interval: std::time::Duration::from_millis(#max_interval), });
+	}
+	if let Some(jitter) = config.jitter {
+		retry_policy_fields.push(quote! { jitter: std::time::Duration::from_millis(#jitter), });
+	}
+	if !config.non_retryable.is_empty() {
+		let non_retryable = &config.non_retryable;
+		retry_policy_fields.push(quote! { non_retryable_groups: &[#(#non_retryable),*], });
+	}
+
 	let expanded = quote! {
 		#vis struct #struct_ident;
 
@@ -148,6 +208,10 @@ pub fn activity(attr: TokenStream, item: TokenStream) -> TokenStream {
 			const NAME: &'static str = #fn_name;
 			const MAX_RETRIES: usize = #max_retries;
 			const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(#timeout);
+			const RETRY_POLICY: gas::activity::RetryPolicy = gas::activity::RetryPolicy {
+				#(#retry_policy_fields)*
+				..gas::activity::RetryPolicy::DEFAULT
+			};
 
 			async fn run(#ctx_ident: #ctx_ty, #input_ident: &Self::Input) -> Result<Self::Output> {
 				#fn_body
@@ -283,39 +347,7 @@ fn parse_trait_fn(
 		return Err(error(arg_types[1].span(), "input type must be a reference"));
 	};
 
-	let output_type = match &item_fn.sig.output {
-		ReturnType::Type(_, ty) => match ty.as_ref() {
-			Type::Path(path) => {
-				let segment = path.path.segments.last().unwrap();
-				if segment.ident == "Result" {
-					match &segment.arguments {
-						PathArguments::AngleBracketed(args) => {
-							if let Some(GenericArgument::Type(ty)) = args.args.first() {
-								ty.clone()
-							} else {
-								return Err(error(args.span(), "unsupported Result type"));
-							}
-						}
-						_ => {
-							return Err(error(segment.arguments.span(), "unsupported Result type"));
-						}
-					}
-				} else {
-					return Err(error(
-						path.span(),
-						&format!("{} function must return a Result type", trait_name),
-					));
-				}
-			}
-			_ => return Err(error(ty.span(), "unsupported output type")),
-		},
-		_ => {
-			return Err(error(
-				item_fn.sig.output.span(),
-				&format!("{} function must have a return type", trait_name),
-			));
-		}
-	};
+	let output_type = parse_result_type(trait_name, &item_fn.sig.output)?;
 
 	Ok(TraitFnOutput {
 		ctx_ident: Ident::new(&arg_names[0], proc_macro2::Span::call_site()),
@@ -325,6 +357,72 @@ fn parse_trait_fn(
 	})
 }
 
+#[proc_macro_attribute]
+pub fn query(attr: TokenStream, item: TokenStream) -> TokenStream {
+	let name = parse_macro_input!(attr as Ident).to_string();
+	let item_fn = parse_macro_input!(item as ItemFn);
+
+	if item_fn.sig.asyncness.is_some() {
+		return error(item_fn.sig.span(), "query function must not be async");
+	}
+
+	let (state_ident, state_type) = match item_fn.sig.inputs.first() {
+		Some(syn::FnArg::Typed(arg)) if item_fn.sig.inputs.len() == 1 => {
+			let syn::Pat::Ident(ident) = arg.pat.as_ref() else {
+				return error(arg.pat.span(), "unsupported input parameter pattern");
+			};
+			let syn::Type::Reference(syn::TypeReference { elem, .. }) = arg.ty.as_ref() else {
+				return error(arg.ty.span(), "state type must be a reference");
+			};
+
+			(ident.ident.clone(), elem.clone())
+		}
+		_ => {
+			return error(
+				item_fn.sig.span(),
+				"Query function must have exactly one parameter: state: &YourStateType",
+			);
+		}
+	};
+
+	let output_type = match parse_result_type("Query", &item_fn.sig.output) {
+		Ok(x) => x,
+		Err(err) => return err,
+	};
+
+	let struct_ident = Ident::new(&name, proc_macro2::Span::call_site());
+	let fn_name = item_fn.sig.ident.to_string();
+	if !fn_name
+		.chars()
+		.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+	{
+		return error(
+			item_fn.sig.ident.span(),
+			"invalid query name, must be [a-z0-9_]",
+		);
+	}
+
+	let fn_body = item_fn.block;
+	let vis = item_fn.vis;
+
+	let expanded = quote! {
+		#vis struct #struct_ident;
+
+		impl gas::query::Query for #struct_ident {
+			type State = #state_type;
+			type Output = #output_type;
+
+			const NAME: &'static str = #fn_name;
+
+			fn run(#state_ident: &Self::State) -> Result<Self::Output> {
+				#fn_body
+			}
+		}
+	};
+
+	TokenStream::from(expanded)
+}
+
 #[proc_macro_attribute]
 pub fn signal(attr: TokenStream, item: TokenStream) -> TokenStream {
 	let name = parse_macro_input!(attr as LitStr);
@@ -425,6 +523,39 @@ pub fn message(attr: TokenStream, item: TokenStream) -> TokenStream {
 	TokenStream::from(expanded)
 }
 
+/// Extracts `T` from a `Result<T>` return type.
+fn parse_result_type(trait_name: &str, output: &ReturnType) -> Result<syn::Type, TokenStream> {
+	match output {
+		ReturnType::Type(_, ty) => match ty.as_ref() {
+			Type::Path(path) => {
+				let segment = path.path.segments.last().unwrap();
+				if segment.ident == "Result" {
+					match &segment.arguments {
+						PathArguments::AngleBracketed(args) => {
+							if let Some(GenericArgument::Type(ty)) = args.args.first() {
+								Ok(ty.clone())
+							} else {
+								Err(error(args.span(), "unsupported Result type"))
+							}
+						}
+						_ => Err(error(segment.arguments.span(), "unsupported Result type")),
+					}
+				} else {
+					Err(error(
+						path.span(),
+						&format!("{} function must return a Result type", trait_name),
+					))
+				}
+			}
+			_ => Err(error(ty.span(), "unsupported output type")),
+		},
+		_ => Err(error(
+			output.span(),
+			&format!("{} function must have a return type", trait_name),
+		)),
+	}
+}
+
 fn error(span: proc_macro2::Span, msg: &str) -> TokenStream {
 	syn::Error::new(span, msg).to_compile_error().into()
 }
@@ -447,6 +578,33 @@ fn parse_config(attrs: &[syn::Attribute]) -> syn::Result<Config> {
 		} else if ident == "timeout" {
 			config.timeout = syn::parse::<syn::LitInt>(name_value.value.to_token_stream().into())?
 				.base10_parse()?;
+		} else if ident == "initial_interval" {
+			config.initial_interval = Some(
+				syn::parse::<syn::LitInt>(name_value.value.to_token_stream().into())?
+					.base10_parse()?,
+			);
+		} else if ident == "multiplier" {
+			config.multiplier = Some(
+				match syn::parse::<syn::Lit>(name_value.value.to_token_stream().into())? {
+					syn::Lit::Float(lit) => lit.base10_parse()?,
+					syn::Lit::Int(lit) => lit.base10_parse()?,
+					lit => return Err(syn::Error::new(lit.span(), "expected a number")),
+				},
+			);
+		} else if ident == "max_interval" {
+			config.max_interval = Some(
+				syn::parse::<syn::LitInt>(name_value.value.to_token_stream().into())?
+					.base10_parse()?,
+			);
+		} else if ident == "jitter" {
+			config.jitter = Some(
+				syn::parse::<syn::LitInt>(name_value.value.to_token_stream().into())?
+					.base10_parse()?,
+			);
+		} else if ident == "non_retryable" {
+			config.non_retryable.push(syn::parse::<syn::LitStr>(
+				name_value.value.to_token_stream().into(),
+			)?);
 		} else if ident != "doc" {
 			return Err(syn::Error::new(
 				name_value.span(),
@@ -458,8 +616,8 @@ fn parse_config(attrs: &[syn::Attribute]) -> syn::Result<Config> {
 	Ok(config)
 }
 
-fn parse_msg_config(attrs: &[syn::Attribute]) -> syn::Result<MessageConfig> {
-	let mut config = MessageConfig::default();
+fn parse_workflow_config(attrs: &[syn::Attribute]) -> syn::Result<WorkflowConfig> {
+	let mut config = WorkflowConfig::default();
 
 	for attr in attrs {
 		let syn::Meta::NameValue(name_value) = &attr.meta else {
@@ -469,9 +627,14 @@ fn parse_msg_config(attrs: &[syn::Attribute]) -> syn::Result<MessageConfig> {
 		let ident = name_value.path.require_ident()?;
 
 		// Verify config property
-		if ident == "tail_ttl" {
-			config.tail_ttl = syn::parse::<syn::LitInt>(name_value.value.to_token_stream().into())?
-				.base10_parse()?;
+		if ident == "on_cancel" {
+			config.on_cancel = Some(syn::parse::<syn::Path>(
+				name_value.value.to_token_stream().into(),
+			)?);
+		} else if ident == "query" {
+			config.queries.push(syn::parse::<syn::Path>(
+				name_value.value.to_token_stream().into(),
+			)?);
 		} else if ident != "doc" {
 			return Err(syn::Error::new(
 				name_value.span(),
@@ -483,7 +646,9 @@ fn parse_msg_config(attrs: &[syn::Attribute]) -> syn::Result<MessageConfig> {
 	Ok(config)
 }
 
-fn parse_empty_config(attrs: &[syn::Attribute]) -> syn::Result<()> {
+fn parse_msg_config(attrs: &[syn::Attribute]) -> syn::Result<MessageConfig> {
+	let mut config = MessageConfig::default();
+
 	for attr in attrs {
 		let syn::Meta::NameValue(name_value) = &attr.meta else {
 			continue;
@@ -491,7 +656,11 @@ fn parse_empty_config(attrs: &[syn::Attribute]) -> syn::Result<()> {
 
 		let ident = name_value.path.require_ident()?;
 
-		if ident != "doc" {
+		// Verify config property
+		if ident == "tail_ttl" {
+			config.tail_ttl = syn::parse::<syn::LitInt>(name_value.value.to_token_stream().into())?
+				.base10_parse()?;
+		} else if ident != "doc" {
 			return Err(syn::Error::new(
 				name_value.span(),
 				format!("Unknown config property `{ident}`"),
@@ -499,7 +668,7 @@ fn parse_empty_config(attrs: &[syn::Attribute]) -> syn::Result<()> {
 		}
 	}
 
-	Ok(())
+	Ok(config)
 }
 
 struct OptionalIdent {
@@ -24,8 +24,25 @@ pub async fn setup(config: Config) -> Result<Option<UdbPool>> {
 				as universaldb::DatabaseDriverHandle
 		}
 		config::Database::FileSystem(fs) => {
-			Arc::new(universaldb::driver::RocksDbDatabaseDriver::new(fs.path.clone()).await?)
-				as universaldb::DatabaseDriverHandle
+			let mut driver =
+				universaldb::driver::RocksDbDatabaseDriver::new(fs.path.clone()).await?;
+			if let Some(backup) = &fs.backup {
+				driver.start_backups(universaldb::driver::rocksdb::backup::BackupConfig {
+					path: backup.path(&fs.path),
+					interval: backup.interval(),
+					keep_last: backup.keep_last(),
+				});
+			}
+
+			Arc::new(driver) as universaldb::DatabaseDriverHandle
+		}
+		#[cfg(feature = "fdb")]
+		config::Database::FoundationDb(fdb) => Arc::new(
+			universaldb::driver::FoundationDbDatabaseDriver::new(fdb.cluster_file.clone())?,
+		) as universaldb::DatabaseDriverHandle,
+		#[cfg(not(feature = "fdb"))]
+		config::Database::FoundationDb(_) => {
+			bail!("foundationdb support requires building with the `fdb` feature")
 		}
 	};
 
@@ -35,3 +52,38 @@ pub async fn setup(config: Config) -> Result<Option<UdbPool>> {
 		db: universaldb::Database::new(db_driver),
 	}))
 }
+
+/// Sets up a pool for tooling that only reads, such as debug and analytics commands. Transactions
+/// reject writes. Reads go to a RocksDB secondary instance or the Postgres read replica when
+/// possible, keeping load off of the database used by live traffic.
+#[tracing::instrument(skip(config))]
+pub async fn setup_read_only(config: Config) -> Result<Option<UdbPool>> {
+	let db_driver = match config.database() {
+		config::Database::Postgres(pg) => {
+			let url = pg.read_replica_url.as_ref().unwrap_or(&pg.url);
+
+			Arc::new(
+				universaldb::driver::PostgresDatabaseDriver::new_read_only(url.read().clone())
+					.await?,
+			) as universaldb::DatabaseDriverHandle
+		}
+		config::Database::FileSystem(fs) => {
+			Arc::new(universaldb::driver::RocksDbDatabaseDriver::new_secondary(&fs.path).await?)
+				as universaldb::DatabaseDriverHandle
+		}
+		#[cfg(feature = "fdb")]
+		config::Database::FoundationDb(fdb) => Arc::new(
+			universaldb::driver::FoundationDbDatabaseDriver::new(fdb.cluster_file.clone())?,
+		) as universaldb::DatabaseDriverHandle,
+		#[cfg(not(feature = "fdb"))]
+		config::Database::FoundationDb(_) => {
+			bail!("foundationdb support requires building with the `fdb` feature")
+		}
+	};
+
+	tracing::debug!("read-only udb started");
+
+	Ok(Some(UdbPool {
+		db: universaldb::Database::new(db_driver).read_only(),
+	}))
+}
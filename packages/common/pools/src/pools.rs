@@ -23,13 +23,30 @@ pub struct Pools(Arc<PoolsInner>);
 impl Pools {
 	#[tracing::instrument(skip(config))]
 	pub async fn new(config: Config) -> Result<Pools> {
+		Self::new_inner(config, false).await
+	}
+
+	/// Same as `new`, but UDB transactions reject writes and read from a secondary instance or
+	/// read replica if possible. See `db::udb::setup_read_only`.
+	#[tracing::instrument(skip(config))]
+	pub async fn new_read_only(config: Config) -> Result<Pools> {
+		Self::new_inner(config, true).await
+	}
+
+	async fn new_inner(config: Config, read_only: bool) -> Result<Pools> {
 		// TODO: Choose client name for this service
 		let client_name = "rivet".to_string();
 		let token = CancellationToken::new();
 
 		let (ups, udb) = tokio::try_join!(
 			crate::db::ups::setup(config.clone(), client_name.clone()),
-			crate::db::udb::setup(config.clone()),
+			async {
+				if read_only {
+					crate::db::udb::setup_read_only(config.clone()).await
+				} else {
+					crate::db::udb::setup(config.clone()).await
+				}
+			},
 		)?;
 		let clickhouse = crate::db::clickhouse::setup(config.clone())?;
 
@@ -57,6 +57,7 @@ impl TestDatabase {
 				let config =
 					rivet_config::config::Database::Postgres(rivet_config::config::db::Postgres {
 						url: rivet_config::secret::Secret::new(connection_string.clone()),
+						read_replica_url: None,
 					});
 
 				let docker_config = DockerRunConfig {
@@ -80,7 +81,10 @@ impl TestDatabase {
 				std::fs::create_dir_all(&temp_dir)?;
 
 				let config = rivet_config::config::Database::FileSystem(
-					rivet_config::config::db::FileSystem { path: temp_dir },
+					rivet_config::config::db::FileSystem {
+						path: temp_dir,
+						backup: None,
+					},
 				);
 
 				Ok((config, None))
@@ -17,7 +17,9 @@ pub fn apply_atomic_op(
 		MutationType::ByteMin => Some(apply_byte_min(current, param)),
 		MutationType::ByteMax => Some(apply_byte_max(current, param)),
 		MutationType::CompareAndClear => apply_compare_and_clear(current, param),
-		MutationType::SetVersionstampedKey | MutationType::SetVersionstampedValue => {
+		// Versionstamped keys change the key, so drivers handle them before applying atomic ops
+		MutationType::SetVersionstampedKey => Some(param.to_vec()),
+		MutationType::SetVersionstampedValue => {
 			// TODO: impl versionstamps
 			Some(param.to_vec())
 		}
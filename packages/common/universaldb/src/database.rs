@@ -1,10 +1,10 @@
-use std::future::Future;
+use std::{future::Future, sync::Arc};
 
 use anyhow::{Context, Result, anyhow};
 use futures_util::FutureExt;
 
 use crate::{
-	driver::{DatabaseDriverHandle, Erased},
+	driver::{DatabaseDriverHandle, Erased, ReadOnlyDatabaseDriver},
 	options::DatabaseOption,
 	transaction::{RetryableTransaction, Transaction},
 };
@@ -40,6 +40,16 @@ impl Database {
 			.context("transaction failed")
 	}
 
+	/// Returns a handle to the same database whose transactions reject writes with
+	/// `DatabaseError::ReadOnly`. Meant for tooling that should never modify data. To also keep load
+	/// off of the primary, create the database from a secondary driver instead (see
+	/// `RocksDbDatabaseDriver::new_secondary` and `PostgresDatabaseDriver::new_read_only`).
+	pub fn read_only(&self) -> Self {
+		Database {
+			driver: Arc::new(ReadOnlyDatabaseDriver::new(self.driver.clone())),
+		}
+	}
+
 	/// Creates a new txn instance.
 	pub fn create_trx(&self) -> Result<Transaction> {
 		self.driver.create_trx()
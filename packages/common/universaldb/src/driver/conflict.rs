@@ -0,0 +1,59 @@
+use rivet_metrics::KeyValue;
+
+use crate::{
+	error::ConflictingKeys,
+	metrics,
+	tuple::{Element, TupleDepth, TupleUnpack},
+	utils::keys::str_from_key,
+};
+
+/// Max amount of leading key elements used to label the subspace of a conflict.
+const SUBSPACE_DEPTH: usize = 3;
+
+/// Logs a `DatabaseError::NotCommitted` error along with its conflicting ranges and records a conflict
+/// metric for each subspace the ranges start in.
+pub(crate) fn report_conflict(error: &anyhow::Error, debug_identifier: Option<&str>, attempt: i32) {
+	let Some(conflicting_keys) = ConflictingKeys::from_error(error) else {
+		tracing::debug!(?debug_identifier, attempt, "transaction conflict, retrying");
+		metrics::TRANSACTION_CONFLICT_COUNT.add(1, &[KeyValue::new("subspace", "unknown")]);
+		return;
+	};
+
+	tracing::debug!(
+		?debug_identifier,
+		attempt,
+		%conflicting_keys,
+		"transaction conflict, retrying"
+	);
+
+	for (begin, _) in &conflicting_keys.ranges {
+		metrics::TRANSACTION_CONFLICT_COUNT
+			.add(1, &[KeyValue::new("subspace", subspace_label(begin))]);
+	}
+}
+
+/// Names the subspace of a key by its leading key codes (see `utils::keys`), for example
+/// `rivet/pegboard/actor_kv`. Stops at the first element that is not a key code to keep the amount
+/// of distinct labels low.
+fn subspace_label(key: &[u8]) -> String {
+	let mut input = key;
+	let mut names = Vec::new();
+
+	while names.len() < SUBSPACE_DEPTH {
+		let Ok((rest, Element::Int(code))) = Element::unpack(input, TupleDepth::new()) else {
+			break;
+		};
+		let Some(name) = usize::try_from(code).ok().and_then(str_from_key) else {
+			break;
+		};
+
+		names.push(name);
+		input = rest;
+	}
+
+	if names.is_empty() {
+		"unknown".to_string()
+	} else {
+		names.join("/")
+	}
+}
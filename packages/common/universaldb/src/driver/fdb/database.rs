@@ -0,0 +1,160 @@
+use std::{
+	path::PathBuf,
+	sync::{Arc, Mutex},
+};
+
+use anyhow::{Context, Result};
+use foundationdb::FdbError;
+
+use crate::{
+	RetryableTransaction, Transaction,
+	driver::{BoxFut, DatabaseDriver, Erased},
+	error::DatabaseError,
+	options::DatabaseOption,
+	utils::MaybeCommitted,
+};
+
+use super::{boot_network, map_fdb_error, transaction::FoundationDbTransactionDriver};
+
+pub struct FoundationDbDatabaseDriver {
+	db: Arc<foundationdb::Database>,
+	max_retries: Arc<Mutex<i32>>,
+}
+
+impl FoundationDbDatabaseDriver {
+	/// Opens the cluster described by `cluster_file`, or the default cluster file if `None`.
+	pub fn new(cluster_file: Option<PathBuf>) -> Result<Self> {
+		tracing::info!(?cluster_file, "starting foundationdb driver");
+
+		boot_network();
+
+		let cluster_file = cluster_file
+			.map(|path| {
+				path.into_os_string()
+					.into_string()
+					.map_err(|_| anyhow::anyhow!("cluster file path is not valid utf-8"))
+			})
+			.transpose()?;
+		let db = foundationdb::Database::new(cluster_file.as_deref())
+			.map_err(map_fdb_error)
+			.context("failed to open foundationdb")?;
+
+		Ok(FoundationDbDatabaseDriver {
+			db: Arc::new(db),
+			max_retries: Arc::new(Mutex::new(100)),
+		})
+	}
+}
+
+impl DatabaseDriver for FoundationDbDatabaseDriver {
+	fn create_trx(&self) -> Result<Transaction> {
+		let tx = self.db.create_trx().map_err(map_fdb_error)?;
+
+		Ok(Transaction::new(Arc::new(
+			FoundationDbTransactionDriver::new(Arc::new(tx)),
+		)))
+	}
+
+	fn run<'a>(
+		&'a self,
+		closure: Box<dyn Fn(RetryableTransaction) -> BoxFut<'a, Result<Erased>> + Send + Sync + 'a>,
+	) -> BoxFut<'a, Result<Erased>> {
+		Box::pin(async move {
+			let mut maybe_committed = MaybeCommitted(false);
+			let max_retries = *self.max_retries.lock().unwrap();
+			let mut fdb_tx = self.db.create_trx().map_err(map_fdb_error)?;
+
+			for _ in 0..max_retries {
+				let shared_tx = Arc::new(fdb_tx);
+				let mut retryable = RetryableTransaction::new(Transaction::new(Arc::new(
+					FoundationDbTransactionDriver::new(shared_tx.clone()),
+				)));
+				retryable.maybe_committed = maybe_committed;
+
+				// The closure owns the only other reference to the transaction, so it can be taken back
+				// to commit once the closure is done
+				let res = closure(retryable).await;
+				let tx = Arc::try_unwrap(shared_tx).map_err(|_| DatabaseError::UsedDuringCommit)?;
+
+				// Execute transaction
+				let error = match res {
+					Ok(res) => match tx.commit().await {
+						Ok(_) => return Ok(res),
+						Err(err) => {
+							if err.is_maybe_committed() {
+								maybe_committed = MaybeCommitted(true);
+							}
+
+							// Resets the transaction and backs off if the error is retryable
+							match err.on_error().await {
+								Ok(tx) => {
+									fdb_tx = tx;
+									continue;
+								}
+								Err(err) => return Err(map_fdb_error(err)),
+							}
+						}
+					},
+					Err(err) => err,
+				};
+
+				let Some(code) = error
+					.chain()
+					.find_map(|x| x.downcast_ref::<FdbError>())
+					.map(|err| err.code())
+				else {
+					return Err(error);
+				};
+				let fdb_error = FdbError::from_code(code);
+
+				if fdb_error.is_maybe_committed() {
+					maybe_committed = MaybeCommitted(true);
+				}
+
+				match tx.on_error(fdb_error).await {
+					Ok(tx) => fdb_tx = tx,
+					Err(_) => return Err(error),
+				}
+			}
+
+			Err(DatabaseError::MaxRetriesReached.into())
+		})
+	}
+
+	fn set_option(&self, opt: DatabaseOption) -> Result<()> {
+		match opt {
+			DatabaseOption::TransactionRetryLimit(limit) => {
+				*self.max_retries.lock().unwrap() = limit;
+				Ok(())
+			}
+			DatabaseOption::TransactionTimeout(timeout_ms) => self
+				.db
+				.set_option(foundationdb::options::DatabaseOption::TransactionTimeout(
+					timeout_ms,
+				))
+				.map_err(map_fdb_error),
+			DatabaseOption::TransactionMaxRetryDelay(max_retry_delay_ms) => self
+				.db
+				.set_option(
+					foundationdb::options::DatabaseOption::TransactionMaxRetryDelay(
+						max_retry_delay_ms,
+					),
+				)
+				.map_err(map_fdb_error),
+			DatabaseOption::TransactionSizeLimit(size_limit) => self
+				.db
+				.set_option(foundationdb::options::DatabaseOption::TransactionSizeLimit(
+					size_limit,
+				))
+				.map_err(map_fdb_error),
+			DatabaseOption::MaxWatches(max_watches) => self
+				.db
+				.set_option(foundationdb::options::DatabaseOption::MaxWatches(
+					max_watches,
+				))
+				.map_err(map_fdb_error),
+			// Watches are handled by the cluster
+			DatabaseOption::WatchNotifications(_) => Ok(()),
+		}
+	}
+}
@@ -0,0 +1,157 @@
+//! Driver for a native FoundationDB cluster using the FDB client library.
+//!
+//! Unlike the emulated drivers, incomplete versionstamps are only substituted by the
+//! `SetVersionstampedKey` and `SetVersionstampedValue` atomic ops, not in plain `set` values.
+
+use std::{borrow::Cow, sync::Once};
+
+use anyhow::{Result, bail};
+use foundationdb::FdbError;
+
+use crate::{
+	error::DatabaseError,
+	key_selector::KeySelector,
+	options::{ConflictRangeType, MutationType, StreamingMode, TransactionOption},
+	range_option::RangeOption,
+};
+
+mod database;
+mod transaction;
+
+pub use database::FoundationDbDatabaseDriver;
+
+// https://apple.github.io/foundationdb/api-error-codes.html
+const TRANSACTION_TOO_OLD: i32 = 1007;
+const NOT_COMMITTED: i32 = 1020;
+const TRANSACTION_CANCELLED: i32 = 1025;
+const TRANSACTION_TIMED_OUT: i32 = 1031;
+const TOO_MANY_WATCHES: i32 = 1032;
+const TRANSACTION_TOO_LARGE: i32 = 2101;
+
+static NETWORK: Once = Once::new();
+
+/// Starts the FDB network thread. It has to be started once per process before any database is
+/// opened and runs until the process exits.
+fn boot_network() {
+	NETWORK.call_once(|| {
+		// SAFETY: The network is only booted once and never stopped, since the FDB client does not
+		// support restarting it.
+		let network = unsafe { foundationdb::boot() };
+		std::mem::forget(network);
+	});
+}
+
+/// Converts an FDB error, adding the matching `DatabaseError` as context so callers can handle
+/// errors the same way across drivers. The original error stays in the chain for `on_error`.
+fn map_fdb_error(err: FdbError) -> anyhow::Error {
+	let db_error = match err.code() {
+		TRANSACTION_TOO_OLD => Some(DatabaseError::TransactionTooOld),
+		NOT_COMMITTED => Some(DatabaseError::NotCommitted),
+		TRANSACTION_TIMED_OUT => Some(DatabaseError::TransactionTimedOut),
+		TOO_MANY_WATCHES => Some(DatabaseError::TooManyWatches),
+		TRANSACTION_TOO_LARGE => Some(DatabaseError::TransactionTooLarge),
+		_ => None,
+	};
+
+	match db_error {
+		Some(db_error) => anyhow::Error::new(err).context(db_error),
+		None => anyhow::Error::new(err),
+	}
+}
+
+fn key_selector<'a>(selector: &KeySelector<'a>) -> foundationdb::KeySelector<'a> {
+	foundationdb::KeySelector::new(
+		Cow::Owned(selector.key().to_vec()),
+		selector.or_equal(),
+		selector.offset(),
+	)
+}
+
+fn range_option<'a>(opt: &RangeOption<'a>) -> foundationdb::RangeOption<'a> {
+	foundationdb::RangeOption {
+		begin: key_selector(&opt.begin),
+		end: key_selector(&opt.end),
+		limit: opt.limit,
+		target_bytes: opt.target_bytes,
+		mode: streaming_mode(opt.mode),
+		reverse: opt.reverse,
+		..Default::default()
+	}
+}
+
+fn streaming_mode(mode: StreamingMode) -> foundationdb::options::StreamingMode {
+	use foundationdb::options::StreamingMode as Fdb;
+
+	match mode {
+		StreamingMode::WantAll => Fdb::WantAll,
+		StreamingMode::Iterator => Fdb::Iterator,
+		StreamingMode::Exact => Fdb::Exact,
+		StreamingMode::Small => Fdb::Small,
+		StreamingMode::Medium => Fdb::Medium,
+		StreamingMode::Large => Fdb::Large,
+		StreamingMode::Serial => Fdb::Serial,
+	}
+}
+
+fn mutation_type(op_type: MutationType) -> foundationdb::options::MutationType {
+	use foundationdb::options::MutationType as Fdb;
+
+	match op_type {
+		MutationType::Add => Fdb::Add,
+		MutationType::And => Fdb::And,
+		MutationType::BitAnd => Fdb::BitAnd,
+		MutationType::Or => Fdb::Or,
+		MutationType::BitOr => Fdb::BitOr,
+		MutationType::Xor => Fdb::Xor,
+		MutationType::BitXor => Fdb::BitXor,
+		MutationType::AppendIfFits => Fdb::AppendIfFits,
+		MutationType::Max => Fdb::Max,
+		MutationType::Min => Fdb::Min,
+		MutationType::SetVersionstampedKey => Fdb::SetVersionstampedKey,
+		MutationType::SetVersionstampedValue => Fdb::SetVersionstampedValue,
+		MutationType::ByteMin => Fdb::ByteMin,
+		MutationType::ByteMax => Fdb::ByteMax,
+		MutationType::CompareAndClear => Fdb::CompareAndClear,
+	}
+}
+
+fn conflict_range_type(
+	conflict_type: ConflictRangeType,
+) -> foundationdb::options::ConflictRangeType {
+	match conflict_type {
+		ConflictRangeType::Read => foundationdb::options::ConflictRangeType::Read,
+		ConflictRangeType::Write => foundationdb::options::ConflictRangeType::Write,
+	}
+}
+
+fn transaction_option(opt: TransactionOption) -> Result<foundationdb::options::TransactionOption> {
+	use foundationdb::options::TransactionOption as Fdb;
+
+	let opt = match opt {
+		TransactionOption::CausalReadRisky => Fdb::CausalReadRisky,
+		TransactionOption::NextWriteNoWriteConflictRange => Fdb::NextWriteNoWriteConflictRange,
+		TransactionOption::ReadYourWritesDisable => Fdb::ReadYourWritesDisable,
+		TransactionOption::PrioritySystemImmediate => Fdb::PrioritySystemImmediate,
+		TransactionOption::PriorityBatch => Fdb::PriorityBatch,
+		TransactionOption::AccessSystemKeys => Fdb::AccessSystemKeys,
+		TransactionOption::ReadSystemKeys => Fdb::ReadSystemKeys,
+		TransactionOption::DebugTransactionIdentifier(identifier) => {
+			Fdb::DebugTransactionIdentifier(identifier)
+		}
+		TransactionOption::LogTransaction => Fdb::LogTransaction,
+		TransactionOption::Timeout(timeout) => Fdb::Timeout(timeout),
+		TransactionOption::RetryLimit(limit) => Fdb::RetryLimit(limit),
+		TransactionOption::MaxRetryDelay(delay) => Fdb::MaxRetryDelay(delay),
+		TransactionOption::SizeLimit(limit) => Fdb::SizeLimit(limit),
+		TransactionOption::SnapshotRywEnable => Fdb::SnapshotRywEnable,
+		TransactionOption::SnapshotRywDisable => Fdb::SnapshotRywDisable,
+		TransactionOption::LockAware => Fdb::LockAware,
+		TransactionOption::ReadLockAware => Fdb::ReadLockAware,
+		TransactionOption::ReportConflictingKeys => Fdb::ReportConflictingKeys,
+		TransactionOption::Tag(tag) => Fdb::Tag(tag),
+		TransactionOption::AutoThrottleTag(tag) => Fdb::AutoThrottleTag(tag),
+		opt => bail!("transaction option {opt:?} is not supported by the foundationdb driver"),
+	};
+
+	Ok(opt)
+}
@@ -0,0 +1,193 @@
+use std::{future::Future, pin::Pin, sync::Arc};
+
+use anyhow::{Context, Result};
+use futures_util::{FutureExt, StreamExt, TryFutureExt};
+
+use crate::{
+	driver::TransactionDriver,
+	error::DatabaseError,
+	key_selector::KeySelector,
+	options::{ConflictRangeType, MutationType, TransactionOption},
+	range_option::RangeOption,
+	utils::IsolationLevel,
+	value::{KeyValue, Slice, Value, Values},
+};
+
+use super::{
+	TRANSACTION_CANCELLED, conflict_range_type, key_selector, map_fdb_error, mutation_type,
+	range_option, transaction_option,
+};
+
+pub struct FoundationDbTransactionDriver {
+	tx: Arc<foundationdb::Transaction>,
+}
+
+impl FoundationDbTransactionDriver {
+	pub fn new(tx: Arc<foundationdb::Transaction>) -> Self {
+		FoundationDbTransactionDriver { tx }
+	}
+}
+
+fn is_snapshot(isolation_level: IsolationLevel) -> bool {
+	matches!(isolation_level, IsolationLevel::Snapshot)
+}
+
+impl TransactionDriver for FoundationDbTransactionDriver {
+	fn atomic_op(&self, key: &[u8], param: &[u8], op_type: MutationType) {
+		self.tx.atomic_op(key, param, mutation_type(op_type));
+	}
+
+	fn get<'a>(
+		&'a self,
+		key: &[u8],
+		isolation_level: IsolationLevel,
+	) -> Pin<Box<dyn Future<Output = Result<Option<Slice>>> + Send + 'a>> {
+		let key = key.to_vec();
+
+		Box::pin(async move {
+			let value = self
+				.tx
+				.get(&key, is_snapshot(isolation_level))
+				.await
+				.map_err(map_fdb_error)?;
+
+			Ok(value.map(|value| Slice::from(value.to_vec())))
+		})
+	}
+
+	fn get_key<'a>(
+		&'a self,
+		selector: &KeySelector<'a>,
+		isolation_level: IsolationLevel,
+	) -> Pin<Box<dyn Future<Output = Result<Slice>> + Send + 'a>> {
+		let selector = key_selector(selector);
+
+		Box::pin(async move {
+			let key = self
+				.tx
+				.get_key(&selector, is_snapshot(isolation_level))
+				.await
+				.map_err(map_fdb_error)?;
+
+			Ok(Slice::from(key.to_vec()))
+		})
+	}
+
+	fn get_range<'a>(
+		&'a self,
+		opt: &RangeOption<'a>,
+		iteration: usize,
+		isolation_level: IsolationLevel,
+	) -> Pin<Box<dyn Future<Output = Result<Values>> + Send + 'a>> {
+		let opt = range_option(opt);
+
+		Box::pin(async move {
+			let values = self
+				.tx
+				.get_range(&opt, iteration, is_snapshot(isolation_level))
+				.await
+				.map_err(map_fdb_error)?;
+
+			Ok(Values::with_more(
+				values
+					.iter()
+					.map(|kv| KeyValue::new(kv.key().to_vec(), kv.value().to_vec()))
+					.collect(),
+				values.more(),
+			))
+		})
+	}
+
+	fn get_ranges_keyvalues<'a>(
+		&'a self,
+		opt: RangeOption<'a>,
+		isolation_level: IsolationLevel,
+	) -> crate::value::Stream<'a, Value> {
+		Box::pin(
+			self.tx
+				.get_ranges_keyvalues(range_option(&opt), is_snapshot(isolation_level))
+				.map(|res| {
+					res.map(|kv| Value::new(kv.key().to_vec(), kv.value().to_vec()))
+						.map_err(map_fdb_error)
+				}),
+		)
+	}
+
+	fn set(&self, key: &[u8], value: &[u8]) {
+		self.tx.set(key, value);
+	}
+
+	fn clear(&self, key: &[u8]) {
+		self.tx.clear(key);
+	}
+
+	fn clear_range(&self, begin: &[u8], end: &[u8]) {
+		self.tx.clear_range(begin, end);
+	}
+
+	fn watch(&self, key: &[u8]) -> Pin<Box<dyn Future<Output = Result<()>> + Send>> {
+		self.tx
+			.watch(key)
+			.map_err(|err| {
+				if err.code() == TRANSACTION_CANCELLED {
+					anyhow::Error::new(err).context(DatabaseError::WatchCancelled)
+				} else {
+					map_fdb_error(err)
+				}
+			})
+			.boxed()
+	}
+
+	fn commit(self: Box<Self>) -> Pin<Box<dyn Future<Output = Result<()>> + Send>> {
+		Box::pin(async move {
+			let tx = Arc::try_unwrap(self.tx).map_err(|_| DatabaseError::UsedDuringCommit)?;
+
+			tx.commit().await.map_err(|err| map_fdb_error(err.into()))?;
+
+			Ok(())
+		})
+	}
+
+	fn reset(&mut self) {
+		// Only possible while no reads are in flight
+		if let Some(tx) = Arc::get_mut(&mut self.tx) {
+			tx.reset();
+		}
+	}
+
+	fn cancel(&self) {
+		// The FDB client can only cancel owned transactions. The transaction is cancelled once all
+		// references to it are dropped.
+	}
+
+	fn set_option(&self, opt: TransactionOption) -> Result<()> {
+		self.tx
+			.set_option(transaction_option(opt)?)
+			.map_err(map_fdb_error)
+	}
+
+	fn add_conflict_range(
+		&self,
+		begin: &[u8],
+		end: &[u8],
+		conflict_type: ConflictRangeType,
+	) -> Result<()> {
+		self.tx
+			.add_conflict_range(begin, end, conflict_range_type(conflict_type))
+			.map_err(map_fdb_error)
+	}
+
+	fn get_estimated_range_size_bytes<'a>(
+		&'a self,
+		begin: &'a [u8],
+		end: &'a [u8],
+	) -> Pin<Box<dyn Future<Output = Result<i64>> + Send + 'a>> {
+		Box::pin(async move {
+			self.tx
+				.get_estimated_range_size_bytes(begin, end)
+				.await
+				.map_err(map_fdb_error)
+				.context("failed to get estimated range size")
+		})
+	}
+}
@@ -4,17 +4,24 @@ use anyhow::{Result, bail};
 
 use crate::{
 	key_selector::KeySelector,
-	options::{ConflictRangeType, DatabaseOption, MutationType},
+	options::{ConflictRangeType, DatabaseOption, MutationType, TransactionOption},
 	range_option::RangeOption,
 	transaction::{RetryableTransaction, Transaction},
 	utils::IsolationLevel,
 	value::{Slice, Value, Values},
 };
 
+mod conflict;
+#[cfg(feature = "fdb")]
+mod fdb;
 mod postgres;
+mod read_only;
 pub mod rocksdb;
 
+#[cfg(feature = "fdb")]
+pub use fdb::FoundationDbDatabaseDriver;
 pub use postgres::PostgresDatabaseDriver;
+pub use read_only::ReadOnlyDatabaseDriver;
 pub use rocksdb::RocksDbDatabaseDriver;
 
 pub type BoxFut<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
@@ -62,10 +69,16 @@ pub trait TransactionDriver: Send + Sync {
 	fn clear(&self, key: &[u8]);
 	fn clear_range(&self, begin: &[u8], end: &[u8]);
 
+	/// Returns a future that resolves once the key is written by another transaction after this
+	/// transaction commits. The future fails if this transaction is reset or dropped without
+	/// committing.
+	fn watch(&self, key: &[u8]) -> Pin<Box<dyn Future<Output = Result<()>> + Send>>;
+
 	// Transaction management
 	fn commit(self: Box<Self>) -> Pin<Box<dyn Future<Output = Result<()>> + Send>>;
 	fn reset(&mut self);
 	fn cancel(&self);
+	fn set_option(&self, opt: TransactionOption) -> Result<()>;
 	fn add_conflict_range(
 		&self,
 		begin: &[u8],
@@ -1,44 +1,49 @@
-use std::sync::{Arc, Mutex};
+use std::{
+	sync::{
+		Arc, Mutex,
+		atomic::{AtomicBool, Ordering},
+	},
+	time::Instant,
+};
 
 use anyhow::{Context, Result};
 use deadpool_postgres::{Config, ManagerConfig, Pool, PoolConfig, RecyclingMethod, Runtime};
+use tokio::task::JoinHandle;
 use tokio_postgres::NoTls;
+use uuid::Uuid;
 
 use crate::{
 	RetryableTransaction, Transaction,
-	driver::{BoxFut, DatabaseDriver, Erased},
+	driver::{BoxFut, DatabaseDriver, Erased, conflict::report_conflict},
 	error::DatabaseError,
 	options::DatabaseOption,
-	utils::{MaybeCommitted, calculate_tx_retry_backoff},
+	tx_limits::{TransactionLimits, wait_for_timeout},
+	utils::MaybeCommitted,
+	watch::WatchRegistry,
 };
 
-use super::transaction::PostgresTransactionDriver;
+use super::{notify, transaction::PostgresTransactionDriver};
 
 pub struct PostgresDatabaseDriver {
 	pool: Arc<Pool>,
 	max_retries: Arc<Mutex<i32>>,
+	limits: Arc<Mutex<TransactionLimits>>,
+	watch_registry: WatchRegistry,
+	/// Identifies watch notifications sent by this driver.
+	driver_id: Uuid,
+	/// Whether committing transactions notify other drivers of the keys they wrote. Disabled by
+	/// default since `pg_notify` serializes commits on a global lock.
+	watch_notifications: Arc<AtomicBool>,
+	/// Not set for read-only drivers, `LISTEN` is not supported by read replicas.
+	listener_handle: Option<JoinHandle<()>>,
+	read_only: bool,
 }
 
 impl PostgresDatabaseDriver {
 	pub async fn new(connection_string: String) -> Result<Self> {
 		tracing::debug!(connection_string = ?connection_string, "Creating PostgresDatabaseDriver");
 
-		// Create deadpool config from connection string
-		let mut config = Config::new();
-		config.url = Some(connection_string);
-		config.pool = Some(PoolConfig {
-			max_size: 64,
-			..Default::default()
-		});
-		config.manager = Some(ManagerConfig {
-			recycling_method: RecyclingMethod::Fast,
-		});
-
-		tracing::debug!("Creating Postgres pool");
-		// Create the pool
-		let pool = config
-			.create_pool(Some(Runtime::Tokio1), NoTls)
-			.context("failed to create postgres connection pool")?;
+		let pool = create_pool(&connection_string)?;
 
 		tracing::debug!("Getting Postgres connection from pool");
 		// Get a connection from the pool to create the table
@@ -114,18 +119,81 @@ impl PostgresDatabaseDriver {
 		// Connection is automatically returned to the pool when dropped
 		drop(conn);
 
+		let watch_registry = WatchRegistry::new();
+		let driver_id = Uuid::new_v4();
+		let listener_handle = tokio::spawn(notify::listen(
+			connection_string,
+			watch_registry.clone(),
+			driver_id,
+		));
+
+		Ok(PostgresDatabaseDriver {
+			pool: Arc::new(pool),
+			max_retries: Arc::new(Mutex::new(100)),
+			limits: Arc::new(Mutex::new(TransactionLimits::default())),
+			watch_registry,
+			driver_id,
+			watch_notifications: Arc::new(AtomicBool::new(false)),
+			listener_handle: Some(listener_handle),
+			read_only: false,
+		})
+	}
+
+	/// Connects to a read replica, or any server the engine should not write to. The schema is not
+	/// created, every read uses a `REPEATABLE READ READ ONLY` transaction, and commits that contain
+	/// writes fail. Watches only fire for writes made through this driver, which never happen.
+	pub async fn new_read_only(connection_string: String) -> Result<Self> {
+		tracing::debug!(connection_string = ?connection_string, "Creating read-only PostgresDatabaseDriver");
+
+		let pool = create_pool(&connection_string)?;
+
+		// Fail early if the server is unreachable
+		pool.get()
+			.await
+			.context("failed to get connection from postgres pool")?;
+
 		Ok(PostgresDatabaseDriver {
 			pool: Arc::new(pool),
 			max_retries: Arc::new(Mutex::new(100)),
+			limits: Arc::new(Mutex::new(TransactionLimits::default())),
+			watch_registry: WatchRegistry::new(),
+			driver_id: Uuid::new_v4(),
+			watch_notifications: Arc::new(AtomicBool::new(false)),
+			listener_handle: None,
+			read_only: true,
 		})
 	}
 }
 
+fn create_pool(connection_string: &str) -> Result<Pool> {
+	// Create deadpool config from connection string
+	let mut config = Config::new();
+	config.url = Some(connection_string.to_string());
+	config.pool = Some(PoolConfig {
+		max_size: 64,
+		..Default::default()
+	});
+	config.manager = Some(ManagerConfig {
+		recycling_method: RecyclingMethod::Fast,
+	});
+
+	tracing::debug!("Creating Postgres pool");
+	config
+		.create_pool(Some(Runtime::Tokio1), NoTls)
+		.context("failed to create postgres connection pool")
+}
+
 impl DatabaseDriver for PostgresDatabaseDriver {
 	fn create_trx(&self) -> Result<Transaction> {
 		// Pass the connection pool to the transaction driver
 		Ok(Transaction::new(Arc::new(PostgresTransactionDriver::new(
 			self.pool.clone(),
+			self.watch_registry.clone(),
+			self.driver_id,
+			self.watch_notifications.load(Ordering::Acquire),
+			*self.limits.lock().unwrap(),
+			Instant::now(),
+			self.read_only,
 		))))
 	}
 
@@ -136,14 +204,30 @@ impl DatabaseDriver for PostgresDatabaseDriver {
 		Box::pin(async move {
 			let mut maybe_committed = MaybeCommitted(false);
 			let max_retries = *self.max_retries.lock().unwrap();
+			let limits = *self.limits.lock().unwrap();
+			let run_started_at = Instant::now();
 
 			for attempt in 0..max_retries {
-				let tx = self.create_trx()?;
-				let mut retryable = RetryableTransaction::new(tx);
+				let driver = Arc::new(PostgresTransactionDriver::new(
+					self.pool.clone(),
+					self.watch_registry.clone(),
+					self.driver_id,
+					self.watch_notifications.load(Ordering::Acquire),
+					limits,
+					run_started_at,
+					self.read_only,
+				));
+				let mut retryable = RetryableTransaction::new(Transaction::new(driver.clone()));
 				retryable.maybe_committed = maybe_committed;
 
-				// Execute transaction
-				let error = match closure(retryable.clone()).await {
+				// Execute transaction, the closure is aborted once the transaction times out
+				let res = tokio::select! {
+					res = closure(retryable.clone()) => res,
+					_ = wait_for_timeout(|| driver.deadline()) => {
+						Err(DatabaseError::TransactionTimedOut.into())
+					}
+				};
+				let error = match res {
 					Ok(res) => match retryable.inner.driver.commit_ref().await {
 						Ok(_) => return Ok(res),
 						Err(e) => e,
@@ -161,9 +245,11 @@ impl DatabaseDriver for PostgresDatabaseDriver {
 						if db_error.is_maybe_committed() {
 							maybe_committed = MaybeCommitted(true);
 						}
+						if let DatabaseError::NotCommitted = db_error {
+							report_conflict(&error, driver.debug_identifier().as_deref(), attempt);
+						}
 
-						let backoff_ms = calculate_tx_retry_backoff(attempt as usize);
-						tokio::time::sleep(tokio::time::Duration::from_millis(backoff_ms)).await;
+						tokio::time::sleep(driver.limits().retry_backoff(attempt as usize)).await;
 						continue;
 					}
 				}
@@ -177,10 +263,36 @@ impl DatabaseDriver for PostgresDatabaseDriver {
 
 	fn set_option(&self, opt: DatabaseOption) -> Result<()> {
 		match opt {
+			DatabaseOption::TransactionTimeout(timeout_ms) => {
+				self.limits.lock().unwrap().set_timeout(timeout_ms)
+			}
 			DatabaseOption::TransactionRetryLimit(limit) => {
 				*self.max_retries.lock().unwrap() = limit;
 				Ok(())
 			}
+			DatabaseOption::TransactionMaxRetryDelay(max_retry_delay_ms) => self
+				.limits
+				.lock()
+				.unwrap()
+				.set_max_retry_delay(max_retry_delay_ms),
+			DatabaseOption::TransactionSizeLimit(size_limit) => {
+				self.limits.lock().unwrap().set_size_limit(size_limit)
+			}
+			DatabaseOption::MaxWatches(max_watches) => {
+				self.watch_registry.set_max_watches(max_watches)
+			}
+			DatabaseOption::WatchNotifications(enabled) => {
+				self.watch_notifications.store(enabled, Ordering::Release);
+				Ok(())
+			}
+		}
+	}
+}
+
+impl Drop for PostgresDatabaseDriver {
+	fn drop(&mut self) {
+		if let Some(handle) = &self.listener_handle {
+			handle.abort();
 		}
 	}
 }
@@ -1,4 +1,5 @@
 mod database;
+mod notify;
 mod transaction;
 mod transaction_task;
 
@@ -0,0 +1,149 @@
+//! Watch notifications across processes sharing the same database.
+//!
+//! When `DatabaseOption::WatchNotifications` is enabled, committing transactions send the ranges they
+//! wrote with `pg_notify` as part of the transaction, so notifications are only delivered if the
+//! transaction commits. Every driver listens on the channel and fires its local watches on the
+//! notified ranges.
+
+use std::time::Duration;
+
+use anyhow::{Context, Result, anyhow, ensure};
+use futures_util::StreamExt;
+use tokio::sync::mpsc;
+use tokio_postgres::{AsyncMessage, NoTls};
+use uuid::Uuid;
+
+use crate::watch::WatchRegistry;
+
+pub const WATCH_CHANNEL: &str = "udb_watch";
+/// Postgres rejects payloads of 8000 bytes or more.
+const MAX_PAYLOAD_LEN: usize = 7900;
+/// Payload sent instead of the ranges if they do not fit, fires all watches.
+const ALL_RANGES: &str = "*";
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+
+/// Encodes written ranges as `{driver_id}|{begin}:{end},...` with hex encoded keys. Returns `None` if
+/// nothing was written.
+pub fn encode_payload(driver_id: Uuid, ranges: &[(Vec<u8>, Vec<u8>)]) -> Option<String> {
+	if ranges.is_empty() {
+		return None;
+	}
+
+	let mut payload = format!("{driver_id}|");
+	let prefix_len = payload.len();
+
+	for (i, (begin, end)) in ranges.iter().enumerate() {
+		if i != 0 {
+			payload.push(',');
+		}
+		payload.push_str(&hex_encode(begin));
+		payload.push(':');
+		payload.push_str(&hex_encode(end));
+
+		if payload.len() > MAX_PAYLOAD_LEN {
+			payload.truncate(prefix_len);
+			payload.push_str(ALL_RANGES);
+			break;
+		}
+	}
+
+	Some(payload)
+}
+
+/// Returns the id of the notifying driver and the written ranges, or `None` for all ranges.
+fn decode_payload(payload: &str) -> Result<(Uuid, Option<Vec<(Vec<u8>, Vec<u8>)>>)> {
+	let (driver_id, ranges) = payload
+		.split_once('|')
+		.context("invalid watch notification")?;
+	let driver_id = driver_id.parse::<Uuid>()?;
+
+	if ranges == ALL_RANGES {
+		return Ok((driver_id, None));
+	}
+
+	let ranges = ranges
+		.split(',')
+		.map(|range| {
+			let (begin, end) = range
+				.split_once(':')
+				.context("invalid watch notification range")?;
+
+			Ok((hex_decode(begin)?, hex_decode(end)?))
+		})
+		.collect::<Result<Vec<_>>>()?;
+
+	Ok((driver_id, Some(ranges)))
+}
+
+/// Fires local watches on ranges written by other drivers until the driver is dropped. Own writes
+/// are fired directly on commit.
+pub async fn listen(connection_string: String, registry: WatchRegistry, driver_id: Uuid) {
+	loop {
+		if let Err(err) = listen_inner(&connection_string, &registry, driver_id).await {
+			tracing::warn!(?err, "postgres watch listener failed, reconnecting");
+		}
+
+		tokio::time::sleep(RECONNECT_DELAY).await;
+	}
+}
+
+async fn listen_inner(
+	connection_string: &str,
+	registry: &WatchRegistry,
+	driver_id: Uuid,
+) -> Result<()> {
+	let (client, mut connection) = tokio_postgres::connect(connection_string, NoTls)
+		.await
+		.context("failed to connect watch listener")?;
+
+	// The connection has to be polled for the client to make progress
+	let (notification_tx, mut notification_rx) = mpsc::unbounded_channel();
+	let connection_handle = tokio::spawn(async move {
+		let mut messages = futures_util::stream::poll_fn(move |cx| connection.poll_message(cx));
+
+		while let Some(message) = messages.next().await {
+			if let AsyncMessage::Notification(notification) = message? {
+				let _ = notification_tx.send(notification.payload().to_string());
+			}
+		}
+
+		anyhow::Ok(())
+	});
+
+	client
+		.batch_execute(&format!("LISTEN {WATCH_CHANNEL}"))
+		.await
+		.context("failed to listen for watch notifications")?;
+
+	// Notifications sent while not listening were missed
+	registry.fire_all();
+
+	while let Some(payload) = notification_rx.recv().await {
+		match decode_payload(&payload) {
+			Ok((id, _)) if id == driver_id => {}
+			Ok((_, Some(ranges))) => registry.fire_ranges(&ranges),
+			Ok((_, None)) => registry.fire_all(),
+			Err(err) => {
+				tracing::warn!(?err, %payload, "failed to decode watch notification");
+				registry.fire_all();
+			}
+		}
+	}
+
+	connection_handle.await??;
+
+	Err(anyhow!("watch listener connection closed"))
+}
+
+fn hex_encode(buf: &[u8]) -> String {
+	buf.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+fn hex_decode(s: &str) -> Result<Vec<u8>> {
+	ensure!(s.is_ascii() && s.len() % 2 == 0, "invalid hex");
+
+	(0..s.len())
+		.step_by(2)
+		.map(|i| u8::from_str_radix(&s[i..i + 2], 16).map_err(Into::into))
+		.collect()
+}
@@ -2,35 +2,46 @@ use std::{
 	future::Future,
 	pin::Pin,
 	sync::{Arc, Mutex},
+	time::Instant,
 };
 
 use anyhow::{Context, Result};
 use deadpool_postgres::Pool;
 use tokio::sync::{OnceCell, mpsc, oneshot};
+use uuid::Uuid;
 
 use crate::{
 	driver::TransactionDriver,
 	error::DatabaseError,
 	key_selector::KeySelector,
-	options::{ConflictRangeType, MutationType},
+	options::{ConflictRangeType, MutationType, TransactionOption},
 	range_option::RangeOption,
+	tx_limits::{TransactionLimiter, TransactionLimits},
 	tx_ops::{Operation, TransactionOperations},
 	utils::IsolationLevel,
 	value::{KeyValue, Slice, Value, Values},
+	watch::{PendingWatch, WatchRegistry, written_ranges},
 };
 
-use super::transaction_task::{TransactionCommand, TransactionIsolationLevel, TransactionTask};
+use super::{
+	notify,
+	transaction_task::{TransactionCommand, TransactionIsolationLevel, TransactionTask},
+};
 
 struct TransactionState {
 	operations: TransactionOperations,
+	watches: Vec<PendingWatch>,
 	committed: bool,
+	debug_identifier: Option<String>,
 }
 
 impl Default for TransactionState {
 	fn default() -> Self {
 		Self {
 			operations: TransactionOperations::default(),
+			watches: Vec::new(),
 			committed: false,
+			debug_identifier: None,
 		}
 	}
 }
@@ -40,20 +51,133 @@ pub struct PostgresTransactionDriver {
 	state: Arc<Mutex<TransactionState>>,
 	tx_sender: Arc<OnceCell<mpsc::Sender<TransactionCommand>>>,
 	snapshot_tx_sender: Arc<OnceCell<mpsc::Sender<TransactionCommand>>>,
+	watch_registry: WatchRegistry,
+	driver_id: Uuid,
+	/// Whether to notify other drivers of written keys on commit.
+	watch_notifications: bool,
+	limiter: Arc<Mutex<TransactionLimiter>>,
+	/// Whether the driver is connected to a read-only server. Reads always use the snapshot
+	/// transaction since serializable reads write conflict ranges.
+	read_only: bool,
 }
 
 impl PostgresTransactionDriver {
-	pub fn new(pool: Arc<Pool>) -> Self {
+	pub fn new(
+		pool: Arc<Pool>,
+		watch_registry: WatchRegistry,
+		driver_id: Uuid,
+		watch_notifications: bool,
+		limits: TransactionLimits,
+		run_started_at: Instant,
+		read_only: bool,
+	) -> Self {
 		PostgresTransactionDriver {
 			pool,
 			state: Arc::new(Mutex::new(TransactionState::default())),
 			tx_sender: Arc::new(OnceCell::new()),
 			snapshot_tx_sender: Arc::new(OnceCell::new()),
+			watch_registry,
+			driver_id,
+			watch_notifications,
+			limiter: Arc::new(Mutex::new(TransactionLimiter::new(limits, run_started_at))),
+			read_only,
+		}
+	}
+
+	/// When the transaction times out, if it has a timeout.
+	pub fn deadline(&self) -> Option<Instant> {
+		self.limiter.lock().unwrap().deadline()
+	}
+
+	pub fn limits(&self) -> TransactionLimits {
+		self.limiter.lock().unwrap().limits
+	}
+
+	/// Fails if the transaction timed out, is too old, or exceeds its size limit.
+	fn check_limits(&self) -> Result<()> {
+		let size = self.state.lock().unwrap().operations.size();
+		self.limiter.lock().unwrap().check(size)
+	}
+
+	/// Identifier set with `TransactionOption::DebugTransactionIdentifier`.
+	pub fn debug_identifier(&self) -> Option<String> {
+		self.state.lock().unwrap().debug_identifier.clone()
+	}
+
+	/// Reads the values of the watched keys as seen by this transaction, including its own writes.
+	async fn read_watched_values(&self, watches: &[PendingWatch]) -> Result<Vec<Option<Slice>>> {
+		let mut values = Vec::with_capacity(watches.len());
+		for watch in watches {
+			values.push(self.get(&watch.key, IsolationLevel::Snapshot).await?);
+		}
+
+		Ok(values)
+	}
+
+	/// Takes the pending watches and reads the current values of their keys. The watches are put back
+	/// if a read fails so the transaction can still be committed.
+	async fn take_watches(&self) -> Result<(Vec<PendingWatch>, Vec<Option<Slice>>)> {
+		let watches = std::mem::take(&mut self.state.lock().unwrap().watches);
+
+		match self.read_watched_values(&watches).await {
+			Ok(values) => Ok((watches, values)),
+			Err(err) => {
+				let mut state = self.state.lock().unwrap();
+				let new_watches = std::mem::replace(&mut state.watches, watches);
+				state.watches.extend(new_watches);
+
+				Err(err)
+			}
 		}
 	}
 
+	/// Fires watches on keys written by this transaction, then registers the watches of this
+	/// transaction. Must be called after a successful commit. Other drivers are notified through
+	/// `pg_notify` during the commit if watch notifications are enabled.
+	async fn finish_watches(
+		&self,
+		written: &[(Vec<u8>, Vec<u8>)],
+		watches: Vec<PendingWatch>,
+		values: Vec<Option<Slice>>,
+	) {
+		self.watch_registry.fire_ranges(written);
+
+		for (watch, value) in watches.into_iter().zip(values) {
+			let key = watch.key.clone();
+			self.watch_registry.register(watch);
+
+			// Fire right away if the key was changed by another transaction after this transaction read
+			// it. Reading after registering guarantees no change is missed. Failed reads fire the watch
+			// since the transaction already committed.
+			let changed = match self.read_committed_value(&key).await {
+				Ok(current) => current.as_ref() != value.as_deref(),
+				Err(_) => true,
+			};
+			if changed {
+				self.watch_registry.fire_key(&key);
+			}
+		}
+	}
+
+	async fn read_committed_value(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+		let conn = self
+			.pool
+			.get()
+			.await
+			.context("failed to get postgres connection")?;
+		let row = conn
+			.query_opt("SELECT value FROM kv WHERE key = $1", &[&key])
+			.await?;
+
+		Ok(row.map(|row| row.get(0)))
+	}
+
 	/// Get or create the transaction task
 	async fn ensure_transaction(&self) -> Result<&mpsc::Sender<TransactionCommand>> {
+		if self.read_only {
+			return self.ensure_snapshot_transaction().await;
+		}
+
 		self.tx_sender
 			.get_or_try_init(|| async {
 				let (sender, receiver) = mpsc::channel(100);
@@ -109,6 +233,8 @@ impl TransactionDriver for PostgresTransactionDriver {
 	) -> Pin<Box<dyn Future<Output = Result<Option<Slice>>> + Send + 'a>> {
 		let key = key.to_vec();
 		Box::pin(async move {
+			self.check_limits()?;
+
 			// Both snapshot and non-snapshot reads check local operations first
 			// This matches FoundationDB behavior where transactions see their own writes
 			let ops = {
@@ -152,6 +278,8 @@ impl TransactionDriver for PostgresTransactionDriver {
 		let selector = selector.clone();
 
 		Box::pin(async move {
+			self.check_limits()?;
+
 			let key = selector.key().to_vec();
 			let offset = selector.offset();
 			let or_equal = selector.or_equal();
@@ -203,6 +331,8 @@ impl TransactionDriver for PostgresTransactionDriver {
 		let opt = opt.clone();
 
 		Box::pin(async move {
+			self.check_limits()?;
+
 			let begin = opt.begin.key().to_vec();
 			let begin_or_equal = opt.begin.or_equal();
 			let begin_offset = opt.begin.offset();
@@ -298,8 +428,27 @@ impl TransactionDriver for PostgresTransactionDriver {
 		}
 	}
 
+	fn watch(&self, key: &[u8]) -> Pin<Box<dyn Future<Output = Result<()>> + Send>> {
+		let (watch, fut) = PendingWatch::new(key);
+		if let Ok(mut state) = self.state.lock() {
+			state.watches.push(watch);
+		}
+
+		fut
+	}
+
 	fn commit(self: Box<Self>) -> Pin<Box<dyn Future<Output = Result<()>> + Send>> {
 		Box::pin(async move {
+			self.check_limits()?;
+
+			if self.state.lock().unwrap().committed {
+				return Ok(());
+			}
+
+			// Read the watched keys before marking the transaction as committed, a failed read leaves
+			// it uncommitted
+			let (watches, watched_values) = self.take_watches().await?;
+
 			// Get operations and mark as committed
 			let operations = {
 				let mut state = self.state.lock().unwrap();
@@ -310,6 +459,15 @@ impl TransactionDriver for PostgresTransactionDriver {
 
 				state.operations.clone()
 			};
+			if self.read_only && !operations.operations().is_empty() {
+				return Err(DatabaseError::ReadOnly.into());
+			}
+			let written = written_ranges(&operations);
+			let notify_payload = if self.watch_notifications {
+				notify::encode_payload(self.driver_id, &written)
+			} else {
+				None
+			};
 
 			// Get the transaction sender if it exists
 			let tx_sender = self.tx_sender.get();
@@ -391,6 +549,7 @@ impl TransactionDriver for PostgresTransactionDriver {
 				sender
 					.send(TransactionCommand::Commit {
 						has_conflict_ranges: !operations.conflict_ranges().is_empty(),
+						notify_payload,
 						response: response_tx,
 					})
 					.await
@@ -478,6 +637,7 @@ impl TransactionDriver for PostgresTransactionDriver {
 				tx_sender
 					.send(TransactionCommand::Commit {
 						has_conflict_ranges: !operations.conflict_ranges().is_empty(),
+						notify_payload,
 						response: response_tx,
 					})
 					.await
@@ -489,6 +649,8 @@ impl TransactionDriver for PostgresTransactionDriver {
 					.context("failed to receive postgres commit response")??;
 			}
 
+			self.finish_watches(&written, watches, watched_values).await;
+
 			Ok(())
 		})
 	}
@@ -510,6 +672,17 @@ impl TransactionDriver for PostgresTransactionDriver {
 		// Transaction will be rolled back when dropped
 	}
 
+	fn set_option(&self, opt: TransactionOption) -> Result<()> {
+		match opt {
+			TransactionOption::DebugTransactionIdentifier(identifier) => {
+				self.state.lock().unwrap().debug_identifier = Some(identifier);
+				Ok(())
+			}
+			// Options other than limits only apply to FoundationDB
+			opt => self.limiter.lock().unwrap().limits.set_option(&opt),
+		}
+	}
+
 	fn add_conflict_range(
 		&self,
 		begin: &[u8],
@@ -589,6 +762,16 @@ impl TransactionDriver for PostgresTransactionDriver {
 
 	fn commit_ref(&self) -> Pin<Box<dyn Future<Output = Result<()>> + Send + '_>> {
 		Box::pin(async move {
+			self.check_limits()?;
+
+			if self.state.lock().unwrap().committed {
+				return Ok(());
+			}
+
+			// Read the watched keys before marking the transaction as committed, a failed read leaves
+			// it uncommitted
+			let (watches, watched_values) = self.take_watches().await?;
+
 			// Get operations and mark as committed
 			let operations = {
 				let mut state = self.state.lock().unwrap();
@@ -599,6 +782,15 @@ impl TransactionDriver for PostgresTransactionDriver {
 
 				state.operations.clone()
 			};
+			if self.read_only && !operations.operations().is_empty() {
+				return Err(DatabaseError::ReadOnly.into());
+			}
+			let written = written_ranges(&operations);
+			let notify_payload = if self.watch_notifications {
+				notify::encode_payload(self.driver_id, &written)
+			} else {
+				None
+			};
 
 			// Get the transaction sender if it exists
 			let tx_sender = self.tx_sender.get();
@@ -680,6 +872,7 @@ impl TransactionDriver for PostgresTransactionDriver {
 				sender
 					.send(TransactionCommand::Commit {
 						has_conflict_ranges: !operations.conflict_ranges().is_empty(),
+						notify_payload,
 						response: response_tx,
 					})
 					.await
@@ -768,6 +961,7 @@ impl TransactionDriver for PostgresTransactionDriver {
 				tx_sender
 					.send(TransactionCommand::Commit {
 						has_conflict_ranges: !operations.conflict_ranges().is_empty(),
+						notify_payload,
 						response: response_tx,
 					})
 					.await
@@ -779,6 +973,8 @@ impl TransactionDriver for PostgresTransactionDriver {
 					.context("failed to receive postgres commit response")??;
 			}
 
+			self.finish_watches(&written, watches, watched_values).await;
+
 			Ok(())
 		})
 	}
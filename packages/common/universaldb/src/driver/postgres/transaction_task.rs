@@ -1,15 +1,19 @@
 use anyhow::{Result, anyhow};
 use deadpool_postgres::Pool;
 use tokio::sync::{mpsc, oneshot};
-use tokio_postgres::IsolationLevel;
+use tokio_postgres::{IsolationLevel, error::SqlState};
 
 use crate::{
 	atomic::apply_atomic_op,
-	error::DatabaseError,
+	error::{ConflictingKeys, DatabaseError},
 	options::{ConflictRangeType, MutationType},
-	versionstamp::substitute_versionstamp_if_incomplete,
+	versionstamp::{
+		generate_versionstamp, substitute_versionstamp_if_incomplete, substitute_versionstamped_key,
+	},
 };
 
+use super::notify;
+
 #[derive(Debug, Clone, Copy)]
 pub enum TransactionIsolationLevel {
 	Serializable,
@@ -64,6 +68,8 @@ pub enum TransactionCommand {
 	// Transaction control
 	Commit {
 		has_conflict_ranges: bool,
+		/// Watch notification payload sent if the transaction commits.
+		notify_payload: Option<String>,
 		response: oneshot::Sender<Result<()>>,
 	},
 	// Conflict ranges
@@ -240,6 +246,9 @@ impl TransactionTask {
 			}
 		}
 
+		// Shared by all versionstamped keys in this transaction
+		let mut commit_versionstamp = None;
+
 		// Process commands
 		while let Some(cmd) = self.receiver.recv().await {
 			match cmd {
@@ -372,7 +381,7 @@ impl TransactionTask {
 						Ok(stmt) => tx
 							.query(&stmt, &[&begin, &end])
 							.await
-							.map_err(map_postgres_error)
+							.map_err(|err| map_postgres_conflict_error(err, &begin, &end))
 							.map(|rows| {
 								rows.into_iter()
 									.map(|row| {
@@ -457,7 +466,7 @@ impl TransactionTask {
 						Ok(stmt) => tx
 							.execute(&stmt, &[&begin, &end])
 							.await
-							.map_err(map_postgres_error)
+							.map_err(|err| map_postgres_conflict_error(err, &begin, &end))
 							.map(|_| ()),
 						Err(e) => Err(map_postgres_error(e)),
 					};
@@ -477,6 +486,32 @@ impl TransactionTask {
 						continue;
 					};
 
+					// TODO: Like other versionstamps, these should be calculated on the sql side
+					if let MutationType::SetVersionstampedKey = op_type {
+						let versionstamp =
+							commit_versionstamp.get_or_insert_with(|| generate_versionstamp(0));
+						let key = match substitute_versionstamped_key(key, versionstamp) {
+							Ok(key) => key,
+							Err(err) => {
+								let _ = response.send(Err(anyhow!(err)));
+								continue;
+							}
+						};
+
+						let query = "INSERT INTO kv (key, value) VALUES ($1, $2) ON CONFLICT (key) DO UPDATE SET value = $2";
+						let result = match tx.prepare_cached(query).await {
+							Ok(stmt) => tx
+								.execute(&stmt, &[&key, &param])
+								.await
+								.map_err(map_postgres_error)
+								.map(|_| ()),
+							Err(e) => Err(map_postgres_error(e)),
+						};
+
+						let _ = response.send(result);
+						continue;
+					}
+
 					// Get current value from database
 					let current_query = "SELECT value FROM kv WHERE key = $1";
 					let current_result = match tx.prepare_cached(current_query).await {
@@ -526,6 +561,7 @@ impl TransactionTask {
 				}
 				TransactionCommand::Commit {
 					has_conflict_ranges,
+					notify_payload,
 					response,
 				} => {
 					if has_conflict_ranges {
@@ -547,6 +583,20 @@ impl TransactionTask {
 						}
 					}
 
+					// Notifications are only delivered once the transaction commits
+					if let Some(payload) = notify_payload
+						&& let Err(err) = tx
+							.execute(
+								"SELECT pg_notify($1, $2)",
+								&[&notify::WATCH_CHANNEL, &payload],
+							)
+							.await
+							.map_err(map_postgres_error)
+					{
+						let _ = response.send(Err(err));
+						return;
+					}
+
 					let result = tx.commit().await.map_err(map_postgres_error);
 					let _ = response.send(result);
 					// Exit after commit
@@ -577,7 +627,7 @@ impl TransactionTask {
 							&[&begin, &end, &conflict_type],
 						)
 						.await
-						.map_err(map_postgres_error)
+						.map_err(|err| map_postgres_conflict_error(err, &begin, &end))
 						.map(|_| ());
 
 					let _ = response.send(result);
@@ -626,6 +676,24 @@ impl TransactionTask {
 	}
 }
 
+/// Like `map_postgres_error`, but reports `[begin, end)` as the conflicting range if adding it to the
+/// conflict ranges table overlapped with a range of another transaction. Overlaps with ranges of
+/// transactions that have not finished yet fail with a lock timeout instead of a violation, since
+/// the lock timeout is 0.
+fn map_postgres_conflict_error(
+	err: tokio_postgres::Error,
+	begin: &[u8],
+	end: &[u8],
+) -> anyhow::Error {
+	if err.code() == Some(&SqlState::EXCLUSION_VIOLATION)
+		|| err.code() == Some(&SqlState::LOCK_NOT_AVAILABLE)
+	{
+		ConflictingKeys::not_committed(vec![(begin.to_vec(), end.to_vec())])
+	} else {
+		map_postgres_error(err)
+	}
+}
+
 /// Maps PostgreSQL error to DatabaseError
 fn map_postgres_error(err: tokio_postgres::Error) -> anyhow::Error {
 	let error_str = err.to_string();
@@ -0,0 +1,202 @@
+use std::{
+	future::Future,
+	pin::Pin,
+	sync::{
+		Arc,
+		atomic::{AtomicBool, Ordering},
+	},
+};
+
+use anyhow::Result;
+use futures_util::FutureExt;
+
+use crate::{
+	RetryableTransaction, Transaction,
+	driver::{BoxFut, DatabaseDriver, DatabaseDriverHandle, Erased, TransactionDriver},
+	error::DatabaseError,
+	key_selector::KeySelector,
+	options::{ConflictRangeType, DatabaseOption, MutationType, TransactionOption},
+	range_option::RangeOption,
+	utils::IsolationLevel,
+	value::{Slice, Value, Values},
+};
+
+/// Wraps a driver so that its transactions reject writes. Writes are dropped and fail the commit
+/// with `DatabaseError::ReadOnly`, write conflict ranges fail right away.
+pub struct ReadOnlyDatabaseDriver {
+	inner: DatabaseDriverHandle,
+}
+
+impl ReadOnlyDatabaseDriver {
+	pub fn new(inner: DatabaseDriverHandle) -> Self {
+		ReadOnlyDatabaseDriver { inner }
+	}
+}
+
+impl DatabaseDriver for ReadOnlyDatabaseDriver {
+	fn create_trx(&self) -> Result<Transaction> {
+		let tx = self.inner.create_trx()?;
+
+		Ok(Transaction::new(Arc::new(ReadOnlyTransactionDriver::new(
+			tx.driver,
+		))))
+	}
+
+	fn run<'a>(
+		&'a self,
+		closure: Box<dyn Fn(RetryableTransaction) -> BoxFut<'a, Result<Erased>> + Send + Sync + 'a>,
+	) -> BoxFut<'a, Result<Erased>> {
+		self.inner.run(Box::new(move |tx| {
+			let driver = Arc::new(ReadOnlyTransactionDriver::new(tx.inner.driver.clone()));
+			let mut retryable = RetryableTransaction::new(Transaction::new(driver.clone()));
+			retryable.maybe_committed = tx.maybe_committed;
+
+			// The inner driver commits its own transaction, which never receives writes
+			let fut = closure(retryable);
+			async move {
+				let res = fut.await?;
+				driver.check_writes()?;
+
+				Ok(res)
+			}
+			.boxed()
+		}))
+	}
+
+	fn set_option(&self, opt: DatabaseOption) -> Result<()> {
+		self.inner.set_option(opt)
+	}
+}
+
+struct ReadOnlyTransactionDriver {
+	inner: Arc<dyn TransactionDriver>,
+	/// Whether a write was issued on this transaction.
+	wrote: AtomicBool,
+}
+
+impl ReadOnlyTransactionDriver {
+	fn new(inner: Arc<dyn TransactionDriver>) -> Self {
+		ReadOnlyTransactionDriver {
+			inner,
+			wrote: AtomicBool::new(false),
+		}
+	}
+
+	fn reject_write(&self) {
+		self.wrote.store(true, Ordering::Release);
+	}
+
+	fn check_writes(&self) -> Result<()> {
+		if self.wrote.load(Ordering::Acquire) {
+			return Err(DatabaseError::ReadOnly.into());
+		}
+
+		Ok(())
+	}
+}
+
+impl TransactionDriver for ReadOnlyTransactionDriver {
+	fn atomic_op(&self, _key: &[u8], _param: &[u8], _op_type: MutationType) {
+		self.reject_write();
+	}
+
+	fn get<'a>(
+		&'a self,
+		key: &[u8],
+		isolation_level: IsolationLevel,
+	) -> Pin<Box<dyn Future<Output = Result<Option<Slice>>> + Send + 'a>> {
+		self.inner.get(key, isolation_level)
+	}
+
+	fn get_key<'a>(
+		&'a self,
+		selector: &KeySelector<'a>,
+		isolation_level: IsolationLevel,
+	) -> Pin<Box<dyn Future<Output = Result<Slice>> + Send + 'a>> {
+		self.inner.get_key(selector, isolation_level)
+	}
+
+	fn get_range<'a>(
+		&'a self,
+		opt: &RangeOption<'a>,
+		iteration: usize,
+		isolation_level: IsolationLevel,
+	) -> Pin<Box<dyn Future<Output = Result<Values>> + Send + 'a>> {
+		self.inner.get_range(opt, iteration, isolation_level)
+	}
+
+	fn get_ranges_keyvalues<'a>(
+		&'a self,
+		opt: RangeOption<'a>,
+		isolation_level: IsolationLevel,
+	) -> crate::value::Stream<'a, Value> {
+		self.inner.get_ranges_keyvalues(opt, isolation_level)
+	}
+
+	fn set(&self, _key: &[u8], _value: &[u8]) {
+		self.reject_write();
+	}
+
+	fn clear(&self, _key: &[u8]) {
+		self.reject_write();
+	}
+
+	fn clear_range(&self, _begin: &[u8], _end: &[u8]) {
+		self.reject_write();
+	}
+
+	fn watch(&self, key: &[u8]) -> Pin<Box<dyn Future<Output = Result<()>> + Send>> {
+		self.inner.watch(key)
+	}
+
+	fn commit(self: Box<Self>) -> Pin<Box<dyn Future<Output = Result<()>> + Send>> {
+		Box::pin(async move {
+			self.check_writes()?;
+			self.inner.commit_ref().await
+		})
+	}
+
+	fn reset(&mut self) {
+		self.wrote.store(false, Ordering::Release);
+
+		if let Some(inner) = Arc::get_mut(&mut self.inner) {
+			inner.reset();
+		}
+	}
+
+	fn cancel(&self) {
+		self.inner.cancel()
+	}
+
+	fn set_option(&self, opt: TransactionOption) -> Result<()> {
+		self.inner.set_option(opt)
+	}
+
+	fn add_conflict_range(
+		&self,
+		begin: &[u8],
+		end: &[u8],
+		conflict_type: ConflictRangeType,
+	) -> Result<()> {
+		if let ConflictRangeType::Write = conflict_type {
+			return Err(DatabaseError::ReadOnly.into());
+		}
+
+		self.inner.add_conflict_range(begin, end, conflict_type)
+	}
+
+	fn get_estimated_range_size_bytes<'a>(
+		&'a self,
+		begin: &'a [u8],
+		end: &'a [u8],
+	) -> Pin<Box<dyn Future<Output = Result<i64>> + Send + 'a>> {
+		self.inner.get_estimated_range_size_bytes(begin, end)
+	}
+
+	fn commit_ref(&self) -> Pin<Box<dyn Future<Output = Result<()>> + Send + '_>> {
+		Box::pin(async move {
+			self.check_writes()?;
+			self.inner.commit_ref().await
+		})
+	}
+}
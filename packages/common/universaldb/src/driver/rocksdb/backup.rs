@@ -0,0 +1,167 @@
+//! Online backups of the RocksDB database using RocksDB's backup engine.
+//!
+//! Backups are incremental, files shared with previous backups are only stored once.
+
+use std::{
+	path::{Path, PathBuf},
+	sync::Arc,
+	time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result, bail, ensure};
+use rocksdb::{
+	Env, OptimisticTransactionDB, Options,
+	backup::{BackupEngine, BackupEngineOptions, RestoreOptions},
+};
+
+use super::database::DbInstance;
+
+#[derive(Debug, Clone)]
+pub struct BackupConfig {
+	/// Directory backups are stored in.
+	pub path: PathBuf,
+	/// Time between backups.
+	pub interval: Duration,
+	/// Amount of most recent backups to keep, older backups are deleted after every backup.
+	pub keep_last: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct BackupInfo {
+	pub id: u32,
+	/// Unix timestamp in seconds.
+	pub timestamp: i64,
+	/// Size in bytes, including files shared with other backups.
+	pub size: u64,
+	pub num_files: u32,
+}
+
+fn open_engine(backup_path: &Path) -> Result<BackupEngine> {
+	let opts = BackupEngineOptions::new(backup_path).context("invalid backup path")?;
+	let env = Env::new().context("failed to create rocksdb env")?;
+
+	BackupEngine::open(&opts, &env).context("failed to open backup engine")
+}
+
+/// Creates a backup of the database, then deletes old backups according to `keep_last`. Blocks
+/// until the backup is complete.
+pub fn create_backup(
+	db: &OptimisticTransactionDB,
+	backup_path: &Path,
+	keep_last: usize,
+) -> Result<BackupInfo> {
+	ensure!(keep_last > 0, "must keep at least 1 backup");
+
+	std::fs::create_dir_all(backup_path).context("failed to create backup directory")?;
+
+	let mut engine = open_engine(backup_path)?;
+	// Flushing includes the memtables in the backup without having to copy the WAL
+	engine
+		.create_new_backup_flush(db, true)
+		.context("failed to create backup")?;
+	engine
+		.purge_old_backups(keep_last)
+		.context("failed to purge old backups")?;
+
+	list_backups_inner(&engine)?
+		.pop()
+		.context("backup not found after creating it")
+}
+
+/// Lists all backups, oldest first.
+pub fn list_backups(backup_path: &Path) -> Result<Vec<BackupInfo>> {
+	if !backup_path.exists() {
+		return Ok(Vec::new());
+	}
+
+	list_backups_inner(&open_engine(backup_path)?)
+}
+
+fn list_backups_inner(engine: &BackupEngine) -> Result<Vec<BackupInfo>> {
+	let mut backups = engine
+		.get_backup_info()
+		.into_iter()
+		.map(|info| BackupInfo {
+			id: info.backup_id,
+			timestamp: info.timestamp,
+			size: info.size,
+			num_files: info.num_files,
+		})
+		.collect::<Vec<_>>();
+	backups.sort_by_key(|backup| backup.id);
+
+	Ok(backups)
+}
+
+/// Replaces the database at `db_path` with the given backup, or the latest backup if `None`. The
+/// database must not be in use.
+pub fn restore_backup(backup_path: &Path, db_path: &Path, backup_id: Option<u32>) -> Result<()> {
+	let mut engine = open_engine(backup_path)?;
+
+	let backup_id = match backup_id {
+		Some(backup_id) => backup_id,
+		None => {
+			list_backups_inner(&engine)?
+				.last()
+				.context("no backups found")?
+				.id
+		}
+	};
+	engine
+		.verify_backup(backup_id)
+		.with_context(|| format!("backup {backup_id} is invalid"))?;
+
+	// Opening the database takes its lock, this fails if the engine is still running
+	{
+		let mut opts = Options::default();
+		opts.create_if_missing(true);
+		std::fs::create_dir_all(db_path).context("failed to create database directory")?;
+		OptimisticTransactionDB::<rocksdb::SingleThreaded>::open(&opts, db_path)
+			.context("failed to open database, make sure the engine is stopped")?;
+	}
+
+	engine
+		.restore_from_backup(db_path, db_path, &RestoreOptions::default(), backup_id)
+		.with_context(|| format!("failed to restore backup {backup_id}"))?;
+
+	Ok(())
+}
+
+/// Creates a backup every `config.interval` until the task is aborted. The first backup is
+/// scheduled relative to the latest existing backup, so restarts do not delay backups.
+pub(super) async fn run(db: Arc<DbInstance>, config: BackupConfig) {
+	let latest = list_backups(&config.path)
+		.map(|backups| backups.last().map(|backup| backup.timestamp))
+		.unwrap_or_else(|err| {
+			tracing::warn!(?err, "failed to list rocksdb backups");
+			None
+		});
+	let mut delay = latest
+		.and_then(|timestamp| {
+			let next =
+				UNIX_EPOCH + Duration::from_secs(timestamp.try_into().ok()?) + config.interval;
+			Some(next.duration_since(SystemTime::now()).unwrap_or_default())
+		})
+		.unwrap_or_default();
+
+	loop {
+		tokio::time::sleep(delay).await;
+		delay = config.interval;
+
+		let db = db.clone();
+		let config = config.clone();
+		let res = tokio::task::spawn_blocking(move || match &*db {
+			DbInstance::Primary(db) => create_backup(db, &config.path, config.keep_last),
+			DbInstance::Secondary(_) => bail!("cannot back up a secondary instance"),
+		})
+		.await;
+
+		match res {
+			Ok(Ok(backup)) => {
+				tracing::info!(id=?backup.id, size=?backup.size, "created rocksdb backup")
+			}
+			Ok(Err(err)) => tracing::error!(?err, "failed to create rocksdb backup"),
+			Err(err) => tracing::error!(?err, "rocksdb backup task failed"),
+		}
+	}
+}
@@ -3,7 +3,7 @@ use std::sync::{Arc, RwLock};
 
 use anyhow::Result;
 
-use crate::error::DatabaseError;
+use crate::error::ConflictingKeys;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct TransactionId(u64);
@@ -78,8 +78,11 @@ impl ConflictRangeTracker {
 
 			for existing_range in ranges {
 				if new_range.conflicts_with(existing_range) {
-					// Found a conflict - return retryable error
-					return Err(DatabaseError::NotCommitted.into());
+					// Found a conflict - return retryable error with the overlapping part of the range
+					let begin = new_range.begin.clone().max(existing_range.begin.clone());
+					let end = new_range.end.clone().min(existing_range.end.clone());
+
+					return Err(ConflictingKeys::not_committed(vec![(begin, end)]));
 				}
 			}
 		}
@@ -153,6 +156,7 @@ impl Clone for ConflictRangeTracker {
 #[cfg(test)]
 mod tests {
 	use super::*;
+	use crate::error::DatabaseError;
 
 	#[test]
 	fn test_no_conflict_different_ranges() {
@@ -234,6 +238,27 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn test_conflicting_keys() {
+		let tracker = ConflictRangeTracker::new();
+		let tx1 = TransactionId::new();
+		let tx2 = TransactionId::new();
+
+		// Add write range for tx1
+		tracker
+			.add_range(tx1, b"a", b"c", true)
+			.expect("Should add write range");
+
+		// Conflict should report the overlapping part of both ranges
+		let err = tracker.add_range(tx2, b"b", b"d", false).unwrap_err();
+		let conflicting_keys =
+			ConflictingKeys::from_error(&err).expect("Should report conflicting keys");
+		assert_eq!(
+			conflicting_keys.ranges,
+			vec![(b"b".to_vec(), b"c".to_vec())]
+		);
+	}
+
 	#[test]
 	fn test_release_transaction() {
 		let tracker = ConflictRangeTracker::new();
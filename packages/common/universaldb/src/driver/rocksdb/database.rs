@@ -1,25 +1,62 @@
 use std::{
-	path::PathBuf,
+	path::{Path, PathBuf},
 	sync::{Arc, Mutex},
+	time::Instant,
 };
 
 use anyhow::{Context, Result};
-use rocksdb::{OptimisticTransactionDB, Options};
+use rocksdb::{DB, OptimisticTransactionDB, Options};
 
 use crate::{
 	RetryableTransaction, Transaction,
-	driver::{BoxFut, DatabaseDriver, Erased},
+	driver::{BoxFut, DatabaseDriver, Erased, conflict::report_conflict},
 	error::DatabaseError,
 	options::DatabaseOption,
-	utils::{MaybeCommitted, calculate_tx_retry_backoff},
+	tx_limits::{TransactionLimits, wait_for_timeout},
+	utils::MaybeCommitted,
+	watch::WatchRegistry,
 };
 
-use super::{conflict_range_tracker::ConflictRangeTracker, transaction::RocksDbTransactionDriver};
+use super::{
+	backup::{self, BackupConfig},
+	conflict_range_tracker::ConflictRangeTracker,
+	transaction::RocksDbTransactionDriver,
+};
+
+pub(super) enum DbInstance {
+	Primary(OptimisticTransactionDB),
+	/// Read-only instance that follows a primary opened by another process.
+	Secondary(DB),
+}
+
+impl DbInstance {
+	pub(super) fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>, rocksdb::Error> {
+		match self {
+			DbInstance::Primary(db) => db.get(key),
+			DbInstance::Secondary(db) => db.get(key),
+		}
+	}
+
+	/// Applies changes made by the primary since the last call. No-op for the primary.
+	fn catch_up(&self) -> Result<()> {
+		if let DbInstance::Secondary(db) = self {
+			db.try_catch_up_with_primary()
+				.context("failed to catch up with primary rocksdb")?;
+		}
+
+		Ok(())
+	}
+}
 
 pub struct RocksDbDatabaseDriver {
-	db: Arc<OptimisticTransactionDB>,
+	db: Arc<DbInstance>,
 	max_retries: Arc<Mutex<i32>>,
+	limits: Arc<Mutex<TransactionLimits>>,
 	conflict_tracker: ConflictRangeTracker,
+	watch_registry: WatchRegistry,
+	backup_handle: Option<tokio::task::JoinHandle<()>>,
+	/// Holds the info logs of a secondary instance, removed once the driver is dropped.
+	_secondary_dir: Option<tempfile::TempDir>,
 }
 
 impl RocksDbDatabaseDriver {
@@ -40,19 +77,77 @@ impl RocksDbDatabaseDriver {
 		tracing::debug!(path=%db_path.display(), "opening rocksdb");
 		let db = OptimisticTransactionDB::open(&opts, db_path).context("failed to open rocksdb")?;
 
-		Ok(RocksDbDatabaseDriver {
+		Ok(Self::from_instance(DbInstance::Primary(db)))
+	}
+
+	/// Opens a read-only secondary instance of the database at `primary_path`, which can be in use
+	/// by another process. The secondary catches up with the primary at the start of every
+	/// transaction and fails commits that contain writes. The secondary's own info logs are stored
+	/// in a temporary directory for the lifetime of the driver.
+	pub async fn new_secondary(primary_path: &Path) -> Result<Self> {
+		let secondary_dir = tempfile::Builder::new()
+			.prefix("rivet-engine-secondary-")
+			.tempdir()
+			.context("failed to create secondary database directory")?;
+
+		tracing::info!(
+			?primary_path,
+			secondary_path=?secondary_dir.path(),
+			"starting file system secondary driver"
+		);
+
+		let mut opts = Options::default();
+		// Secondary instances must keep all files open to follow the primary
+		opts.set_max_open_files(-1);
+
+		let db = DB::open_as_secondary(&opts, primary_path, secondary_dir.path())
+			.context("failed to open rocksdb secondary")?;
+
+		let mut driver = Self::from_instance(DbInstance::Secondary(db));
+		driver._secondary_dir = Some(secondary_dir);
+
+		Ok(driver)
+	}
+
+	fn from_instance(db: DbInstance) -> Self {
+		RocksDbDatabaseDriver {
 			db: Arc::new(db),
 			max_retries: Arc::new(Mutex::new(100)),
+			limits: Arc::new(Mutex::new(TransactionLimits::default())),
 			conflict_tracker: ConflictRangeTracker::new(),
-		})
+			watch_registry: WatchRegistry::new(),
+			backup_handle: None,
+			_secondary_dir: None,
+		}
+	}
+
+	/// Starts creating backups in the background according to the given config. Backups stop
+	/// once the driver is dropped.
+	pub fn start_backups(&mut self, config: BackupConfig) {
+		if let DbInstance::Secondary(_) = &*self.db {
+			tracing::warn!("backups are not supported by secondary instances");
+			return;
+		}
+
+		tracing::info!(path=?config.path, interval=?config.interval, "starting rocksdb backups");
+
+		if let Some(handle) = self.backup_handle.take() {
+			handle.abort();
+		}
+		self.backup_handle = Some(tokio::spawn(backup::run(self.db.clone(), config)));
 	}
 }
 
 impl DatabaseDriver for RocksDbDatabaseDriver {
 	fn create_trx(&self) -> Result<Transaction> {
+		self.db.catch_up()?;
+
 		Ok(Transaction::new(Arc::new(RocksDbTransactionDriver::new(
 			self.db.clone(),
 			self.conflict_tracker.clone(),
+			self.watch_registry.clone(),
+			*self.limits.lock().unwrap(),
+			Instant::now(),
 		))))
 	}
 
@@ -63,14 +158,30 @@ impl DatabaseDriver for RocksDbDatabaseDriver {
 		Box::pin(async move {
 			let mut maybe_committed = MaybeCommitted(false);
 			let max_retries = *self.max_retries.lock().unwrap();
+			let limits = *self.limits.lock().unwrap();
+			let run_started_at = Instant::now();
 
 			for attempt in 0..max_retries {
-				let tx = self.create_trx()?;
-				let mut retryable = RetryableTransaction::new(tx);
+				self.db.catch_up()?;
+
+				let driver = Arc::new(RocksDbTransactionDriver::new(
+					self.db.clone(),
+					self.conflict_tracker.clone(),
+					self.watch_registry.clone(),
+					limits,
+					run_started_at,
+				));
+				let mut retryable = RetryableTransaction::new(Transaction::new(driver.clone()));
 				retryable.maybe_committed = maybe_committed;
 
-				// Execute transaction
-				let error = match closure(retryable.clone()).await {
+				// Execute transaction, the closure is aborted once the transaction times out
+				let res = tokio::select! {
+					res = closure(retryable.clone()) => res,
+					_ = wait_for_timeout(|| driver.deadline()) => {
+						Err(DatabaseError::TransactionTimedOut.into())
+					}
+				};
+				let error = match res {
 					Ok(res) => match retryable.inner.driver.commit_ref().await {
 						Ok(_) => return Ok(res),
 						Err(e) => e,
@@ -88,9 +199,11 @@ impl DatabaseDriver for RocksDbDatabaseDriver {
 						if db_error.is_maybe_committed() {
 							maybe_committed = MaybeCommitted(true);
 						}
+						if let DatabaseError::NotCommitted = db_error {
+							report_conflict(&error, driver.debug_identifier().as_deref(), attempt);
+						}
 
-						let backoff_ms = calculate_tx_retry_backoff(attempt as usize);
-						tokio::time::sleep(tokio::time::Duration::from_millis(backoff_ms)).await;
+						tokio::time::sleep(driver.limits().retry_backoff(attempt as usize)).await;
 						continue;
 					}
 				}
@@ -104,16 +217,38 @@ impl DatabaseDriver for RocksDbDatabaseDriver {
 
 	fn set_option(&self, opt: DatabaseOption) -> Result<()> {
 		match opt {
+			DatabaseOption::TransactionTimeout(timeout_ms) => {
+				self.limits.lock().unwrap().set_timeout(timeout_ms)
+			}
 			DatabaseOption::TransactionRetryLimit(limit) => {
 				*self.max_retries.lock().unwrap() = limit;
 				Ok(())
 			}
+			DatabaseOption::TransactionMaxRetryDelay(max_retry_delay_ms) => self
+				.limits
+				.lock()
+				.unwrap()
+				.set_max_retry_delay(max_retry_delay_ms),
+			DatabaseOption::TransactionSizeLimit(size_limit) => {
+				self.limits.lock().unwrap().set_size_limit(size_limit)
+			}
+			DatabaseOption::MaxWatches(max_watches) => {
+				self.watch_registry.set_max_watches(max_watches)
+			}
+			// Only one process can open the database
+			DatabaseOption::WatchNotifications(_) => Ok(()),
 		}
 	}
 }
 
 impl Drop for RocksDbDatabaseDriver {
 	fn drop(&mut self) {
-		self.db.cancel_all_background_work(true);
+		if let Some(handle) = self.backup_handle.take() {
+			handle.abort();
+		}
+		match &*self.db {
+			DbInstance::Primary(db) => db.cancel_all_background_work(true),
+			DbInstance::Secondary(db) => db.cancel_all_background_work(true),
+		}
 	}
 }
@@ -1,3 +1,4 @@
+pub mod backup;
 pub mod conflict_range_tracker;
 mod database;
 mod transaction;
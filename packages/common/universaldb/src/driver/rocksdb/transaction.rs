@@ -2,49 +2,60 @@ use std::{
 	future::Future,
 	pin::Pin,
 	sync::{Arc, Mutex},
+	time::Instant,
 };
 
 use anyhow::{Context, Result, anyhow};
-use rocksdb::OptimisticTransactionDB;
 use tokio::sync::{OnceCell, mpsc, oneshot};
 
 use crate::{
 	driver::TransactionDriver,
 	error::DatabaseError,
 	key_selector::KeySelector,
-	options::{ConflictRangeType, MutationType},
+	options::{ConflictRangeType, MutationType, TransactionOption},
 	range_option::RangeOption,
+	tx_limits::{TransactionLimiter, TransactionLimits},
 	tx_ops::TransactionOperations,
 	utils::IsolationLevel,
 	value::{Slice, Value, Values},
+	watch::{PendingWatch, WatchRegistry, written_ranges},
 };
 
 use super::{
 	conflict_range_tracker::{ConflictRangeTracker, TransactionId},
+	database::DbInstance,
 	transaction_task::{TransactionCommand, TransactionTask},
 };
 
 struct TransactionState {
 	operations: TransactionOperations,
+	watches: Vec<PendingWatch>,
 	committed: bool,
+	debug_identifier: Option<String>,
 }
 
 impl Default for TransactionState {
 	fn default() -> Self {
 		Self {
 			operations: TransactionOperations::default(),
+			watches: Vec::new(),
 			committed: false,
+			debug_identifier: None,
 		}
 	}
 }
 
 pub struct RocksDbTransactionDriver {
-	db: Arc<OptimisticTransactionDB>,
+	db: Arc<DbInstance>,
 	state: Arc<Mutex<TransactionState>>,
 	tx_sender: Arc<OnceCell<mpsc::Sender<TransactionCommand>>>,
 	snapshot_tx_sender: Arc<OnceCell<mpsc::Sender<TransactionCommand>>>,
 	conflict_tracker: ConflictRangeTracker,
+	watch_registry: WatchRegistry,
 	tx_id: TransactionId,
+	/// Limits set for the database, restored on reset.
+	db_limits: TransactionLimits,
+	limiter: Arc<Mutex<TransactionLimiter>>,
 }
 
 impl Drop for RocksDbTransactionDriver {
@@ -55,14 +66,97 @@ impl Drop for RocksDbTransactionDriver {
 }
 
 impl RocksDbTransactionDriver {
-	pub fn new(db: Arc<OptimisticTransactionDB>, conflict_tracker: ConflictRangeTracker) -> Self {
+	pub fn new(
+		db: Arc<DbInstance>,
+		conflict_tracker: ConflictRangeTracker,
+		watch_registry: WatchRegistry,
+		limits: TransactionLimits,
+		run_started_at: Instant,
+	) -> Self {
 		RocksDbTransactionDriver {
 			db,
 			state: Arc::new(Mutex::new(TransactionState::default())),
 			tx_sender: Arc::new(OnceCell::new()),
 			snapshot_tx_sender: Arc::new(OnceCell::new()),
 			conflict_tracker,
+			watch_registry,
 			tx_id: TransactionId::new(),
+			db_limits: limits,
+			limiter: Arc::new(Mutex::new(TransactionLimiter::new(limits, run_started_at))),
+		}
+	}
+
+	/// When the transaction times out, if it has a timeout.
+	pub fn deadline(&self) -> Option<Instant> {
+		self.limiter.lock().unwrap().deadline()
+	}
+
+	pub fn limits(&self) -> TransactionLimits {
+		self.limiter.lock().unwrap().limits
+	}
+
+	/// Fails if the transaction timed out, is too old, or exceeds its size limit.
+	fn check_limits(&self) -> Result<()> {
+		let size = self.state.lock().unwrap().operations.size();
+		self.limiter.lock().unwrap().check(size)
+	}
+
+	/// Identifier set with `TransactionOption::DebugTransactionIdentifier`.
+	pub fn debug_identifier(&self) -> Option<String> {
+		self.state.lock().unwrap().debug_identifier.clone()
+	}
+
+	/// Reads the values of the watched keys as seen by this transaction, including its own writes.
+	async fn read_watched_values(&self, watches: &[PendingWatch]) -> Result<Vec<Option<Slice>>> {
+		let mut values = Vec::with_capacity(watches.len());
+		for watch in watches {
+			values.push(self.get(&watch.key, IsolationLevel::Snapshot).await?);
+		}
+
+		Ok(values)
+	}
+
+	/// Takes the pending watches and reads the current values of their keys. The watches are put back
+	/// if a read fails so the transaction can still be committed.
+	async fn take_watches(&self) -> Result<(Vec<PendingWatch>, Vec<Option<Slice>>)> {
+		let watches = std::mem::take(&mut self.state.lock().unwrap().watches);
+
+		match self.read_watched_values(&watches).await {
+			Ok(values) => Ok((watches, values)),
+			Err(err) => {
+				let mut state = self.state.lock().unwrap();
+				let new_watches = std::mem::replace(&mut state.watches, watches);
+				state.watches.extend(new_watches);
+
+				Err(err)
+			}
+		}
+	}
+
+	/// Fires watches on keys written by this transaction, then registers the watches of this
+	/// transaction. Must be called after a successful commit.
+	fn finish_watches(
+		&self,
+		written: &[(Vec<u8>, Vec<u8>)],
+		watches: Vec<PendingWatch>,
+		values: Vec<Option<Slice>>,
+	) {
+		self.watch_registry.fire_ranges(written);
+
+		for (watch, value) in watches.into_iter().zip(values) {
+			let key = watch.key.clone();
+			self.watch_registry.register(watch);
+
+			// Fire right away if the key was changed by another transaction after this transaction read
+			// it. Reading after registering guarantees no change is missed. Failed reads fire the watch
+			// since the transaction already committed.
+			let changed = match self.db.get(&key) {
+				Ok(current) => current.as_ref() != value.as_deref(),
+				Err(_) => true,
+			};
+			if changed {
+				self.watch_registry.fire_key(&key);
+			}
 		}
 	}
 
@@ -128,6 +222,8 @@ impl TransactionDriver for RocksDbTransactionDriver {
 	) -> Pin<Box<dyn Future<Output = Result<Option<Slice>>> + Send + 'a>> {
 		let key = key.to_vec();
 		Box::pin(async move {
+			self.check_limits()?;
+
 			// Both snapshot and non-snapshot reads check local operations first
 			// Transactions always see their own writes
 			let ops = {
@@ -197,6 +293,8 @@ impl TransactionDriver for RocksDbTransactionDriver {
 		let selector = selector.clone();
 
 		Box::pin(async move {
+			self.check_limits()?;
+
 			let key = selector.key().to_vec();
 			let offset = selector.offset();
 			let or_equal = selector.or_equal();
@@ -253,6 +351,8 @@ impl TransactionDriver for RocksDbTransactionDriver {
 		let reverse = opt.reverse;
 
 		Box::pin(async move {
+			self.check_limits()?;
+
 			// Both snapshot and non-snapshot reads check local operations first
 			// Transactions always see their own writes
 			let ops = {
@@ -345,6 +445,10 @@ impl TransactionDriver for RocksDbTransactionDriver {
 
 		Box::pin(
 			futures_util::stream::once(async move {
+				if let Err(err) = self.check_limits() {
+					return futures_util::stream::iter(vec![Err(err)]);
+				}
+
 				// Get the transaction sender based on snapshot mode
 				let tx_sender = if let IsolationLevel::Snapshot = isolation_level {
 					match self.ensure_snapshot_transaction().await {
@@ -435,8 +539,25 @@ impl TransactionDriver for RocksDbTransactionDriver {
 		state.operations.clear_range(begin, end);
 	}
 
+	fn watch(&self, key: &[u8]) -> Pin<Box<dyn Future<Output = Result<()>> + Send>> {
+		let (watch, fut) = PendingWatch::new(key);
+		self.state.lock().unwrap().watches.push(watch);
+
+		fut
+	}
+
 	fn commit(self: Box<Self>) -> Pin<Box<dyn Future<Output = Result<()>> + Send>> {
 		Box::pin(async move {
+			self.check_limits()?;
+
+			if self.state.lock().unwrap().committed {
+				return Err(DatabaseError::UsedDuringCommit.into());
+			}
+
+			// Read the watched keys before marking the transaction as committed, a failed read leaves
+			// it uncommitted
+			let (watches, watched_values) = self.take_watches().await?;
+
 			// Get the operations and conflict ranges to commit
 			let operations = {
 				let mut state = self.state.lock().unwrap();
@@ -447,6 +568,7 @@ impl TransactionDriver for RocksDbTransactionDriver {
 
 				state.operations.clone()
 			};
+			let written = written_ranges(&operations);
 
 			// Get the transaction sender
 			let tx_sender = self.ensure_transaction().await.map_err(|e| e)?;
@@ -469,6 +591,7 @@ impl TransactionDriver for RocksDbTransactionDriver {
 			// Release conflict ranges after successful commit
 			if result.is_ok() {
 				self.conflict_tracker.release_transaction(self.tx_id);
+				self.finish_watches(&written, watches, watched_values);
 			}
 
 			result.map_err(|e| e)
@@ -484,6 +607,7 @@ impl TransactionDriver for RocksDbTransactionDriver {
 
 		let mut state = self.state.lock().unwrap();
 		*state = TransactionState::default();
+		*self.limiter.lock().unwrap() = TransactionLimiter::new(self.db_limits, Instant::now());
 		// Clear the transaction senders to reset connections
 		self.tx_sender = Arc::new(OnceCell::new());
 		self.snapshot_tx_sender = Arc::new(OnceCell::new());
@@ -502,6 +626,17 @@ impl TransactionDriver for RocksDbTransactionDriver {
 		}
 	}
 
+	fn set_option(&self, opt: TransactionOption) -> Result<()> {
+		match opt {
+			TransactionOption::DebugTransactionIdentifier(identifier) => {
+				self.state.lock().unwrap().debug_identifier = Some(identifier);
+				Ok(())
+			}
+			// Options other than limits only apply to FoundationDB
+			opt => self.limiter.lock().unwrap().limits.set_option(&opt),
+		}
+	}
+
 	fn add_conflict_range(
 		&self,
 		begin: &[u8],
@@ -559,6 +694,16 @@ impl TransactionDriver for RocksDbTransactionDriver {
 
 	fn commit_ref(&self) -> Pin<Box<dyn Future<Output = Result<()>> + Send + '_>> {
 		Box::pin(async move {
+			self.check_limits()?;
+
+			if self.state.lock().unwrap().committed {
+				return Err(DatabaseError::UsedDuringCommit.into());
+			}
+
+			// Read the watched keys before marking the transaction as committed, a failed read leaves
+			// it uncommitted
+			let (watches, watched_values) = self.take_watches().await?;
+
 			// Get the operations to commit
 			let operations = {
 				let mut state = self.state.lock().unwrap();
@@ -569,6 +714,7 @@ impl TransactionDriver for RocksDbTransactionDriver {
 
 				state.operations.clone()
 			};
+			let written = written_ranges(&operations);
 
 			// Get the transaction sender
 			let tx_sender = self.ensure_transaction().await?;
@@ -591,6 +737,7 @@ impl TransactionDriver for RocksDbTransactionDriver {
 			// Release conflict ranges after successful commit
 			if result.is_ok() {
 				self.conflict_tracker.release_transaction(self.tx_id);
+				self.finish_watches(&written, watches, watched_values);
 			}
 
 			result.map(|_| ())
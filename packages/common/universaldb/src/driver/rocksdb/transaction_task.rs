@@ -1,8 +1,8 @@
 use std::sync::Arc;
 
-use anyhow::{Context, Result, bail};
+use anyhow::{Context, Result, anyhow, bail};
 use rocksdb::{
-	OptimisticTransactionDB, ReadOptions, Transaction as RocksDbTransaction, WriteOptions,
+	DB, OptimisticTransactionDB, ReadOptions, Transaction as RocksDbTransaction, WriteOptions,
 };
 use tokio::sync::{mpsc, oneshot};
 
@@ -10,10 +10,14 @@ use crate::{
 	atomic::apply_atomic_op,
 	error::DatabaseError,
 	key_selector::KeySelector,
+	options::MutationType,
 	tx_ops::{Operation, TransactionOperations},
 	value::{KeyValue, Slice, Values},
+	versionstamp::{generate_versionstamp, substitute_versionstamped_key},
 };
 
+use super::database::DbInstance;
+
 pub enum TransactionCommand {
 	Get {
 		key: Vec<u8>,
@@ -49,15 +53,48 @@ pub enum TransactionCommand {
 	Cancel,
 }
 
+/// Reads through a transaction of the primary instance, or directly from a secondary instance.
+enum Reader<'a> {
+	Transaction(RocksDbTransaction<'a, OptimisticTransactionDB>),
+	Secondary(&'a DB),
+}
+
+type ReaderIterator<'a> =
+	Box<dyn Iterator<Item = Result<(Box<[u8]>, Box<[u8]>), rocksdb::Error>> + 'a>;
+
+impl Reader<'_> {
+	fn get_opt(
+		&self,
+		key: &[u8],
+		read_opts: &ReadOptions,
+	) -> Result<Option<Vec<u8>>, rocksdb::Error> {
+		match self {
+			Reader::Transaction(txn) => txn.get_opt(key, read_opts),
+			Reader::Secondary(db) => db.get_opt(key, read_opts),
+		}
+	}
+
+	fn iterator_opt(
+		&self,
+		mode: rocksdb::IteratorMode<'_>,
+		read_opts: ReadOptions,
+	) -> ReaderIterator<'_> {
+		match self {
+			Reader::Transaction(txn) => Box::new(txn.iterator_opt(mode, read_opts)),
+			Reader::Secondary(db) => Box::new(db.iterator_opt(mode, read_opts)),
+		}
+	}
+}
+
 pub struct TransactionTask {
-	db: Arc<OptimisticTransactionDB>,
+	db: Arc<DbInstance>,
 	receiver: mpsc::Receiver<TransactionCommand>,
 	_exclusive: bool,
 }
 
 impl TransactionTask {
 	pub fn new(
-		db: Arc<OptimisticTransactionDB>,
+		db: Arc<DbInstance>,
 		receiver: mpsc::Receiver<TransactionCommand>,
 		exclusive: bool,
 	) -> Self {
@@ -134,14 +171,23 @@ impl TransactionTask {
 		}
 	}
 
-	fn create_transaction(&self) -> RocksDbTransaction<OptimisticTransactionDB> {
+	fn create_transaction(
+		db: &OptimisticTransactionDB,
+	) -> RocksDbTransaction<'_, OptimisticTransactionDB> {
 		let write_opts = WriteOptions::default();
 		let txn_opts = rocksdb::OptimisticTransactionOptions::default();
-		self.db.transaction_opt(&write_opts, &txn_opts)
+		db.transaction_opt(&write_opts, &txn_opts)
+	}
+
+	fn reader(&self) -> Reader<'_> {
+		match &*self.db {
+			DbInstance::Primary(db) => Reader::Transaction(Self::create_transaction(db)),
+			DbInstance::Secondary(db) => Reader::Secondary(db),
+		}
 	}
 
 	async fn handle_get(&mut self, key: &[u8]) -> Result<Option<Slice>> {
-		let txn = self.create_transaction();
+		let txn = self.reader();
 
 		let read_opts = ReadOptions::default();
 
@@ -157,7 +203,7 @@ impl TransactionTask {
 		or_equal: bool,
 		offset: i32,
 	) -> Result<Option<Slice>> {
-		let txn = self.create_transaction();
+		let txn = self.reader();
 
 		let read_opts = ReadOptions::default();
 
@@ -243,7 +289,7 @@ impl TransactionTask {
 	#[allow(dead_code)]
 	fn resolve_key_selector(
 		&self,
-		txn: &RocksDbTransaction<OptimisticTransactionDB>,
+		txn: &Reader<'_>,
 		selector: &KeySelector<'_>,
 		_read_opts: &ReadOptions,
 	) -> Result<Vec<u8>> {
@@ -303,8 +349,22 @@ impl TransactionTask {
 	}
 
 	async fn handle_commit(&mut self, operations: TransactionOperations) -> Result<()> {
+		let db = match &*self.db {
+			DbInstance::Primary(db) => db,
+			DbInstance::Secondary(_) => {
+				if operations.operations().is_empty() {
+					return Ok(());
+				}
+
+				return Err(DatabaseError::ReadOnly.into());
+			}
+		};
+
 		// Create a new transaction for this commit
-		let txn = self.create_transaction();
+		let txn = Self::create_transaction(db);
+
+		// Shared by all versionstamped keys in this commit
+		let mut commit_versionstamp = None;
 
 		// Apply all operations to the transaction
 		for op in operations.operations() {
@@ -342,6 +402,20 @@ impl TransactionTask {
 							.context("failed to delete key in range from rocksdb")?;
 					}
 				}
+				Operation::AtomicOp {
+					key,
+					param,
+					op_type: MutationType::SetVersionstampedKey,
+				} => {
+					let versionstamp =
+						commit_versionstamp.get_or_insert_with(|| generate_versionstamp(0));
+					let key = substitute_versionstamped_key(key.clone(), versionstamp)
+						.map_err(|err| anyhow!(err))
+						.context("failed to substitute versionstamped key")?;
+
+					txn.put(&key, param)
+						.context("failed to set versionstamped key in rocksdb")?;
+				}
 				Operation::AtomicOp {
 					key,
 					param,
@@ -400,7 +474,7 @@ impl TransactionTask {
 		reverse: bool,
 		_iteration: usize,
 	) -> Result<Values> {
-		let txn = self.create_transaction();
+		let txn = self.reader();
 		let read_opts = ReadOptions::default();
 
 		// Resolve the begin selector
@@ -444,7 +518,7 @@ impl TransactionTask {
 
 	fn resolve_key_selector_for_range(
 		&self,
-		txn: &RocksDbTransaction<OptimisticTransactionDB>,
+		txn: &Reader<'_>,
 		key: &[u8],
 		or_equal: bool,
 		offset: i32,
@@ -503,11 +577,11 @@ impl TransactionTask {
 	async fn handle_get_estimated_range_size(&mut self, begin: &[u8], end: &[u8]) -> Result<i64> {
 		let range = rocksdb::Range::new(begin, end);
 
-		Ok(self
-			.db
-			.get_approximate_sizes(&[range])
-			.first()
-			.copied()
-			.unwrap_or(0) as i64)
+		let sizes = match &*self.db {
+			DbInstance::Primary(db) => db.get_approximate_sizes(&[range]),
+			DbInstance::Secondary(db) => db.get_approximate_sizes(&[range]),
+		};
+
+		Ok(sizes.first().copied().unwrap_or(0) as i64)
 	}
 }
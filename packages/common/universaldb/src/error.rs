@@ -1,17 +1,33 @@
+use std::fmt;
+
 #[derive(thiserror::Error, Debug)]
 pub enum DatabaseError {
 	#[error("transaction not committed due to conflict with another transaction")]
 	NotCommitted,
 
-	// TODO: Implement in rocksdb and postgres drivers
 	#[error("transaction is too old to perform reads or be committed")]
 	TransactionTooOld,
 
+	#[error("operation aborted because the transaction timed out")]
+	TransactionTimedOut,
+
+	#[error("transaction exceeds byte limit")]
+	TransactionTooLarge,
+
 	#[error("max number of transaction retries reached")]
 	MaxRetriesReached,
 
 	#[error("operation issued while a commit was outstanding")]
 	UsedDuringCommit,
+
+	#[error("watch cancelled because its transaction was reset or dropped before committing")]
+	WatchCancelled,
+
+	#[error("too many watches currently set")]
+	TooManyWatches,
+
+	#[error("write issued on a read-only database")]
+	ReadOnly,
 }
 
 impl DatabaseError {
@@ -28,3 +44,47 @@ impl DatabaseError {
 		false
 	}
 }
+
+/// Key ranges of a transaction that conflicted with another transaction, the equivalent of FDB's
+/// `ReportConflictingKeys`. Attached as context to `DatabaseError::NotCommitted` by drivers that know
+/// which ranges caused the conflict.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConflictingKeys {
+	/// `[begin, end)` ranges.
+	pub ranges: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl ConflictingKeys {
+	/// Returns the conflicting ranges attached to the given error, if any.
+	pub fn from_error(err: &anyhow::Error) -> Option<&ConflictingKeys> {
+		// Context is not part of `chain`, `downcast_ref` also looks through nested context
+		err.downcast_ref::<ConflictingKeys>()
+	}
+
+	/// Creates a `DatabaseError::NotCommitted` error carrying the given ranges.
+	pub(crate) fn not_committed(ranges: Vec<(Vec<u8>, Vec<u8>)>) -> anyhow::Error {
+		anyhow::Error::new(DatabaseError::NotCommitted).context(ConflictingKeys { ranges })
+	}
+}
+
+impl fmt::Display for ConflictingKeys {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "conflicting key ranges:")?;
+
+		for (i, (begin, end)) in self.ranges.iter().enumerate() {
+			if i != 0 {
+				write!(f, ",")?;
+			}
+			write!(f, " [{}, {})", escape_key(begin), escape_key(end))?;
+		}
+
+		Ok(())
+	}
+}
+
+fn escape_key(key: &[u8]) -> String {
+	key.iter()
+		.flat_map(|b| std::ascii::escape_default(*b))
+		.map(char::from)
+		.collect()
+}
@@ -24,4 +24,9 @@ lazy_static::lazy_static! {
 	pub static ref KEY_UNPACK_COUNT: Counter<u64> = METER.u64_counter("rivet_udb_key_unpack_count")
 		.with_description("How many times a key has been unpacked.")
 		.build();
+
+	/// Expected attributes: "subspace"
+	pub static ref TRANSACTION_CONFLICT_COUNT: Counter<u64> = METER.u64_counter("rivet_udb_transaction_conflict_count")
+		.with_description("How many times a transaction was not committed due to a conflict with another transaction.")
+		.build();
 }
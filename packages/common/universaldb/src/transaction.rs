@@ -6,7 +6,7 @@ use futures_util::StreamExt;
 use crate::{
 	driver::TransactionDriver,
 	key_selector::KeySelector,
-	options::{ConflictRangeType, MutationType},
+	options::{ConflictRangeType, MutationType, TransactionOption},
 	range_option::RangeOption,
 	tuple::{self, TuplePack, TupleUnpack},
 	utils::{
@@ -160,6 +160,15 @@ impl Transaction {
 			.atomic_op(&self.subspace.pack(key), param, op_type)
 	}
 
+	/// Watches a key for changes. The returned future resolves once another transaction writes to the
+	/// key after this transaction commits, or immediately after commit if the key changed since this
+	/// transaction read it. Writes that keep the same value can also fire the watch.
+	///
+	/// The future must not be awaited before the transaction commits.
+	pub fn watch<T: TuplePack>(&self, key: &T) -> Pin<Box<dyn Future<Output = Result<()>> + Send>> {
+		self.driver.watch(&self.subspace.pack(key))
+	}
+
 	pub fn read_range<'a>(
 		&'a self,
 		opt: RangeOption<'a>,
@@ -246,6 +255,11 @@ impl Transaction {
 		self.driver.cancel()
 	}
 
+	/// Set a transaction option
+	pub fn set_option(&self, opt: TransactionOption) -> Result<()> {
+		self.driver.set_option(opt)
+	}
+
 	pub fn add_conflict_range(
 		&self,
 		begin: &[u8],
@@ -320,6 +334,10 @@ impl<'t> InformalTransaction<'t> {
 		self.inner.driver.clear_range(begin, end)
 	}
 
+	pub fn watch(&self, key: &[u8]) -> Pin<Box<dyn Future<Output = Result<()>> + Send>> {
+		self.inner.driver.watch(key)
+	}
+
 	/// Clear all keys in a subspace range
 	pub fn clear_subspace_range(&self, subspace: &tuple::Subspace) {
 		let (begin, end) = subspace.range();
@@ -0,0 +1,143 @@
+use std::time::{Duration, Instant};
+
+use anyhow::{Result, ensure};
+
+use crate::{error::DatabaseError, options::TransactionOption, utils::calculate_tx_retry_backoff};
+
+/// FDB only keeps versions of the last 5 seconds, so older transactions can no longer read or
+/// commit. The emulated drivers enforce the same limit to behave the same way.
+const MAX_TRANSACTION_AGE: Duration = Duration::from_secs(5);
+const DEFAULT_SIZE_LIMIT: usize = 10_000_000;
+const MIN_SIZE_LIMIT: usize = 32;
+
+/// Limits set with `DatabaseOption` for every transaction, or with `TransactionOption` for a single
+/// transaction.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TransactionLimits {
+	timeout: Option<Duration>,
+	size_limit: usize,
+	max_retry_delay: Option<Duration>,
+}
+
+impl Default for TransactionLimits {
+	fn default() -> Self {
+		TransactionLimits {
+			timeout: None,
+			size_limit: DEFAULT_SIZE_LIMIT,
+			max_retry_delay: None,
+		}
+	}
+}
+
+impl TransactionLimits {
+	/// A timeout of 0 disables the timeout.
+	pub fn set_timeout(&mut self, timeout_ms: i32) -> Result<()> {
+		let timeout_ms = u64::try_from(timeout_ms)?;
+		self.timeout = (timeout_ms != 0).then(|| Duration::from_millis(timeout_ms));
+
+		Ok(())
+	}
+
+	pub fn set_size_limit(&mut self, size_limit: i32) -> Result<()> {
+		let size_limit = usize::try_from(size_limit)?;
+		ensure!(
+			(MIN_SIZE_LIMIT..=DEFAULT_SIZE_LIMIT).contains(&size_limit),
+			"size limit must be between {MIN_SIZE_LIMIT} and {DEFAULT_SIZE_LIMIT} bytes"
+		);
+		self.size_limit = size_limit;
+
+		Ok(())
+	}
+
+	pub fn set_max_retry_delay(&mut self, max_retry_delay_ms: i32) -> Result<()> {
+		let max_retry_delay_ms = u64::try_from(max_retry_delay_ms)?;
+		self.max_retry_delay = Some(Duration::from_millis(max_retry_delay_ms));
+
+		Ok(())
+	}
+
+	/// Applies a limit option of a single transaction. Other options are ignored.
+	pub fn set_option(&mut self, opt: &TransactionOption) -> Result<()> {
+		match opt {
+			TransactionOption::Timeout(timeout_ms) => self.set_timeout(*timeout_ms),
+			TransactionOption::SizeLimit(size_limit) => self.set_size_limit(*size_limit),
+			TransactionOption::MaxRetryDelay(max_retry_delay_ms) => {
+				self.set_max_retry_delay(*max_retry_delay_ms)
+			}
+			_ => Ok(()),
+		}
+	}
+
+	/// Backoff before the given retry attempt, clamped to the max retry delay.
+	pub fn retry_backoff(&self, attempt: usize) -> Duration {
+		let backoff = Duration::from_millis(calculate_tx_retry_backoff(attempt));
+
+		match self.max_retry_delay {
+			Some(max_retry_delay) => backoff.min(max_retry_delay),
+			None => backoff,
+		}
+	}
+}
+
+/// Limits of a single transaction attempt.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TransactionLimiter {
+	pub limits: TransactionLimits,
+	/// When the first attempt of the transaction started. Like in FDB, the timeout is not reset by
+	/// retries.
+	run_started_at: Instant,
+	created_at: Instant,
+}
+
+impl TransactionLimiter {
+	pub fn new(limits: TransactionLimits, run_started_at: Instant) -> Self {
+		TransactionLimiter {
+			limits,
+			run_started_at,
+			created_at: Instant::now(),
+		}
+	}
+
+	pub fn deadline(&self) -> Option<Instant> {
+		self.limits
+			.timeout
+			.map(|timeout| self.run_started_at + timeout)
+	}
+
+	/// Fails if the transaction timed out, is too old, or has written more than the size limit.
+	pub fn check(&self, size: usize) -> Result<()> {
+		if let Some(deadline) = self.deadline() {
+			ensure!(
+				Instant::now() < deadline,
+				DatabaseError::TransactionTimedOut
+			);
+		}
+		ensure!(
+			self.created_at.elapsed() < MAX_TRANSACTION_AGE,
+			DatabaseError::TransactionTooOld
+		);
+		ensure!(
+			size <= self.limits.size_limit,
+			DatabaseError::TransactionTooLarge
+		);
+
+		Ok(())
+	}
+}
+
+/// Resolves once the timeout of the transaction elapses. The timeout is read again after every
+/// interval since the transaction can change it while running.
+pub(crate) async fn wait_for_timeout(deadline: impl Fn() -> Option<Instant>) {
+	const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+	loop {
+		let now = Instant::now();
+		match deadline() {
+			Some(deadline) if now >= deadline => return,
+			Some(deadline) => {
+				tokio::time::sleep(deadline.duration_since(now).min(POLL_INTERVAL)).await
+			}
+			None => tokio::time::sleep(POLL_INTERVAL).await,
+		}
+	}
+}
@@ -43,13 +43,25 @@ pub enum GetOutput {
 pub struct TransactionOperations {
 	operations: Vec<Operation>,
 	conflict_ranges: Vec<(Vec<u8>, Vec<u8>, ConflictRangeType)>,
+	/// Total size of all keys, values and ranges, checked against the transaction size limit.
+	size: usize,
 }
 
 impl TransactionOperations {
 	pub fn add_operation(&mut self, op: Operation) {
+		self.size += match &op {
+			Operation::Set { key, value } => key.len() + value.len(),
+			Operation::Clear { key } => key.len(),
+			Operation::ClearRange { begin, end } => begin.len() + end.len(),
+			Operation::AtomicOp { key, param, .. } => key.len() + param.len(),
+		};
 		self.operations.push(op);
 	}
 
+	pub fn size(&self) -> usize {
+		self.size
+	}
+
 	pub fn operations(&self) -> &[Operation] {
 		&self.operations
 	}
@@ -359,6 +371,7 @@ impl TransactionOperations {
 	pub fn clear_all(&mut self) {
 		self.operations.clear();
 		self.conflict_ranges.clear();
+		self.size = 0;
 	}
 
 	pub fn add_conflict_range(
@@ -367,6 +380,7 @@ impl TransactionOperations {
 		end: &[u8],
 		conflict_type: ConflictRangeType,
 	) {
+		self.size += begin.len() + end.len();
 		self.conflict_ranges
 			.push((begin.to_vec(), end.to_vec(), conflict_type));
 	}
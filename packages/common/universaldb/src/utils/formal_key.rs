@@ -1,6 +1,11 @@
 use anyhow::Result;
+use serde::Serialize;
 
-use crate::value::Value;
+use crate::{
+	tuple::{TuplePack, TupleUnpack},
+	utils::Subspace,
+	value::Value,
+};
 
 pub trait FormalKey {
 	type Value;
@@ -21,3 +26,59 @@ pub trait FormalChunkedKey {
 
 	fn split(&self, value: Self::Value) -> Result<Vec<Vec<u8>>>;
 }
+
+/// Signature of `decode_formal_key`, used to list the known keys of a subspace.
+pub type FormalKeyDecoder = fn(&Subspace, &[u8], &[u8]) -> Option<Result<serde_json::Value>>;
+/// Signature of `decode_formal_chunked_key`, used to list the known chunked keys of a subspace.
+pub type FormalChunkedKeyDecoder =
+	fn(&Subspace, &[u8], &[Value]) -> Option<Result<serde_json::Value>>;
+
+/// Decodes the value of `key` as JSON if `key` is a `K` in the given subspace. Used by debugging
+/// tools which don't know the type of a key ahead of time.
+pub fn decode_formal_key<K>(
+	subspace: &Subspace,
+	key: &[u8],
+	value: &[u8],
+) -> Option<Result<serde_json::Value>>
+where
+	K: FormalKey + TuplePack + for<'de> TupleUnpack<'de>,
+	K::Value: Serialize,
+{
+	let formal_key = unpack_exact::<K>(subspace, key)?;
+
+	Some(
+		formal_key
+			.deserialize(value)
+			.and_then(|value| Ok(serde_json::to_value(value)?)),
+	)
+}
+
+/// Combines the chunks of `key` and decodes them as JSON if `key` is a `K` in the given subspace.
+pub fn decode_formal_chunked_key<K>(
+	subspace: &Subspace,
+	key: &[u8],
+	chunks: &[Value],
+) -> Option<Result<serde_json::Value>>
+where
+	K: FormalChunkedKey + TuplePack + for<'de> TupleUnpack<'de>,
+	K::Value: Serialize,
+{
+	let formal_key = unpack_exact::<K>(subspace, key)?;
+
+	Some(
+		formal_key
+			.combine(chunks.to_vec())
+			.and_then(|value| Ok(serde_json::to_value(value)?)),
+	)
+}
+
+/// Most keys skip their constant segments when unpacking, so the key is packed again to make sure
+/// it is actually a `K`.
+fn unpack_exact<K>(subspace: &Subspace, key: &[u8]) -> Option<K>
+where
+	K: TuplePack + for<'de> TupleUnpack<'de>,
+{
+	let formal_key = subspace.unpack::<K>(key).ok()?;
+
+	(subspace.pack(&formal_key) == key).then_some(formal_key)
+}
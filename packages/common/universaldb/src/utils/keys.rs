@@ -125,4 +125,16 @@ define_keys! {
 	(97, ACL, "acl"),
 	(98, TOKEN, "token"),
 	(99, SECRET, "secret"),
+	(100, ACTOR_KV_EXPIRY, "actor_kv_expiry"),
+	(101, KV_STORAGE_SIZE, "kv_storage_size"),
+	(102, ACTOR_KV_CHANGE, "actor_kv_change"),
+	(103, COMPLETE_TS, "complete_ts"),
+	(104, RETENTION, "retention"),
+	(105, CANCEL_TS, "cancel_ts"),
+	(106, SCHEDULE, "schedule"),
+	(107, NEXT_TS, "next_ts"),
+	(108, BY_NEXT_TS, "by_next_ts"),
+	(109, PRIORITY, "priority"),
+	(110, KV_CHANGE_SEQ, "kv_change_seq"),
+	(111, RETENTION_BACKFILLED, "retention_backfilled"),
 }
@@ -115,6 +115,39 @@ pub fn substitute_versionstamp(
 	Ok(())
 }
 
+/// Completes a key packed with `pack_with_versionstamp` for `MutationType::SetVersionstampedKey`.
+///
+/// Like FoundationDB, only the first 10 bytes of the versionstamp are substituted and the user
+/// version packed in the key is kept.
+pub fn substitute_versionstamped_key(
+	mut key: Vec<u8>,
+	versionstamp: &Versionstamp,
+) -> Result<Vec<u8>, String> {
+	if key.len() < 4 {
+		return Err("Key too short to contain versionstamp offset".to_string());
+	}
+
+	let offset_bytes = key.split_off(key.len() - 4);
+	let offset = u32::from_le_bytes([
+		offset_bytes[0],
+		offset_bytes[1],
+		offset_bytes[2],
+		offset_bytes[3],
+	]) as usize;
+
+	if offset + 10 > key.len() {
+		return Err(format!(
+			"Invalid versionstamp offset: {} exceeds key length {}",
+			offset,
+			key.len()
+		));
+	}
+
+	key[offset..offset + 10].copy_from_slice(&versionstamp.as_bytes()[..10]);
+
+	Ok(key)
+}
+
 pub fn pack_and_substitute_versionstamp<T: TuplePack>(
 	value: &T,
 	user_version: u16,
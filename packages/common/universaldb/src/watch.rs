@@ -0,0 +1,175 @@
+use std::{
+	collections::BTreeMap,
+	ops::Bound,
+	sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use tokio::sync::oneshot;
+
+use crate::{
+	driver::BoxFut,
+	error::DatabaseError,
+	tx_ops::{Operation, TransactionOperations},
+};
+
+const DEFAULT_MAX_WATCHES: usize = 10_000;
+const MAX_WATCHES_LIMIT: usize = 1_000_000;
+
+/// A watch created in a transaction that has not committed yet. Dropping it (for example when the
+/// transaction is reset or dropped) cancels the watch.
+pub(crate) struct PendingWatch {
+	pub key: Vec<u8>,
+	sender: oneshot::Sender<Result<()>>,
+}
+
+impl PendingWatch {
+	/// Returns the pending watch and the future that resolves once it fires.
+	pub fn new(key: &[u8]) -> (Self, BoxFut<'static, Result<()>>) {
+		let (sender, receiver) = oneshot::channel();
+
+		(
+			PendingWatch {
+				key: key.to_vec(),
+				sender,
+			},
+			Box::pin(async move {
+				match receiver.await {
+					Ok(res) => res,
+					Err(_) => Err(DatabaseError::WatchCancelled.into()),
+				}
+			}),
+		)
+	}
+}
+
+/// Outstanding watches of committed transactions, shared by all transactions of a database driver.
+///
+/// Watches fire when a committed transaction writes to their key. Writes that do not change the
+/// value still fire the watch, so watchers should re-read the key after it fires.
+#[derive(Clone)]
+pub(crate) struct WatchRegistry {
+	inner: Arc<Mutex<WatchRegistryInner>>,
+}
+
+struct WatchRegistryInner {
+	watches: BTreeMap<Vec<u8>, Vec<oneshot::Sender<Result<()>>>>,
+	len: usize,
+	max_watches: usize,
+}
+
+impl WatchRegistryInner {
+	/// Removes watches whose future was dropped.
+	fn prune(&mut self) {
+		self.watches.retain(|_, senders| {
+			senders.retain(|sender| !sender.is_closed());
+			!senders.is_empty()
+		});
+		self.len = self.watches.values().map(Vec::len).sum();
+	}
+}
+
+impl WatchRegistry {
+	pub fn new() -> Self {
+		WatchRegistry {
+			inner: Arc::new(Mutex::new(WatchRegistryInner {
+				watches: BTreeMap::new(),
+				len: 0,
+				max_watches: DEFAULT_MAX_WATCHES,
+			})),
+		}
+	}
+
+	pub fn set_max_watches(&self, max_watches: i32) -> Result<()> {
+		let max_watches = usize::try_from(max_watches)?;
+		anyhow::ensure!(
+			max_watches <= MAX_WATCHES_LIMIT,
+			"max watches cannot be larger than {MAX_WATCHES_LIMIT}"
+		);
+
+		self.inner.lock().unwrap().max_watches = max_watches;
+
+		Ok(())
+	}
+
+	/// Registers the watch of a committed transaction. The watch fails with
+	/// `DatabaseError::TooManyWatches` if the limit of outstanding watches is reached.
+	pub fn register(&self, watch: PendingWatch) {
+		let mut inner = self.inner.lock().unwrap();
+
+		if inner.len >= inner.max_watches {
+			inner.prune();
+		}
+		if inner.len >= inner.max_watches {
+			let _ = watch.sender.send(Err(DatabaseError::TooManyWatches.into()));
+			return;
+		}
+
+		inner
+			.watches
+			.entry(watch.key)
+			.or_default()
+			.push(watch.sender);
+		inner.len += 1;
+	}
+
+	pub fn fire_key(&self, key: &[u8]) {
+		self.fire_range(key, &[key, &[0]].concat());
+	}
+
+	/// Fires all watches with keys in `[begin, end)`.
+	pub fn fire_range(&self, begin: &[u8], end: &[u8]) {
+		if begin >= end {
+			return;
+		}
+
+		let mut inner = self.inner.lock().unwrap();
+
+		let keys = inner
+			.watches
+			.range::<[u8], _>((Bound::Included(begin), Bound::Excluded(end)))
+			.map(|(key, _)| key.clone())
+			.collect::<Vec<_>>();
+
+		for key in keys {
+			if let Some(senders) = inner.watches.remove(&key) {
+				inner.len -= senders.len();
+
+				for sender in senders {
+					let _ = sender.send(Ok(()));
+				}
+			}
+		}
+	}
+
+	/// Fires every outstanding watch. Used when changes may have been missed.
+	pub fn fire_all(&self) {
+		let mut inner = self.inner.lock().unwrap();
+
+		inner.len = 0;
+		for sender in std::mem::take(&mut inner.watches).into_values().flatten() {
+			let _ = sender.send(Ok(()));
+		}
+	}
+
+	/// Fires all watches on the given written ranges.
+	pub fn fire_ranges(&self, ranges: &[(Vec<u8>, Vec<u8>)]) {
+		for (begin, end) in ranges {
+			self.fire_range(begin, end);
+		}
+	}
+}
+
+/// Returns the `[begin, end)` ranges written by the given operations.
+pub(crate) fn written_ranges(operations: &TransactionOperations) -> Vec<(Vec<u8>, Vec<u8>)> {
+	operations
+		.operations()
+		.iter()
+		.map(|op| match op {
+			Operation::Set { key, .. }
+			| Operation::Clear { key }
+			| Operation::AtomicOp { key, .. } => (key.clone(), [key.as_slice(), &[0]].concat()),
+			Operation::ClearRange { begin, end } => (begin.clone(), end.clone()),
+		})
+		.collect()
+}
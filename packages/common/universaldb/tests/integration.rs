@@ -4,7 +4,7 @@ use std::{borrow::Cow, sync::Arc};
 use universaldb::{
 	Database,
 	key_selector::KeySelector,
-	options::{ConflictRangeType, StreamingMode},
+	options::{ConflictRangeType, StreamingMode, TransactionOption},
 	range_option::RangeOption,
 	tuple::{Element, Subspace, Versionstamp, pack_with_versionstamp},
 	utils::IsolationLevel::*,
@@ -34,12 +34,14 @@ async fn test_postgres_driver() {
 	// Get the connection string from the secret
 	let connection_string = postgres_config.url.read().clone();
 
-	let driver = universaldb::driver::PostgresDatabaseDriver::new(connection_string)
+	let driver = universaldb::driver::PostgresDatabaseDriver::new(connection_string.clone())
 		.await
 		.unwrap();
 	let db = Database::new(Arc::new(driver));
 
-	run_all_tests(db).await;
+	run_all_tests(db.clone()).await;
+
+	test_postgres_watch_notifications(&db, connection_string).await;
 }
 
 #[tokio::test]
@@ -53,12 +55,14 @@ async fn test_rocksdb_driver() {
 		unreachable!()
 	};
 
-	let driver = universaldb::driver::RocksDbDatabaseDriver::new(fs_config.path)
+	let driver = universaldb::driver::RocksDbDatabaseDriver::new(fs_config.path.clone())
 		.await
 		.unwrap();
 	let db = Database::new(Arc::new(driver));
 
-	run_all_tests(db).await;
+	run_all_tests(db.clone()).await;
+
+	test_rocksdb_secondary(&db, &fs_config.path).await;
 }
 
 async fn run_all_tests(db: universaldb::Database) {
@@ -119,9 +123,237 @@ async fn run_all_tests(db: universaldb::Database) {
 	// test_versionstamps(&db).await;
 	// clear_test_namespace(&db).await.unwrap();
 
+	// Test watches
+	test_watches(&db).await;
+	clear_test_namespace(&db).await.unwrap();
+
+	// Test transaction limits
+	test_transaction_limits(&db).await;
+	clear_test_namespace(&db).await.unwrap();
+
 	// Test database options
 	test_database_options(&db).await;
 	clear_test_namespace(&db).await.unwrap();
+
+	// Test read-only handles
+	test_read_only(&db).await;
+	clear_test_namespace(&db).await.unwrap();
+}
+
+async fn test_watches(db: &Database) {
+	use std::time::Duration;
+	use universaldb::error::DatabaseError;
+
+	let test_subspace = Subspace::from("test");
+	let key = test_subspace.pack(&("watch_key",));
+	let other_key = test_subspace.pack(&("watch_other",));
+
+	db.run(|tx| {
+		let key = key.clone();
+		async move {
+			tx.set(&key, b"initial");
+			Ok(())
+		}
+	})
+	.await
+	.unwrap();
+
+	// Watch fires when another transaction writes the key
+	let watch = db
+		.run(|tx| {
+			let key = key.clone();
+			async move { Ok(tx.informal().watch(&key)) }
+		})
+		.await
+		.unwrap();
+	tokio::pin!(watch);
+
+	// Writing another key does not fire the watch
+	db.run(|tx| {
+		let other_key = other_key.clone();
+		async move {
+			tx.set(&other_key, b"value");
+			Ok(())
+		}
+	})
+	.await
+	.unwrap();
+	assert!(
+		tokio::time::timeout(Duration::from_millis(200), &mut watch)
+			.await
+			.is_err(),
+		"watch should not fire before the key changes"
+	);
+
+	db.run(|tx| {
+		let key = key.clone();
+		async move {
+			tx.set(&key, b"changed");
+			Ok(())
+		}
+	})
+	.await
+	.unwrap();
+	tokio::time::timeout(Duration::from_secs(5), &mut watch)
+		.await
+		.expect("watch should fire after the key changes")
+		.unwrap();
+
+	// Writes of the watching transaction itself do not fire the watch, clearing a range does
+	let watch = db
+		.run(|tx| {
+			let key = key.clone();
+			async move {
+				tx.set(&key, b"own write");
+				Ok(tx.informal().watch(&key))
+			}
+		})
+		.await
+		.unwrap();
+	tokio::pin!(watch);
+	assert!(
+		tokio::time::timeout(Duration::from_millis(200), &mut watch)
+			.await
+			.is_err(),
+		"watch should not fire on its own transaction's writes"
+	);
+
+	db.run(|tx| async move {
+		let (begin, end) = Subspace::from("test").range();
+		tx.clear_range(&begin, &end);
+		Ok(())
+	})
+	.await
+	.unwrap();
+	tokio::time::timeout(Duration::from_secs(5), &mut watch)
+		.await
+		.expect("watch should fire after the key is cleared")
+		.unwrap();
+
+	// Watches of transactions that never commit are cancelled
+	let tx = db.create_trx().unwrap();
+	let watch = tx.informal().watch(&key);
+	drop(tx);
+	let err = tokio::time::timeout(Duration::from_secs(5), watch)
+		.await
+		.expect("watch should be cancelled when its transaction is dropped")
+		.unwrap_err();
+	assert!(matches!(
+		err.downcast_ref::<DatabaseError>(),
+		Some(DatabaseError::WatchCancelled)
+	));
+}
+
+/// Watches fire on writes from another driver only once it enables watch notifications.
+async fn test_postgres_watch_notifications(db: &Database, connection_string: String) {
+	use std::time::Duration;
+	use universaldb::options::DatabaseOption;
+
+	let other_driver = universaldb::driver::PostgresDatabaseDriver::new(connection_string)
+		.await
+		.unwrap();
+	let other_db = Database::new(Arc::new(other_driver));
+
+	let key = Subspace::from("test").pack(&("notify_key",));
+	let watch_key = |db: &Database| {
+		let key = key.clone();
+		let db = db.clone();
+		async move {
+			db.run(|tx| {
+				let key = key.clone();
+				async move { Ok(tx.informal().watch(&key)) }
+			})
+			.await
+			.unwrap()
+		}
+	};
+	let write_key = |db: &Database, value: &'static [u8]| {
+		let key = key.clone();
+		let db = db.clone();
+		async move {
+			db.run(|tx| {
+				let key = key.clone();
+				async move {
+					tx.set(&key, value);
+					Ok(())
+				}
+			})
+			.await
+			.unwrap()
+		}
+	};
+
+	// Disabled by default
+	let watch = watch_key(db).await;
+	tokio::pin!(watch);
+	write_key(&other_db, b"first").await;
+	assert!(
+		tokio::time::timeout(Duration::from_millis(500), &mut watch)
+			.await
+			.is_err(),
+		"watch should not fire without watch notifications"
+	);
+
+	other_db
+		.set_option(DatabaseOption::WatchNotifications(true))
+		.unwrap();
+	write_key(&other_db, b"second").await;
+	tokio::time::timeout(Duration::from_secs(5), &mut watch)
+		.await
+		.expect("watch should fire on a notified write")
+		.unwrap();
+
+	clear_test_namespace(db).await.unwrap();
+}
+
+async fn test_transaction_limits(db: &Database) {
+	use std::time::Duration;
+	use universaldb::error::DatabaseError;
+
+	// Writes over the size limit fail the commit
+	let err = db
+		.run(|tx| async move {
+			tx.set_option(TransactionOption::SizeLimit(100))?;
+			tx.set(&Subspace::from("test").pack(&("size_limit",)), &[0; 200]);
+			Ok(())
+		})
+		.await
+		.unwrap_err();
+	assert!(matches!(
+		err.downcast_ref::<DatabaseError>(),
+		Some(DatabaseError::TransactionTooLarge)
+	));
+
+	// Writes under the size limit commit
+	db.run(|tx| async move {
+		tx.set_option(TransactionOption::SizeLimit(100))?;
+		tx.set(&Subspace::from("test").pack(&("size_limit",)), &[0; 10]);
+		Ok(())
+	})
+	.await
+	.unwrap();
+
+	// Closures running past the timeout are aborted
+	let err = tokio::time::timeout(
+		Duration::from_secs(5),
+		db.run(|tx| async move {
+			tx.set_option(TransactionOption::Timeout(100))?;
+			tokio::time::sleep(Duration::from_secs(60)).await;
+			Ok(())
+		}),
+	)
+	.await
+	.expect("transaction should time out")
+	.unwrap_err();
+	assert!(matches!(
+		err.downcast_ref::<DatabaseError>(),
+		Some(DatabaseError::TransactionTimedOut)
+	));
+
+	// Invalid limits are rejected
+	let tx = db.create_trx().unwrap();
+	assert!(tx.set_option(TransactionOption::SizeLimit(1)).is_err());
+	assert!(tx.set_option(TransactionOption::Timeout(-1)).is_err());
 }
 
 async fn test_database_options(db: &Database) {
@@ -197,6 +429,134 @@ async fn test_database_options(db: &Database) {
 	// Reset to a reasonable retry limit
 	db.set_option(DatabaseOption::TransactionRetryLimit(100))
 		.unwrap();
+
+	// Test transaction limits
+	assert!(
+		db.set_option(DatabaseOption::TransactionSizeLimit(10))
+			.is_err()
+	);
+	db.set_option(DatabaseOption::TransactionMaxRetryDelay(100))
+		.unwrap();
+	db.set_option(DatabaseOption::TransactionTimeout(0))
+		.unwrap();
+}
+
+async fn test_read_only(db: &Database) {
+	use universaldb::error::DatabaseError;
+
+	let key = Subspace::from("test").pack(&("read_only",));
+	db.run(|tx| {
+		let key = key.clone();
+		async move {
+			tx.set(&key, b"value");
+			Ok(())
+		}
+	})
+	.await
+	.unwrap();
+
+	let read_only = db.read_only();
+
+	// Reads see the data of the underlying database
+	let value = read_only
+		.run(|tx| {
+			let key = key.clone();
+			async move { Ok(tx.get(&key, Serializable).await?) }
+		})
+		.await
+		.unwrap();
+	assert_eq!(value, Some(b"value".to_vec().into()));
+
+	// Writes fail the transaction without being applied
+	let err = read_only
+		.run(|tx| {
+			let key = key.clone();
+			async move {
+				tx.set(&key, b"other");
+				Ok(())
+			}
+		})
+		.await
+		.unwrap_err();
+	assert!(matches!(
+		err.downcast_ref::<DatabaseError>(),
+		Some(DatabaseError::ReadOnly)
+	));
+
+	let err = read_only
+		.run(|tx| {
+			let key = key.clone();
+			async move {
+				tx.add_conflict_range(
+					&key,
+					&[key.as_slice(), &[0]].concat(),
+					ConflictRangeType::Write,
+				)
+			}
+		})
+		.await
+		.unwrap_err();
+	assert!(matches!(
+		err.downcast_ref::<DatabaseError>(),
+		Some(DatabaseError::ReadOnly)
+	));
+
+	let value = db
+		.run(|tx| {
+			let key = key.clone();
+			async move { Ok(tx.get(&key, Serializable).await?) }
+		})
+		.await
+		.unwrap();
+	assert_eq!(value, Some(b"value".to_vec().into()));
+}
+
+async fn test_rocksdb_secondary(db: &Database, primary_path: &std::path::Path) {
+	use universaldb::error::DatabaseError;
+
+	let driver = universaldb::driver::RocksDbDatabaseDriver::new_secondary(primary_path)
+		.await
+		.unwrap();
+	let secondary = Database::new(Arc::new(driver));
+
+	// Writes to the primary after opening the secondary are visible to it
+	let key = Subspace::from("test").pack(&("secondary",));
+	db.run(|tx| {
+		let key = key.clone();
+		async move {
+			tx.set(&key, b"value");
+			Ok(())
+		}
+	})
+	.await
+	.unwrap();
+
+	let value = secondary
+		.run(|tx| {
+			let key = key.clone();
+			async move { Ok(tx.get(&key, Serializable).await?) }
+		})
+		.await
+		.unwrap();
+	assert_eq!(value, Some(b"value".to_vec().into()));
+
+	// The secondary rejects writes even without the read-only wrapper
+	let err = secondary
+		.run(|tx| {
+			let key = key.clone();
+			async move {
+				tx.set(&key, b"other");
+				Ok(())
+			}
+		})
+		.await
+		.unwrap_err();
+	assert!(matches!(
+		err.downcast_ref::<DatabaseError>(),
+		Some(DatabaseError::ReadOnly)
+	));
+
+	clear_test_namespace(db).await.unwrap();
 }
 
 async fn clear_test_namespace(db: &Database) -> Result<()> {
@@ -433,6 +793,9 @@ async fn test_conflict_ranges(db: &Database) {
 
 		let (begin, end) = test_subspace.range();
 		tx.add_conflict_range(&begin, &end, ConflictRangeType::Read)?;
+		tx.set_option(TransactionOption::DebugTransactionIdentifier(
+			"test_conflict_ranges".to_string(),
+		))?;
 		Ok(())
 	})
 	.await
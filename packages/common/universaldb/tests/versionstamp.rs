@@ -120,6 +120,40 @@ fn test_pack_and_substitute_versionstamp() {
 	}
 }
 
+#[test]
+fn test_substitute_versionstamped_key() {
+	let tuple = vec![
+		Element::String("mykey".into()),
+		Element::Versionstamp(Versionstamp::incomplete(7)),
+	];
+
+	let packed = pack_with_versionstamp(&tuple);
+	let versionstamp = generate_versionstamp(100);
+
+	let key = substitute_versionstamped_key(packed, &versionstamp).unwrap();
+
+	let unpacked: Vec<Element> = unpack(&key).unwrap();
+	match &unpacked[1] {
+		Element::Versionstamp(v) => {
+			assert!(v.is_complete());
+			assert_eq!(v.as_bytes()[..10], versionstamp.as_bytes()[..10]);
+			// The user version is kept from the key
+			assert_eq!(v.user_version(), 7);
+		}
+		_ => panic!("Expected versionstamp"),
+	}
+}
+
+#[test]
+fn test_substitute_versionstamped_key_invalid_offset() {
+	let mut packed = vec![1, 2, 3, 4, 5];
+	packed.extend_from_slice(&100u32.to_le_bytes());
+
+	let result = substitute_versionstamped_key(packed, &generate_versionstamp(0));
+	assert!(result.is_err());
+	assert!(result.unwrap_err().contains("Invalid versionstamp offset"));
+}
+
 #[test]
 fn test_versionstamp_incomplete_preserves_user_version() {
 	// Test that Versionstamp::incomplete(user_version) properly preserves the user version
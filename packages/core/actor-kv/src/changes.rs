@@ -0,0 +1,283 @@
+use std::result::Result::Ok;
+use std::time::Duration;
+
+use anyhow::*;
+use futures_util::TryStreamExt;
+use rivet_runner_protocol as rp;
+use rivet_util_id::Id;
+use universaldb::prelude::*;
+use universalpubsub::{NextOutput, PubSub, PublishOpts};
+
+use crate::{MAX_KEY_SIZE, entry::EntryChangeKey, key::KeyWrapper, utils};
+
+/// How long changes are kept in the change log.
+const CHANGE_RETENTION_MS: i64 = 60 * 60 * 1000;
+/// Maximum amount of change log entries read per call, including entries not matching the prefix.
+const MAX_CHANGES_SCANNED: usize = 10_000;
+const DEFAULT_CHANGES_LIMIT: usize = 1024;
+/// How often the change log is re-read while waiting, in case a notification was missed.
+const WATCH_POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// Maximum amount of change log entries read per transaction when trimming.
+const TRIM_BATCH_SIZE: usize = 256;
+
+/// Appends changes to the actor's change log.
+///
+/// Changes are numbered from a per-actor sequence counter that is read serializably, so concurrent
+/// writers to the same actor conflict and changes are numbered in the order their transactions
+/// commit. A reader can never hold a cursor past a change that has yet to commit.
+pub(crate) async fn record(
+	tx: &universaldb::Transaction,
+	actor_id: Id,
+	changes: Vec<(rp::KvChangeOp, rp::KvKey)>,
+) -> Result<()> {
+	if changes.is_empty() {
+		return Ok(());
+	}
+
+	let seq_key = pegboard::keys::actor::KvChangeSeqKey::new(actor_id);
+	let pb_tx = tx.with_subspace(pegboard::keys::subspace());
+	let change_tx = tx.with_subspace(pegboard::keys::actor_kv_change_subspace());
+	let now = utils::now();
+
+	let mut seq = pb_tx.read_opt(&seq_key, Serializable).await?.unwrap_or(0);
+
+	for (op, key) in changes {
+		seq += 1;
+		change_tx.write(&EntryChangeKey::new(actor_id, seq), (op, key, now))?;
+	}
+
+	pb_tx.write(&seq_key, seq)?;
+
+	Ok(())
+}
+
+/// Deletes changes older than the retention window from every actor's change log. Returns the
+/// amount of changes deleted.
+pub(crate) async fn trim(db: &universaldb::Database) -> Result<usize> {
+	let change_subspace = pegboard::keys::actor_kv_change_subspace();
+	let cutoff = utils::now() - CHANGE_RETENTION_MS;
+	let mut start = change_subspace.range().0;
+	let mut total_changes = 0;
+
+	loop {
+		let (changes, next_start) = db
+			.run(|tx| {
+				let change_subspace = change_subspace.clone();
+				let mut start = start.clone();
+
+				async move {
+					let tx = tx.with_subspace(change_subspace.clone());
+					let end = change_subspace.range().1;
+					let mut scanned = 0;
+					let mut changes = 0;
+
+					while scanned < TRIM_BATCH_SIZE {
+						let mut stream = tx.get_ranges_keyvalues(
+							universaldb::RangeOption {
+								mode: StreamingMode::Iterator,
+								limit: Some(TRIM_BATCH_SIZE - scanned),
+								..(start.clone(), end.clone()).into()
+							},
+							Snapshot,
+						);
+						let mut next_actor_start = None;
+
+						while let Some(entry) = stream.try_next().await? {
+							scanned += 1;
+
+							let change_key = tx.unpack::<EntryChangeKey>(entry.key())?;
+							let (_, _, ts) = change_key.deserialize(entry.value())?;
+
+							// Changes are in commit order, so the rest of this actor's change log is newer
+							if ts >= cutoff {
+								next_actor_start =
+									Some(change_subspace.subspace(&change_key.actor_id).range().1);
+								break;
+							}
+
+							tx.delete(&change_key);
+							changes += 1;
+							start = [entry.key(), &[0]].concat();
+						}
+
+						match next_actor_start {
+							Some(next_actor_start) => start = next_actor_start,
+							// Reached the end of the change logs
+							None if scanned < TRIM_BATCH_SIZE => return Ok((changes, None)),
+							None => {}
+						}
+					}
+
+					Ok((changes, Some(start)))
+				}
+			})
+			.await?;
+
+		total_changes += changes;
+
+		let Some(next_start) = next_start else {
+			break;
+		};
+		start = next_start;
+	}
+
+	Ok(total_changes)
+}
+
+/// Lists changes to keys starting with `prefix` committed after the `after` cursor, in commit order.
+/// An `after` of `None` starts from the latest change without returning any changes.
+///
+/// Returns the changes and a cursor to pass as `after` to resume from where the call ended.
+pub async fn list(
+	db: &universaldb::Database,
+	actor_id: Id,
+	prefix: rp::KvKey,
+	after: Option<Vec<u8>>,
+	limit: Option<usize>,
+) -> Result<(Vec<rp::KvChange>, Vec<u8>)> {
+	ensure!(
+		KeyWrapper::tuple_len(&prefix) <= MAX_KEY_SIZE,
+		"prefix key is too long (max 2048 bytes)"
+	);
+	let after = after.map(|after| decode_cursor(&after)).transpose()?;
+	let limit = limit.unwrap_or(DEFAULT_CHANGES_LIMIT);
+
+	db.run(|tx| {
+		let prefix = prefix.clone();
+
+		async move {
+			let change_subspace = pegboard::keys::actor_kv_change_subspace();
+			let tx = tx.with_subspace(change_subspace.clone());
+			let latest_seq = tx
+				.with_subspace(pegboard::keys::subspace())
+				.read_opt(
+					&pegboard::keys::actor::KvChangeSeqKey::new(actor_id),
+					Snapshot,
+				)
+				.await?
+				.unwrap_or(0);
+
+			let Some(after) = after else {
+				return Ok(Some((Vec::new(), encode_cursor(latest_seq))));
+			};
+
+			let actor_range = change_subspace.subspace(&actor_id).range();
+
+			// Changes older than the retention window are trimmed by the sweeper. A cursor is only still
+			// valid if no change after it has been trimmed yet.
+			let first_entry = tx
+				.get_ranges_keyvalues(
+					universaldb::RangeOption {
+						mode: StreamingMode::Exact,
+						limit: Some(1),
+						..actor_range.clone().into()
+					},
+					Snapshot,
+				)
+				.try_next()
+				.await?;
+			let first_seq = match first_entry {
+				Some(entry) => tx.unpack::<EntryChangeKey>(entry.key())?.seq,
+				None => latest_seq + 1,
+			};
+			if after.saturating_add(1) < first_seq {
+				return Ok(None);
+			}
+
+			let after_key = change_subspace.pack(&EntryChangeKey::new(actor_id, after));
+			let mut stream = tx.get_ranges_keyvalues(
+				universaldb::RangeOption {
+					mode: StreamingMode::Iterator,
+					limit: Some(MAX_CHANGES_SCANNED),
+					..([after_key.as_slice(), &[0]].concat(), actor_range.1).into()
+				},
+				Snapshot,
+			);
+
+			let mut changes = Vec::new();
+			let mut cursor = after;
+
+			while let Some(entry) = stream.try_next().await? {
+				let change_key = tx.unpack::<EntryChangeKey>(entry.key())?;
+				let (op, key, _) = change_key.deserialize(entry.value())?;
+
+				cursor = change_key.seq;
+
+				// Drops apply to every key
+				if matches!(op, rp::KvChangeOp::Drop) || key.starts_with(&prefix) {
+					changes.push(rp::KvChange {
+						versionstamp: encode_cursor(cursor),
+						op,
+						key,
+					});
+
+					if changes.len() >= limit {
+						break;
+					}
+				}
+			}
+
+			Ok(Some((changes, encode_cursor(cursor))))
+		}
+	})
+	.await?
+	.context("change feed cursor has expired, list all keys again and resume from a new cursor")
+}
+
+/// Same as `list`, but waits up to `wait` for a matching change if there are none yet.
+pub async fn watch(
+	db: &universaldb::Database,
+	ups: &PubSub,
+	actor_id: Id,
+	prefix: rp::KvKey,
+	mut after: Option<Vec<u8>>,
+	limit: Option<usize>,
+	wait: Duration,
+) -> Result<(Vec<rp::KvChange>, Vec<u8>)> {
+	// Subscribe before reading so no notifications are missed in between
+	let mut sub = ups
+		.subscribe(&pegboard::pubsub_subjects::ActorKvChangesSubject::new(actor_id).to_string())
+		.await?;
+	let deadline = tokio::time::Instant::now() + wait;
+
+	loop {
+		let (changes, cursor) = list(db, actor_id, prefix.clone(), after, limit).await?;
+
+		let now = tokio::time::Instant::now();
+		if !changes.is_empty() || now >= deadline {
+			return Ok((changes, cursor));
+		}
+
+		// Skips over changes that did not match the prefix
+		after = Some(cursor);
+
+		// Times out on the poll interval or deadline, after which the change log is read again
+		if let Ok(res) =
+			tokio::time::timeout((deadline - now).min(WATCH_POLL_INTERVAL), sub.next()).await
+			&& let NextOutput::Unsubscribed = res?
+		{
+			bail!("change feed subscription closed");
+		}
+	}
+}
+
+/// Wakes up any pending `watch` calls for the actor. Should be called after a transaction that
+/// recorded changes commits.
+pub async fn notify(ups: &PubSub, actor_id: Id) -> Result<()> {
+	ups.publish(
+		&pegboard::pubsub_subjects::ActorKvChangesSubject::new(actor_id).to_string(),
+		&[],
+		PublishOpts::broadcast(),
+	)
+	.await
+}
+
+fn encode_cursor(seq: u64) -> Vec<u8> {
+	seq.to_be_bytes().to_vec()
+}
+
+fn decode_cursor(cursor: &[u8]) -> Result<u64> {
+	<[u8; 8]>::try_from(cursor)
+		.map(u64::from_be_bytes)
+		.map_err(|_| anyhow!("invalid change feed cursor"))
+}
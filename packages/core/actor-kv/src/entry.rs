@@ -1,6 +1,8 @@
 use std::result::Result::Ok;
 
 use anyhow::*;
+use rivet_util_id::Id;
+use serde::Deserialize;
 use universaldb::prelude::*;
 
 use rivet_runner_protocol as rp;
@@ -114,7 +116,16 @@ impl FormalKey for EntryMetadataKey {
 	type Value = rp::KvMetadata;
 
 	fn deserialize(&self, raw: &[u8]) -> Result<Self::Value> {
-		serde_bare::from_slice(raw).map_err(Into::into)
+		serde_bare::from_slice(raw).or_else(|err| {
+			// Fall back to the format written before `expire_ts` was added
+			let legacy = serde_bare::from_slice::<LegacyKvMetadata>(raw).map_err(|_| err)?;
+
+			Ok(rp::KvMetadata {
+				version: legacy.version,
+				create_ts: legacy.create_ts,
+				expire_ts: None,
+			})
+		})
 	}
 
 	fn serialize(&self, value: Self::Value) -> Result<Vec<u8>> {
@@ -145,3 +156,100 @@ impl<'de> TupleUnpack<'de> for EntryMetadataKey {
 		Ok((input, v))
 	}
 }
+
+#[derive(Deserialize)]
+struct LegacyKvMetadata {
+	version: Vec<u8>,
+	create_ts: i64,
+}
+
+/// Index of entries by expiry timestamp. Lives outside of the actor's subspace so the sweeper can scan
+/// all actors at once.
+pub struct EntryExpiryKey {
+	pub expire_ts: i64,
+	pub actor_id: Id,
+	pub key: KeyWrapper,
+}
+
+impl EntryExpiryKey {
+	pub fn new(expire_ts: i64, actor_id: Id, key: KeyWrapper) -> Self {
+		EntryExpiryKey {
+			expire_ts,
+			actor_id,
+			key,
+		}
+	}
+}
+
+impl TuplePack for EntryExpiryKey {
+	fn pack<W: std::io::Write>(
+		&self,
+		w: &mut W,
+		tuple_depth: TupleDepth,
+	) -> std::io::Result<VersionstampOffset> {
+		let t = (self.expire_ts, self.actor_id, &self.key);
+		t.pack(w, tuple_depth)
+	}
+}
+
+impl<'de> TupleUnpack<'de> for EntryExpiryKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, (expire_ts, actor_id, key)) =
+			<(i64, Id, KeyWrapper)>::unpack(input, tuple_depth)?;
+
+		let v = EntryExpiryKey {
+			expire_ts,
+			actor_id,
+			key,
+		};
+
+		Ok((input, v))
+	}
+}
+
+/// Change log entry, ordered by sequence number within the actor. Lives outside of the actor's subspace
+/// so it is not counted towards the storage size and survives `delete_all`.
+pub struct EntryChangeKey {
+	pub actor_id: Id,
+	pub seq: u64,
+}
+
+impl EntryChangeKey {
+	pub fn new(actor_id: Id, seq: u64) -> Self {
+		EntryChangeKey { actor_id, seq }
+	}
+}
+
+impl FormalKey for EntryChangeKey {
+	/// Operation, key, and timestamp the change was recorded at.
+	type Value = (rp::KvChangeOp, rp::KvKey, i64);
+
+	fn deserialize(&self, raw: &[u8]) -> Result<Self::Value> {
+		serde_bare::from_slice(raw).map_err(Into::into)
+	}
+
+	fn serialize(&self, value: Self::Value) -> Result<Vec<u8>> {
+		serde_bare::to_vec(&value).map_err(Into::into)
+	}
+}
+
+impl TuplePack for EntryChangeKey {
+	fn pack<W: std::io::Write>(
+		&self,
+		w: &mut W,
+		tuple_depth: TupleDepth,
+	) -> std::io::Result<VersionstampOffset> {
+		let t = (self.actor_id, self.seq);
+		t.pack(w, tuple_depth)
+	}
+}
+
+impl<'de> TupleUnpack<'de> for EntryChangeKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, (actor_id, seq)) = <(Id, u64)>::unpack(input, tuple_depth)?;
+
+		let v = EntryChangeKey { actor_id, seq };
+
+		Ok((input, v))
+	}
+}
@@ -1,17 +1,24 @@
+use std::collections::HashMap;
 use std::result::Result::{Err, Ok};
 
 use anyhow::*;
-use entry::{EntryBaseKey, EntryBuilder, EntryMetadataKey, EntryValueChunkKey};
+use entry::{EntryBaseKey, EntryBuilder, EntryExpiryKey, EntryMetadataKey, EntryValueChunkKey};
 use futures_util::{StreamExt, TryStreamExt};
 use key::{KeyWrapper, ListKeyWrapper};
 use rivet_runner_protocol as rp;
 use rivet_util_id::Id;
+use sha2::{Digest, Sha256};
+use universaldb::options::MutationType;
 use universaldb::prelude::*;
 use universaldb::tuple::Subspace;
-use utils::{validate_entries, validate_keys};
+use utils::{validate_atomic_entries, validate_conditions, validate_entries, validate_keys};
 
+pub mod changes;
 mod entry;
 mod key;
+mod metrics;
+pub mod snapshot;
+pub mod sweeper;
 mod utils;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -19,22 +26,115 @@ const MAX_KEY_SIZE: usize = 2 * 1024;
 const MAX_VALUE_SIZE: usize = 128 * 1024;
 const MAX_KEYS: usize = 128;
 const MAX_PUT_PAYLOAD_SIZE: usize = 976 * 1024;
-const MAX_STORAGE_SIZE: usize = 1024 * 1024 * 1024; // 1 GiB
+const MAX_ATOMIC_PARAM_SIZE: usize = 8;
 const VALUE_CHUNK_SIZE: usize = 10_000; // 10 KB, not KiB, see https://apple.github.io/foundationdb/blob.html
+const STORAGE_SIZE_SCAN_PAGE_SIZE: usize = 1024;
+/// Bumped when the layout of list cursors changes, older cursors are rejected.
+const LIST_CURSOR_VERSION: u8 = 1;
+/// Length of the query hash in list cursors.
+const LIST_CURSOR_HASH_LEN: usize = 8;
 
 fn subspace(actor_id: Id) -> universaldb::utils::Subspace {
 	pegboard::keys::actor_kv_subspace().subspace(&actor_id)
 }
 
-/// Returns estimated size of the given subspace.
-pub async fn get_subspace_size(db: &universaldb::Database, subspace: &Subspace) -> Result<i64> {
-	let (start, end) = subspace.range();
+/// Returned from inside a transaction when a write can't be applied to the actor's storage counter.
+enum StorageSizeError {
+	/// The write does not fit in the actor's storage quota.
+	QuotaExceeded { remaining: i64, payload_size: i64 },
+	/// The actor has no storage counter yet. See `init_storage_size`.
+	Uninitialized,
+}
+
+impl StorageSizeError {
+	fn into_error(self) -> anyhow::Error {
+		match self {
+			StorageSizeError::QuotaExceeded {
+				remaining,
+				payload_size,
+			} => anyhow!(
+				"not enough space left in storage ({remaining} bytes remaining, current payload is {payload_size} bytes)",
+			),
+			StorageSizeError::Uninitialized => anyhow!("storage size not initialized, try again"),
+		}
+	}
+}
+
+/// Returns the amount of bytes the actor currently has stored. Keys and values count towards this,
+/// metadata does not.
+pub async fn get_storage_size(db: &universaldb::Database, actor_id: Id) -> Result<u64> {
+	let storage_size = init_storage_size(db, actor_id).await?;
+
+	Ok(storage_size.max(0) as u64)
+}
+
+/// Returns the actor's storage counter, initializing it first if the actor wrote data before the
+/// counter existed. The entries are summed page by page in separate transactions so actors with a
+/// lot of data don't hit the transaction time limit.
+async fn init_storage_size(db: &universaldb::Database, actor_id: Id) -> Result<i64> {
+	let storage_size = db
+		.run(|tx| async move {
+			let storage_size = tx
+				.with_subspace(pegboard::keys::subspace())
+				.read_opt(
+					&pegboard::keys::actor::KvStorageSizeKey::new(actor_id),
+					Snapshot,
+				)
+				.await?;
+
+			Ok(storage_size)
+		})
+		.await?;
+	if let Some(storage_size) = storage_size {
+		return Ok(storage_size);
+	}
+
+	let subspace = subspace(actor_id);
+	let (mut start, end) = subspace.range();
+	let mut total = 0;
+	let mut current_key: Option<rp::KvKey> = None;
+
+	loop {
+		let (page_total, page_current_key, last_key) = db
+			.run(|tx| {
+				let subspace = subspace.clone();
+				let start = start.clone();
+				let end = end.clone();
+				let current_key = current_key.clone();
+
+				async move { scan_storage_size_page(&tx, &subspace, start, end, current_key).await }
+			})
+			.await?;
+
+		total += page_total;
+		current_key = page_current_key;
 
-	// This txn does not have to be committed because we are not modifying any data
-	let tx = db.create_trx()?;
-	tx.get_estimated_range_size_bytes(&start, &end)
-		.await
-		.map_err(Into::into)
+		let Some(mut last_key) = last_key else {
+			break;
+		};
+
+		// Continue right after the last key read
+		last_key.push(0);
+		start = last_key;
+	}
+
+	// Entries deleted behind the scan are still counted, which only makes the quota stricter until
+	// the keys are written again
+	db.run(|tx| async move {
+		let storage_size_key = pegboard::keys::actor::KvStorageSizeKey::new(actor_id);
+		let counter_tx = tx.with_subspace(pegboard::keys::subspace());
+
+		// Another initialization or write may have won the race
+		if let Some(storage_size) = counter_tx.read_opt(&storage_size_key, Serializable).await? {
+			return Ok(storage_size);
+		}
+
+		counter_tx.write(&storage_size_key, total)?;
+
+		Ok(total)
+	})
+	.await
+	.map_err(Into::into)
 }
 
 /// Gets keys from the KV store.
@@ -49,6 +149,7 @@ pub async fn get(
 		let keys = keys.clone();
 		async move {
 			let tx = tx.with_subspace(subspace(actor_id));
+			let now = utils::now();
 
 			let size_estimate = keys.len().min(1024);
 
@@ -86,9 +187,11 @@ pub async fn get(
 						let (key, value, meta) =
 							std::mem::replace(inner, EntryBuilder::new(key)).build()?;
 
-						keys.push(key);
-						values.push(value);
-						metadata.push(meta);
+						if !utils::is_expired(&meta, now) {
+							keys.push(key);
+							values.push(value);
+							metadata.push(meta);
+						}
 					}
 
 					inner
@@ -112,9 +215,11 @@ pub async fn get(
 			if let Some(inner) = current_entry {
 				let (key, value, meta) = inner.build()?;
 
-				keys.push(key);
-				values.push(value);
-				metadata.push(meta);
+				if !utils::is_expired(&meta, now) {
+					keys.push(key);
+					values.push(value);
+					metadata.push(meta);
+				}
 			}
 
 			Ok((keys, values, metadata))
@@ -125,18 +230,37 @@ pub async fn get(
 }
 
 /// Gets keys from the KV store.
+///
+/// Returns a cursor if the limit was reached before the end of the range. Passing it to a subsequent
+/// call with the same query and direction resumes listing after the last returned key. Cursors used
+/// with a different query or direction are rejected.
 pub async fn list(
 	db: &universaldb::Database,
 	actor_id: Id,
 	query: rp::KvListQuery,
 	reverse: bool,
 	limit: Option<usize>,
-) -> Result<(Vec<rp::KvKey>, Vec<rp::KvValue>, Vec<rp::KvMetadata>)> {
+	cursor: Option<Vec<u8>>,
+) -> Result<(
+	Vec<rp::KvKey>,
+	Vec<rp::KvValue>,
+	Vec<rp::KvMetadata>,
+	Option<Vec<u8>>,
+)> {
 	utils::validate_list_query(&query)?;
+	let query_hash = list_query_hash(&query)?;
+	let cursor_key = cursor
+		.map(|cursor| decode_list_cursor(&cursor, query_hash, reverse))
+		.transpose()?;
 
 	let limit = limit.unwrap_or(16384);
 	let subspace = subspace(actor_id);
-	let list_range = list_query_range(query, &subspace);
+	let list_range = apply_list_cursor(
+		list_query_range(query, &subspace),
+		cursor_key,
+		reverse,
+		&subspace,
+	);
 
 	db.run(|tx| {
 		let list_range = list_range.clone();
@@ -144,6 +268,7 @@ pub async fn list(
 
 		async move {
 			let tx = tx.with_subspace(subspace);
+			let now = utils::now();
 
 			let mut stream = tx.get_ranges_keyvalues(
 				universaldb::RangeOption {
@@ -158,6 +283,7 @@ pub async fn list(
 			let mut values = Vec::new();
 			let mut metadata = Vec::new();
 			let mut current_entry: Option<EntryBuilder> = None;
+			let mut limit_reached = false;
 
 			loop {
 				let Some(entry) = stream.try_next().await? else {
@@ -171,13 +297,16 @@ pub async fn list(
 						let (key, value, meta) =
 							std::mem::replace(inner, EntryBuilder::new(key)).build()?;
 
-						keys.push(key);
-						values.push(value);
-						metadata.push(meta);
+						if !utils::is_expired(&meta, now) {
+							keys.push(key);
+							values.push(value);
+							metadata.push(meta);
 
-						if keys.len() >= limit {
-							current_entry = None;
-							break;
+							if keys.len() >= limit {
+								current_entry = None;
+								limit_reached = true;
+								break;
+							}
 						}
 					}
 
@@ -202,12 +331,22 @@ pub async fn list(
 			if let Some(inner) = current_entry {
 				let (key, value, meta) = inner.build()?;
 
-				keys.push(key);
-				values.push(value);
-				metadata.push(meta);
+				if !utils::is_expired(&meta, now) {
+					keys.push(key);
+					values.push(value);
+					metadata.push(meta);
+				}
 			}
 
-			Ok((keys, values, metadata))
+			// The cursor holds the last returned key since listing stopped before the end of the range
+			let cursor = if limit_reached {
+				keys.last()
+					.map(|key| encode_list_cursor(query_hash, reverse, key))
+			} else {
+				None
+			};
+
+			Ok((keys, values, metadata, cursor))
 		}
 	})
 	.await
@@ -220,11 +359,23 @@ pub async fn put(
 	actor_id: Id,
 	keys: Vec<rp::KvKey>,
 	values: Vec<rp::KvValue>,
+	ttl: Option<u64>,
+	quota: u64,
 ) -> Result<()> {
 	let subspace = subspace(actor_id);
-	let total_size = get_subspace_size(&db, &subspace).await? as usize;
 
-	validate_entries(&keys, &values, total_size)?;
+	validate_entries(&keys, &values)?;
+
+	let expire_ts = ttl
+		.map(|ttl| {
+			i64::try_from(ttl)
+				.ok()
+				.and_then(|ttl| utils::now().checked_add(ttl))
+				.context("ttl too large")
+		})
+		.transpose()?;
+
+	init_storage_size(db, actor_id).await?;
 
 	db.run(|tx| {
 		// TODO: Costly clone
@@ -235,46 +386,349 @@ pub async fn put(
 		async move {
 			let tx = tx.with_subspace(subspace.clone());
 
-			futures_util::stream::iter(keys.into_iter().zip(values.into_iter()))
-				.map(|(key, value)| {
-					let tx = tx.clone();
-					let key = KeyWrapper(key.clone());
-					let subspace = subspace.clone();
-
-					async move {
-						// Clear previous key data before setting
-						tx.clear_subspace_range(&subspace.subspace(&key));
-
-						// Set metadata
-						tx.write(
-							&EntryMetadataKey::new(key.clone()),
-							rp::KvMetadata {
-								version: VERSION.as_bytes().to_vec(),
-								create_ts: utils::now(),
-							},
-						)?;
-
-						// Set key data in chunks
-						for start in (0..value.len()).step_by(VALUE_CHUNK_SIZE) {
-							let idx = start / VALUE_CHUNK_SIZE;
-							let end = (start + VALUE_CHUNK_SIZE).min(value.len());
-
-							tx.set(
-								&subspace.pack(&EntryValueChunkKey::new(key.clone(), idx)),
-								&value.get(start..end).context("bad slice")?,
-							);
-						}
+			let new_sizes = keys
+				.iter()
+				.zip(values.iter())
+				.map(|(key, value)| (key, entry_size(key, value.len())))
+				.collect();
+			let delta = storage_delta(&tx, &subspace, new_sizes).await?;
+			if let Err(err) = update_storage_size(&tx, actor_id, delta, Some(quota)).await? {
+				return Ok(Err(err));
+			}
+
+			changes::record(
+				&tx,
+				actor_id,
+				keys.iter()
+					.map(|key| (rp::KvChangeOp::Put, key.clone()))
+					.collect(),
+			)
+			.await?;
+
+			for (key, value) in keys.into_iter().zip(values.into_iter()) {
+				let key = KeyWrapper(key);
+
+				write_entry(&tx, &subspace, key.clone(), &value, expire_ts)?;
+
+				if let Some(expire_ts) = expire_ts {
+					tx.set(
+						&pegboard::keys::actor_kv_expiry_subspace()
+							.pack(&EntryExpiryKey::new(expire_ts, actor_id, key)),
+						&[],
+					);
+				}
+			}
+
+			Ok(Ok(()))
+		}
+	})
+	.await?
+	.map_err(StorageSizeError::into_error)
+}
 
-						Ok(())
+/// Checks the given conditions and, if all of them pass, deletes and then puts the given keys in a
+/// single transaction. Returns `false` without writing anything if any condition fails.
+pub async fn transact(
+	db: &universaldb::Database,
+	actor_id: Id,
+	conditions: Vec<rp::KvCondition>,
+	put_keys: Vec<rp::KvKey>,
+	put_values: Vec<rp::KvValue>,
+	delete_keys: Vec<rp::KvKey>,
+	quota: u64,
+) -> Result<bool> {
+	let subspace = subspace(actor_id);
+
+	validate_conditions(&conditions)?;
+	validate_entries(&put_keys, &put_values)?;
+	validate_keys(&delete_keys)?;
+
+	init_storage_size(db, actor_id).await?;
+
+	db.run(|tx| {
+		// TODO: Costly clone
+		let conditions = conditions.clone();
+		let put_keys = put_keys.clone();
+		let put_values = put_values.clone();
+		let delete_keys = delete_keys.clone();
+		let subspace = subspace.clone();
+
+		async move {
+			let tx = tx.with_subspace(subspace.clone());
+
+			for condition in conditions {
+				let passed = match condition {
+					rp::KvCondition::KvConditionExists(cond) => {
+						let entry = read_entry(&tx, &subspace, KeyWrapper(cond.key)).await?;
+
+						entry.is_some() == cond.exists
 					}
-				})
-				.buffer_unordered(32)
-				.try_collect()
-				.await
+					rp::KvCondition::KvConditionValue(cond) => {
+						let entry = read_entry(&tx, &subspace, KeyWrapper(cond.key)).await?;
+
+						entry.is_some_and(|(value, _)| value == cond.value)
+					}
+					rp::KvCondition::KvConditionCreateTs(cond) => {
+						let entry = read_entry(&tx, &subspace, KeyWrapper(cond.key)).await?;
+
+						entry.is_some_and(|(_, metadata)| metadata.create_ts == cond.create_ts)
+					}
+				};
+
+				if !passed {
+					return Ok(Ok(false));
+				}
+			}
+
+			// Deletes are applied before puts so a key present in both lists ends up written
+			let new_sizes = delete_keys
+				.iter()
+				.map(|key| (key, 0))
+				.chain(
+					put_keys
+						.iter()
+						.zip(put_values.iter())
+						.map(|(key, value)| (key, entry_size(key, value.len()))),
+				)
+				.collect();
+			let delta = storage_delta(&tx, &subspace, new_sizes).await?;
+			if let Err(err) = update_storage_size(&tx, actor_id, delta, Some(quota)).await? {
+				return Ok(Err(err));
+			}
+
+			changes::record(
+				&tx,
+				actor_id,
+				delete_keys
+					.iter()
+					.map(|key| (rp::KvChangeOp::Delete, key.clone()))
+					.chain(
+						put_keys
+							.iter()
+							.map(|key| (rp::KvChangeOp::Put, key.clone())),
+					)
+					.collect(),
+			)
+			.await?;
+
+			for key in delete_keys {
+				tx.clear_subspace_range(&subspace.subspace(&KeyWrapper(key)));
+			}
+
+			for (key, value) in put_keys.into_iter().zip(put_values.into_iter()) {
+				write_entry(&tx, &subspace, KeyWrapper(key), &value, None)?;
+			}
+
+			Ok(Ok(true))
 		}
 	})
-	.await
-	.map_err(Into::into)
+	.await?
+	.map_err(StorageSizeError::into_error)
+}
+
+/// Applies an atomic operation to each key. `Add`, `Max` and `Min` are applied as database
+/// mutations instead of rewriting the value. The size of the previous value is still read for
+/// storage accounting, so concurrent operations on the same key conflict.
+pub async fn atomic(
+	db: &universaldb::Database,
+	actor_id: Id,
+	op: rp::KvAtomicOpType,
+	keys: Vec<rp::KvKey>,
+	params: Vec<rp::KvValue>,
+	quota: u64,
+) -> Result<()> {
+	let subspace = subspace(actor_id);
+
+	validate_atomic_entries(&op, &keys, &params)?;
+
+	// `None` for append, which cannot be applied as a single mutation
+	let mutation_type = match op {
+		rp::KvAtomicOpType::Add => Some(MutationType::Add),
+		rp::KvAtomicOpType::Max => Some(MutationType::Max),
+		rp::KvAtomicOpType::Min => Some(MutationType::Min),
+		rp::KvAtomicOpType::AppendIfFits => None,
+	};
+
+	init_storage_size(db, actor_id).await?;
+
+	db.run(|tx| {
+		let keys = keys.clone();
+		let params = params.clone();
+		let subspace = subspace.clone();
+
+		async move {
+			let tx = tx.with_subspace(subspace.clone());
+
+			let Some(mutation_type) = mutation_type else {
+				// Values are split into chunks so appending rewrites the entire value instead of using
+				// `MutationType::AppendIfFits` on a single chunk. All new values are resolved before
+				// writing so the storage quota can be checked first
+				let mut new_values = HashMap::<&rp::KvKey, rp::KvValue>::new();
+
+				for (key, param) in keys.iter().zip(params.iter()) {
+					let current = match new_values.get(key) {
+						Some(value) => value.clone(),
+						None => read_entry(&tx, &subspace, KeyWrapper(key.clone()))
+							.await?
+							.map(|(value, _)| value)
+							.unwrap_or_default(),
+					};
+
+					if current.len() + param.len() <= MAX_VALUE_SIZE {
+						new_values.insert(key, [current, param.clone()].concat());
+					}
+				}
+
+				let new_sizes = new_values
+					.iter()
+					.map(|(key, value)| (*key, entry_size(key, value.len())))
+					.collect();
+				let delta = storage_delta(&tx, &subspace, new_sizes).await?;
+				if let Err(err) = update_storage_size(&tx, actor_id, delta, Some(quota)).await? {
+					return Ok(Err(err));
+				}
+
+				changes::record(
+					&tx,
+					actor_id,
+					new_values
+						.keys()
+						.map(|key| (rp::KvChangeOp::Put, (*key).clone()))
+						.collect(),
+				)
+				.await?;
+
+				for (key, value) in new_values {
+					write_entry(&tx, &subspace, KeyWrapper(key.clone()), &value, None)?;
+				}
+
+				return Ok(Ok(()));
+			};
+
+			// Numeric results always have the same length as the param
+			let new_sizes = keys
+				.iter()
+				.zip(params.iter())
+				.map(|(key, param)| (key, entry_size(key, param.len())))
+				.collect();
+			let delta = storage_delta(&tx, &subspace, new_sizes).await?;
+			if let Err(err) = update_storage_size(&tx, actor_id, delta, Some(quota)).await? {
+				return Ok(Err(err));
+			}
+
+			changes::record(
+				&tx,
+				actor_id,
+				keys.iter()
+					.map(|key| (rp::KvChangeOp::Put, key.clone()))
+					.collect(),
+			)
+			.await?;
+
+			for (key, param) in keys.into_iter().zip(params.into_iter()) {
+				let key = KeyWrapper(key);
+
+				// Numeric values always fit in the first chunk. Clear any trailing chunks left over from a
+				// previous, longer value
+				tx.clear_range(
+					&subspace.pack(&EntryValueChunkKey::new(key.clone(), 1)),
+					&subspace.subspace(&(&key, DATA)).range().1,
+				);
+
+				tx.write(
+					&EntryMetadataKey::new(key.clone()),
+					rp::KvMetadata {
+						version: VERSION.as_bytes().to_vec(),
+						create_ts: utils::now(),
+						expire_ts: None,
+					},
+				)?;
+
+				tx.informal().atomic_op(
+					&subspace.pack(&EntryValueChunkKey::new(key, 0)),
+					&param,
+					mutation_type,
+				);
+			}
+
+			Ok(Ok(()))
+		}
+	})
+	.await?
+	.map_err(StorageSizeError::into_error)
+}
+
+/// Sets the key to `value` if its current value equals `expected`. An `expected` of `None` requires
+/// the key to not exist and a `value` of `None` deletes the key.
+///
+/// Returns whether the swap happened and the value of the key afterwards.
+pub async fn compare_and_swap(
+	db: &universaldb::Database,
+	actor_id: Id,
+	key: rp::KvKey,
+	expected: Option<rp::KvValue>,
+	value: Option<rp::KvValue>,
+	quota: u64,
+) -> Result<(bool, Option<rp::KvValue>)> {
+	let subspace = subspace(actor_id);
+
+	validate_keys(std::slice::from_ref(&key))?;
+	if let Some(expected) = &expected {
+		ensure!(
+			expected.len() <= MAX_VALUE_SIZE,
+			"expected value is too large (max 128 KiB)"
+		);
+	}
+	if let Some(value) = &value {
+		validate_entries(std::slice::from_ref(&key), std::slice::from_ref(value))?;
+	}
+
+	init_storage_size(db, actor_id).await?;
+
+	db.run(|tx| {
+		let key = KeyWrapper(key.clone());
+		let expected = expected.clone();
+		let value = value.clone();
+		let subspace = subspace.clone();
+
+		async move {
+			let tx = tx.with_subspace(subspace.clone());
+
+			let current = read_entry(&tx, &subspace, key.clone())
+				.await?
+				.map(|(value, _)| value);
+
+			if current != expected {
+				return Ok(Ok((false, current)));
+			}
+
+			let new_size = value
+				.as_ref()
+				.map(|value| entry_size(&key.0, value.len()))
+				.unwrap_or_default();
+			let delta = storage_delta(&tx, &subspace, HashMap::from([(&key.0, new_size)])).await?;
+			if let Err(err) = update_storage_size(&tx, actor_id, delta, Some(quota)).await? {
+				return Ok(Err(err));
+			}
+
+			let op = if value.is_some() {
+				rp::KvChangeOp::Put
+			} else {
+				rp::KvChangeOp::Delete
+			};
+			changes::record(&tx, actor_id, vec![(op, key.0.clone())]).await?;
+
+			if let Some(value) = &value {
+				write_entry(&tx, &subspace, key, value, None)?;
+			} else {
+				tx.clear_subspace_range(&subspace.subspace(&key));
+			}
+
+			Ok(Ok((true, value)))
+		}
+	})
+	.await?
+	.map_err(StorageSizeError::into_error)
 }
 
 /// Deletes keys from the KV store. Cannot be undone.
@@ -284,10 +738,26 @@ pub async fn delete(db: &universaldb::Database, actor_id: Id, keys: Vec<rp::KvKe
 	db.run(|tx| {
 		let keys = keys.clone();
 		async move {
-			for key in keys {
-				let key_subspace = subspace(actor_id).subspace(&KeyWrapper(key));
+			let subspace = subspace(actor_id);
+			let tx = tx.with_subspace(subspace.clone());
+
+			let new_sizes = keys.iter().map(|key| (key, 0)).collect();
+			let delta = storage_delta(&tx, &subspace, new_sizes).await?;
+			if let Err(err) = update_storage_size(&tx, actor_id, delta, None).await? {
+				return Err(err.into_error());
+			}
+
+			changes::record(
+				&tx,
+				actor_id,
+				keys.iter()
+					.map(|key| (rp::KvChangeOp::Delete, key.clone()))
+					.collect(),
+			)
+			.await?;
 
-				tx.clear_subspace_range(&key_subspace);
+			for key in keys {
+				tx.clear_subspace_range(&subspace.subspace(&KeyWrapper(key)));
 			}
 
 			Ok(())
@@ -301,12 +771,360 @@ pub async fn delete(db: &universaldb::Database, actor_id: Id, keys: Vec<rp::KvKe
 pub async fn delete_all(db: &universaldb::Database, actor_id: Id) -> Result<()> {
 	db.run(|tx| async move {
 		tx.clear_subspace_range(&subspace(actor_id));
+		tx.with_subspace(pegboard::keys::subspace())
+			.write(&pegboard::keys::actor::KvStorageSizeKey::new(actor_id), 0)?;
+
+		changes::record(&tx, actor_id, vec![(rp::KvChangeOp::Drop, Vec::new())]).await?;
+
 		Ok(())
 	})
 	.await
 	.map_err(Into::into)
 }
 
+/// Reads a single entry and its metadata. Returns `None` if the key does not exist or has expired.
+async fn read_entry(
+	tx: &universaldb::Transaction,
+	subspace: &Subspace,
+	key: KeyWrapper,
+) -> Result<Option<(rp::KvValue, rp::KvMetadata)>> {
+	let key_subspace = subspace.subspace(&key);
+
+	let mut stream = tx.get_ranges_keyvalues(
+		universaldb::RangeOption {
+			mode: universaldb::options::StreamingMode::WantAll,
+			..key_subspace.range().into()
+		},
+		Serializable,
+	);
+
+	let mut entry_builder = EntryBuilder::new(key);
+	let mut found = false;
+
+	loop {
+		let Some(entry) = stream.try_next().await? else {
+			break;
+		};
+
+		found = true;
+
+		if let Ok(chunk_key) = tx.unpack::<EntryValueChunkKey>(&entry.key()) {
+			entry_builder.append_chunk(chunk_key.chunk, entry.value());
+		} else if let Ok(metadata_key) = tx.unpack::<EntryMetadataKey>(&entry.key()) {
+			let value = metadata_key.deserialize(entry.value())?;
+
+			entry_builder.append_metadata(value);
+		} else {
+			bail!("unexpected sub key");
+		}
+	}
+
+	if !found {
+		return Ok(None);
+	}
+
+	let (_, value, metadata) = entry_builder.build()?;
+
+	if utils::is_expired(&metadata, utils::now()) {
+		return Ok(None);
+	}
+
+	Ok(Some((value, metadata)))
+}
+
+/// Size of an entry as counted towards the storage quota.
+fn entry_size(key: &rp::KvKey, value_len: usize) -> i64 {
+	(KeyWrapper::tuple_len(key) + value_len) as i64
+}
+
+/// Returns the size of the entry currently stored under the key, or 0 if it does not exist. Expired
+/// entries still count until they are swept.
+pub(crate) async fn stored_entry_size(
+	tx: &universaldb::Transaction,
+	subspace: &Subspace,
+	key: &KeyWrapper,
+) -> Result<i64> {
+	let key_subspace = subspace.subspace(key);
+
+	// Metadata sorts after the value chunks, so reading two sub keys in reverse yields the metadata
+	// and the last chunk
+	let mut stream = tx.get_ranges_keyvalues(
+		universaldb::RangeOption {
+			mode: universaldb::options::StreamingMode::WantAll,
+			limit: Some(2),
+			reverse: true,
+			..key_subspace.range().into()
+		},
+		Serializable,
+	);
+
+	let mut found = false;
+	let mut value_len = 0;
+
+	loop {
+		let Some(entry) = stream.try_next().await? else {
+			break;
+		};
+
+		found = true;
+
+		if let Ok(chunk_key) = tx.unpack::<EntryValueChunkKey>(&entry.key()) {
+			value_len = chunk_key.chunk * VALUE_CHUNK_SIZE + entry.value().len();
+		}
+	}
+
+	if !found {
+		return Ok(0);
+	}
+
+	Ok(entry_size(&key.0, value_len))
+}
+
+/// Returns the change in stored bytes from setting each key to the given entry size. A size of 0
+/// deletes the key.
+async fn storage_delta(
+	tx: &universaldb::Transaction,
+	subspace: &Subspace,
+	new_sizes: HashMap<&rp::KvKey, i64>,
+) -> Result<i64> {
+	let old_size = futures_util::stream::iter(new_sizes.keys())
+		.map(|key| {
+			let key = KeyWrapper((*key).clone());
+
+			async move { stored_entry_size(tx, subspace, &key).await }
+		})
+		.buffer_unordered(32)
+		.try_fold(0, |acc, size| async move { Ok(acc + size) })
+		.await?;
+
+	Ok(new_sizes.values().sum::<i64>() - old_size)
+}
+
+/// Applies a change in stored bytes to the actor's storage counter. Returns an error instead of
+/// applying the change if it grows storage past the quota, or grows storage before the counter was
+/// initialized with `init_storage_size`. Nothing should be written before calling this since the
+/// transaction still commits in that case.
+pub(crate) async fn update_storage_size(
+	tx: &universaldb::Transaction,
+	actor_id: Id,
+	delta: i64,
+	quota: Option<u64>,
+) -> Result<std::result::Result<(), StorageSizeError>> {
+	let storage_size_key = pegboard::keys::actor::KvStorageSizeKey::new(actor_id);
+	let counter_tx = tx.with_subspace(pegboard::keys::subspace());
+
+	// Writes that grow storage read the counter serializably so concurrent writes to the same actor
+	// conflict instead of overshooting the quota together. Deletes can't exceed the quota, they read
+	// it with a snapshot read so they don't conflict with other writes
+	let isolation_level = if delta > 0 { Serializable } else { Snapshot };
+	let Some(storage_size) = counter_tx
+		.read_opt(&storage_size_key, isolation_level)
+		.await?
+	else {
+		// Shrinking storage can't exceed the quota and the initialization scan won't see the removed
+		// entries, so there is nothing to track
+		if delta <= 0 {
+			return Ok(Ok(()));
+		}
+
+		return Ok(Err(StorageSizeError::Uninitialized));
+	};
+
+	if let Some(quota) = quota
+		&& delta > 0
+	{
+		let remaining = i64::try_from(quota)
+			.unwrap_or(i64::MAX)
+			.saturating_sub(storage_size)
+			.max(0);
+
+		if delta > remaining {
+			return Ok(Err(StorageSizeError::QuotaExceeded {
+				remaining,
+				payload_size: delta,
+			}));
+		}
+	}
+
+	if delta != 0 {
+		counter_tx.atomic_op(&storage_size_key, &delta.to_le_bytes(), MutationType::Add);
+	}
+
+	Ok(Ok(()))
+}
+
+/// Sums the size of the entries in a single page of the subspace, starting at `start`. Entries span
+/// multiple sub keys, so `current_key` is the entry the previous page ended in and is not counted
+/// again.
+///
+/// Returns the page total, the entry the page ended in and the last key read, which is `None` once
+/// the end of the range is reached.
+async fn scan_storage_size_page(
+	tx: &universaldb::Transaction,
+	subspace: &Subspace,
+	start: Vec<u8>,
+	end: Vec<u8>,
+	mut current_key: Option<rp::KvKey>,
+) -> Result<(i64, Option<rp::KvKey>, Option<Vec<u8>>)> {
+	let tx = tx.with_subspace(subspace.clone());
+
+	let entries = tx
+		.get_ranges_keyvalues(
+			universaldb::RangeOption {
+				mode: universaldb::options::StreamingMode::WantAll,
+				limit: Some(STORAGE_SIZE_SCAN_PAGE_SIZE),
+				..(start, end).into()
+			},
+			Snapshot,
+		)
+		.try_collect::<Vec<_>>()
+		.await?;
+
+	let mut total = 0;
+
+	for entry in &entries {
+		let key = tx.unpack::<EntryBaseKey>(entry.key())?.key;
+
+		if current_key.as_ref() != Some(&key.0) {
+			total += entry_size(&key.0, 0);
+			current_key = Some(key.0);
+		}
+
+		if tx.unpack::<EntryValueChunkKey>(entry.key()).is_ok() {
+			total += entry.value().len() as i64;
+		}
+	}
+
+	let last_key = if entries.len() < STORAGE_SIZE_SCAN_PAGE_SIZE {
+		None
+	} else {
+		entries.last().map(|entry| entry.key().to_vec())
+	};
+
+	Ok((total, current_key, last_key))
+}
+
+/// Clears any previous data for the key, then writes its metadata and value chunks.
+fn write_entry(
+	tx: &universaldb::Transaction,
+	subspace: &Subspace,
+	key: KeyWrapper,
+	value: &[u8],
+	expire_ts: Option<i64>,
+) -> Result<()> {
+	write_entry_with_metadata(
+		tx,
+		subspace,
+		key,
+		value,
+		rp::KvMetadata {
+			version: VERSION.as_bytes().to_vec(),
+			create_ts: utils::now(),
+			expire_ts,
+		},
+	)
+}
+
+fn write_entry_with_metadata(
+	tx: &universaldb::Transaction,
+	subspace: &Subspace,
+	key: KeyWrapper,
+	value: &[u8],
+	metadata: rp::KvMetadata,
+) -> Result<()> {
+	// Clear previous key data before setting
+	tx.clear_subspace_range(&subspace.subspace(&key));
+
+	// Set metadata
+	tx.write(&EntryMetadataKey::new(key.clone()), metadata)?;
+
+	// Set key data in chunks
+	for start in (0..value.len()).step_by(VALUE_CHUNK_SIZE) {
+		let idx = start / VALUE_CHUNK_SIZE;
+		let end = (start + VALUE_CHUNK_SIZE).min(value.len());
+
+		tx.set(
+			&subspace.pack(&EntryValueChunkKey::new(key.clone(), idx)),
+			&value.get(start..end).context("bad slice")?,
+		);
+	}
+
+	Ok(())
+}
+
+/// Hash of the list query that identifies which query a cursor belongs to.
+fn list_query_hash(query: &rp::KvListQuery) -> Result<[u8; LIST_CURSOR_HASH_LEN]> {
+	let digest = Sha256::digest(serde_bare::to_vec(query)?);
+
+	Ok(digest[..LIST_CURSOR_HASH_LEN]
+		.try_into()
+		.expect("digest is longer than the hash"))
+}
+
+/// Encodes a list cursor as the version, the direction, the query hash, and the last returned key.
+fn encode_list_cursor(
+	query_hash: [u8; LIST_CURSOR_HASH_LEN],
+	reverse: bool,
+	key: &[u8],
+) -> Vec<u8> {
+	let mut cursor = Vec::with_capacity(2 + LIST_CURSOR_HASH_LEN + key.len());
+	cursor.push(LIST_CURSOR_VERSION);
+	cursor.push(reverse as u8);
+	cursor.extend_from_slice(&query_hash);
+	cursor.extend_from_slice(key);
+
+	cursor
+}
+
+/// Returns the last returned key of the cursor after checking that it was created for the same query
+/// and direction.
+fn decode_list_cursor(
+	cursor: &[u8],
+	query_hash: [u8; LIST_CURSOR_HASH_LEN],
+	reverse: bool,
+) -> Result<Vec<u8>> {
+	let (&[version, cursor_reverse], rest) = cursor
+		.split_first_chunk::<2>()
+		.context("invalid list cursor")?;
+	ensure!(version == LIST_CURSOR_VERSION, "invalid list cursor");
+	let (cursor_query_hash, key) = rest
+		.split_first_chunk::<LIST_CURSOR_HASH_LEN>()
+		.context("invalid list cursor")?;
+
+	ensure!(
+		cursor_query_hash == &query_hash,
+		"list cursor was created for a different query"
+	);
+	ensure!(
+		(cursor_reverse != 0) == reverse,
+		"list cursor was created for a different direction"
+	);
+	utils::validate_list_cursor(key)?;
+
+	Ok(key.to_vec())
+}
+
+/// Narrows the list range to exclude everything up to and including the cursor key in the direction of
+/// iteration.
+fn apply_list_cursor(
+	range: (Vec<u8>, Vec<u8>),
+	cursor: Option<Vec<u8>>,
+	reverse: bool,
+	subspace: &Subspace,
+) -> (Vec<u8>, Vec<u8>) {
+	let Some(cursor) = cursor else {
+		return range;
+	};
+
+	let (cursor_start, cursor_end) = subspace.subspace(&KeyWrapper(cursor)).range();
+
+	if reverse {
+		(range.0, range.1.min(cursor_start))
+	} else {
+		(range.0.max(cursor_end), range.1)
+	}
+}
+
 fn list_query_range(query: rp::KvListQuery, subspace: &Subspace) -> (Vec<u8>, Vec<u8>) {
 	match query {
 		rp::KvListQuery::KvListAllQuery => subspace.range(),
@@ -319,7 +1137,26 @@ fn list_query_range(query: rp::KvListQuery, subspace: &Subspace) -> (Vec<u8>, Ve
 			},
 		),
 		rp::KvListQuery::KvListPrefixQuery(prefix) => {
-			subspace.subspace(&KeyWrapper(prefix.key)).range()
+			// Drop the terminator of the packed byte string so the range covers every key starting
+			// with the prefix bytes instead of only the prefix itself
+			let mut start = subspace.pack(&ListKeyWrapper(prefix.key));
+			start.pop();
+			let end = prefix_range_end(&start);
+
+			(start, end)
 		}
 	}
 }
+
+/// Returns the first key after all keys starting with `prefix`.
+fn prefix_range_end(prefix: &[u8]) -> Vec<u8> {
+	let mut end = prefix.to_vec();
+	while end.last() == Some(&0xff) {
+		end.pop();
+	}
+	if let Some(last) = end.last_mut() {
+		*last += 1;
+	}
+
+	end
+}
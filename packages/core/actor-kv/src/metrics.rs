@@ -0,0 +1,15 @@
+use rivet_metrics::otel::{global::*, metrics::*};
+
+lazy_static::lazy_static! {
+	static ref METER: Meter = meter("rivet-actor-kv");
+
+	/// Has no expected attributes
+	pub static ref EXPIRED_ENTRIES: Counter<u64> = METER.u64_counter("rivet_actor_kv_expired_entries")
+		.with_description("Total KV entries deleted by the expiry sweeper.")
+		.build();
+
+	/// Has no expected attributes
+	pub static ref EXPIRED_BYTES: Counter<u64> = METER.u64_counter("rivet_actor_kv_expired_bytes")
+		.with_description("Total bytes freed by the expiry sweeper.")
+		.build();
+}
@@ -0,0 +1,273 @@
+//! Portable snapshots of an actor's KV store.
+//!
+//! A snapshot starts with `MAGIC` and a little endian u16 format version, followed by records. Each
+//! record is a little endian u32 length followed by a BARE encoded `SnapshotRecord`. The last record
+//! is always `SnapshotRecord::End`, which is used to detect truncated snapshots.
+
+use std::collections::HashMap;
+use std::result::Result::{Err, Ok};
+
+use anyhow::*;
+use rivet_runner_protocol as rp;
+use rivet_util_id::Id;
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use universaldb::prelude::*;
+
+use crate::{
+	MAX_KEY_SIZE, MAX_KEYS, MAX_PUT_PAYLOAD_SIZE, MAX_VALUE_SIZE, StorageSizeError, changes,
+	entry::EntryExpiryKey, entry_size, key::KeyWrapper, storage_delta, subspace,
+	update_storage_size, utils, write_entry_with_metadata,
+};
+
+const MAGIC: &[u8; 8] = b"RIVETKV\0";
+const SNAPSHOT_VERSION: u16 = 1;
+const EXPORT_PAGE_SIZE: usize = 128;
+/// Upper bound for the length of a single record, used to reject corrupt snapshots before
+/// allocating.
+const MAX_RECORD_SIZE: usize = MAX_KEY_SIZE + MAX_VALUE_SIZE + 1024;
+
+#[derive(Serialize, Deserialize)]
+enum SnapshotRecord {
+	Entry {
+		key: rp::KvKey,
+		value: rp::KvValue,
+		metadata: rp::KvMetadata,
+	},
+	End {
+		entries: u64,
+	},
+}
+
+/// Writes all keys of the actor to `w`. Keys are read in pages across multiple transactions, so
+/// the snapshot is not point-in-time if the actor writes to its KV store during the export.
+///
+/// Returns the amount of entries written.
+pub async fn export<W: AsyncWrite + Unpin>(
+	db: &universaldb::Database,
+	actor_id: Id,
+	w: &mut W,
+) -> Result<u64> {
+	w.write_all(MAGIC).await?;
+	w.write_all(&SNAPSHOT_VERSION.to_le_bytes()).await?;
+
+	let mut entries = 0;
+	let mut cursor = None;
+
+	loop {
+		let (keys, values, metadata, next_cursor) = crate::list(
+			db,
+			actor_id,
+			rp::KvListQuery::KvListAllQuery,
+			false,
+			Some(EXPORT_PAGE_SIZE),
+			cursor,
+		)
+		.await?;
+
+		for ((key, value), metadata) in keys.into_iter().zip(values).zip(metadata) {
+			write_record(
+				w,
+				&SnapshotRecord::Entry {
+					key,
+					value,
+					metadata,
+				},
+			)
+			.await?;
+
+			entries += 1;
+		}
+
+		let Some(next_cursor) = next_cursor else {
+			break;
+		};
+		cursor = Some(next_cursor);
+	}
+
+	write_record(w, &SnapshotRecord::End { entries }).await?;
+	w.flush().await?;
+
+	Ok(entries)
+}
+
+/// Loads a snapshot written by `export` into the actor. Entries keep their original metadata and
+/// entries that expired since the export are skipped.
+///
+/// Fails if the actor already has keys unless `replace` is set, in which case all existing keys are
+/// deleted first. Entries are written in batches, so a failed import leaves the batches written so
+/// far in place.
+///
+/// Returns the amount of entries read from the snapshot.
+pub async fn import<R: AsyncRead + Unpin>(
+	db: &universaldb::Database,
+	actor_id: Id,
+	r: &mut R,
+	replace: bool,
+	quota: Option<u64>,
+) -> Result<u64> {
+	let mut magic = [0u8; MAGIC.len()];
+	r.read_exact(&mut magic)
+		.await
+		.context("failed to read snapshot header")?;
+	ensure!(&magic == MAGIC, "not a kv snapshot");
+
+	let version = r.read_u16_le().await?;
+	ensure!(
+		version == SNAPSHOT_VERSION,
+		"unsupported kv snapshot version {version}"
+	);
+
+	if replace {
+		crate::delete_all(db, actor_id).await?;
+	} else {
+		let (keys, _, _, _) = crate::list(
+			db,
+			actor_id,
+			rp::KvListQuery::KvListAllQuery,
+			false,
+			Some(1),
+			None,
+		)
+		.await?;
+		ensure!(
+			keys.is_empty(),
+			"actor already has keys, replace them to import a snapshot"
+		);
+	}
+
+	crate::init_storage_size(db, actor_id).await?;
+
+	let mut entries = 0;
+	let mut batch = Vec::new();
+	let mut batch_size = 0;
+
+	loop {
+		let record = read_record(r).await?.context("kv snapshot is truncated")?;
+
+		match record {
+			SnapshotRecord::Entry {
+				key,
+				value,
+				metadata,
+			} => {
+				utils::validate_entries(std::slice::from_ref(&key), std::slice::from_ref(&value))?;
+
+				batch_size += KeyWrapper::tuple_len(&key) + value.len();
+				batch.push((key, value, metadata));
+				entries += 1;
+
+				if batch.len() >= MAX_KEYS || batch_size >= MAX_PUT_PAYLOAD_SIZE {
+					import_batch(db, actor_id, std::mem::take(&mut batch), quota).await?;
+					batch_size = 0;
+				}
+			}
+			SnapshotRecord::End {
+				entries: expected_entries,
+			} => {
+				ensure!(
+					entries == expected_entries,
+					"kv snapshot has {entries} entries, expected {expected_entries}"
+				);
+
+				break;
+			}
+		}
+	}
+
+	if !batch.is_empty() {
+		import_batch(db, actor_id, batch, quota).await?;
+	}
+
+	Ok(entries)
+}
+
+async fn import_batch(
+	db: &universaldb::Database,
+	actor_id: Id,
+	batch: Vec<(rp::KvKey, rp::KvValue, rp::KvMetadata)>,
+	quota: Option<u64>,
+) -> Result<()> {
+	let subspace = subspace(actor_id);
+	let now = utils::now();
+	let batch = batch
+		.into_iter()
+		.filter(|(_, _, metadata)| !utils::is_expired(metadata, now))
+		.collect::<Vec<_>>();
+
+	db.run(|tx| {
+		// TODO: Costly clone
+		let batch = batch.clone();
+		let subspace = subspace.clone();
+
+		async move {
+			let tx = tx.with_subspace(subspace.clone());
+
+			let new_sizes = batch
+				.iter()
+				.map(|(key, value, _)| (key, entry_size(key, value.len())))
+				.collect::<HashMap<_, _>>();
+			let delta = storage_delta(&tx, &subspace, new_sizes).await?;
+			if let Err(err) = update_storage_size(&tx, actor_id, delta, quota).await? {
+				return Ok(Err(err));
+			}
+
+			changes::record(
+				&tx,
+				actor_id,
+				batch
+					.iter()
+					.map(|(key, _, _)| (rp::KvChangeOp::Put, key.clone()))
+					.collect(),
+			)
+			.await?;
+
+			for (key, value, metadata) in batch {
+				let key = KeyWrapper(key);
+
+				if let Some(expire_ts) = metadata.expire_ts {
+					tx.set(
+						&pegboard::keys::actor_kv_expiry_subspace().pack(&EntryExpiryKey::new(
+							expire_ts,
+							actor_id,
+							key.clone(),
+						)),
+						&[],
+					);
+				}
+
+				write_entry_with_metadata(&tx, &subspace, key, &value, metadata)?;
+			}
+
+			Ok(Ok(()))
+		}
+	})
+	.await?
+	.map_err(StorageSizeError::into_error)
+}
+
+async fn write_record<W: AsyncWrite + Unpin>(w: &mut W, record: &SnapshotRecord) -> Result<()> {
+	let buf = serde_bare::to_vec(record)?;
+
+	w.write_u32_le(buf.len().try_into()?).await?;
+	w.write_all(&buf).await?;
+
+	Ok(())
+}
+
+/// Returns `None` if the reader ended before the record started.
+async fn read_record<R: AsyncRead + Unpin>(r: &mut R) -> Result<Option<SnapshotRecord>> {
+	let len = match r.read_u32_le().await {
+		Ok(len) => usize::try_from(len)?,
+		Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+		Err(err) => return Err(err.into()),
+	};
+	ensure!(len <= MAX_RECORD_SIZE, "kv snapshot record is too large");
+
+	let mut buf = vec![0u8; len];
+	r.read_exact(&mut buf)
+		.await
+		.context("kv snapshot is truncated")?;
+
+	Ok(Some(serde_bare::from_slice(&buf)?))
+}
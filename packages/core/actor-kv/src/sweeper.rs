@@ -0,0 +1,125 @@
+use std::collections::HashSet;
+use std::result::Result::Ok;
+
+use anyhow::*;
+use futures_util::TryStreamExt;
+use rivet_runner_protocol as rp;
+use universaldb::prelude::*;
+use universalpubsub::PubSub;
+
+use crate::{
+	changes,
+	entry::{EntryExpiryKey, EntryMetadataKey},
+	metrics, stored_entry_size, subspace, update_storage_size, utils,
+};
+
+const SWEEP_BATCH_SIZE: usize = 1024;
+
+#[tracing::instrument(skip_all)]
+pub async fn start(_config: rivet_config::Config, pools: rivet_pools::Pools) -> Result<()> {
+	let udb = pools.udb()?;
+	let ups = pools.ups()?;
+
+	let (entries, bytes) = sweep_expired(&udb, &ups).await?;
+
+	tracing::debug!(?entries, ?bytes, "swept expired kv entries");
+
+	let changes = changes::trim(&udb).await?;
+
+	tracing::debug!(?changes, "trimmed kv change logs");
+
+	Ok(())
+}
+
+/// Deletes all entries whose expiry has passed. Returns the amount of entries deleted and the amount of
+/// bytes freed.
+pub async fn sweep_expired(db: &universaldb::Database, ups: &PubSub) -> Result<(usize, usize)> {
+	let now = utils::now();
+	let mut total_entries = 0;
+	let mut total_bytes = 0;
+
+	loop {
+		let (scanned, entries, bytes, actor_ids) = db
+			.run(|tx| async move {
+				let expiry_subspace = pegboard::keys::actor_kv_expiry_subspace();
+
+				// Index keys are ordered by expiry so everything up to and including `now` has expired
+				let start = expiry_subspace.range().0;
+				let end = expiry_subspace.subspace(&now).range().1;
+
+				let index_entries = tx
+					.get_ranges_keyvalues(
+						universaldb::RangeOption {
+							mode: StreamingMode::WantAll,
+							limit: Some(SWEEP_BATCH_SIZE),
+							..(start, end).into()
+						},
+						Serializable,
+					)
+					.try_collect::<Vec<_>>()
+					.await?;
+
+				let mut entries = 0;
+				let mut bytes = 0;
+				let mut actor_ids = HashSet::new();
+
+				for index_entry in &index_entries {
+					let expiry_key = expiry_subspace.unpack::<EntryExpiryKey>(index_entry.key())?;
+
+					tx.clear(index_entry.key());
+
+					let subspace = subspace(expiry_key.actor_id);
+					let tx = tx.with_subspace(subspace.clone());
+
+					// Skip entries that were overwritten or deleted after the index key was written
+					let metadata_key = EntryMetadataKey::new(expiry_key.key.clone());
+					let metadata = tx.read_opt(&metadata_key, Serializable).await?;
+					if metadata.and_then(|metadata| metadata.expire_ts)
+						!= Some(expiry_key.expire_ts)
+					{
+						continue;
+					}
+
+					let size = stored_entry_size(&tx, &subspace, &expiry_key.key).await?;
+					if let Err(err) =
+						update_storage_size(&tx, expiry_key.actor_id, -size, None).await?
+					{
+						return Err(err.into_error());
+					}
+
+					changes::record(
+						&tx,
+						expiry_key.actor_id,
+						vec![(rp::KvChangeOp::Delete, expiry_key.key.0.clone())],
+					)
+					.await?;
+
+					tx.clear_subspace_range(&subspace.subspace(&expiry_key.key));
+
+					bytes += size as usize;
+
+					entries += 1;
+					actor_ids.insert(expiry_key.actor_id);
+				}
+
+				Ok((index_entries.len(), entries, bytes, actor_ids))
+			})
+			.await?;
+
+		metrics::EXPIRED_ENTRIES.add(entries as u64, &[]);
+		metrics::EXPIRED_BYTES.add(bytes as u64, &[]);
+
+		for actor_id in actor_ids {
+			changes::notify(ups, actor_id).await?;
+		}
+
+		total_entries += entries;
+		total_bytes += bytes;
+
+		if scanned < SWEEP_BATCH_SIZE {
+			break;
+		}
+	}
+
+	Ok((total_entries, total_bytes))
+}
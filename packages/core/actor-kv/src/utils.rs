@@ -4,7 +4,8 @@ use anyhow::*;
 use rivet_runner_protocol as rp;
 
 use crate::{
-	MAX_KEY_SIZE, MAX_KEYS, MAX_PUT_PAYLOAD_SIZE, MAX_STORAGE_SIZE, MAX_VALUE_SIZE, key::KeyWrapper,
+	MAX_ATOMIC_PARAM_SIZE, MAX_KEY_SIZE, MAX_KEYS, MAX_PUT_PAYLOAD_SIZE, MAX_VALUE_SIZE,
+	key::KeyWrapper,
 };
 
 pub fn now() -> i64 {
@@ -16,6 +17,10 @@ pub fn now() -> i64 {
 		.expect("now doesn't fit in i64")
 }
 
+pub fn is_expired(metadata: &rp::KvMetadata, now: i64) -> bool {
+	metadata.expire_ts.is_some_and(|expire_ts| expire_ts <= now)
+}
+
 pub fn validate_list_query(query: &rp::KvListQuery) -> Result<()> {
 	match query {
 		rp::KvListQuery::KvListAllQuery => {}
@@ -40,6 +45,15 @@ pub fn validate_list_query(query: &rp::KvListQuery) -> Result<()> {
 	Ok(())
 }
 
+pub fn validate_list_cursor(cursor: &[u8]) -> Result<()> {
+	ensure!(
+		cursor.len() + 2 <= MAX_KEY_SIZE,
+		"cursor is too long (max 2048 bytes)"
+	);
+
+	Ok(())
+}
+
 pub fn validate_keys(keys: &[rp::KvKey]) -> Result<()> {
 	ensure!(keys.len() <= MAX_KEYS, "a maximum of 128 keys is allowed");
 
@@ -53,11 +67,36 @@ pub fn validate_keys(keys: &[rp::KvKey]) -> Result<()> {
 	Ok(())
 }
 
-pub fn validate_entries(
-	keys: &[rp::KvKey],
-	values: &[rp::KvValue],
-	total_size: usize,
-) -> Result<()> {
+pub fn validate_conditions(conditions: &[rp::KvCondition]) -> Result<()> {
+	ensure!(
+		conditions.len() <= MAX_KEYS,
+		"a maximum of 128 conditions is allowed"
+	);
+
+	for condition in conditions {
+		let key = match condition {
+			rp::KvCondition::KvConditionExists(cond) => &cond.key,
+			rp::KvCondition::KvConditionValue(cond) => {
+				ensure!(
+					cond.value.len() <= MAX_VALUE_SIZE,
+					"condition value is too large (max 128 KiB)"
+				);
+
+				&cond.key
+			}
+			rp::KvCondition::KvConditionCreateTs(cond) => &cond.key,
+		};
+
+		ensure!(
+			KeyWrapper::tuple_len(key) <= MAX_KEY_SIZE,
+			"condition key is too long (max 2048 bytes)"
+		);
+	}
+
+	Ok(())
+}
+
+pub fn validate_entries(keys: &[rp::KvKey], values: &[rp::KvValue]) -> Result<()> {
 	ensure!(
 		keys.len() == values.len(),
 		"Keys list length != values list length"
@@ -77,12 +116,6 @@ pub fn validate_entries(
 		"total payload is too large (max 976 KiB)"
 	);
 
-	let storage_remaining = MAX_STORAGE_SIZE.saturating_sub(total_size);
-	ensure!(
-		payload_size <= storage_remaining,
-		"not enough space left in storage ({storage_remaining} bytes remaining, current payload is {payload_size} bytes)"
-	);
-
 	for key in keys {
 		ensure!(
 			KeyWrapper::tuple_len(key) <= MAX_KEY_SIZE,
@@ -99,3 +132,24 @@ pub fn validate_entries(
 
 	Ok(())
 }
+
+pub fn validate_atomic_entries(
+	op: &rp::KvAtomicOpType,
+	keys: &[rp::KvKey],
+	params: &[rp::KvValue],
+) -> Result<()> {
+	validate_entries(keys, params)?;
+
+	for param in params {
+		ensure!(!param.is_empty(), "atomic param cannot be empty");
+
+		if !matches!(op, rp::KvAtomicOpType::AppendIfFits) {
+			ensure!(
+				param.len() <= MAX_ATOMIC_PARAM_SIZE,
+				"atomic param is too large (max 8 bytes)"
+			);
+		}
+	}
+
+	Ok(())
+}
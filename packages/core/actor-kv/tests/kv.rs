@@ -0,0 +1,675 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use pegboard_actor_kv as kv;
+use rivet_runner_protocol as rp;
+use rivet_util_id::Id;
+use universalpubsub::PubSub;
+
+struct TestDb {
+	db: universaldb::Database,
+	ups: PubSub,
+	_dir: tempfile::TempDir,
+}
+
+async fn setup() -> TestDb {
+	let _ = tracing_subscriber::fmt::try_init();
+
+	let dir = tempfile::tempdir().unwrap();
+	let driver = universaldb::driver::RocksDbDatabaseDriver::new(dir.path().to_path_buf())
+		.await
+		.unwrap();
+	let ups = PubSub::new(Arc::new(
+		universalpubsub::driver::memory::MemoryDriver::new("test".to_string()),
+	));
+
+	TestDb {
+		db: universaldb::Database::new(Arc::new(driver)),
+		ups,
+		_dir: dir,
+	}
+}
+
+fn keys(keys: &[&str]) -> Vec<rp::KvKey> {
+	keys.iter().map(|key| key.as_bytes().to_vec()).collect()
+}
+
+async fn get_one(db: &universaldb::Database, actor_id: Id, key: &str) -> Option<rp::KvValue> {
+	let (_, values, _) = kv::get(db, actor_id, keys(&[key])).await.unwrap();
+
+	values.into_iter().next()
+}
+
+async fn list_all(
+	db: &universaldb::Database,
+	actor_id: Id,
+	query: rp::KvListQuery,
+	reverse: bool,
+	limit: usize,
+) -> Vec<rp::KvKey> {
+	let mut all_keys = Vec::new();
+	let mut cursor = None;
+
+	loop {
+		let (keys, _, _, next_cursor) =
+			kv::list(db, actor_id, query.clone(), reverse, Some(limit), cursor)
+				.await
+				.unwrap();
+		assert!(keys.len() <= limit);
+		all_keys.extend(keys);
+
+		let Some(next_cursor) = next_cursor else {
+			break;
+		};
+		cursor = Some(next_cursor);
+	}
+
+	all_keys
+}
+
+#[tokio::test]
+async fn test_transact_condition_failure() {
+	let test = setup().await;
+	let actor_id = Id::new_v1(1);
+
+	kv::put(
+		&test.db,
+		actor_id,
+		keys(&["existing"]),
+		vec![b"value".to_vec()],
+		None,
+		u64::MAX,
+	)
+	.await
+	.unwrap();
+
+	// The second condition fails, so neither the delete nor the put is applied
+	let applied = kv::transact(
+		&test.db,
+		actor_id,
+		vec![
+			rp::KvCondition::KvConditionExists(rp::KvConditionExists {
+				key: b"existing".to_vec(),
+				exists: true,
+			}),
+			rp::KvCondition::KvConditionValue(rp::KvConditionValue {
+				key: b"existing".to_vec(),
+				value: b"other".to_vec(),
+			}),
+		],
+		keys(&["new"]),
+		vec![b"new".to_vec()],
+		keys(&["existing"]),
+		u64::MAX,
+	)
+	.await
+	.unwrap();
+	assert!(!applied);
+
+	assert_eq!(
+		get_one(&test.db, actor_id, "existing").await,
+		Some(b"value".to_vec())
+	);
+	assert_eq!(get_one(&test.db, actor_id, "new").await, None);
+
+	let applied = kv::transact(
+		&test.db,
+		actor_id,
+		vec![rp::KvCondition::KvConditionValue(rp::KvConditionValue {
+			key: b"existing".to_vec(),
+			value: b"value".to_vec(),
+		})],
+		keys(&["new"]),
+		vec![b"new".to_vec()],
+		keys(&["existing"]),
+		u64::MAX,
+	)
+	.await
+	.unwrap();
+	assert!(applied);
+
+	assert_eq!(get_one(&test.db, actor_id, "existing").await, None);
+	assert_eq!(
+		get_one(&test.db, actor_id, "new").await,
+		Some(b"new".to_vec())
+	);
+}
+
+#[tokio::test]
+async fn test_compare_and_swap() {
+	let test = setup().await;
+	let actor_id = Id::new_v1(1);
+
+	// Expecting no value creates the key
+	let (swapped, value) = kv::compare_and_swap(
+		&test.db,
+		actor_id,
+		b"key".to_vec(),
+		None,
+		Some(b"a".to_vec()),
+		u64::MAX,
+	)
+	.await
+	.unwrap();
+	assert!(swapped);
+	assert_eq!(value, Some(b"a".to_vec()));
+
+	// Mismatch returns the current value without writing
+	let (swapped, value) = kv::compare_and_swap(
+		&test.db,
+		actor_id,
+		b"key".to_vec(),
+		Some(b"b".to_vec()),
+		Some(b"c".to_vec()),
+		u64::MAX,
+	)
+	.await
+	.unwrap();
+	assert!(!swapped);
+	assert_eq!(value, Some(b"a".to_vec()));
+	assert_eq!(
+		get_one(&test.db, actor_id, "key").await,
+		Some(b"a".to_vec())
+	);
+
+	// Swapping to no value deletes the key
+	let (swapped, value) = kv::compare_and_swap(
+		&test.db,
+		actor_id,
+		b"key".to_vec(),
+		Some(b"a".to_vec()),
+		None,
+		u64::MAX,
+	)
+	.await
+	.unwrap();
+	assert!(swapped);
+	assert_eq!(value, None);
+	assert_eq!(get_one(&test.db, actor_id, "key").await, None);
+}
+
+#[tokio::test]
+async fn test_atomic_missing_keys() {
+	let test = setup().await;
+	let actor_id = Id::new_v1(1);
+
+	for (op, key) in [
+		(rp::KvAtomicOpType::Add, "add"),
+		(rp::KvAtomicOpType::Max, "max"),
+		(rp::KvAtomicOpType::Min, "min"),
+	] {
+		// Missing keys take the value of the param
+		kv::atomic(
+			&test.db,
+			actor_id,
+			op,
+			keys(&[key]),
+			vec![5u64.to_le_bytes().to_vec()],
+			u64::MAX,
+		)
+		.await
+		.unwrap();
+
+		assert_eq!(
+			get_one(&test.db, actor_id, key).await,
+			Some(5u64.to_le_bytes().to_vec()),
+			"{key}"
+		);
+	}
+
+	kv::atomic(
+		&test.db,
+		actor_id,
+		rp::KvAtomicOpType::Add,
+		keys(&["add"]),
+		vec![3u64.to_le_bytes().to_vec()],
+		u64::MAX,
+	)
+	.await
+	.unwrap();
+	assert_eq!(
+		get_one(&test.db, actor_id, "add").await,
+		Some(8u64.to_le_bytes().to_vec())
+	);
+}
+
+#[tokio::test]
+async fn test_ttl_expiry() {
+	let test = setup().await;
+	let actor_id = Id::new_v1(1);
+
+	kv::put(
+		&test.db,
+		actor_id,
+		keys(&["expiring"]),
+		vec![b"value".to_vec()],
+		Some(1),
+		u64::MAX,
+	)
+	.await
+	.unwrap();
+	kv::put(
+		&test.db,
+		actor_id,
+		keys(&["kept"]),
+		vec![b"value".to_vec()],
+		None,
+		u64::MAX,
+	)
+	.await
+	.unwrap();
+	let size_before = kv::get_storage_size(&test.db, actor_id).await.unwrap();
+
+	tokio::time::sleep(Duration::from_millis(50)).await;
+
+	// Expired entries are hidden before they are swept
+	assert_eq!(get_one(&test.db, actor_id, "expiring").await, None);
+	assert_eq!(
+		list_all(
+			&test.db,
+			actor_id,
+			rp::KvListQuery::KvListAllQuery,
+			false,
+			10
+		)
+		.await,
+		keys(&["kept"])
+	);
+	assert_eq!(
+		kv::get_storage_size(&test.db, actor_id).await.unwrap(),
+		size_before
+	);
+
+	let (entries, bytes) = kv::sweeper::sweep_expired(&test.db, &test.ups)
+		.await
+		.unwrap();
+	assert_eq!(entries, 1);
+	assert!(bytes > 0);
+
+	assert_eq!(
+		kv::get_storage_size(&test.db, actor_id).await.unwrap(),
+		size_before - bytes as u64
+	);
+}
+
+#[tokio::test]
+async fn test_list_pagination() {
+	let test = setup().await;
+	let actor_id = Id::new_v1(1);
+
+	let all_keys = (0..10).map(|i| format!("k{i:02}")).collect::<Vec<_>>();
+	let mut put_keys = all_keys.clone();
+	put_keys.extend(["j".to_string(), "l".to_string()]);
+	kv::put(
+		&test.db,
+		actor_id,
+		put_keys.iter().map(|key| key.as_bytes().to_vec()).collect(),
+		put_keys.iter().map(|key| key.as_bytes().to_vec()).collect(),
+		None,
+		u64::MAX,
+	)
+	.await
+	.unwrap();
+
+	let mut expected_all = put_keys.clone();
+	expected_all.sort();
+
+	let cases = [
+		(rp::KvListQuery::KvListAllQuery, expected_all),
+		(
+			rp::KvListQuery::KvListRangeQuery(rp::KvListRangeQuery {
+				start: b"k02".to_vec(),
+				end: b"k07".to_vec(),
+				exclusive: false,
+			}),
+			all_keys[2..=7].to_vec(),
+		),
+		(
+			rp::KvListQuery::KvListRangeQuery(rp::KvListRangeQuery {
+				start: b"k02".to_vec(),
+				end: b"k07".to_vec(),
+				exclusive: true,
+			}),
+			all_keys[2..7].to_vec(),
+		),
+		(
+			rp::KvListQuery::KvListPrefixQuery(rp::KvListPrefixQuery {
+				key: b"k0".to_vec(),
+			}),
+			all_keys.clone(),
+		),
+	];
+
+	for (query, expected) in cases {
+		let expected = expected
+			.iter()
+			.map(|key| key.as_bytes().to_vec())
+			.collect::<Vec<_>>();
+
+		let forward = list_all(&test.db, actor_id, query.clone(), false, 3).await;
+		assert_eq!(forward, expected, "{query:?}");
+
+		let reverse = list_all(&test.db, actor_id, query.clone(), true, 3).await;
+		assert_eq!(
+			reverse,
+			expected.iter().rev().cloned().collect::<Vec<_>>(),
+			"{query:?}"
+		);
+	}
+
+	// Cursors only resume the query and direction they were created for
+	let prefix_query =
+		rp::KvListQuery::KvListPrefixQuery(rp::KvListPrefixQuery { key: b"k".to_vec() });
+	let (_, _, _, cursor) = kv::list(
+		&test.db,
+		actor_id,
+		prefix_query.clone(),
+		false,
+		Some(3),
+		None,
+	)
+	.await
+	.unwrap();
+	let cursor = cursor.unwrap();
+
+	let rejected = [
+		(
+			rp::KvListQuery::KvListAllQuery,
+			false,
+			cursor.clone(),
+			"different query",
+		),
+		(
+			prefix_query.clone(),
+			true,
+			cursor.clone(),
+			"different direction",
+		),
+		(
+			prefix_query.clone(),
+			false,
+			cursor[..4].to_vec(),
+			"invalid list cursor",
+		),
+		(prefix_query, false, b"k02".to_vec(), "invalid list cursor"),
+	];
+	for (query, reverse, cursor, expected_error) in rejected {
+		let err = kv::list(&test.db, actor_id, query, reverse, Some(3), Some(cursor))
+			.await
+			.unwrap_err();
+		assert!(err.to_string().contains(expected_error), "{err}");
+	}
+}
+
+#[tokio::test]
+async fn test_quota() {
+	let test = setup().await;
+	let actor_id = Id::new_v1(1);
+
+	kv::put(
+		&test.db,
+		actor_id,
+		keys(&["a"]),
+		vec![vec![0; 32]],
+		None,
+		64,
+	)
+	.await
+	.unwrap();
+
+	let err = kv::put(
+		&test.db,
+		actor_id,
+		keys(&["b"]),
+		vec![vec![0; 32]],
+		None,
+		64,
+	)
+	.await
+	.unwrap_err();
+	assert!(
+		err.to_string().contains("not enough space left in storage"),
+		"{err}"
+	);
+	assert_eq!(get_one(&test.db, actor_id, "b").await, None);
+
+	// Overwriting with a value of the same size fits
+	kv::put(
+		&test.db,
+		actor_id,
+		keys(&["a"]),
+		vec![vec![1; 32]],
+		None,
+		64,
+	)
+	.await
+	.unwrap();
+	assert_eq!(
+		kv::get_storage_size(&test.db, actor_id).await.unwrap(),
+		// Packed key plus value
+		3 + 32
+	);
+
+	// Concurrent writes can't exceed the quota together, room is left for exactly 4 entries
+	let quota = 3 + 32 + 4 * (4 + 32);
+	let writes = (0..10).map(|i| {
+		let db = test.db.clone();
+		tokio::spawn(async move {
+			kv::put(
+				&db,
+				actor_id,
+				vec![format!("c{i}").into_bytes()],
+				vec![vec![0; 32]],
+				None,
+				quota,
+			)
+			.await
+		})
+	});
+	let succeeded = futures_util::future::join_all(writes)
+		.await
+		.into_iter()
+		.filter(|write| write.as_ref().unwrap().is_ok())
+		.count();
+	assert_eq!(succeeded, 4);
+	assert_eq!(
+		kv::get_storage_size(&test.db, actor_id).await.unwrap(),
+		quota
+	);
+}
+
+#[tokio::test]
+async fn test_change_feed() {
+	let test = setup().await;
+	let actor_id = Id::new_v1(1);
+
+	let (changes, cursor) = kv::changes::list(&test.db, actor_id, b"feed/".to_vec(), None, None)
+		.await
+		.unwrap();
+	assert!(changes.is_empty());
+
+	kv::put(
+		&test.db,
+		actor_id,
+		keys(&["feed/a"]),
+		vec![b"a".to_vec()],
+		None,
+		u64::MAX,
+	)
+	.await
+	.unwrap();
+	kv::put(
+		&test.db,
+		actor_id,
+		keys(&["other/a"]),
+		vec![b"a".to_vec()],
+		None,
+		u64::MAX,
+	)
+	.await
+	.unwrap();
+	kv::transact(
+		&test.db,
+		actor_id,
+		Vec::new(),
+		keys(&["feed/b"]),
+		vec![b"b".to_vec()],
+		keys(&["feed/a"]),
+		u64::MAX,
+	)
+	.await
+	.unwrap();
+
+	let (changes, cursor) =
+		kv::changes::list(&test.db, actor_id, b"feed/".to_vec(), Some(cursor), None)
+			.await
+			.unwrap();
+	assert_eq!(
+		changes
+			.iter()
+			.map(|change| (change.op.clone(), change.key.clone()))
+			.collect::<Vec<_>>(),
+		vec![
+			(rp::KvChangeOp::Put, b"feed/a".to_vec()),
+			(rp::KvChangeOp::Delete, b"feed/a".to_vec()),
+			(rp::KvChangeOp::Put, b"feed/b".to_vec()),
+		]
+	);
+	assert!(
+		changes
+			.windows(2)
+			.all(|w| w[0].versionstamp < w[1].versionstamp)
+	);
+
+	// Resuming from a change returns only the changes after it
+	let (resumed, _) = kv::changes::list(
+		&test.db,
+		actor_id,
+		b"feed/".to_vec(),
+		Some(changes[0].versionstamp.clone()),
+		None,
+	)
+	.await
+	.unwrap();
+	assert_eq!(resumed, changes[1..]);
+
+	let (empty, _) = kv::changes::list(
+		&test.db,
+		actor_id,
+		b"feed/".to_vec(),
+		Some(cursor.clone()),
+		None,
+	)
+	.await
+	.unwrap();
+	assert!(empty.is_empty());
+
+	// Concurrent writers are all seen after the cursor, regardless of which commits first
+	let writes = (0..10).map(|i| {
+		let db = test.db.clone();
+		tokio::spawn(async move {
+			kv::put(
+				&db,
+				actor_id,
+				vec![format!("feed/c{i}").into_bytes()],
+				vec![b"c".to_vec()],
+				None,
+				u64::MAX,
+			)
+			.await
+		})
+	});
+	for write in futures_util::future::join_all(writes).await {
+		write.unwrap().unwrap();
+	}
+
+	let (changes, _) =
+		kv::changes::list(&test.db, actor_id, b"feed/c".to_vec(), Some(cursor), None)
+			.await
+			.unwrap();
+	assert_eq!(changes.len(), 10);
+}
+
+#[tokio::test]
+async fn test_snapshot_round_trip() {
+	let test = setup().await;
+	let actor_id = Id::new_v1(1);
+	let other_actor_id = Id::new_v1(1);
+
+	let snapshot_keys = (0..200).map(|i| format!("key{i:03}")).collect::<Vec<_>>();
+	for chunk in snapshot_keys.chunks(100) {
+		kv::put(
+			&test.db,
+			actor_id,
+			chunk.iter().map(|key| key.as_bytes().to_vec()).collect(),
+			chunk
+				.iter()
+				.map(|key| key.repeat(100).into_bytes())
+				.collect(),
+			None,
+			u64::MAX,
+		)
+		.await
+		.unwrap();
+	}
+
+	let mut snapshot = Vec::new();
+	let exported = kv::snapshot::export(&test.db, actor_id, &mut snapshot)
+		.await
+		.unwrap();
+	assert_eq!(exported, 200);
+
+	let imported = kv::snapshot::import(
+		&test.db,
+		other_actor_id,
+		&mut snapshot.as_slice(),
+		false,
+		None,
+	)
+	.await
+	.unwrap();
+	assert_eq!(imported, 200);
+
+	let original = kv::list(
+		&test.db,
+		actor_id,
+		rp::KvListQuery::KvListAllQuery,
+		false,
+		None,
+		None,
+	)
+	.await
+	.unwrap();
+	let copy = kv::list(
+		&test.db,
+		other_actor_id,
+		rp::KvListQuery::KvListAllQuery,
+		false,
+		None,
+		None,
+	)
+	.await
+	.unwrap();
+	assert_eq!(copy.0, original.0);
+	assert_eq!(copy.1, original.1);
+	assert_eq!(copy.2, original.2);
+	assert_eq!(
+		kv::get_storage_size(&test.db, other_actor_id)
+			.await
+			.unwrap(),
+		kv::get_storage_size(&test.db, actor_id).await.unwrap()
+	);
+
+	// Importing into an actor with keys requires replacing them
+	assert!(
+		kv::snapshot::import(
+			&test.db,
+			other_actor_id,
+			&mut snapshot.as_slice(),
+			false,
+			None
+		)
+		.await
+		.is_err()
+	);
+}
@@ -0,0 +1,146 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use base64::Engine;
+use base64::prelude::BASE64_STANDARD;
+use gas::prelude::*;
+use rivet_api_builder::ApiCtx;
+use rivet_runner_protocol as rp;
+use rivet_util::Id;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+/// Maximum time a request waits for a change.
+const MAX_WAIT_MS: u64 = 25_000;
+
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
+#[serde(deny_unknown_fields)]
+#[into_params(parameter_in = Query)]
+pub struct KvChangesQuery {
+	pub namespace: String,
+	/// Base64 encoded key prefix. Defaults to all keys.
+	pub prefix: Option<String>,
+	/// Base64 encoded cursor from a previous response. Starts from the latest change if omitted.
+	pub after: Option<String>,
+	pub limit: Option<usize>,
+	/// Time to wait for a change if there are none yet, in milliseconds (max 25s).
+	pub wait_ms: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+#[schema(as = ActorsKvChangesResponse)]
+pub struct KvChangesResponse {
+	pub changes: Vec<KvChange>,
+	/// Base64 encoded cursor. Pass as `after` to resume from where this response ended.
+	pub cursor: String,
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+#[schema(as = ActorsKvChange)]
+pub struct KvChange {
+	/// Base64 encoded position in the change log. Changes are ordered by it and it can be passed as
+	/// `after` to resume after this change.
+	pub versionstamp: String,
+	pub op: KvChangeOp,
+	/// Base64 encoded key. Empty for `drop`, which deletes every key.
+	pub key: String,
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+#[schema(as = ActorsKvChangeOp)]
+pub enum KvChangeOp {
+	Put,
+	Delete,
+	Drop,
+}
+
+impl From<rp::KvChangeOp> for KvChangeOp {
+	fn from(value: rp::KvChangeOp) -> Self {
+		match value {
+			rp::KvChangeOp::Put => KvChangeOp::Put,
+			rp::KvChangeOp::Delete => KvChangeOp::Delete,
+			rp::KvChangeOp::Drop => KvChangeOp::Drop,
+		}
+	}
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KvChangesPath {
+	pub actor_id: Id,
+}
+
+#[utoipa::path(
+    get,
+	operation_id = "actors_kv_changes",
+    path = "/actors/{actor_id}/kv/changes",
+    params(
+        ("actor_id" = Id, Path),
+        KvChangesQuery,
+    ),
+    responses(
+        (status = 200, body = KvChangesResponse),
+    ),
+)]
+pub async fn kv_changes(
+	ctx: ApiCtx,
+	path: KvChangesPath,
+	query: KvChangesQuery,
+) -> Result<KvChangesResponse> {
+	let prefix = query
+		.prefix
+		.map(|prefix| BASE64_STANDARD.decode(prefix))
+		.transpose()?
+		.unwrap_or_default();
+	let after = query
+		.after
+		.map(|after| BASE64_STANDARD.decode(after))
+		.transpose()?;
+
+	let namespace = ctx
+		.op(namespace::ops::resolve_for_name_global::Input {
+			name: query.namespace.clone(),
+		})
+		.await?
+		.ok_or_else(|| namespace::errors::Namespace::NotFound.build())?;
+
+	let actors_res = ctx
+		.op(pegboard::ops::actor::get::Input {
+			actor_ids: vec![path.actor_id],
+		})
+		.await?;
+
+	// Verify the actor belongs to the namespace
+	let actor = actors_res
+		.actors
+		.into_iter()
+		.next()
+		.filter(|actor| actor.namespace_id == namespace.namespace_id)
+		.ok_or_else(|| pegboard::errors::Actor::NotFound.build())?;
+
+	let (changes, cursor) = pegboard_actor_kv::changes::watch(
+		&*ctx.udb()?,
+		&ctx.ups()?,
+		actor.actor_id,
+		prefix,
+		after,
+		query.limit,
+		Duration::from_millis(query.wait_ms.unwrap_or_default().min(MAX_WAIT_MS)),
+	)
+	.await?;
+
+	Ok(KvChangesResponse {
+		changes: changes
+			.into_iter()
+			.map(|change| KvChange {
+				versionstamp: BASE64_STANDARD.encode(change.versionstamp),
+				op: change.op.into(),
+				key: BASE64_STANDARD.encode(change.key),
+			})
+			.collect(),
+		cursor: BASE64_STANDARD.encode(cursor),
+	})
+}
@@ -0,0 +1,191 @@
+use anyhow::Result;
+use axum::{
+	body::Body,
+	extract::Path,
+	http::header::CONTENT_TYPE,
+	response::{IntoResponse, Response},
+};
+use futures_util::TryStreamExt;
+use gas::prelude::*;
+use rivet_api_builder::{
+	ApiCtx, ApiError,
+	extract::{Extension, Json, Query},
+};
+use rivet_util::Id;
+use serde::{Deserialize, Serialize};
+use tokio_util::io::{ReaderStream, StreamReader};
+use utoipa::{IntoParams, ToSchema};
+
+const EXPORT_BUFFER_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
+#[serde(deny_unknown_fields)]
+#[into_params(parameter_in = Query)]
+pub struct KvSnapshotExportQuery {
+	pub namespace: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
+#[serde(deny_unknown_fields)]
+#[into_params(parameter_in = Query)]
+pub struct KvSnapshotImportQuery {
+	pub namespace: String,
+	/// Deletes all existing keys of the actor before importing.
+	#[serde(default)]
+	pub replace: bool,
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+#[schema(as = ActorsKvSnapshotImportResponse)]
+pub struct KvSnapshotImportResponse {
+	/// Amount of entries read from the snapshot.
+	pub entries: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KvSnapshotPath {
+	pub actor_id: Id,
+}
+
+/// Streams a snapshot of all keys of the actor. See `pegboard_actor_kv::snapshot` for the format.
+#[utoipa::path(
+    get,
+	operation_id = "actors_kv_snapshot_export",
+    path = "/actors/{actor_id}/kv/snapshot",
+    params(
+        ("actor_id" = Id, Path),
+        KvSnapshotExportQuery,
+    ),
+    responses(
+        (status = 200, content_type = "application/octet-stream", body = Vec<u8>),
+    ),
+)]
+pub async fn kv_snapshot_export(
+	Extension(ctx): Extension<ApiCtx>,
+	Path(path): Path<KvSnapshotPath>,
+	Query(query): Query<KvSnapshotExportQuery>,
+) -> Response {
+	match kv_snapshot_export_inner(ctx, path, query).await {
+		Ok(response) => response,
+		Err(err) => ApiError::from(err).into_response(),
+	}
+}
+
+async fn kv_snapshot_export_inner(
+	ctx: ApiCtx,
+	path: KvSnapshotPath,
+	query: KvSnapshotExportQuery,
+) -> Result<Response> {
+	let namespace = ctx
+		.op(namespace::ops::resolve_for_name_global::Input {
+			name: query.namespace.clone(),
+		})
+		.await?
+		.ok_or_else(|| namespace::errors::Namespace::NotFound.build())?;
+
+	let actors_res = ctx
+		.op(pegboard::ops::actor::get::Input {
+			actor_ids: vec![path.actor_id],
+		})
+		.await?;
+
+	// Verify the actor belongs to the namespace
+	let actor = actors_res
+		.actors
+		.into_iter()
+		.next()
+		.filter(|actor| actor.namespace_id == namespace.namespace_id)
+		.ok_or_else(|| pegboard::errors::Actor::NotFound.build())?;
+
+	let udb = ctx.udb()?;
+	let actor_id = actor.actor_id;
+	let (mut w, r) = tokio::io::duplex(EXPORT_BUFFER_SIZE);
+
+	tokio::spawn(async move {
+		// The snapshot ends without its end record if the export fails, which is rejected on import
+		if let Err(err) = pegboard_actor_kv::snapshot::export(&udb, actor_id, &mut w).await {
+			tracing::error!(?err, ?actor_id, "failed to export kv snapshot");
+		}
+	});
+
+	Ok((
+		[(CONTENT_TYPE, "application/octet-stream")],
+		Body::from_stream(ReaderStream::new(r)),
+	)
+		.into_response())
+}
+
+/// Loads a snapshot into the actor. The request body is streamed, so snapshots of any size can be
+/// imported.
+#[utoipa::path(
+    put,
+	operation_id = "actors_kv_snapshot_import",
+    path = "/actors/{actor_id}/kv/snapshot",
+    params(
+        ("actor_id" = Id, Path),
+        KvSnapshotImportQuery,
+    ),
+    request_body(content = Vec<u8>, content_type = "application/octet-stream"),
+    responses(
+        (status = 200, body = KvSnapshotImportResponse),
+    ),
+)]
+pub async fn kv_snapshot_import(
+	Extension(ctx): Extension<ApiCtx>,
+	Path(path): Path<KvSnapshotPath>,
+	Query(query): Query<KvSnapshotImportQuery>,
+	body: Body,
+) -> Response {
+	match kv_snapshot_import_inner(ctx, path, query, body).await {
+		Ok(response) => Json(response).into_response(),
+		Err(err) => ApiError::from(err).into_response(),
+	}
+}
+
+async fn kv_snapshot_import_inner(
+	ctx: ApiCtx,
+	path: KvSnapshotPath,
+	query: KvSnapshotImportQuery,
+	body: Body,
+) -> Result<KvSnapshotImportResponse> {
+	let namespace = ctx
+		.op(namespace::ops::resolve_for_name_global::Input {
+			name: query.namespace.clone(),
+		})
+		.await?
+		.ok_or_else(|| namespace::errors::Namespace::NotFound.build())?;
+
+	let actors_res = ctx
+		.op(pegboard::ops::actor::get::Input {
+			actor_ids: vec![path.actor_id],
+		})
+		.await?;
+
+	// Verify the actor belongs to the namespace
+	let actor = actors_res
+		.actors
+		.into_iter()
+		.next()
+		.filter(|actor| actor.namespace_id == namespace.namespace_id)
+		.ok_or_else(|| pegboard::errors::Actor::NotFound.build())?;
+
+	let mut r = StreamReader::new(body.into_data_stream().map_err(std::io::Error::other));
+	let entries = pegboard_actor_kv::snapshot::import(
+		&*ctx.udb()?,
+		actor.actor_id,
+		&mut r,
+		query.replace,
+		Some(
+			ctx.config()
+				.pegboard()
+				.actor_kv_storage_quota(&namespace.name),
+		),
+	)
+	.await?;
+
+	pegboard_actor_kv::changes::notify(&ctx.ups()?, actor.actor_id).await?;
+
+	Ok(KvSnapshotImportResponse { entries })
+}
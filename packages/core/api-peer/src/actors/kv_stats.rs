@@ -0,0 +1,78 @@
+use anyhow::Result;
+use gas::prelude::*;
+use rivet_api_builder::ApiCtx;
+use rivet_util::Id;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+#[derive(Debug, Deserialize, Serialize, IntoParams)]
+#[serde(deny_unknown_fields)]
+#[into_params(parameter_in = Query)]
+pub struct KvStatsQuery {
+	pub namespace: String,
+}
+
+#[derive(Deserialize, Serialize, ToSchema)]
+#[serde(deny_unknown_fields)]
+#[schema(as = ActorsKvStatsResponse)]
+pub struct KvStatsResponse {
+	/// Bytes used by keys and values.
+	pub used_bytes: u64,
+	/// Maximum bytes the actor can store.
+	pub quota_bytes: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct KvStatsPath {
+	pub actor_id: Id,
+}
+
+#[utoipa::path(
+    get,
+	operation_id = "actors_kv_stats",
+    path = "/actors/{actor_id}/kv/stats",
+    params(
+        ("actor_id" = Id, Path),
+        KvStatsQuery,
+    ),
+    responses(
+        (status = 200, body = KvStatsResponse),
+    ),
+)]
+pub async fn kv_stats(
+	ctx: ApiCtx,
+	path: KvStatsPath,
+	query: KvStatsQuery,
+) -> Result<KvStatsResponse> {
+	let namespace = ctx
+		.op(namespace::ops::resolve_for_name_global::Input {
+			name: query.namespace.clone(),
+		})
+		.await?
+		.ok_or_else(|| namespace::errors::Namespace::NotFound.build())?;
+
+	let actors_res = ctx
+		.op(pegboard::ops::actor::get::Input {
+			actor_ids: vec![path.actor_id],
+		})
+		.await?;
+
+	// Verify the actor belongs to the namespace
+	let actor = actors_res
+		.actors
+		.into_iter()
+		.next()
+		.filter(|actor| actor.namespace_id == namespace.namespace_id)
+		.ok_or_else(|| pegboard::errors::Actor::NotFound.build())?;
+
+	let used_bytes = pegboard_actor_kv::get_storage_size(&*ctx.udb()?, actor.actor_id).await?;
+
+	Ok(KvStatsResponse {
+		used_bytes,
+		quota_bytes: ctx
+			.config()
+			.pegboard()
+			.actor_kv_storage_quota(&namespace.name),
+	})
+}
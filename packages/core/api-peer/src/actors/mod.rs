@@ -1,4 +1,7 @@
 pub mod create;
 pub mod delete;
+pub mod kv_changes;
+pub mod kv_snapshot;
+pub mod kv_stats;
 pub mod list;
 pub mod list_names;
@@ -25,6 +25,19 @@ pub async fn router(
 			.route("/actors", post(actors::create::create))
 			.route("/actors/{actor_id}", delete(actors::delete::delete))
 			.route("/actors/names", get(actors::list_names::list_names))
+			.route(
+				"/actors/{actor_id}/kv/stats",
+				get(actors::kv_stats::kv_stats),
+			)
+			.route(
+				"/actors/{actor_id}/kv/changes",
+				get(actors::kv_changes::kv_changes),
+			)
+			.route(
+				"/actors/{actor_id}/kv/snapshot",
+				axum::routing::get(actors::kv_snapshot::kv_snapshot_export)
+					.put(actors::kv_snapshot::kv_snapshot_import),
+			)
 			// MARK: Runners
 			.route("/runners", get(runners::list))
 			.route("/runners/names", get(runners::list_names))
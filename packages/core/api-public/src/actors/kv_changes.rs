@@ -0,0 +1,71 @@
+use anyhow::Result;
+use axum::{
+	extract::Path,
+	http::HeaderMap,
+	response::{IntoResponse, Response},
+};
+use rivet_api_builder::{
+	ApiError,
+	extract::{Extension, Json, Query},
+};
+use rivet_api_peer::actors::kv_changes::*;
+use rivet_api_util::request_remote_datacenter_raw;
+use rivet_util::Id;
+
+use crate::ctx::ApiCtx;
+
+/// ## Datacenter Round Trips
+///
+/// 2 round trip:
+/// - GET /actors/{}/kv/changes
+/// - [api-peer] namespace::ops::resolve_for_name_global
+#[utoipa::path(
+    get,
+	operation_id = "actors_kv_changes",
+    path = "/actors/{actor_id}/kv/changes",
+    params(
+        ("actor_id" = Id, Path),
+        KvChangesQuery,
+    ),
+    responses(
+        (status = 200, body = KvChangesResponse),
+    ),
+	security(("bearer_auth" = [])),
+)]
+pub async fn kv_changes(
+	Extension(ctx): Extension<ApiCtx>,
+	headers: HeaderMap,
+	Path(path): Path<KvChangesPath>,
+	Query(query): Query<KvChangesQuery>,
+) -> Response {
+	match kv_changes_inner(ctx, headers, path, query).await {
+		Ok(response) => response,
+		Err(err) => ApiError::from(err).into_response(),
+	}
+}
+
+async fn kv_changes_inner(
+	ctx: ApiCtx,
+	headers: HeaderMap,
+	path: KvChangesPath,
+	query: KvChangesQuery,
+) -> Result<Response> {
+	ctx.auth().await?;
+
+	if path.actor_id.label() == ctx.config().dc_label() {
+		let res = rivet_api_peer::actors::kv_changes::kv_changes(ctx.into(), path, query).await?;
+
+		Ok(Json(res).into_response())
+	} else {
+		request_remote_datacenter_raw(
+			&ctx,
+			path.actor_id.label(),
+			&format!("/actors/{}/kv/changes", path.actor_id),
+			axum::http::Method::GET,
+			headers,
+			Some(&query),
+			Option::<&()>::None,
+		)
+		.await
+	}
+}
@@ -0,0 +1,71 @@
+use anyhow::Result;
+use axum::{
+	extract::Path,
+	http::HeaderMap,
+	response::{IntoResponse, Response},
+};
+use rivet_api_builder::{
+	ApiError,
+	extract::{Extension, Json, Query},
+};
+use rivet_api_peer::actors::kv_stats::*;
+use rivet_api_util::request_remote_datacenter_raw;
+use rivet_util::Id;
+
+use crate::ctx::ApiCtx;
+
+/// ## Datacenter Round Trips
+///
+/// 2 round trip:
+/// - GET /actors/{}/kv/stats
+/// - [api-peer] namespace::ops::resolve_for_name_global
+#[utoipa::path(
+    get,
+	operation_id = "actors_kv_stats",
+    path = "/actors/{actor_id}/kv/stats",
+    params(
+        ("actor_id" = Id, Path),
+        KvStatsQuery,
+    ),
+    responses(
+        (status = 200, body = KvStatsResponse),
+    ),
+	security(("bearer_auth" = [])),
+)]
+pub async fn kv_stats(
+	Extension(ctx): Extension<ApiCtx>,
+	headers: HeaderMap,
+	Path(path): Path<KvStatsPath>,
+	Query(query): Query<KvStatsQuery>,
+) -> Response {
+	match kv_stats_inner(ctx, headers, path, query).await {
+		Ok(response) => response,
+		Err(err) => ApiError::from(err).into_response(),
+	}
+}
+
+async fn kv_stats_inner(
+	ctx: ApiCtx,
+	headers: HeaderMap,
+	path: KvStatsPath,
+	query: KvStatsQuery,
+) -> Result<Response> {
+	ctx.auth().await?;
+
+	if path.actor_id.label() == ctx.config().dc_label() {
+		let res = rivet_api_peer::actors::kv_stats::kv_stats(ctx.into(), path, query).await?;
+
+		Ok(Json(res).into_response())
+	} else {
+		request_remote_datacenter_raw(
+			&ctx,
+			path.actor_id.label(),
+			&format!("/actors/{}/kv/stats", path.actor_id),
+			axum::http::Method::GET,
+			headers,
+			Some(&query),
+			Option::<&()>::None,
+		)
+		.await
+	}
+}
@@ -1,6 +1,8 @@
 pub mod create;
 pub mod delete;
 pub mod get_or_create;
+pub mod kv_changes;
+pub mod kv_stats;
 pub mod list;
 pub mod list_names;
 pub mod utils;
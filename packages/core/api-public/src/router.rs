@@ -18,6 +18,8 @@ use crate::{actors, ctx, datacenters, namespaces, runner_configs, runners, ui};
 		actors::delete::delete,
 		actors::list_names::list_names,
 		actors::get_or_create::get_or_create,
+		actors::kv_stats::kv_stats,
+		actors::kv_changes::kv_changes,
 		runners::list,
 		runners::list_names,
 		namespaces::list,
@@ -74,6 +76,14 @@ pub async fn router(
 				"/actors/names",
 				axum::routing::get(actors::list_names::list_names),
 			)
+			.route(
+				"/actors/{actor_id}/kv/stats",
+				axum::routing::get(actors::kv_stats::kv_stats),
+			)
+			.route(
+				"/actors/{actor_id}/kv/changes",
+				axum::routing::get(actors::kv_changes::kv_changes),
+			)
 			// MARK: Runners
 			.route("/runners", axum::routing::get(runners::list))
 			.route("/runners/names", axum::routing::get(runners::list_names))
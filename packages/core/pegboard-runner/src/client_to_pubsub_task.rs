@@ -8,6 +8,7 @@ use pegboard_actor_kv as kv;
 use rivet_guard_core::websocket_handle::WebSocketReceiver;
 use rivet_runner_protocol::{self as protocol, PROTOCOL_VERSION, versioned};
 use std::sync::{Arc, atomic::Ordering};
+use std::time::Duration;
 use universalpubsub::PublishOpts;
 use vbare::OwnedVersionedData;
 
@@ -16,6 +17,9 @@ use crate::{
 	utils::{self},
 };
 
+/// Maximum time a KV changes request waits for a change. Runners time out KV requests after 30s.
+const MAX_KV_CHANGES_WAIT_MS: u64 = 25_000;
+
 #[tracing::instrument(skip_all, fields(runner_id=?conn.runner_id, workflow_id=?conn.workflow_id, protocol_version=%conn.protocol_version))]
 pub async fn task(ctx: StandaloneCtx, conn: Arc<Conn>, ws_rx: WebSocketReceiver) {
 	match task_inner(ctx, conn, ws_rx).await {
@@ -42,20 +46,17 @@ async fn task_inner(
 				);
 
 				// Parse message
-				let msg =
-					match versioned::ToServer::deserialize_version(&data, conn.protocol_version)
-						.and_then(|x| x.into_latest())
-					{
-						Result::Ok(x) => x,
-						Err(err) => {
-							tracing::warn!(
-								?err,
-								data_len = data.len(),
-								"failed to deserialize message"
-							);
-							continue;
-						}
-					};
+				let msg = match versioned::ToServer::deserialize(&data, conn.protocol_version) {
+					Result::Ok(x) => x,
+					Err(err) => {
+						tracing::warn!(
+							?err,
+							data_len = data.len(),
+							"failed to deserialize message"
+						);
+						continue;
+					}
+				};
 
 				handle_message(&ctx, &conn, msg)
 					.await
@@ -168,6 +169,9 @@ async fn handle_message(
 				return Ok(());
 			}
 
+			// Only set once a write succeeded, so watchers aren't woken up for failed writes
+			let mut changed = false;
+
 			// TODO: Add queue and bg thread for processing kv ops
 			// Run kv operation
 			match req.data {
@@ -215,6 +219,7 @@ async fn handle_message(
 							.map(TryInto::try_into)
 							.transpose()
 							.context("KV list limit value overflow")?,
+						body.cursor,
 					)
 					.await;
 
@@ -222,12 +227,13 @@ async fn handle_message(
 						protocol::ToClient::ToClientKvResponse(protocol::ToClientKvResponse {
 							request_id: req.request_id,
 							data: match res {
-								Ok((keys, values, metadata)) => {
+								Ok((keys, values, metadata, cursor)) => {
 									protocol::KvResponseData::KvListResponse(
 										protocol::KvListResponse {
 											keys,
 											values,
 											metadata,
+											cursor,
 										},
 									)
 								}
@@ -250,7 +256,17 @@ async fn handle_message(
 						.context("failed to send KV list response to client")?;
 				}
 				protocol::KvRequestData::KvPutRequest(body) => {
-					let res = kv::put(&*ctx.udb()?, actor_id, body.keys, body.values).await;
+					let res = kv::put(
+						&*ctx.udb()?,
+						actor_id,
+						body.keys,
+						body.values,
+						body.ttl,
+						conn.kv_storage_quota,
+					)
+					.await;
+
+					changed = res.is_ok();
 
 					let res_msg = versioned::ToClient::latest(
 						protocol::ToClient::ToClientKvResponse(protocol::ToClientKvResponse {
@@ -280,6 +296,8 @@ async fn handle_message(
 				protocol::KvRequestData::KvDeleteRequest(body) => {
 					let res = kv::delete(&*ctx.udb()?, actor_id, body.keys).await;
 
+					changed = res.is_ok();
+
 					let res_msg = versioned::ToClient::latest(
 						protocol::ToClient::ToClientKvResponse(protocol::ToClientKvResponse {
 							request_id: req.request_id,
@@ -306,6 +324,8 @@ async fn handle_message(
 				protocol::KvRequestData::KvDropRequest => {
 					let res = kv::delete_all(&*ctx.udb()?, actor_id).await;
 
+					changed = res.is_ok();
+
 					let res_msg = versioned::ToClient::latest(
 						protocol::ToClient::ToClientKvResponse(protocol::ToClientKvResponse {
 							request_id: req.request_id,
@@ -329,6 +349,169 @@ async fn handle_message(
 						.await
 						.context("failed to send KV drop response to client")?;
 				}
+				protocol::KvRequestData::KvTransactRequest(body) => {
+					let res = kv::transact(
+						&*ctx.udb()?,
+						actor_id,
+						body.conditions,
+						body.put_keys,
+						body.put_values,
+						body.delete_keys,
+						conn.kv_storage_quota,
+					)
+					.await;
+
+					changed = matches!(res, Ok(true));
+
+					let res_msg = versioned::ToClient::latest(
+						protocol::ToClient::ToClientKvResponse(protocol::ToClientKvResponse {
+							request_id: req.request_id,
+							data: match res {
+								Ok(committed) => protocol::KvResponseData::KvTransactResponse(
+									protocol::KvTransactResponse { committed },
+								),
+								Err(err) => protocol::KvResponseData::KvErrorResponse(
+									protocol::KvErrorResponse {
+										// TODO: Don't return actual error?
+										message: err.to_string(),
+									},
+								),
+							},
+						}),
+					);
+
+					let res_msg_serialized = res_msg
+						.serialize(conn.protocol_version)
+						.context("failed to serialize KV transact response")?;
+					conn.ws_handle
+						.send(Message::Binary(res_msg_serialized.into()))
+						.await
+						.context("failed to send KV transact response to client")?;
+				}
+				protocol::KvRequestData::KvAtomicRequest(body) => {
+					let res = kv::atomic(
+						&*ctx.udb()?,
+						actor_id,
+						body.op,
+						body.keys,
+						body.params,
+						conn.kv_storage_quota,
+					)
+					.await;
+
+					changed = res.is_ok();
+
+					let res_msg = versioned::ToClient::latest(
+						protocol::ToClient::ToClientKvResponse(protocol::ToClientKvResponse {
+							request_id: req.request_id,
+							data: match res {
+								Ok(()) => protocol::KvResponseData::KvAtomicResponse,
+								Err(err) => protocol::KvResponseData::KvErrorResponse(
+									protocol::KvErrorResponse {
+										// TODO: Don't return actual error?
+										message: err.to_string(),
+									},
+								),
+							},
+						}),
+					);
+
+					let res_msg_serialized = res_msg
+						.serialize(conn.protocol_version)
+						.context("failed to serialize KV atomic response")?;
+					conn.ws_handle
+						.send(Message::Binary(res_msg_serialized.into()))
+						.await
+						.context("failed to send KV atomic response to client")?;
+				}
+				protocol::KvRequestData::KvCompareAndSwapRequest(body) => {
+					let res = kv::compare_and_swap(
+						&*ctx.udb()?,
+						actor_id,
+						body.key,
+						body.expected,
+						body.value,
+						conn.kv_storage_quota,
+					)
+					.await;
+
+					changed = matches!(res, Ok((true, _)));
+
+					let res_msg = versioned::ToClient::latest(
+						protocol::ToClient::ToClientKvResponse(protocol::ToClientKvResponse {
+							request_id: req.request_id,
+							data: match res {
+								Ok((swapped, current)) => {
+									protocol::KvResponseData::KvCompareAndSwapResponse(
+										protocol::KvCompareAndSwapResponse { swapped, current },
+									)
+								}
+								Err(err) => protocol::KvResponseData::KvErrorResponse(
+									protocol::KvErrorResponse {
+										// TODO: Don't return actual error?
+										message: err.to_string(),
+									},
+								),
+							},
+						}),
+					);
+
+					let res_msg_serialized = res_msg
+						.serialize(conn.protocol_version)
+						.context("failed to serialize KV compare and swap response")?;
+					conn.ws_handle
+						.send(Message::Binary(res_msg_serialized.into()))
+						.await
+						.context("failed to send KV compare and swap response to client")?;
+				}
+				protocol::KvRequestData::KvStatsRequest => {
+					let res = kv::get_storage_size(&*ctx.udb()?, actor_id).await;
+
+					let res_msg = versioned::ToClient::latest(
+						protocol::ToClient::ToClientKvResponse(protocol::ToClientKvResponse {
+							request_id: req.request_id,
+							data: match res {
+								Ok(used_bytes) => protocol::KvResponseData::KvStatsResponse(
+									protocol::KvStatsResponse {
+										used_bytes,
+										quota_bytes: conn.kv_storage_quota,
+									},
+								),
+								Err(err) => protocol::KvResponseData::KvErrorResponse(
+									protocol::KvErrorResponse {
+										// TODO: Don't return actual error?
+										message: err.to_string(),
+									},
+								),
+							},
+						}),
+					);
+
+					let res_msg_serialized = res_msg
+						.serialize(conn.protocol_version)
+						.context("failed to serialize KV stats response")?;
+					conn.ws_handle
+						.send(Message::Binary(res_msg_serialized.into()))
+						.await
+						.context("failed to send KV stats response to client")?;
+				}
+				protocol::KvRequestData::KvChangesRequest(body) => {
+					// Changes requests can wait for a long time so they are handled in the background
+					let ctx = ctx.clone();
+					let conn = conn.clone();
+					tokio::spawn(async move {
+						if let Err(err) =
+							handle_kv_changes(&ctx, &conn, req.request_id, actor_id, body).await
+						{
+							tracing::error!(?err, "failed to handle KV changes request");
+						}
+					});
+				}
+			}
+
+			// Wake up change feed watchers
+			if changed && let Err(err) = kv::changes::notify(&ctx.ups()?, actor_id).await {
+				tracing::warn!(?err, ?actor_id, "failed to notify KV change watchers");
 			}
 		}
 		protocol::ToServer::ToServerTunnelMessage(tunnel_msg) => {
@@ -357,6 +540,61 @@ async fn handle_message(
 	Ok(())
 }
 
+#[tracing::instrument(skip_all)]
+async fn handle_kv_changes(
+	ctx: &StandaloneCtx,
+	conn: &Arc<Conn>,
+	request_id: u32,
+	actor_id: Id,
+	body: protocol::KvChangesRequest,
+) -> Result<()> {
+	// Every error is sent back to the client since nothing else waits on this request
+	let res = async {
+		kv::changes::watch(
+			&*ctx.udb()?,
+			&ctx.ups()?,
+			actor_id,
+			body.prefix,
+			body.after,
+			body.limit
+				.map(TryInto::try_into)
+				.transpose()
+				.context("KV changes limit value overflow")?,
+			Duration::from_millis(body.wait_ms.unwrap_or_default().min(MAX_KV_CHANGES_WAIT_MS)),
+		)
+		.await
+	}
+	.await;
+
+	let res_msg = versioned::ToClient::latest(protocol::ToClient::ToClientKvResponse(
+		protocol::ToClientKvResponse {
+			request_id,
+			data: match res {
+				Ok((changes, cursor)) => {
+					protocol::KvResponseData::KvChangesResponse(protocol::KvChangesResponse {
+						changes,
+						cursor,
+					})
+				}
+				Err(err) => protocol::KvResponseData::KvErrorResponse(protocol::KvErrorResponse {
+					// TODO: Don't return actual error?
+					message: err.to_string(),
+				}),
+			},
+		},
+	));
+
+	let res_msg_serialized = res_msg
+		.serialize(conn.protocol_version)
+		.context("failed to serialize KV changes response")?;
+	conn.ws_handle
+		.send(Message::Binary(res_msg_serialized.into()))
+		.await
+		.context("failed to send KV changes response to client")?;
+
+	Ok(())
+}
+
 #[tracing::instrument(skip_all)]
 async fn handle_tunnel_message(
 	ctx: &StandaloneCtx,
@@ -33,6 +33,9 @@ pub struct Conn {
 
 	pub last_rtt: AtomicU32,
 
+	/// KV storage quota in bytes for each actor, based on the runner's namespace.
+	pub kv_storage_quota: u64,
+
 	/// Active HTTP & WebSocket requests. They are separate but use the same mechanism to
 	/// maintain state.
 	pub tunnel_active_requests: Mutex<HashMap<RequestId, TunnelActiveRequest>>,
@@ -186,6 +189,10 @@ pub async fn init_conn(
 		protocol_version,
 		ws_handle,
 		last_rtt: AtomicU32::new(0),
+		kv_storage_quota: ctx
+			.config()
+			.pegboard()
+			.actor_kv_storage_quota(&namespace.name),
 		tunnel_active_requests: Mutex::new(HashMap::new()),
 	}))
 }
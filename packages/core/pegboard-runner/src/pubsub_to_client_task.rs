@@ -47,14 +47,14 @@ async fn task_inner(ctx: StandaloneCtx, conn: Arc<Conn>, mut sub: Subscriber) ->
 		}
 
 		// Forward raw message to WebSocket
-		let serialized_msg =
-			match versioned::ToClient::latest(msg).serialize_version(conn.protocol_version) {
-				Result::Ok(x) => x,
-				Err(err) => {
-					tracing::error!(?err, "failed to serialize tunnel message");
-					continue;
-				}
-			};
+		let serialized_msg = match versioned::ToClient::latest(msg).serialize(conn.protocol_version)
+		{
+			Result::Ok(x) => x,
+			Err(err) => {
+				tracing::error!(?err, "failed to serialize tunnel message");
+				continue;
+			}
+		};
 		let ws_msg = WsMessage::Binary(serialized_msg.into());
 		if let Err(e) = conn.ws_handle.send(ws_msg).await {
 			tracing::error!(?e, "failed to send message to WebSocket");
@@ -1,11 +1,18 @@
 use anyhow::Result;
 use gas::prelude::*;
 
+pub mod retention;
+
+/// Every workflow run by the engine.
+pub fn registry() -> WorkflowResult<Registry> {
+	pegboard::registry()?
+		.merge(namespace::registry()?)?
+		.merge(epoxy::registry()?)
+}
+
 #[tracing::instrument(skip_all)]
 pub async fn start(config: rivet_config::Config, pools: rivet_pools::Pools) -> Result<()> {
-	let reg = pegboard::registry()?
-		.merge(namespace::registry()?)?
-		.merge(epoxy::registry()?)?;
+	let reg = registry()?;
 
 	let db = db::DatabaseKv::from_pools(pools.clone()).await?;
 	let worker = Worker::new(reg.handle(), db, config, pools);
@@ -0,0 +1,31 @@
+use anyhow::Result;
+use gas::{db::debug::DatabaseDebug, prelude::*};
+
+/// Purges workflows that completed or were silenced longer ago than their configured retention.
+#[tracing::instrument(skip_all)]
+pub async fn start(config: rivet_config::Config, pools: rivet_pools::Pools) -> Result<()> {
+	let workflow_config = config.workflow();
+	if workflow_config.retention.is_empty() {
+		return Ok(());
+	}
+
+	let db = db::DatabaseKv::from_pools(pools).await?;
+	let archive_path = workflow_config.archive_path();
+	let now = util::timestamp::now();
+
+	for (workflow_name, retention) in &workflow_config.retention {
+		let before_ts = now - retention.duration().as_millis() as i64;
+
+		let purged = db
+			.purge_expired_workflows(
+				workflow_name,
+				before_ts,
+				retention.archive.then_some(archive_path.as_path()),
+			)
+			.await?;
+
+		tracing::debug!(%workflow_name, ?purged, "purged expired workflows");
+	}
+
+	Ok(())
+}
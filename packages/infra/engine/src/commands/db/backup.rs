@@ -0,0 +1,105 @@
+use anyhow::*;
+use chrono::{Local, TimeZone};
+use clap::Parser;
+use rivet_config::config;
+use tabled::Tabled;
+use universaldb::driver::rocksdb::backup;
+
+#[derive(Parser)]
+pub enum SubCommand {
+	/// Lists all backups of the file system database
+	#[clap(alias = "ls")]
+	List,
+	/// Replaces the file system database with a backup. The engine must be stopped.
+	Restore {
+		/// Backup to restore. Defaults to the latest backup.
+		#[clap(index = 1)]
+		backup_id: Option<u32>,
+		/// Skips the confirmation prompt.
+		#[clap(short = 'y', long)]
+		yes: bool,
+	},
+}
+
+#[derive(Tabled)]
+struct BackupTableRow {
+	pub id: u32,
+	pub created_at: String,
+	pub size: String,
+	pub files: u32,
+}
+
+impl SubCommand {
+	pub async fn execute(self, config: rivet_config::Config) -> Result<()> {
+		let config::Database::FileSystem(fs) = config.database() else {
+			bail!("backups are only supported by the file system database");
+		};
+		let backup_path = fs.backup.clone().unwrap_or_default().path(&fs.path);
+		let db_path = fs.path.clone();
+
+		match self {
+			Self::List => {
+				let backups =
+					tokio::task::spawn_blocking(move || backup::list_backups(&backup_path))
+						.await??;
+
+				if backups.is_empty() {
+					println!("no backups found");
+					return Ok(());
+				}
+
+				let rows = backups
+					.into_iter()
+					.map(|backup| {
+						let created_at = Local
+							.timestamp_opt(backup.timestamp, 0)
+							.single()
+							.context("invalid ts")?;
+
+						Ok(BackupTableRow {
+							id: backup.id,
+							created_at: created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+							size: crate::util::format::bytes(backup.size),
+							files: backup.num_files,
+						})
+					})
+					.collect::<Result<Vec<_>>>()?;
+
+				rivet_term::format::table(rows);
+
+				Ok(())
+			}
+			Self::Restore { backup_id, yes } => {
+				if !yes {
+					let backup = match backup_id {
+						Some(backup_id) => format!("backup {backup_id}"),
+						None => "the latest backup".to_string(),
+					};
+					let term = rivet_term::terminal();
+					let response = rivet_term::prompt::PromptBuilder::default()
+						.message(format!(
+							"Replace the database at {} with {backup}?",
+							db_path.display()
+						))
+						.build()
+						.expect("failed to build prompt")
+						.bool(&term)
+						.await
+						.expect("failed to show prompt");
+					if !response {
+						return Ok(());
+					}
+				}
+
+				tokio::task::spawn_blocking(move || {
+					backup::restore_backup(&backup_path, &db_path, backup_id)
+				})
+				.await??;
+
+				println!("restored backup");
+
+				Ok(())
+			}
+		}
+	}
+}
@@ -0,0 +1,518 @@
+use std::{
+	collections::BTreeMap,
+	path::{Path, PathBuf},
+	result::Result::{Err, Ok},
+};
+
+use anyhow::*;
+use clap::Parser;
+use futures_util::TryStreamExt;
+use rivet_pools::UdbPool;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use universaldb::{options::StreamingMode, utils::IsolationLevel::*};
+
+use crate::util::udb::SimpleTuple;
+
+const DEFAULT_BATCH_SIZE: usize = 1000;
+/// Soft cap on the size of a single batch, well below the transaction size limit of every driver.
+const MAX_BATCH_BYTES: usize = 1024 * 1024;
+/// Min amount of keys per checksummed chunk. Chunks start at batch boundaries so they are usually
+/// slightly larger.
+const CHUNK_SIZE: u64 = 100_000;
+/// How often progress is printed, in batches.
+const PROGRESS_INTERVAL: u64 = 100;
+/// Keys starting with `0xff` are reserved by FoundationDB.
+const KEYSPACE_END: &[u8] = &[0xff];
+/// Max amount of times chunks that changed while copying are copied again before giving up.
+const MAX_CATCH_UP_PASSES: usize = 5;
+
+/// Copies every key from the configured database to another database, for example to move off of
+/// a single node RocksDB deployment onto Postgres.
+///
+/// Keys are copied in batches, each read from a single source transaction and written in a single
+/// destination transaction. Keys and values are copied byte for byte, so versionstamps are kept.
+///
+/// The source database stays online while copying. The keyspace is split into chunks of roughly
+/// `CHUNK_SIZE` keys while copying. Afterwards the checksums of every chunk are compared and chunks
+/// that changed in the meantime are copied again, until the checksums match.
+/// The migration fails if the source keeps changing, in which case stop all writes to it and run
+/// the migration again.
+///
+/// Progress is saved to the state file after every batch, so an interrupted migration resumes
+/// where it left off.
+#[derive(Parser)]
+pub struct Opts {
+	/// Path to the config file or directory of config files of the destination database.
+	#[clap(long, required = true)]
+	to: Vec<PathBuf>,
+	/// File used to track progress.
+	#[clap(long, default_value = "udb-migration.json")]
+	state: PathBuf,
+	/// Starts over instead of resuming from the state file. Overwrites all data in the destination
+	/// database.
+	#[clap(long)]
+	restart: bool,
+	/// Max amount of keys copied per transaction.
+	#[clap(long, default_value_t = DEFAULT_BATCH_SIZE)]
+	batch_size: usize,
+	/// Skips comparing the checksums of every chunk after copying, which also skips copying chunks
+	/// that changed while copying again.
+	#[clap(long)]
+	skip_verify: bool,
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct MigrationState {
+	/// Hex encoded key to resume copying from.
+	cursor: String,
+	keys: u64,
+	bytes: u64,
+	done: bool,
+	/// Hex encoded start keys of every chunk after the first, which starts at the beginning of the
+	/// keyspace. Each chunk ends where the next one starts.
+	#[serde(default)]
+	chunks: Vec<String>,
+	/// Amount of keys copied since the start of the last chunk.
+	#[serde(default)]
+	chunk_keys: u64,
+}
+
+impl MigrationState {
+	/// Begin and end of every chunk.
+	fn chunk_ranges(&self) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+		let mut bounds = vec![Vec::new()];
+		for chunk in &self.chunks {
+			bounds.push(hex::decode(chunk)?);
+		}
+		bounds.push(KEYSPACE_END.to_vec());
+
+		Ok(bounds
+			.windows(2)
+			.map(|bounds| (bounds[0].clone(), bounds[1].clone()))
+			.collect())
+	}
+}
+
+struct Batch {
+	entries: Vec<(Vec<u8>, Vec<u8>)>,
+	bytes: usize,
+	/// Exclusive end of the range covered by this batch.
+	end: Vec<u8>,
+}
+
+impl Opts {
+	pub async fn execute(self, config: rivet_config::Config) -> Result<()> {
+		ensure!(self.batch_size > 0, "batch size must be greater than 0");
+
+		let src = rivet_pools::db::udb::setup(config)
+			.await?
+			.context("source database not configured")?;
+		let dst_config = rivet_config::Config::load(&self.to).await?;
+		let dst = rivet_pools::db::udb::setup(dst_config)
+			.await?
+			.context("destination database not configured")?;
+
+		let mut state = if self.restart {
+			None
+		} else {
+			read_state(&self.state).await?
+		};
+
+		if let Some(state) = &state {
+			println!(
+				"resuming migration from {} ({} keys copied)",
+				display_key(&hex::decode(&state.cursor)?),
+				state.keys
+			);
+		} else if !self.restart {
+			// Batches clear their range in the destination, this prevents accidentally wiping an existing
+			// database
+			let dst_batch = read_batch(&dst, Vec::new(), KEYSPACE_END.to_vec(), 1).await?;
+			ensure!(
+				dst_batch.entries.is_empty(),
+				"destination database is not empty, pass --restart to overwrite it"
+			);
+		}
+
+		let state = state.get_or_insert_with(MigrationState::default);
+		if !state.done {
+			copy(&src, &dst, state, &self.state, self.batch_size, CHUNK_SIZE).await?;
+		}
+
+		if !self.skip_verify {
+			catch_up(&src, &dst, &state.chunk_ranges()?, self.batch_size).await?;
+		}
+
+		Ok(())
+	}
+}
+
+async fn copy(
+	src: &UdbPool,
+	dst: &UdbPool,
+	state: &mut MigrationState,
+	state_path: &Path,
+	batch_size: usize,
+	chunk_size: u64,
+) -> Result<()> {
+	let mut cursor = hex::decode(&state.cursor)?;
+	let mut batches = 0u64;
+
+	loop {
+		let batch = read_batch(src, cursor.clone(), KEYSPACE_END.to_vec(), batch_size).await?;
+		let keys = batch.entries.len();
+		let last_key = batch.entries.last().map(|(key, _)| key.clone());
+
+		write_batch(dst, &cursor, &batch).await?;
+
+		// Start a new chunk at this batch once the current one is large enough
+		if state.chunk_keys >= chunk_size {
+			state.chunks.push(hex::encode(&cursor));
+			state.chunk_keys = 0;
+		}
+		state.chunk_keys += keys as u64;
+
+		state.keys += keys as u64;
+		state.bytes += batch.bytes as u64;
+		state.done = batch.end == KEYSPACE_END;
+		state.cursor = hex::encode(&batch.end);
+		write_state(state_path, state).await?;
+
+		batches += 1;
+		if state.done || batches % PROGRESS_INTERVAL == 0 {
+			println!(
+				"copied {} keys ({} bytes), last key {}",
+				state.keys,
+				state.bytes,
+				last_key.as_deref().map(display_key).unwrap_or_default()
+			);
+		}
+
+		if state.done {
+			break;
+		}
+
+		cursor = batch.end;
+	}
+
+	println!("copy complete");
+
+	Ok(())
+}
+
+/// Verifies both databases and copies chunks that differ again until all checksums match.
+async fn catch_up(
+	src: &UdbPool,
+	dst: &UdbPool,
+	chunks: &[(Vec<u8>, Vec<u8>)],
+	batch_size: usize,
+) -> Result<()> {
+	for pass in 0.. {
+		let mismatches = verify(src, dst, chunks, batch_size).await?;
+		if mismatches.is_empty() {
+			break;
+		}
+
+		ensure!(
+			pass < MAX_CATCH_UP_PASSES,
+			"{} chunks still differ after {MAX_CATCH_UP_PASSES} catch up passes, stop writes to the source database and run the migration again",
+			mismatches.len()
+		);
+
+		println!("copying {} changed chunks again", mismatches.len());
+
+		for (begin, end) in mismatches {
+			let mut cursor = begin;
+
+			loop {
+				let batch = read_batch(src, cursor.clone(), end.clone(), batch_size).await?;
+				write_batch(dst, &cursor, &batch).await?;
+
+				if batch.end == end {
+					break;
+				}
+				cursor = batch.end;
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Replaces the range covered by the batch in the destination with the entries of the batch.
+async fn write_batch(dst: &UdbPool, begin: &[u8], batch: &Batch) -> Result<()> {
+	dst.run(|tx| async move {
+		// Removes keys deleted from the source since the range was last copied
+		tx.clear_range(begin, &batch.end);
+
+		for (key, value) in &batch.entries {
+			tx.set(key, value);
+		}
+
+		Ok(())
+	})
+	.await
+	.context("failed to write batch to destination")
+}
+
+/// Reads a batch of keys between `begin` and `end` in a single transaction.
+async fn read_batch(
+	db: &UdbPool,
+	begin: Vec<u8>,
+	end: Vec<u8>,
+	batch_size: usize,
+) -> Result<Batch> {
+	db.run(|tx| {
+		let begin = begin.clone();
+		let end = end.clone();
+
+		async move {
+			let mut stream = tx.get_ranges_keyvalues(
+				universaldb::RangeOption {
+					mode: StreamingMode::WantAll,
+					limit: Some(batch_size),
+					..(begin, end.clone()).into()
+				},
+				Snapshot,
+			);
+
+			let mut entries = Vec::new();
+			let mut bytes = 0;
+			let mut more = false;
+
+			while let Some(entry) = stream.try_next().await? {
+				bytes += entry.key().len() + entry.value().len();
+				entries.push(entry.into_parts());
+
+				if entries.len() >= batch_size || bytes >= MAX_BATCH_BYTES {
+					more = true;
+					break;
+				}
+			}
+
+			// The batch covers everything up to and including its last key
+			let end = match entries.last() {
+				Some((key, _)) if more => [key.as_slice(), &[0]].concat(),
+				_ => end,
+			};
+
+			Ok(Batch {
+				entries,
+				bytes,
+				end,
+			})
+		}
+	})
+	.await
+}
+
+/// Compares a checksum of every chunk in both databases. Returns the ranges of the chunks that
+/// differ.
+async fn verify(
+	src: &UdbPool,
+	dst: &UdbPool,
+	chunks: &[(Vec<u8>, Vec<u8>)],
+	batch_size: usize,
+) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+	println!("verifying");
+
+	let (src_checksums, dst_checksums) = tokio::try_join!(
+		checksums(src, chunks, batch_size),
+		checksums(dst, chunks, batch_size)
+	)?;
+
+	let mut mismatches = Vec::new();
+
+	for (i, (begin, end)) in chunks.iter().enumerate() {
+		let src_checksum = src_checksums.get(&i);
+		let dst_checksum = dst_checksums.get(&i);
+
+		if src_checksum != dst_checksum {
+			mismatches.push((begin.clone(), end.clone()));
+			println!(
+				"{} to {}: source {}, destination {}",
+				display_key(begin),
+				display_key(end),
+				display_checksum(src_checksum),
+				display_checksum(dst_checksum)
+			);
+		}
+	}
+
+	if mismatches.is_empty() {
+		println!("verified {} chunks", chunks.len());
+	} else {
+		println!("{} of {} chunks differ", mismatches.len(), chunks.len());
+	}
+
+	Ok(mismatches)
+}
+
+/// Returns the amount of keys and a hash of all keys and values of every non-empty chunk, by chunk
+/// index.
+async fn checksums(
+	db: &UdbPool,
+	chunks: &[(Vec<u8>, Vec<u8>)],
+	batch_size: usize,
+) -> Result<BTreeMap<usize, (u64, [u8; 32])>> {
+	let mut hashers = BTreeMap::<usize, (u64, Sha256)>::new();
+	let mut cursor = Vec::new();
+
+	loop {
+		let batch = read_batch(db, cursor, KEYSPACE_END.to_vec(), batch_size).await?;
+
+		for (key, value) in &batch.entries {
+			// Chunks are sorted and cover the entire keyspace
+			let chunk = chunks
+				.partition_point(|(begin, _)| begin.as_slice() <= key.as_slice())
+				.saturating_sub(1);
+			let (keys, hasher) = hashers.entry(chunk).or_default();
+
+			*keys += 1;
+			hasher.update((key.len() as u32).to_le_bytes());
+			hasher.update(key);
+			hasher.update((value.len() as u32).to_le_bytes());
+			hasher.update(value);
+		}
+
+		if batch.end == KEYSPACE_END {
+			break;
+		}
+		cursor = batch.end;
+	}
+
+	Ok(hashers
+		.into_iter()
+		.map(|(chunk, (keys, hasher))| (chunk, (keys, hasher.finalize().into())))
+		.collect())
+}
+
+async fn read_state(path: &Path) -> Result<Option<MigrationState>> {
+	match tokio::fs::read(path).await {
+		Ok(buf) => Ok(Some(
+			serde_json::from_slice(&buf).context("invalid migration state file")?,
+		)),
+		Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(None),
+		Err(err) => Err(err.into()),
+	}
+}
+
+/// Writes to a temporary file first so an interruption does not leave a corrupt state file.
+async fn write_state(path: &Path, state: &MigrationState) -> Result<()> {
+	let tmp_path = path.with_extension("tmp");
+
+	tokio::fs::write(&tmp_path, serde_json::to_vec_pretty(state)?).await?;
+	tokio::fs::rename(&tmp_path, path).await?;
+
+	Ok(())
+}
+
+fn display_key(key: &[u8]) -> String {
+	if key == KEYSPACE_END {
+		return "end".to_string();
+	}
+
+	match universaldb::tuple::unpack::<SimpleTuple>(key) {
+		Ok(tuple) => tuple.to_string(),
+		Err(_) => hex::encode(key),
+	}
+}
+
+fn display_checksum(checksum: Option<&(u64, [u8; 32])>) -> String {
+	match checksum {
+		Some((keys, hash)) => format!("{keys} keys ({})", hex::encode(&hash[..8])),
+		None => "missing".to_string(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	async fn setup_db(path: PathBuf) -> UdbPool {
+		let config = rivet_config::Config::from_root(rivet_config::config::Root {
+			database: Some(rivet_config::config::Database::FileSystem(
+				rivet_config::config::db::FileSystem { path, backup: None },
+			)),
+			..Default::default()
+		});
+
+		rivet_pools::db::udb::setup(config).await.unwrap().unwrap()
+	}
+
+	async fn populate(db: &UdbPool, keys: std::ops::Range<u32>) {
+		db.run(|tx| {
+			let keys = keys.clone();
+
+			async move {
+				for i in keys {
+					tx.set(
+						&universaldb::tuple::pack(&("test", "migrate", i)),
+						&i.to_le_bytes(),
+					);
+				}
+
+				Ok(())
+			}
+		})
+		.await
+		.unwrap();
+	}
+
+	#[tokio::test]
+	async fn migrate_rocksdb() {
+		let dir = tempfile::tempdir().unwrap();
+		let src = setup_db(dir.path().join("src")).await;
+		let dst = setup_db(dir.path().join("dst")).await;
+
+		populate(&src, 0..1000).await;
+
+		let mut state = MigrationState::default();
+		copy(
+			&src,
+			&dst,
+			&mut state,
+			&dir.path().join("state.json"),
+			100,
+			250,
+		)
+		.await
+		.unwrap();
+
+		assert!(state.done);
+		assert_eq!(state.keys, 1000);
+
+		// A new chunk starts at the first batch after every 250 keys
+		let chunks = state.chunk_ranges().unwrap();
+		assert_eq!(chunks.len(), 4);
+		assert_eq!(
+			chunks[1].0,
+			[
+				universaldb::tuple::pack(&("test", "migrate", 299u32)).as_slice(),
+				&[0]
+			]
+			.concat()
+		);
+		assert!(verify(&src, &dst, &chunks, 100).await.unwrap().is_empty());
+
+		// Keys written to the source after they were copied are copied again, only the chunks they
+		// are in differ
+		populate(&src, 1000..1010).await;
+		src.run(|tx| async move {
+			tx.set(
+				&universaldb::tuple::pack(&("test", "migrate", 5u32)),
+				b"changed",
+			);
+			Ok(())
+		})
+		.await
+		.unwrap();
+
+		let mismatches = verify(&src, &dst, &chunks, 100).await.unwrap();
+		assert_eq!(mismatches, vec![chunks[0].clone(), chunks[3].clone()]);
+
+		catch_up(&src, &dst, &chunks, 100).await.unwrap();
+		assert!(verify(&src, &dst, &chunks, 100).await.unwrap().is_empty());
+	}
+}
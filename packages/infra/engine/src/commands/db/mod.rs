@@ -1,6 +1,10 @@
 use anyhow::*;
 use clap::{Parser, ValueEnum};
 
+mod backup;
+mod migrate;
+mod stats;
+
 #[derive(Parser)]
 pub enum SubCommand {
 	#[clap(alias = "sh")]
@@ -12,6 +16,15 @@ pub enum SubCommand {
 		#[clap(short = 'q', long)]
 		query: Option<String>,
 	},
+	/// Copies all UDB data to another database
+	Migrate(migrate::Opts),
+	/// Manages backups of the file system database
+	Backup {
+		#[clap(subcommand)]
+		command: backup::SubCommand,
+	},
+	/// Reports the amount of keys and bytes stored in every UDB subspace
+	Stats(stats::Opts),
 }
 
 #[derive(ValueEnum, Clone, PartialEq)]
@@ -54,6 +67,9 @@ impl SubCommand {
 
 				Ok(())
 			}
+			Self::Migrate(opts) => opts.execute(config).await,
+			Self::Backup { command } => command.execute(config).await,
+			Self::Stats(opts) => opts.execute(config).await,
 		}
 	}
 }
@@ -0,0 +1,366 @@
+use std::{
+	collections::HashMap,
+	result::Result::{Err, Ok},
+};
+
+use anyhow::*;
+use clap::Parser;
+use futures_util::TryStreamExt;
+use rivet_pools::UdbPool;
+use rivet_util::Id;
+use serde::Serialize;
+use tabled::Tabled;
+use universaldb::{
+	options::StreamingMode,
+	prelude::*,
+	tuple::{Subspace, TupleDepth, TupleUnpack},
+	utils::IsolationLevel::*,
+};
+
+use crate::util::{format, udb::SimpleTuple};
+
+const DEFAULT_SAMPLE_SIZE: usize = 100_000;
+const DEFAULT_TOP: usize = 10;
+/// Max amount of keys read per transaction.
+const BATCH_SIZE: usize = 1000;
+
+struct Target {
+	name: &'static str,
+	prefix: &'static [usize],
+	/// Amount of tuple elements after the prefix that make up a sub-prefix.
+	sub_prefix_depth: usize,
+	/// Only keys for which this returns true are counted. Receives the key without the prefix.
+	filter: Option<fn(&[u8]) -> bool>,
+}
+
+const TARGETS: &[Target] = &[
+	Target {
+		name: "pegboard",
+		prefix: &[RIVET, PEGBOARD],
+		sub_prefix_depth: 1,
+		filter: None,
+	},
+	Target {
+		name: "actor kv",
+		prefix: &[RIVET, PEGBOARD, ACTOR_KV],
+		sub_prefix_depth: 1,
+		filter: None,
+	},
+	Target {
+		name: "namespace",
+		prefix: &[RIVET, NAMESPACE],
+		sub_prefix_depth: 1,
+		filter: None,
+	},
+	Target {
+		name: "workflows",
+		prefix: &[RIVET, GASOLINE, KV, WORKFLOW],
+		sub_prefix_depth: 2,
+		filter: None,
+	},
+	Target {
+		name: "workflow history",
+		prefix: &[RIVET, GASOLINE, KV, WORKFLOW],
+		sub_prefix_depth: 2,
+		filter: Some(is_history_key),
+	},
+	Target {
+		name: "signals",
+		prefix: &[RIVET, GASOLINE, KV, SIGNAL],
+		sub_prefix_depth: 2,
+		filter: None,
+	},
+	Target {
+		name: "epoxy",
+		prefix: &[RIVET, EPOXY],
+		sub_prefix_depth: 3,
+		filter: None,
+	},
+];
+
+/// Reports the amount of keys and bytes of the top-level subspaces of UDB, and their largest
+/// sub-prefixes.
+///
+/// Up to `--sample-size` keys are read from the start of every subspace. Subspaces that are
+/// larger than the sample are extrapolated from the estimated size of their range, so their
+/// numbers are approximate and their largest sub-prefixes only cover the sample. Subspaces overlap:
+/// pegboard includes actor KV and workflows include workflow history.
+#[derive(Parser)]
+pub struct Opts {
+	/// Max amount of keys read per subspace.
+	#[clap(long, default_value_t = DEFAULT_SAMPLE_SIZE)]
+	sample_size: usize,
+	/// Amount of largest sub-prefixes shown per subspace.
+	#[clap(long, default_value_t = DEFAULT_TOP)]
+	top: usize,
+	/// Prints JSON instead of tables.
+	#[clap(long)]
+	json: bool,
+}
+
+#[derive(Serialize)]
+struct SubspaceStats {
+	name: &'static str,
+	prefix: String,
+	keys: u64,
+	bytes: u64,
+	/// Size of the whole range as estimated by the database.
+	estimated_range_bytes: u64,
+	/// Whether the whole subspace was read. If false, `keys` and `bytes` are extrapolated.
+	exact: bool,
+	sampled_keys: u64,
+	largest_prefixes: Vec<PrefixStats>,
+}
+
+#[derive(Serialize)]
+struct PrefixStats {
+	prefix: String,
+	keys: u64,
+	bytes: u64,
+}
+
+#[derive(Tabled)]
+struct SubspaceTableRow {
+	subspace: &'static str,
+	prefix: String,
+	keys: String,
+	size: String,
+}
+
+#[derive(Tabled)]
+struct PrefixTableRow {
+	prefix: String,
+	keys: u64,
+	size: String,
+}
+
+struct Batch {
+	entries: Vec<(Vec<u8>, usize)>,
+	/// Key to continue reading from, `None` if the end of the range was reached.
+	next: Option<Vec<u8>>,
+}
+
+impl Opts {
+	pub async fn execute(self, config: rivet_config::Config) -> Result<()> {
+		ensure!(self.sample_size > 0, "sample size must be greater than 0");
+
+		let db = rivet_pools::db::udb::setup_read_only(config)
+			.await?
+			.context("database not configured")?;
+
+		if self.json {
+			// Prefixes are formatted with colors otherwise
+			rivet_term::console::set_colors_enabled(false);
+		}
+
+		let mut stats = Vec::with_capacity(TARGETS.len());
+		for target in TARGETS {
+			stats.push(subspace_stats(&db, target, self.sample_size, self.top).await?);
+		}
+
+		if self.json {
+			println!("{}", serde_json::to_string_pretty(&stats)?);
+		} else {
+			print_stats(&stats);
+		}
+
+		Ok(())
+	}
+}
+
+async fn subspace_stats(
+	db: &UdbPool,
+	target: &Target,
+	sample_size: usize,
+	top: usize,
+) -> Result<SubspaceStats> {
+	let subspace = Subspace::from_bytes(
+		target
+			.prefix
+			.iter()
+			.flat_map(universaldb::tuple::pack)
+			.collect::<Vec<_>>(),
+	);
+	let (begin, end) = subspace.range();
+
+	let estimated_range_bytes = db
+		.run(|tx| {
+			let (begin, end) = (begin.clone(), end.clone());
+			async move { tx.get_estimated_range_size_bytes(&begin, &end).await }
+		})
+		.await?
+		.max(0) as u64;
+
+	let mut cursor = begin;
+	let mut sampled_keys = 0u64;
+	let mut sampled_bytes = 0u64;
+	let mut matched_keys = 0u64;
+	let mut matched_bytes = 0u64;
+	let mut prefixes = HashMap::<Vec<u8>, (u64, u64)>::new();
+	let mut exact = false;
+
+	while (sampled_keys as usize) < sample_size {
+		let limit = BATCH_SIZE.min(sample_size - sampled_keys as usize);
+		let batch = read_batch(db, cursor, end.clone(), limit).await?;
+
+		for (key, size) in &batch.entries {
+			sampled_keys += 1;
+			sampled_bytes += *size as u64;
+
+			let rel_key = &key[subspace.bytes().len()..];
+			if target.filter.is_none_or(|filter| filter(rel_key)) {
+				matched_keys += 1;
+				matched_bytes += *size as u64;
+
+				let prefix = sub_prefix(key, target.prefix.len() + target.sub_prefix_depth);
+				let (keys, bytes) = prefixes.entry(prefix.to_vec()).or_default();
+				*keys += 1;
+				*bytes += *size as u64;
+			}
+		}
+
+		let Some(next) = batch.next else {
+			exact = true;
+			break;
+		};
+		cursor = next;
+	}
+
+	let (keys, bytes) = if exact || sampled_bytes == 0 {
+		(matched_keys, matched_bytes)
+	} else {
+		// Scale the sample to the estimated size of the range, the estimate can be lower than what
+		// was actually read
+		let range_bytes = estimated_range_bytes.max(sampled_bytes) as f64;
+		let bytes = range_bytes * matched_bytes as f64 / sampled_bytes as f64;
+		let keys = if matched_bytes == 0 {
+			0.0
+		} else {
+			bytes * matched_keys as f64 / matched_bytes as f64
+		};
+
+		(keys as u64, bytes as u64)
+	};
+
+	let mut largest_prefixes = prefixes
+		.into_iter()
+		.map(|(prefix, (keys, bytes))| PrefixStats {
+			prefix: display_key(&prefix),
+			keys,
+			bytes,
+		})
+		.collect::<Vec<_>>();
+	largest_prefixes.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+	largest_prefixes.truncate(top);
+
+	Ok(SubspaceStats {
+		name: target.name,
+		prefix: display_key(subspace.bytes()),
+		keys,
+		bytes,
+		estimated_range_bytes,
+		exact,
+		sampled_keys,
+		largest_prefixes,
+	})
+}
+
+async fn read_batch(db: &UdbPool, begin: Vec<u8>, end: Vec<u8>, limit: usize) -> Result<Batch> {
+	db.run(|tx| {
+		let (begin, end) = (begin.clone(), end.clone());
+
+		async move {
+			let entries = tx
+				.get_ranges_keyvalues(
+					universaldb::RangeOption {
+						mode: StreamingMode::WantAll,
+						limit: Some(limit),
+						..(begin, end).into()
+					},
+					Snapshot,
+				)
+				.map_ok(|entry| {
+					let size = entry.key().len() + entry.value().len();
+					(entry.key().to_vec(), size)
+				})
+				.try_collect::<Vec<_>>()
+				.await?;
+
+			// Continue after the last key if the range was not exhausted
+			let next = if entries.len() >= limit {
+				entries
+					.last()
+					.map(|(key, _)| [key.as_slice(), &[0]].concat())
+			} else {
+				None
+			};
+
+			Ok(Batch { entries, next })
+		}
+	})
+	.await
+}
+
+/// Workflow history keys are `(DATA, workflow_id, HISTORY, ...)` within the workflow subspace.
+fn is_history_key(key: &[u8]) -> bool {
+	matches!(
+		<(usize, Id, usize)>::unpack(key, TupleDepth::new()),
+		Ok((_, (DATA, _, HISTORY)))
+	)
+}
+
+/// The first `depth` tuple elements of the key, or the whole key if it has less elements.
+fn sub_prefix(key: &[u8], depth: usize) -> &[u8] {
+	let mut input = key;
+
+	for _ in 0..depth {
+		match universaldb::tuple::Element::unpack(input, TupleDepth::new()) {
+			Ok((rest, _)) => input = rest,
+			Err(_) => break,
+		}
+	}
+
+	&key[..key.len() - input.len()]
+}
+
+fn display_key(key: &[u8]) -> String {
+	match universaldb::tuple::unpack::<SimpleTuple>(key) {
+		Ok(tuple) => tuple.to_string(),
+		Err(_) => hex::encode(key),
+	}
+}
+
+fn print_stats(stats: &[SubspaceStats]) {
+	rivet_term::format::table(stats.iter().map(|stats| {
+		let approx = if stats.exact { "" } else { "~" };
+
+		SubspaceTableRow {
+			subspace: stats.name,
+			prefix: stats.prefix.clone(),
+			keys: format!("{approx}{}", stats.keys),
+			size: format!("{approx}{}", format::bytes(stats.bytes)),
+		}
+	}));
+
+	for stats in stats {
+		if stats.largest_prefixes.is_empty() {
+			continue;
+		}
+
+		println!();
+		if stats.exact {
+			println!("largest prefixes of {}", stats.name);
+		} else {
+			println!(
+				"largest prefixes of {} (sample of {} keys)",
+				stats.name, stats.sampled_keys
+			);
+		}
+
+		rivet_term::format::table(stats.largest_prefixes.iter().map(|prefix| PrefixTableRow {
+			prefix: prefix.prefix.clone(),
+			keys: prefix.keys,
+			size: format::bytes(prefix.bytes),
+		}));
+	}
+}
@@ -0,0 +1,75 @@
+use std::path::PathBuf;
+
+use anyhow::*;
+use clap::Parser;
+use pegboard_actor_kv as kv;
+use rivet_util::Id;
+use tokio::io::{AsyncWriteExt, BufReader, BufWriter};
+
+#[derive(Parser)]
+pub enum SubCommand {
+	/// Writes all keys of an actor to a snapshot file.
+	Export {
+		#[clap(index = 1)]
+		actor_id: Id,
+		/// File to write the snapshot to. Writes to stdout if omitted.
+		#[clap(long, short = 'o')]
+		output: Option<PathBuf>,
+	},
+	/// Loads a snapshot file into an actor. The storage quota is not enforced.
+	Import {
+		#[clap(index = 1)]
+		actor_id: Id,
+		/// Snapshot file to read. Reads from stdin if omitted.
+		#[clap(index = 2)]
+		input: Option<PathBuf>,
+		/// Deletes all existing keys of the actor before importing.
+		#[clap(long)]
+		replace: bool,
+	},
+}
+
+impl SubCommand {
+	pub async fn execute(self, config: rivet_config::Config) -> Result<()> {
+		let pools = rivet_pools::Pools::new(config.clone()).await?;
+		let udb = pools.udb()?;
+
+		match self {
+			Self::Export { actor_id, output } => {
+				let entries = if let Some(output) = output {
+					let mut w = BufWriter::new(tokio::fs::File::create(&output).await?);
+					let entries = kv::snapshot::export(&udb, actor_id, &mut w).await?;
+					w.shutdown().await?;
+
+					entries
+				} else {
+					let mut w = BufWriter::new(tokio::io::stdout());
+					kv::snapshot::export(&udb, actor_id, &mut w).await?
+				};
+
+				eprintln!("exported {entries} entries");
+
+				Ok(())
+			}
+			Self::Import {
+				actor_id,
+				input,
+				replace,
+			} => {
+				let entries = if let Some(input) = input {
+					let mut r = BufReader::new(tokio::fs::File::open(&input).await?);
+					kv::snapshot::import(&udb, actor_id, &mut r, replace, None).await?
+				} else {
+					let mut r = BufReader::new(tokio::io::stdin());
+					kv::snapshot::import(&udb, actor_id, &mut r, replace, None).await?
+				};
+
+				kv::changes::notify(&pools.ups()?, actor_id).await?;
+
+				eprintln!("imported {entries} entries");
+
+				Ok(())
+			}
+		}
+	}
+}
@@ -1,5 +1,6 @@
 pub mod config;
 pub mod db;
+pub mod kv;
 pub mod start;
 pub mod udb;
 pub mod wf;
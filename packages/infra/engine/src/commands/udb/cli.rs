@@ -31,7 +31,8 @@ pub enum SubCommand {
 		/// Key path to get. Supports relative key paths.
 		key: Option<String>,
 
-		/// Optional type hint for value parsing.
+		/// Optional type hint for value parsing. Values of known keys are decoded automatically
+		/// if not set. Use "raw" to show the raw bytes.
 		#[arg(short = 't', long = "type")]
 		type_hint: Option<String>,
 	},
@@ -128,28 +129,60 @@ impl SubCommand {
 					async move {
 						let key = universaldb::tuple::pack(&current_tuple);
 						let entry = tx.get(&key, Snapshot).await?;
-						Ok(entry)
+
+						// Chunked keys have no value, their value is split into numbered chunks
+						// directly under the key
+						let chunks = if entry.is_none() {
+							let subspace =
+								universaldb::tuple::Subspace::all().subspace(&current_tuple);
+
+							tx.get_ranges_keyvalues(
+								universaldb::RangeOption {
+									mode: StreamingMode::Iterator,
+									..(&subspace).into()
+								},
+								Snapshot,
+							)
+							.try_take_while(|entry| {
+								std::future::ready(Ok(subspace
+									.unpack::<(usize,)>(entry.key())
+									.is_ok()))
+							})
+							.try_collect::<Vec<_>>()
+							.await?
+						} else {
+							Vec::new()
+						};
+
+						Ok((key, entry, chunks))
 					}
 				});
 
 				match tokio::time::timeout(Duration::from_secs(5), fut).await {
-					Ok(Ok(entry)) => {
-						if let Some(entry) = entry {
+					Ok(Ok((key, entry, chunks))) => {
+						let parsed = if let Some(entry) = entry {
 							if type_hint.as_deref() == Some("raw") {
-								println!("{}", SimpleTupleValue::Unknown(entry.to_vec()));
+								Some(Ok(SimpleTupleValue::Unknown(entry.to_vec())))
 							} else {
-								match SimpleTupleValue::deserialize(type_hint.as_deref(), &entry) {
-									Ok(parsed) => {
-										let mut s = String::new();
-										parsed.write(&mut s, false).unwrap();
-										println!("{s}");
-									}
-									Err(err) => println!("error: {err:#}"),
-								}
+								Some(SimpleTupleValue::deserialize_with_key(
+									type_hint.as_deref(),
+									&key,
+									&entry,
+								))
 							}
 						} else {
-							println!("key does not exist");
+							SimpleTupleValue::deserialize_chunks(&key, &chunks)
 						};
+
+						match parsed {
+							Some(Ok(parsed)) => {
+								let mut s = String::new();
+								parsed.write(&mut s, false).unwrap();
+								println!("{s}");
+							}
+							Some(Err(err)) => println!("error: {err:#}"),
+							None => println!("key does not exist"),
+						}
 					}
 					Ok(Err(err)) => println!("txn error: {err:#}"),
 					Err(_) => println!("txn timed out"),
@@ -202,8 +235,11 @@ impl SubCommand {
 											key.print(&list_style, &last_key);
 											println!();
 										} else {
-											match SimpleTupleValue::deserialize(None, entry.value())
-											{
+											match SimpleTupleValue::deserialize_with_key(
+												None,
+												entry.key(),
+												entry.value(),
+											) {
 												Ok(value) => {
 													let mut s = String::new();
 													value.write(&mut s, false).unwrap();
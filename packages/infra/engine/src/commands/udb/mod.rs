@@ -18,12 +18,19 @@ pub struct Opts {
 	/// Immediately execute the given query without interactivity.
 	#[arg(short = 'q', long)]
 	query: Option<String>,
+	/// Rejects writes and reads from a secondary instance or read replica if possible.
+	#[arg(long)]
+	read_only: bool,
 }
 
 impl Opts {
 	pub async fn execute(&self, config: rivet_config::Config) -> Result<()> {
 		// Start server
-		let pools = rivet_pools::Pools::new(config.clone()).await?;
+		let pools = if self.read_only {
+			rivet_pools::Pools::new_read_only(config.clone()).await?
+		} else {
+			rivet_pools::Pools::new(config.clone()).await?
+		};
 		let pool = pools.udb()?;
 
 		if let Some(query) = &self.query {
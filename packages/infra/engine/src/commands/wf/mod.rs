@@ -1,15 +1,21 @@
-use std::sync::Arc;
+use std::{io::Read, path::PathBuf, sync::Arc};
 
 use anyhow::*;
 use clap::{Parser, ValueEnum};
-use gas::db::{
-	self, Database,
-	debug::{DatabaseDebug, WorkflowState as DebugWorkflowState},
+use gas::{
+	db::{
+		self, Database,
+		debug::{
+			DatabaseDebug, WorkflowArchive, WorkflowExport, WorkflowState as DebugWorkflowState,
+		},
+	},
+	replay::Replayer,
 };
 use rivet_util::Id;
 
 use crate::util::{self, wf::KvPair};
 
+mod schedule;
 mod signal;
 
 #[derive(Parser)]
@@ -30,8 +36,31 @@ pub enum SubCommand {
 	},
 	/// Silences a workflow from showing up as dead or running again.
 	Silence { workflow_ids: Vec<Id> },
+	/// Runs a query against the current state of a workflow and prints its output.
+	Query {
+		workflow_id: Id,
+		/// Name of the query, as declared with `#[query = ...]` on the workflow.
+		query_name: String,
+	},
+	/// Replays the history of workflows against the workflow code in this binary without running
+	/// activities. Fails if any history diverged, meaning deploying this binary would break it.
+	Replay {
+		workflow_ids: Vec<Id>,
+		/// Also replays all running, sleeping and dead workflows with this name.
+		#[clap(long, short = 'n')]
+		name: Option<String>,
+	},
 	/// Sets the wake immediate property of a workflow to true.
 	Wake { workflow_ids: Vec<Id> },
+	/// Cancels workflows and the sub workflows they dispatched.
+	Cancel { workflow_ids: Vec<Id> },
+	/// Permanently deletes completed or silenced workflows and the signals they received.
+	Purge {
+		workflow_ids: Vec<Id>,
+		/// Writes workflows to the configured archive path before deleting them.
+		#[clap(long)]
+		archive: bool,
+	},
 	/// Lists the entire event history of a workflow.
 	History {
 		#[clap(index = 1)]
@@ -49,16 +78,45 @@ pub enum SubCommand {
 		#[clap(short = 't', action = clap::ArgAction::Count, long)]
 		print_ts: u8,
 	},
+	/// Writes a workflow with its entire history, the signals it received and its sub workflows to a JSON
+	/// file.
+	Export {
+		workflow_id: Id,
+		/// Defaults to `{workflow_id}.json`.
+		#[clap(long, short = 'o')]
+		output: Option<PathBuf>,
+	},
+	/// Imports workflows from a file written by `wf export` or from a workflow archive (`.json.lz4`).
+	/// Imported workflows that did not complete are woken.
+	Import { path: PathBuf },
 	Signal {
 		#[clap(subcommand)]
 		command: signal::SubCommand,
 	},
+	Schedule {
+		#[clap(subcommand)]
+		command: schedule::SubCommand,
+	},
 }
 
 impl SubCommand {
 	pub async fn execute(self, config: rivet_config::Config) -> Result<()> {
-		let pools = rivet_pools::Pools::new(config.clone()).await?;
-		let db = db::DatabaseKv::from_pools(pools).await? as Arc<dyn DatabaseDebug>;
+		// Commands that only read don't need to touch the primary database
+		let read_only = matches!(
+			self,
+			Self::Get { .. }
+				| Self::List { .. }
+				| Self::Query { .. }
+				| Self::Replay { .. }
+				| Self::History { .. }
+				| Self::Export { .. }
+		);
+		let pools = if read_only {
+			rivet_pools::Pools::new_read_only(config.clone()).await?
+		} else {
+			rivet_pools::Pools::new(config.clone()).await?
+		};
+		let db = db::DatabaseKv::from_pools(pools.clone()).await? as Arc<dyn DatabaseDebug>;
 
 		match self {
 			Self::Get { workflow_ids } => {
@@ -83,8 +141,100 @@ impl SubCommand {
 					.await?;
 				util::wf::print_workflows(workflows, pretty).await
 			}
+			Self::Query {
+				workflow_id,
+				query_name,
+			} => {
+				let workflow = DatabaseDebug::get_workflows(&*db, vec![workflow_id])
+					.await?
+					.into_iter()
+					.next()
+					.context("workflow not found")?;
+
+				let reg = rivet_workflow_worker::registry()?;
+				let query = reg.get_query(&workflow.workflow_name, &query_name)?;
+
+				let state = serde_json::value::to_raw_value(&workflow.data)?;
+				let output = (query.run)(&state)?;
+				let output = serde_json::from_str::<serde_json::Value>(output.get())?;
+
+				println!("{}", util::format::colored_json(&output)?);
+
+				Ok(())
+			}
+			Self::Replay {
+				mut workflow_ids,
+				name,
+			} => {
+				if let Some(name) = name {
+					for state in [
+						DebugWorkflowState::Running,
+						DebugWorkflowState::Sleeping,
+						DebugWorkflowState::Dead,
+					] {
+						workflow_ids.extend(
+							db.find_workflows(&[], Some(&name), Some(state))
+								.await?
+								.into_iter()
+								.map(|wf| wf.workflow_id),
+						);
+					}
+				}
+
+				let replayer = Replayer::new(
+					rivet_workflow_worker::registry()?.handle(),
+					db::DatabaseKv::from_pools(pools.clone()).await?,
+					config.clone(),
+					pools,
+				)?;
+
+				let mut diverged = 0;
+				for workflow_id in workflow_ids {
+					let Some(history) = db.get_workflow_history(workflow_id, false).await? else {
+						println!("{workflow_id} not found");
+						continue;
+					};
+
+					let report = replayer.replay(history).await?;
+					if report.outcome.is_diverged() {
+						diverged += 1;
+					}
+
+					println!(
+						"{} {} {}",
+						report.workflow_id, report.workflow_name, report.outcome
+					);
+				}
+
+				ensure!(diverged == 0, "{diverged} workflow(s) diverged");
+
+				Ok(())
+			}
 			Self::Silence { workflow_ids } => db.silence_workflows(workflow_ids).await,
 			Self::Wake { workflow_ids } => db.wake_workflows(workflow_ids).await,
+			Self::Cancel { workflow_ids } => {
+				for workflow_id in workflow_ids {
+					db.cancel_workflow(workflow_id).await?;
+				}
+
+				Ok(())
+			}
+			Self::Purge {
+				workflow_ids,
+				archive,
+			} => {
+				let archive_path = config.workflow().archive_path();
+				let purged = db
+					.purge_workflows(workflow_ids, archive.then_some(archive_path.as_path()))
+					.await?;
+
+				println!("purged {} workflow(s)", purged.len());
+				if archive {
+					println!("archived to {}", archive_path.display());
+				}
+
+				Ok(())
+			}
 			Self::History {
 				workflow_id,
 				exclude_json,
@@ -97,7 +247,48 @@ impl SubCommand {
 					.await?;
 				util::wf::print_history(history, exclude_json, print_location, print_ts).await
 			}
+			Self::Export {
+				workflow_id,
+				output,
+			} => {
+				let export = db
+					.export_workflow(workflow_id)
+					.await?
+					.context("workflow not found")?;
+
+				let path = output.unwrap_or_else(|| PathBuf::from(format!("{workflow_id}.json")));
+				std::fs::write(&path, serde_json::to_vec_pretty(&export)?)
+					.with_context(|| format!("failed to write {}", path.display()))?;
+
+				println!(
+					"exported {} workflow(s) to {}",
+					export.workflows.len(),
+					path.display()
+				);
+
+				Ok(())
+			}
+			Self::Import { path } => {
+				let buf = std::fs::read(&path)
+					.with_context(|| format!("failed to read {}", path.display()))?;
+
+				let workflows = if path.extension().is_some_and(|ext| ext == "lz4") {
+					let mut json = Vec::new();
+					lz4_flex::frame::FrameDecoder::new(buf.as_slice()).read_to_end(&mut json)?;
+
+					vec![serde_json::from_slice::<WorkflowArchive>(&json)?]
+				} else {
+					serde_json::from_slice::<WorkflowExport>(&buf)?.workflows
+				};
+
+				for workflow_id in db.import_workflows(&workflows).await? {
+					println!("imported {workflow_id}");
+				}
+
+				Ok(())
+			}
 			Self::Signal { command } => command.execute(db).await,
+			Self::Schedule { command } => command.execute(db).await,
 		}
 	}
 }
@@ -110,6 +301,7 @@ pub enum WorkflowState {
 	Sleeping,
 	Dead,
 	Silenced,
+	Cancelled,
 }
 
 impl From<WorkflowState> for DebugWorkflowState {
@@ -120,6 +312,7 @@ impl From<WorkflowState> for DebugWorkflowState {
 			WorkflowState::Sleeping => DebugWorkflowState::Sleeping,
 			WorkflowState::Dead => DebugWorkflowState::Dead,
 			WorkflowState::Silenced => DebugWorkflowState::Silenced,
+			WorkflowState::Cancelled => DebugWorkflowState::Cancelled,
 		}
 	}
 }
@@ -0,0 +1,41 @@
+use std::sync::Arc;
+
+use anyhow::*;
+use clap::Parser;
+use gas::db::{Database, debug::DatabaseDebug};
+use rivet_util::Id;
+
+use crate::util;
+
+#[derive(Parser)]
+pub enum SubCommand {
+	/// Lists workflow schedules.
+	List {
+		/// Only lists schedules that dispatch the workflow with this name.
+		#[clap(long, short = 'n')]
+		name: Option<String>,
+		/// Prints paragraphs instead of a table.
+		#[clap(long, short = 'p')]
+		pretty: bool,
+	},
+	/// Deletes schedules. Workflows they already dispatched are not affected.
+	Delete { schedule_ids: Vec<Id> },
+}
+
+impl SubCommand {
+	pub async fn execute(self, db: Arc<dyn DatabaseDebug>) -> Result<()> {
+		match self {
+			Self::List { name, pretty } => {
+				let schedules = db.list_schedules(name.as_deref()).await?;
+				util::wf::schedule::print_schedules(schedules, pretty).await
+			}
+			Self::Delete { schedule_ids } => {
+				for schedule_id in schedule_ids {
+					db.delete_schedule(schedule_id).await?;
+				}
+
+				Ok(())
+			}
+		}
+	}
+}
@@ -30,6 +30,11 @@ pub enum SubCommand {
 	},
 	/// Allows inspection of UDB data
 	Udb(udb::Opts),
+	/// Exports and imports actor KV snapshots
+	Kv {
+		#[clap(subcommand)]
+		command: kv::SubCommand,
+	},
 }
 
 impl SubCommand {
@@ -40,6 +45,7 @@ impl SubCommand {
 			SubCommand::Workflow { command } => command.execute(config).await,
 			SubCommand::Config { command } => command.execute(config).await,
 			SubCommand::Udb(opts) => opts.execute(config).await,
+			SubCommand::Kv { command } => command.execute(config).await,
 		}
 	}
 }
@@ -1,5 +1,5 @@
 use anyhow::*;
-use rivet_service_manager::{RunConfigData, Service, ServiceKind};
+use rivet_service_manager::{CronConfig, RunConfigData, Service, ServiceKind};
 
 pub fn config(_rivet_config: rivet_config::Config) -> Result<RunConfigData> {
 	let services = vec![
@@ -22,6 +22,24 @@ pub fn config(_rivet_config: rivet_config::Config) -> Result<RunConfigData> {
 			ServiceKind::Standalone,
 			|config, pools| Box::pin(pegboard_serverless::start(config, pools)),
 		),
+		Service::new(
+			"pegboard_actor_kv_sweeper",
+			ServiceKind::Cron(CronConfig {
+				run_immediately: true,
+				// Every minute
+				schedule: "0 * * * * *".into(),
+			}),
+			|config, pools| Box::pin(pegboard_actor_kv::sweeper::start(config, pools)),
+		),
+		Service::new(
+			"workflow_retention",
+			ServiceKind::Cron(CronConfig {
+				run_immediately: true,
+				// Every hour
+				schedule: "0 0 * * * *".into(),
+			}),
+			|config, pools| Box::pin(rivet_workflow_worker::retention::start(config, pools)),
+		),
 	];
 
 	Ok(RunConfigData { services })
@@ -28,6 +28,24 @@ pub fn chunk_string(s: &str, size: usize) -> Vec<String> {
 		.collect()
 }
 
+/// Formats a byte count with a binary unit, e.g. `1.5 MiB`.
+pub fn bytes(bytes: u64) -> String {
+	const UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB"];
+
+	let mut value = bytes as f64;
+	let mut unit = 0;
+	while value >= 1024.0 && unit < UNITS.len() - 1 {
+		value /= 1024.0;
+		unit += 1;
+	}
+
+	if unit == 0 {
+		format!("{bytes} B")
+	} else {
+		format!("{value:.1} {}", UNITS[unit])
+	}
+}
+
 pub fn colored_json(value: &serde_json::Value) -> Result<String> {
 	colored_json_inner(value, colored_json::PrettyFormatter::new())
 }
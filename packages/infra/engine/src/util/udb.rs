@@ -225,6 +225,33 @@ impl SimpleTupleValue {
 		Ok(parsed_value)
 	}
 
+	/// Decodes the value with the schema of the key if it is a known key (see `FormalKey`),
+	/// otherwise the same as `deserialize`.
+	pub fn deserialize_with_key(type_hint: Option<&str>, key: &[u8], value: &[u8]) -> Result<Self> {
+		if type_hint.is_none()
+			&& let Some(decoded) = decode_known_value(key, value)
+		{
+			let decoded = decoded.context("Could not decode value of known key")?;
+			return Ok(SimpleTupleValue::String(serde_json::to_string(&decoded)?));
+		}
+
+		Self::deserialize(type_hint, value)
+	}
+
+	/// Combines and decodes the chunks of a known chunked key (see `FormalChunkedKey`).
+	pub fn deserialize_chunks(
+		key: &[u8],
+		chunks: &[universaldb::value::Value],
+	) -> Option<Result<Self>> {
+		let decoded = decode_known_chunked_value(key, chunks)?;
+
+		Some(
+			decoded
+				.context("Could not decode chunks of known key")
+				.and_then(|decoded| Ok(SimpleTupleValue::String(serde_json::to_string(&decoded)?))),
+		)
+	}
+
 	pub fn write(&self, f: &mut impl std::fmt::Write, convert_keys: bool) -> fmt::Result {
 		match &self {
 			SimpleTupleValue::U64(v) => {
@@ -668,6 +695,23 @@ impl<'de> TupleUnpack<'de> for SimpleTuple {
 	}
 }
 
+/// Decodes the value of any known key of the engine as JSON.
+fn decode_known_value(key: &[u8], value: &[u8]) -> Option<Result<serde_json::Value>> {
+	gas::db::decode_value(key, value)
+		.or_else(|| pegboard::keys::decode_value(key, value))
+		.or_else(|| namespace::keys::decode_value(key, value))
+		.or_else(|| epoxy::keys::decode_value(key, value))
+}
+
+/// Combines and decodes the chunks of any known chunked key of the engine as JSON.
+fn decode_known_chunked_value(
+	key: &[u8],
+	chunks: &[universaldb::value::Value],
+) -> Option<Result<serde_json::Value>> {
+	gas::db::decode_chunked_value(key, chunks)
+		.or_else(|| pegboard::keys::decode_chunked_value(key, chunks))
+}
+
 fn unescape(s: &str) -> String {
 	let mut result = String::new();
 	let mut escaped = false;
@@ -6,6 +6,7 @@ use rivet_term::console::{Style, style};
 
 use crate::util::format::{chunk_string, colored_json, indent_string};
 
+pub mod schedule;
 pub mod signal;
 
 #[derive(Debug, Clone)]
@@ -504,6 +505,14 @@ pub async fn print_history(
 				style(error).green(),
 			);
 		}
+	} else if let WorkflowState::Cancelled = history.wf.state {
+		println!();
+
+		println!("{}", style("Workflow cancelled").bold());
+
+		if let Some(error) = history.wf.error {
+			println!("{} error {}", style("|").dim(), style(error).green());
+		}
 	} else if let WorkflowState::Silenced = history.wf.state {
 		println!();
 
@@ -522,7 +531,7 @@ pub async fn print_history(
 		println!("{}", style("Workflow dead").red().bold());
 
 		if let Some(error) = history.wf.error {
-			println!("{} error {}", style("|").red().dim(), style(error).green(),);
+			println!("{} error {}", style("|").red().dim(), style(error).green());
 		}
 	}
 
@@ -606,6 +615,7 @@ fn display_state(state: &WorkflowState) -> String {
 		WorkflowState::Sleeping => style("sleeping").yellow().to_string(),
 		WorkflowState::Dead => style("dead").red().to_string(),
 		WorkflowState::Silenced => style("silenced").bright().magenta().to_string(),
+		WorkflowState::Cancelled => style("cancelled").dim().to_string(),
 	}
 }
 
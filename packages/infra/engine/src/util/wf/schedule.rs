@@ -0,0 +1,117 @@
+use anyhow::*;
+use chrono::{Local, TimeZone};
+use rivet_term::console::style;
+
+use gas::db::ScheduleData;
+
+use crate::util::format::{colored_json, indent_string};
+
+pub async fn print_schedules(schedules: Vec<ScheduleData>, pretty: bool) -> Result<()> {
+	if schedules.is_empty() {
+		rivet_term::status::success("No schedules found", "");
+		return Ok(());
+	}
+
+	rivet_term::status::success("Schedules", schedules.len());
+
+	if pretty {
+		for schedule in schedules {
+			println!();
+
+			println!("{}", style(schedule.workflow_name).bold());
+
+			println!("  {} {}", style("id").bold(), schedule.schedule_id);
+
+			println!("  {} {}", style("cron").bold(), schedule.cron);
+
+			println!("  {} {}", style("catch up").bold(), schedule.catch_up);
+
+			let datetime = Local
+				.timestamp_millis_opt(schedule.create_ts)
+				.single()
+				.context("invalid ts")?;
+			let date = datetime.format("%Y-%m-%d %H:%M:%S%.3f");
+
+			println!("  {} {}", style("created at").bold(), style(date).magenta());
+
+			let datetime = Local
+				.timestamp_millis_opt(schedule.next_ts)
+				.single()
+				.context("invalid ts")?;
+			let date = datetime.format("%Y-%m-%d %H:%M:%S%.3f");
+
+			println!("  {} {}", style("next run at").bold(), style(date).magenta());
+
+			if let Some(tags) = &schedule.tags {
+				println!(
+					"  {} {}",
+					style("tags").bold(),
+					&indent_string(&colored_json(&tags)?, "    ", true)
+				);
+			}
+
+			let input = serde_json::from_str::<serde_json::Value>(schedule.input.get())?;
+			println!(
+				"  {} {}",
+				style("input").bold(),
+				&indent_string(&colored_json(&input)?, "    ", true)
+			);
+		}
+	} else {
+		table::schedules(schedules)?;
+	}
+
+	Ok(())
+}
+
+mod table {
+	use anyhow::*;
+	use chrono::{Local, TimeZone};
+	use gas::db::ScheduleData;
+	use rivet_util::Id;
+	use tabled::Tabled;
+
+	use crate::util::format::colored_json_ugly;
+
+	#[derive(Tabled)]
+	struct ScheduleTableRow {
+		pub workflow_name: String,
+		pub schedule_id: Id,
+		pub cron: String,
+		pub catch_up: String,
+		pub next_run_at: String,
+		pub tags: String,
+	}
+
+	pub fn schedules(schedules: Vec<ScheduleData>) -> Result<()> {
+		let mut rows = schedules
+			.iter()
+			.map(|s| {
+				Ok(ScheduleTableRow {
+					workflow_name: s.workflow_name.clone(),
+					schedule_id: s.schedule_id,
+					cron: s.cron.clone(),
+					catch_up: s.catch_up.to_string(),
+					next_run_at: Local
+						.timestamp_millis_opt(s.next_ts)
+						.single()
+						.context("invalid ts")?
+						.format("%Y-%m-%d %H:%M:%S")
+						.to_string(),
+					tags: s
+						.tags
+						.as_ref()
+						.map(colored_json_ugly)
+						.transpose()?
+						.unwrap_or_default(),
+				})
+			})
+			.collect::<Result<Vec<_>>>()?;
+
+		rows.sort_by(|a, b| a.workflow_name.cmp(&b.workflow_name));
+
+		rivet_term::format::table(rows);
+
+		Ok(())
+	}
+}
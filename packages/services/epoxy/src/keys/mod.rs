@@ -1,5 +1,9 @@
+use anyhow::Result;
 use epoxy_protocol::protocol::ReplicaId;
-use universaldb::prelude::*;
+use universaldb::{
+	prelude::*,
+	utils::{FormalKeyDecoder, decode_formal_key},
+};
 
 pub mod keys;
 pub mod replica;
@@ -7,3 +11,27 @@ pub mod replica;
 pub fn subspace(replica_id: ReplicaId) -> universaldb::utils::Subspace {
 	universaldb::utils::Subspace::new(&(RIVET, EPOXY, REPLICA, replica_id))
 }
+
+/// Decodes the value of an epoxy key of any replica as JSON. Returns `None` if the key is not a
+/// known key.
+pub fn decode_value(key: &[u8], value: &[u8]) -> Option<Result<serde_json::Value>> {
+	const DECODERS: &[FormalKeyDecoder] = &[
+		decode_formal_key::<keys::KvValueKey>,
+		decode_formal_key::<keys::KvOptimisticCacheKey>,
+		decode_formal_key::<replica::InstanceNumberKey>,
+		decode_formal_key::<replica::LogEntryKey>,
+		decode_formal_key::<replica::KeyInstanceKey>,
+		decode_formal_key::<replica::ConfigKey>,
+		decode_formal_key::<replica::CurrentBallotKey>,
+		decode_formal_key::<replica::InstanceBallotKey>,
+	];
+
+	// Read the replica id from the key prefix
+	let (_, (_, _, _, replica_id)) =
+		<(usize, usize, usize, ReplicaId)>::unpack(key, TupleDepth::new()).ok()?;
+	let subspace = subspace(replica_id);
+
+	DECODERS
+		.iter()
+		.find_map(|decode| decode(&subspace, key, value))
+}
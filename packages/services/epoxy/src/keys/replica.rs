@@ -29,6 +29,14 @@ impl TuplePack for InstanceNumberKey {
 	}
 }
 
+impl<'de> TupleUnpack<'de> for InstanceNumberKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, _) = <(usize,)>::unpack(input, tuple_depth)?;
+
+		Result::Ok((input, InstanceNumberKey))
+	}
+}
+
 #[derive(Debug)]
 pub struct LogEntryKey {
 	pub instance_replica_id: ReplicaId,
@@ -178,6 +186,14 @@ impl TuplePack for ConfigKey {
 	}
 }
 
+impl<'de> TupleUnpack<'de> for ConfigKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, _) = <(usize,)>::unpack(input, tuple_depth)?;
+
+		Result::Ok((input, ConfigKey))
+	}
+}
+
 pub struct KeyInstanceSubspaceKey {
 	key: Vec<u8>,
 }
@@ -239,6 +255,14 @@ impl TuplePack for CurrentBallotKey {
 	}
 }
 
+impl<'de> TupleUnpack<'de> for CurrentBallotKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, _) = <(usize,)>::unpack(input, tuple_depth)?;
+
+		Result::Ok((input, CurrentBallotKey))
+	}
+}
+
 #[derive(Debug)]
 pub struct InstanceBallotKey {
 	instance_replica_id: ReplicaId,
@@ -281,3 +305,17 @@ impl TuplePack for InstanceBallotKey {
 		t.pack(w, tuple_depth)
 	}
 }
+
+impl<'de> TupleUnpack<'de> for InstanceBallotKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, (_, instance_replica_id, instance_slot_id)) =
+			<(usize, ReplicaId, SlotId)>::unpack(input, tuple_depth)?;
+
+		let v = InstanceBallotKey {
+			instance_replica_id,
+			instance_slot_id,
+		};
+
+		Result::Ok((input, v))
+	}
+}
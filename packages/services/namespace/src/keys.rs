@@ -3,7 +3,10 @@ use std::result::Result::Ok;
 use anyhow::*;
 use gas::prelude::*;
 use serde::Serialize;
-use universaldb::prelude::*;
+use universaldb::{
+	prelude::*,
+	utils::{FormalKeyDecoder, decode_formal_key},
+};
 use utoipa::ToSchema;
 use vbare::OwnedVersionedData;
 
@@ -11,6 +14,23 @@ pub fn subspace() -> universaldb::utils::Subspace {
 	universaldb::utils::Subspace::new(&(RIVET, NAMESPACE))
 }
 
+/// Decodes the value of a namespace key as JSON. Returns `None` if the key is not a known key.
+pub fn decode_value(key: &[u8], value: &[u8]) -> Option<Result<serde_json::Value>> {
+	const DECODERS: &[FormalKeyDecoder] = &[
+		decode_formal_key::<NameKey>,
+		decode_formal_key::<DisplayNameKey>,
+		decode_formal_key::<CreateTsKey>,
+		decode_formal_key::<ByNameKey>,
+		decode_formal_key::<RunnerConfigKey>,
+		decode_formal_key::<RunnerConfigByVariantKey>,
+	];
+
+	let subspace = subspace();
+	DECODERS
+		.iter()
+		.find_map(|decode| decode(&subspace, key, value))
+}
+
 #[derive(Debug)]
 pub struct NameKey {
 	namespace_id: Id,
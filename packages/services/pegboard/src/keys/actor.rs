@@ -269,3 +269,95 @@ impl<'de> TupleUnpack<'de> for DestroyTsKey {
 		Ok((input, v))
 	}
 }
+
+#[derive(Debug)]
+pub struct KvStorageSizeKey {
+	actor_id: Id,
+}
+
+impl KvStorageSizeKey {
+	pub fn new(actor_id: Id) -> Self {
+		KvStorageSizeKey { actor_id }
+	}
+}
+
+impl FormalKey for KvStorageSizeKey {
+	/// Bytes.
+	type Value = i64;
+
+	fn deserialize(&self, raw: &[u8]) -> Result<Self::Value> {
+		// NOTE: Atomic ops use little endian
+		Ok(i64::from_le_bytes(raw.try_into()?))
+	}
+
+	fn serialize(&self, value: Self::Value) -> Result<Vec<u8>> {
+		// NOTE: Atomic ops use little endian
+		Ok(value.to_le_bytes().to_vec())
+	}
+}
+
+impl TuplePack for KvStorageSizeKey {
+	fn pack<W: std::io::Write>(
+		&self,
+		w: &mut W,
+		tuple_depth: TupleDepth,
+	) -> std::io::Result<VersionstampOffset> {
+		let t = (ACTOR, DATA, self.actor_id, KV_STORAGE_SIZE);
+		t.pack(w, tuple_depth)
+	}
+}
+
+impl<'de> TupleUnpack<'de> for KvStorageSizeKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, (_, _, actor_id, _)) = <(usize, usize, Id, usize)>::unpack(input, tuple_depth)?;
+
+		let v = KvStorageSizeKey { actor_id };
+
+		Ok((input, v))
+	}
+}
+
+#[derive(Debug)]
+pub struct KvChangeSeqKey {
+	actor_id: Id,
+}
+
+impl KvChangeSeqKey {
+	pub fn new(actor_id: Id) -> Self {
+		KvChangeSeqKey { actor_id }
+	}
+}
+
+impl FormalKey for KvChangeSeqKey {
+	/// Sequence number of the latest change in the actor's KV change log.
+	type Value = u64;
+
+	fn deserialize(&self, raw: &[u8]) -> Result<Self::Value> {
+		Ok(u64::from_be_bytes(raw.try_into()?))
+	}
+
+	fn serialize(&self, value: Self::Value) -> Result<Vec<u8>> {
+		Ok(value.to_be_bytes().to_vec())
+	}
+}
+
+impl TuplePack for KvChangeSeqKey {
+	fn pack<W: std::io::Write>(
+		&self,
+		w: &mut W,
+		tuple_depth: TupleDepth,
+	) -> std::io::Result<VersionstampOffset> {
+		let t = (ACTOR, DATA, self.actor_id, KV_CHANGE_SEQ);
+		t.pack(w, tuple_depth)
+	}
+}
+
+impl<'de> TupleUnpack<'de> for KvChangeSeqKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, (_, _, actor_id, _)) = <(usize, usize, Id, usize)>::unpack(input, tuple_depth)?;
+
+		let v = KvChangeSeqKey { actor_id };
+
+		Ok((input, v))
+	}
+}
@@ -1,4 +1,10 @@
-use universaldb::prelude::*;
+use anyhow::Result;
+use universaldb::{
+	prelude::*,
+	utils::{
+		FormalChunkedKeyDecoder, FormalKeyDecoder, decode_formal_chunked_key, decode_formal_key,
+	},
+};
 
 pub mod actor;
 pub mod epoxy;
@@ -12,3 +18,70 @@ pub fn subspace() -> universaldb::utils::Subspace {
 pub fn actor_kv_subspace() -> universaldb::utils::Subspace {
 	universaldb::utils::Subspace::new(&(RIVET, PEGBOARD, ACTOR_KV))
 }
+
+pub fn actor_kv_expiry_subspace() -> universaldb::utils::Subspace {
+	universaldb::utils::Subspace::new(&(RIVET, PEGBOARD, ACTOR_KV_EXPIRY))
+}
+
+pub fn actor_kv_change_subspace() -> universaldb::utils::Subspace {
+	universaldb::utils::Subspace::new(&(RIVET, PEGBOARD, ACTOR_KV_CHANGE))
+}
+
+/// Decodes the value of a pegboard key as JSON. Returns `None` if the key is not a known key.
+pub fn decode_value(key: &[u8], value: &[u8]) -> Option<Result<serde_json::Value>> {
+	const DECODERS: &[FormalKeyDecoder] = &[
+		decode_formal_key::<actor::CreateTsKey>,
+		decode_formal_key::<actor::WorkflowIdKey>,
+		decode_formal_key::<actor::RunnerIdKey>,
+		decode_formal_key::<actor::ConnectableKey>,
+		decode_formal_key::<actor::SleepTsKey>,
+		decode_formal_key::<actor::DestroyTsKey>,
+		decode_formal_key::<actor::KvStorageSizeKey>,
+		decode_formal_key::<actor::KvChangeSeqKey>,
+		decode_formal_key::<ns::RunnerAllocIdxKey>,
+		decode_formal_key::<ns::PendingActorByRunnerNameSelectorKey>,
+		decode_formal_key::<ns::ActiveActorKey>,
+		decode_formal_key::<ns::AllActorKey>,
+		decode_formal_key::<ns::ActorByKeyKey>,
+		decode_formal_key::<ns::ActiveRunnerKey>,
+		decode_formal_key::<ns::AllRunnerKey>,
+		decode_formal_key::<ns::ActiveRunnerByNameKey>,
+		decode_formal_key::<ns::AllRunnerByNameKey>,
+		decode_formal_key::<ns::RunnerByKeyKey>,
+		decode_formal_key::<ns::ActorNameKey>,
+		decode_formal_key::<ns::RunnerNameKey>,
+		decode_formal_key::<runner::CreateTsKey>,
+		decode_formal_key::<runner::RemainingSlotsKey>,
+		decode_formal_key::<runner::LastPingTsKey>,
+		decode_formal_key::<runner::TotalSlotsKey>,
+		decode_formal_key::<runner::ActorKey>,
+		decode_formal_key::<runner::WorkflowIdKey>,
+		decode_formal_key::<runner::NamespaceIdKey>,
+		decode_formal_key::<runner::NameKey>,
+		decode_formal_key::<runner::KeyKey>,
+		decode_formal_key::<runner::VersionKey>,
+		decode_formal_key::<runner::StopTsKey>,
+		decode_formal_key::<runner::DrainTsKey>,
+		decode_formal_key::<runner::LastRttKey>,
+		decode_formal_key::<runner::ConnectedTsKey>,
+		decode_formal_key::<runner::ExpiredTsKey>,
+		decode_formal_key::<rivet_types::keys::pegboard::ns::ServerlessDesiredSlotsKey>,
+	];
+
+	let subspace = subspace();
+	DECODERS
+		.iter()
+		.find_map(|decode| decode(&subspace, key, value))
+}
+
+/// Combines and decodes the chunks of a chunked pegboard key as JSON. Returns `None` if the key is
+/// not a known chunked key.
+pub fn decode_chunked_value(key: &[u8], chunks: &[Value]) -> Option<Result<serde_json::Value>> {
+	const DECODERS: &[FormalChunkedKeyDecoder] =
+		&[decode_formal_chunked_key::<runner::MetadataKey>];
+
+	let subspace = subspace();
+	DECODERS
+		.iter()
+		.find_map(|decode| decode(&subspace, key, chunks))
+}
@@ -787,6 +787,20 @@ impl TuplePack for MetadataKey {
 	}
 }
 
+impl<'de> TupleUnpack<'de> for MetadataKey {
+	fn unpack(input: &[u8], tuple_depth: TupleDepth) -> PackResult<(&[u8], Self)> {
+		let (input, (_, _, runner_id, data)) =
+			<(usize, usize, Id, usize)>::unpack(input, tuple_depth)?;
+		if data != METADATA {
+			return Err(PackError::Message("expected METADATA data".into()));
+		}
+
+		let v = MetadataKey { runner_id };
+
+		Ok((input, v))
+	}
+}
+
 pub struct MetadataChunkKey {
 	runner_id: Id,
 	chunk: usize,
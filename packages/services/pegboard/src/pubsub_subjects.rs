@@ -31,3 +31,19 @@ impl std::fmt::Display for GatewayReceiverSubject {
 		write!(f, "pegboard.gateway.{}", self.gateway_id)
 	}
 }
+
+pub struct ActorKvChangesSubject {
+	actor_id: Id,
+}
+
+impl ActorKvChangesSubject {
+	pub fn new(actor_id: Id) -> Self {
+		Self { actor_id }
+	}
+}
+
+impl std::fmt::Display for ActorKvChangesSubject {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(f, "pegboard.actor.kv_changes.{}", self.actor_id)
+	}
+}
@@ -145,11 +145,28 @@ async fn clear_kv(ctx: &ActivityCtx, input: &ClearKvInput) -> Result<ClearKvOutp
 		.udb()?
 		.run(|tx| async move {
 			let subspace = keys::actor_kv_subspace().subspace(&input.actor_id);
-
-			let (start, end) = subspace.range();
-			let final_size = tx.get_estimated_range_size_bytes(&start, &end).await?;
+			let storage_size_key = keys::actor::KvStorageSizeKey::new(input.actor_id);
+
+			// Actors that never wrote since the storage counter was added don't have one
+			let final_size = match tx
+				.with_subspace(keys::subspace())
+				.read_opt(&storage_size_key, Serializable)
+				.await?
+			{
+				Some(final_size) => final_size,
+				None => {
+					let (start, end) = subspace.range();
+					tx.get_estimated_range_size_bytes(&start, &end).await?
+				}
+			};
 
 			tx.clear_subspace_range(&subspace);
+			tx.with_subspace(keys::subspace()).delete(&storage_size_key);
+
+			// The change log is not needed once the actor is destroyed
+			tx.clear_subspace_range(&keys::actor_kv_change_subspace().subspace(&input.actor_id));
+			tx.with_subspace(keys::subspace())
+				.delete(&keys::actor::KvChangeSeqKey::new(input.actor_id));
 
 			Ok(final_size)
 		})
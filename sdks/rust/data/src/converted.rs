@@ -3,6 +3,7 @@ use gas::prelude::*;
 
 use crate::generated::*;
 
+#[derive(Serialize)]
 pub struct RunnerAllocIdxKeyData {
 	pub workflow_id: Id,
 	pub remaining_slots: u32,
@@ -33,6 +34,7 @@ impl TryFrom<RunnerAllocIdxKeyData> for pegboard_namespace_runner_alloc_idx_v1::
 	}
 }
 
+#[derive(Serialize)]
 pub struct MetadataKeyData {
 	pub metadata: serde_json::Map<String, serde_json::Value>,
 }
@@ -57,6 +59,7 @@ impl TryFrom<MetadataKeyData> for pegboard_runner_metadata_v1::Data {
 	}
 }
 
+#[derive(Serialize)]
 pub struct ActorByKeyKeyData {
 	pub workflow_id: Id,
 	pub is_destroyed: bool,
@@ -84,6 +87,7 @@ impl TryFrom<ActorByKeyKeyData> for pegboard_namespace_actor_by_key_v1::Data {
 	}
 }
 
+#[derive(Serialize)]
 pub struct RunnerByKeyKeyData {
 	pub runner_id: Id,
 	pub workflow_id: Id,
@@ -111,7 +115,7 @@ impl TryFrom<RunnerByKeyKeyData> for pegboard_namespace_runner_by_key_v1::Data {
 	}
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize)]
 pub struct ActorNameKeyData {
 	pub metadata: serde_json::Map<String, serde_json::Value>,
 }
@@ -1,22 +1,26 @@
 use anyhow::{Ok, Result, bail};
+use serde::{Serialize, de::DeserializeOwned};
 use vbare::OwnedVersionedData;
 
-use crate::{PROTOCOL_VERSION, generated::v1};
+use crate::{
+	PROTOCOL_VERSION,
+	generated::{v1, v2},
+};
 
 pub enum ToClient {
 	V1(v1::ToClient),
+	V2(v2::ToClient),
 }
 
 impl OwnedVersionedData for ToClient {
-	type Latest = v1::ToClient;
+	type Latest = v2::ToClient;
 
-	fn latest(latest: v1::ToClient) -> Self {
-		ToClient::V1(latest)
+	fn latest(latest: v2::ToClient) -> Self {
+		ToClient::V2(latest)
 	}
 
 	fn into_latest(self) -> Result<Self::Latest> {
-		#[allow(irrefutable_let_patterns)]
-		if let ToClient::V1(data) = self {
+		if let ToClient::V2(data) = self {
 			Ok(data)
 		} else {
 			bail!("version not latest");
@@ -26,6 +30,7 @@ impl OwnedVersionedData for ToClient {
 	fn deserialize_version(payload: &[u8], version: u16) -> Result<Self> {
 		match version {
 			1 => Ok(ToClient::V1(serde_bare::from_slice(payload)?)),
+			2 => Ok(ToClient::V2(serde_bare::from_slice(payload)?)),
 			_ => bail!("invalid version: {version}"),
 		}
 	}
@@ -33,24 +38,71 @@ impl OwnedVersionedData for ToClient {
 	fn serialize_version(self, _version: u16) -> Result<Vec<u8>> {
 		match self {
 			ToClient::V1(data) => serde_bare::to_vec(&data).map_err(Into::into),
+			ToClient::V2(data) => serde_bare::to_vec(&data).map_err(Into::into),
 		}
 	}
+
+	fn deserialize_converters() -> Vec<impl Fn(Self) -> Result<Self>> {
+		vec![Self::v1_to_v2]
+	}
+
+	fn serialize_converters() -> Vec<impl Fn(Self) -> Result<Self>> {
+		vec![Self::v2_to_v1]
+	}
+}
+
+impl ToClient {
+	fn v1_to_v2(self) -> Result<Self> {
+		let ToClient::V1(data) = self else {
+			bail!("unexpected version");
+		};
+
+		let data = match data {
+			v1::ToClient::ToClientKvResponse(res) => {
+				v2::ToClient::ToClientKvResponse(v2::ToClientKvResponse {
+					request_id: res.request_id,
+					data: kv_response_data_v1_to_v2(res.data)?,
+				})
+			}
+			data => transcode(&data)?,
+		};
+
+		Ok(ToClient::V2(data))
+	}
+
+	fn v2_to_v1(self) -> Result<Self> {
+		let ToClient::V2(data) = self else {
+			bail!("unexpected version");
+		};
+
+		let data = match data {
+			v2::ToClient::ToClientKvResponse(res) => {
+				v1::ToClient::ToClientKvResponse(v1::ToClientKvResponse {
+					request_id: res.request_id,
+					data: kv_response_data_v2_to_v1(res.data)?,
+				})
+			}
+			data => transcode(&data)?,
+		};
+
+		Ok(ToClient::V1(data))
+	}
 }
 
 pub enum ToServer {
 	V1(v1::ToServer),
+	V2(v2::ToServer),
 }
 
 impl OwnedVersionedData for ToServer {
-	type Latest = v1::ToServer;
+	type Latest = v2::ToServer;
 
-	fn latest(latest: v1::ToServer) -> Self {
-		ToServer::V1(latest)
+	fn latest(latest: v2::ToServer) -> Self {
+		ToServer::V2(latest)
 	}
 
 	fn into_latest(self) -> Result<Self::Latest> {
-		#[allow(irrefutable_let_patterns)]
-		if let ToServer::V1(data) = self {
+		if let ToServer::V2(data) = self {
 			Ok(data)
 		} else {
 			bail!("version not latest");
@@ -60,6 +112,7 @@ impl OwnedVersionedData for ToServer {
 	fn deserialize_version(payload: &[u8], version: u16) -> Result<Self> {
 		match version {
 			1 => Ok(ToServer::V1(serde_bare::from_slice(payload)?)),
+			2 => Ok(ToServer::V2(serde_bare::from_slice(payload)?)),
 			_ => bail!("invalid version: {version}"),
 		}
 	}
@@ -67,24 +120,73 @@ impl OwnedVersionedData for ToServer {
 	fn serialize_version(self, _version: u16) -> Result<Vec<u8>> {
 		match self {
 			ToServer::V1(data) => serde_bare::to_vec(&data).map_err(Into::into),
+			ToServer::V2(data) => serde_bare::to_vec(&data).map_err(Into::into),
 		}
 	}
+
+	fn deserialize_converters() -> Vec<impl Fn(Self) -> Result<Self>> {
+		vec![Self::v1_to_v2]
+	}
+
+	fn serialize_converters() -> Vec<impl Fn(Self) -> Result<Self>> {
+		vec![Self::v2_to_v1]
+	}
+}
+
+impl ToServer {
+	fn v1_to_v2(self) -> Result<Self> {
+		let ToServer::V1(data) = self else {
+			bail!("unexpected version");
+		};
+
+		let data = match data {
+			v1::ToServer::ToServerKvRequest(req) => {
+				v2::ToServer::ToServerKvRequest(v2::ToServerKvRequest {
+					actor_id: req.actor_id,
+					request_id: req.request_id,
+					data: kv_request_data_v1_to_v2(req.data)?,
+				})
+			}
+			data => transcode(&data)?,
+		};
+
+		Ok(ToServer::V2(data))
+	}
+
+	fn v2_to_v1(self) -> Result<Self> {
+		let ToServer::V2(data) = self else {
+			bail!("unexpected version");
+		};
+
+		let data = match data {
+			v2::ToServer::ToServerKvRequest(req) => {
+				v1::ToServer::ToServerKvRequest(v1::ToServerKvRequest {
+					actor_id: req.actor_id,
+					request_id: req.request_id,
+					data: kv_request_data_v2_to_v1(req.data)?,
+				})
+			}
+			data => transcode(&data)?,
+		};
+
+		Ok(ToServer::V1(data))
+	}
 }
 
 pub enum ToGateway {
 	V1(v1::ToGateway),
+	V2(v2::ToGateway),
 }
 
 impl OwnedVersionedData for ToGateway {
-	type Latest = v1::ToGateway;
+	type Latest = v2::ToGateway;
 
-	fn latest(latest: v1::ToGateway) -> Self {
-		ToGateway::V1(latest)
+	fn latest(latest: v2::ToGateway) -> Self {
+		ToGateway::V2(latest)
 	}
 
 	fn into_latest(self) -> Result<Self::Latest> {
-		#[allow(irrefutable_let_patterns)]
-		if let ToGateway::V1(data) = self {
+		if let ToGateway::V2(data) = self {
 			Ok(data)
 		} else {
 			bail!("version not latest");
@@ -94,6 +196,7 @@ impl OwnedVersionedData for ToGateway {
 	fn deserialize_version(payload: &[u8], version: u16) -> Result<Self> {
 		match version {
 			1 => Ok(ToGateway::V1(serde_bare::from_slice(payload)?)),
+			2 => Ok(ToGateway::V2(serde_bare::from_slice(payload)?)),
 			_ => bail!("invalid version: {version}"),
 		}
 	}
@@ -101,30 +204,56 @@ impl OwnedVersionedData for ToGateway {
 	fn serialize_version(self, _version: u16) -> Result<Vec<u8>> {
 		match self {
 			ToGateway::V1(data) => serde_bare::to_vec(&data).map_err(Into::into),
+			ToGateway::V2(data) => serde_bare::to_vec(&data).map_err(Into::into),
 		}
 	}
+
+	fn deserialize_converters() -> Vec<impl Fn(Self) -> Result<Self>> {
+		vec![Self::v1_to_v2]
+	}
+
+	fn serialize_converters() -> Vec<impl Fn(Self) -> Result<Self>> {
+		vec![Self::v2_to_v1]
+	}
 }
 
 impl ToGateway {
 	pub fn serialize(self) -> Result<Vec<u8>> {
 		<Self as OwnedVersionedData>::serialize(self, PROTOCOL_VERSION)
 	}
+
+	// Tunnel messages did not change in v2
+	fn v1_to_v2(self) -> Result<Self> {
+		let ToGateway::V1(data) = self else {
+			bail!("unexpected version");
+		};
+
+		Ok(ToGateway::V2(transcode(&data)?))
+	}
+
+	fn v2_to_v1(self) -> Result<Self> {
+		let ToGateway::V2(data) = self else {
+			bail!("unexpected version");
+		};
+
+		Ok(ToGateway::V1(transcode(&data)?))
+	}
 }
 
 pub enum ToServerlessServer {
 	V1(v1::ToServerlessServer),
+	V2(v2::ToServerlessServer),
 }
 
 impl OwnedVersionedData for ToServerlessServer {
-	type Latest = v1::ToServerlessServer;
+	type Latest = v2::ToServerlessServer;
 
-	fn latest(latest: v1::ToServerlessServer) -> Self {
-		ToServerlessServer::V1(latest)
+	fn latest(latest: v2::ToServerlessServer) -> Self {
+		ToServerlessServer::V2(latest)
 	}
 
 	fn into_latest(self) -> Result<Self::Latest> {
-		#[allow(irrefutable_let_patterns)]
-		if let ToServerlessServer::V1(data) = self {
+		if let ToServerlessServer::V2(data) = self {
 			Ok(data)
 		} else {
 			bail!("version not latest");
@@ -134,6 +263,7 @@ impl OwnedVersionedData for ToServerlessServer {
 	fn deserialize_version(payload: &[u8], version: u16) -> Result<Self> {
 		match version {
 			1 => Ok(ToServerlessServer::V1(serde_bare::from_slice(payload)?)),
+			2 => Ok(ToServerlessServer::V2(serde_bare::from_slice(payload)?)),
 			_ => bail!("invalid version: {version}"),
 		}
 	}
@@ -141,6 +271,164 @@ impl OwnedVersionedData for ToServerlessServer {
 	fn serialize_version(self, _version: u16) -> Result<Vec<u8>> {
 		match self {
 			ToServerlessServer::V1(data) => serde_bare::to_vec(&data).map_err(Into::into),
+			ToServerlessServer::V2(data) => serde_bare::to_vec(&data).map_err(Into::into),
+		}
+	}
+
+	fn deserialize_converters() -> Vec<impl Fn(Self) -> Result<Self>> {
+		vec![Self::v1_to_v2]
+	}
+
+	fn serialize_converters() -> Vec<impl Fn(Self) -> Result<Self>> {
+		vec![Self::v2_to_v1]
+	}
+}
+
+impl ToServerlessServer {
+	// Serverless messages did not change in v2
+	fn v1_to_v2(self) -> Result<Self> {
+		let ToServerlessServer::V1(data) = self else {
+			bail!("unexpected version");
+		};
+
+		Ok(ToServerlessServer::V2(transcode(&data)?))
+	}
+
+	fn v2_to_v1(self) -> Result<Self> {
+		let ToServerlessServer::V2(data) = self else {
+			bail!("unexpected version");
+		};
+
+		Ok(ToServerlessServer::V1(transcode(&data)?))
+	}
+}
+
+fn kv_request_data_v1_to_v2(data: v1::KvRequestData) -> Result<v2::KvRequestData> {
+	let data = match data {
+		v1::KvRequestData::KvListRequest(req) => {
+			v2::KvRequestData::KvListRequest(v2::KvListRequest {
+				query: transcode(&req.query)?,
+				reverse: req.reverse,
+				limit: req.limit,
+				cursor: None,
+			})
+		}
+		v1::KvRequestData::KvPutRequest(req) => v2::KvRequestData::KvPutRequest(v2::KvPutRequest {
+			keys: req.keys,
+			values: req.values,
+			ttl: None,
+		}),
+		data => transcode(&data)?,
+	};
+
+	Ok(data)
+}
+
+fn kv_request_data_v2_to_v1(data: v2::KvRequestData) -> Result<v1::KvRequestData> {
+	let data = match data {
+		v2::KvRequestData::KvListRequest(req) => {
+			if req.cursor.is_some() {
+				bail!("list cursors are not supported by protocol v1");
+			}
+
+			v1::KvRequestData::KvListRequest(v1::KvListRequest {
+				query: transcode(&req.query)?,
+				reverse: req.reverse,
+				limit: req.limit,
+			})
+		}
+		v2::KvRequestData::KvPutRequest(req) => {
+			if req.ttl.is_some() {
+				bail!("ttl is not supported by protocol v1");
+			}
+
+			v1::KvRequestData::KvPutRequest(v1::KvPutRequest {
+				keys: req.keys,
+				values: req.values,
+			})
+		}
+		v2::KvRequestData::KvTransactRequest(_)
+		| v2::KvRequestData::KvAtomicRequest(_)
+		| v2::KvRequestData::KvCompareAndSwapRequest(_)
+		| v2::KvRequestData::KvStatsRequest
+		| v2::KvRequestData::KvChangesRequest(_) => {
+			bail!("kv request is not supported by protocol v1")
 		}
+		data => transcode(&data)?,
+	};
+
+	Ok(data)
+}
+
+fn kv_response_data_v1_to_v2(data: v1::KvResponseData) -> Result<v2::KvResponseData> {
+	let data = match data {
+		v1::KvResponseData::KvGetResponse(res) => {
+			v2::KvResponseData::KvGetResponse(v2::KvGetResponse {
+				keys: res.keys,
+				values: res.values,
+				metadata: res.metadata.into_iter().map(kv_metadata_v1_to_v2).collect(),
+			})
+		}
+		v1::KvResponseData::KvListResponse(res) => {
+			v2::KvResponseData::KvListResponse(v2::KvListResponse {
+				keys: res.keys,
+				values: res.values,
+				metadata: res.metadata.into_iter().map(kv_metadata_v1_to_v2).collect(),
+				cursor: None,
+			})
+		}
+		data => transcode(&data)?,
+	};
+
+	Ok(data)
+}
+
+fn kv_response_data_v2_to_v1(data: v2::KvResponseData) -> Result<v1::KvResponseData> {
+	let data = match data {
+		v2::KvResponseData::KvGetResponse(res) => {
+			v1::KvResponseData::KvGetResponse(v1::KvGetResponse {
+				keys: res.keys,
+				values: res.values,
+				metadata: res.metadata.into_iter().map(kv_metadata_v2_to_v1).collect(),
+			})
+		}
+		// v1 runners never send a cursor, so the cursor of a truncated list is dropped like before
+		v2::KvResponseData::KvListResponse(res) => {
+			v1::KvResponseData::KvListResponse(v1::KvListResponse {
+				keys: res.keys,
+				values: res.values,
+				metadata: res.metadata.into_iter().map(kv_metadata_v2_to_v1).collect(),
+			})
+		}
+		v2::KvResponseData::KvTransactResponse(_)
+		| v2::KvResponseData::KvAtomicResponse
+		| v2::KvResponseData::KvCompareAndSwapResponse(_)
+		| v2::KvResponseData::KvStatsResponse(_)
+		| v2::KvResponseData::KvChangesResponse(_) => {
+			bail!("kv response is not supported by protocol v1")
+		}
+		data => transcode(&data)?,
+	};
+
+	Ok(data)
+}
+
+fn kv_metadata_v1_to_v2(metadata: v1::KvMetadata) -> v2::KvMetadata {
+	v2::KvMetadata {
+		version: metadata.version,
+		create_ts: metadata.create_ts,
+		expire_ts: None,
 	}
 }
+
+fn kv_metadata_v2_to_v1(metadata: v2::KvMetadata) -> v1::KvMetadata {
+	v1::KvMetadata {
+		version: metadata.version,
+		create_ts: metadata.create_ts,
+	}
+}
+
+/// Converts between versions of a type whose encoding is the same in both versions.
+fn transcode<T: Serialize, U: DeserializeOwned>(value: &T) -> Result<U> {
+	Ok(serde_bare::from_slice(&serde_bare::to_vec(value)?)?)
+}
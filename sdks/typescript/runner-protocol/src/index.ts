@@ -51,21 +51,35 @@ export function writeKvValue(bc: bare.ByteCursor, x: KvValue): void {
     bare.writeData(bc, x)
 }
 
+function read15(bc: bare.ByteCursor): i64 | null {
+    return bare.readBool(bc) ? bare.readI64(bc) : null
+}
+
+function write15(bc: bare.ByteCursor, x: i64 | null): void {
+    bare.writeBool(bc, x != null)
+    if (x != null) {
+        bare.writeI64(bc, x)
+    }
+}
+
 export type KvMetadata = {
     readonly version: ArrayBuffer
     readonly createTs: i64
+    readonly expireTs: i64 | null
 }
 
 export function readKvMetadata(bc: bare.ByteCursor): KvMetadata {
     return {
         version: bare.readData(bc),
         createTs: bare.readI64(bc),
+        expireTs: read15(bc),
     }
 }
 
 export function writeKvMetadata(bc: bare.ByteCursor, x: KvMetadata): void {
     bare.writeData(bc, x.version)
     bare.writeI64(bc, x.createTs)
+    write15(bc, x.expireTs)
 }
 
 /**
@@ -206,10 +220,25 @@ function write2(bc: bare.ByteCursor, x: u64 | null): void {
     }
 }
 
+function read16(bc: bare.ByteCursor): ArrayBuffer | null {
+    return bare.readBool(bc) ? bare.readData(bc) : null
+}
+
+function write16(bc: bare.ByteCursor, x: ArrayBuffer | null): void {
+    bare.writeBool(bc, x != null)
+    if (x != null) {
+        bare.writeData(bc, x)
+    }
+}
+
 export type KvListRequest = {
     readonly query: KvListQuery
     readonly reverse: boolean | null
     readonly limit: u64 | null
+    /**
+     * Opaque cursor from a previous `KvListResponse` with the same query.
+     */
+    readonly cursor: ArrayBuffer | null
 }
 
 export function readKvListRequest(bc: bare.ByteCursor): KvListRequest {
@@ -217,6 +246,7 @@ export function readKvListRequest(bc: bare.ByteCursor): KvListRequest {
         query: readKvListQuery(bc),
         reverse: read1(bc),
         limit: read2(bc),
+        cursor: read16(bc),
     }
 }
 
@@ -224,6 +254,7 @@ export function writeKvListRequest(bc: bare.ByteCursor, x: KvListRequest): void
     writeKvListQuery(bc, x.query)
     write1(bc, x.reverse)
     write2(bc, x.limit)
+    write16(bc, x.cursor)
 }
 
 function read3(bc: bare.ByteCursor): readonly KvValue[] {
@@ -248,18 +279,24 @@ function write3(bc: bare.ByteCursor, x: readonly KvValue[]): void {
 export type KvPutRequest = {
     readonly keys: readonly KvKey[]
     readonly values: readonly KvValue[]
+    /**
+     * Time to live in milliseconds, applied to every key in the request.
+     */
+    readonly ttl: u64 | null
 }
 
 export function readKvPutRequest(bc: bare.ByteCursor): KvPutRequest {
     return {
         keys: read0(bc),
         values: read3(bc),
+        ttl: read2(bc),
     }
 }
 
 export function writeKvPutRequest(bc: bare.ByteCursor, x: KvPutRequest): void {
     write0(bc, x.keys)
     write3(bc, x.values)
+    write2(bc, x.ttl)
 }
 
 export type KvDeleteRequest = {
@@ -278,6 +315,352 @@ export function writeKvDeleteRequest(bc: bare.ByteCursor, x: KvDeleteRequest): v
 
 export type KvDropRequest = null
 
+/**
+ * Transaction types
+ */
+export type KvConditionExists = {
+    readonly key: KvKey
+    readonly exists: boolean
+}
+
+export function readKvConditionExists(bc: bare.ByteCursor): KvConditionExists {
+    return {
+        key: readKvKey(bc),
+        exists: bare.readBool(bc),
+    }
+}
+
+export function writeKvConditionExists(bc: bare.ByteCursor, x: KvConditionExists): void {
+    writeKvKey(bc, x.key)
+    bare.writeBool(bc, x.exists)
+}
+
+export type KvConditionValue = {
+    readonly key: KvKey
+    readonly value: KvValue
+}
+
+export function readKvConditionValue(bc: bare.ByteCursor): KvConditionValue {
+    return {
+        key: readKvKey(bc),
+        value: readKvValue(bc),
+    }
+}
+
+export function writeKvConditionValue(bc: bare.ByteCursor, x: KvConditionValue): void {
+    writeKvKey(bc, x.key)
+    writeKvValue(bc, x.value)
+}
+
+export type KvConditionCreateTs = {
+    readonly key: KvKey
+    readonly createTs: i64
+}
+
+export function readKvConditionCreateTs(bc: bare.ByteCursor): KvConditionCreateTs {
+    return {
+        key: readKvKey(bc),
+        createTs: bare.readI64(bc),
+    }
+}
+
+export function writeKvConditionCreateTs(bc: bare.ByteCursor, x: KvConditionCreateTs): void {
+    writeKvKey(bc, x.key)
+    bare.writeI64(bc, x.createTs)
+}
+
+export type KvCondition =
+    | { readonly tag: "KvConditionExists"; readonly val: KvConditionExists }
+    | { readonly tag: "KvConditionValue"; readonly val: KvConditionValue }
+    | { readonly tag: "KvConditionCreateTs"; readonly val: KvConditionCreateTs }
+
+export function readKvCondition(bc: bare.ByteCursor): KvCondition {
+    const offset = bc.offset
+    const tag = bare.readU8(bc)
+    switch (tag) {
+        case 0:
+            return { tag: "KvConditionExists", val: readKvConditionExists(bc) }
+        case 1:
+            return { tag: "KvConditionValue", val: readKvConditionValue(bc) }
+        case 2:
+            return { tag: "KvConditionCreateTs", val: readKvConditionCreateTs(bc) }
+        default: {
+            bc.offset = offset
+            throw new bare.BareError(offset, "invalid tag")
+        }
+    }
+}
+
+export function writeKvCondition(bc: bare.ByteCursor, x: KvCondition): void {
+    switch (x.tag) {
+        case "KvConditionExists": {
+            bare.writeU8(bc, 0)
+            writeKvConditionExists(bc, x.val)
+            break
+        }
+        case "KvConditionValue": {
+            bare.writeU8(bc, 1)
+            writeKvConditionValue(bc, x.val)
+            break
+        }
+        case "KvConditionCreateTs": {
+            bare.writeU8(bc, 2)
+            writeKvConditionCreateTs(bc, x.val)
+            break
+        }
+    }
+}
+
+function read13(bc: bare.ByteCursor): readonly KvCondition[] {
+    const len = bare.readUintSafe(bc)
+    if (len === 0) {
+        return []
+    }
+    const result = [readKvCondition(bc)]
+    for (let i = 1; i < len; i++) {
+        result[i] = readKvCondition(bc)
+    }
+    return result
+}
+
+function write13(bc: bare.ByteCursor, x: readonly KvCondition[]): void {
+    bare.writeUintSafe(bc, x.length)
+    for (let i = 0; i < x.length; i++) {
+        writeKvCondition(bc, x[i])
+    }
+}
+
+/**
+ * Checks all conditions, then applies deletes followed by puts in a single transaction.
+ */
+export type KvTransactRequest = {
+    readonly conditions: readonly KvCondition[]
+    readonly putKeys: readonly KvKey[]
+    readonly putValues: readonly KvValue[]
+    readonly deleteKeys: readonly KvKey[]
+}
+
+export function readKvTransactRequest(bc: bare.ByteCursor): KvTransactRequest {
+    return {
+        conditions: read13(bc),
+        putKeys: read0(bc),
+        putValues: read3(bc),
+        deleteKeys: read0(bc),
+    }
+}
+
+export function writeKvTransactRequest(bc: bare.ByteCursor, x: KvTransactRequest): void {
+    write13(bc, x.conditions)
+    write0(bc, x.putKeys)
+    write3(bc, x.putValues)
+    write0(bc, x.deleteKeys)
+}
+
+/**
+ * Atomic types
+ *
+ * ADD, MAX and MIN treat values as little-endian integers of up to 8 bytes.
+ */
+export enum KvAtomicOpType {
+    Add = "Add",
+    Max = "Max",
+    Min = "Min",
+    AppendIfFits = "AppendIfFits",
+}
+
+export function readKvAtomicOpType(bc: bare.ByteCursor): KvAtomicOpType {
+    const offset = bc.offset
+    const tag = bare.readU8(bc)
+    switch (tag) {
+        case 0:
+            return KvAtomicOpType.Add
+        case 1:
+            return KvAtomicOpType.Max
+        case 2:
+            return KvAtomicOpType.Min
+        case 3:
+            return KvAtomicOpType.AppendIfFits
+        default: {
+            bc.offset = offset
+            throw new bare.BareError(offset, "invalid tag")
+        }
+    }
+}
+
+export function writeKvAtomicOpType(bc: bare.ByteCursor, x: KvAtomicOpType): void {
+    switch (x) {
+        case KvAtomicOpType.Add: {
+            bare.writeU8(bc, 0)
+            break
+        }
+        case KvAtomicOpType.Max: {
+            bare.writeU8(bc, 1)
+            break
+        }
+        case KvAtomicOpType.Min: {
+            bare.writeU8(bc, 2)
+            break
+        }
+        case KvAtomicOpType.AppendIfFits: {
+            bare.writeU8(bc, 3)
+            break
+        }
+    }
+}
+
+export type KvAtomicRequest = {
+    readonly op: KvAtomicOpType
+    readonly keys: readonly KvKey[]
+    readonly params: readonly KvValue[]
+}
+
+export function readKvAtomicRequest(bc: bare.ByteCursor): KvAtomicRequest {
+    return {
+        op: readKvAtomicOpType(bc),
+        keys: read0(bc),
+        params: read3(bc),
+    }
+}
+
+export function writeKvAtomicRequest(bc: bare.ByteCursor, x: KvAtomicRequest): void {
+    writeKvAtomicOpType(bc, x.op)
+    write0(bc, x.keys)
+    write3(bc, x.params)
+}
+
+function read14(bc: bare.ByteCursor): KvValue | null {
+    return bare.readBool(bc) ? readKvValue(bc) : null
+}
+
+function write14(bc: bare.ByteCursor, x: KvValue | null): void {
+    bare.writeBool(bc, x != null)
+    if (x != null) {
+        writeKvValue(bc, x)
+    }
+}
+
+/**
+ * Sets the key to `value` only if its current value equals `expected`.
+ *
+ * A null `expected` requires the key to not exist. A null `value` deletes the key.
+ */
+export type KvCompareAndSwapRequest = {
+    readonly key: KvKey
+    readonly expected: KvValue | null
+    readonly value: KvValue | null
+}
+
+export function readKvCompareAndSwapRequest(bc: bare.ByteCursor): KvCompareAndSwapRequest {
+    return {
+        key: readKvKey(bc),
+        expected: read14(bc),
+        value: read14(bc),
+    }
+}
+
+export function writeKvCompareAndSwapRequest(bc: bare.ByteCursor, x: KvCompareAndSwapRequest): void {
+    writeKvKey(bc, x.key)
+    write14(bc, x.expected)
+    write14(bc, x.value)
+}
+
+export type KvStatsRequest = null
+
+/**
+ * Change feed types
+ */
+export enum KvChangeOp {
+    Put = "Put",
+    Delete = "Delete",
+    /**
+     * Every key was deleted.
+     */
+    Drop = "Drop",
+}
+
+export function readKvChangeOp(bc: bare.ByteCursor): KvChangeOp {
+    const offset = bc.offset
+    const tag = bare.readU8(bc)
+    switch (tag) {
+        case 0:
+            return KvChangeOp.Put
+        case 1:
+            return KvChangeOp.Delete
+        case 2:
+            return KvChangeOp.Drop
+        default: {
+            bc.offset = offset
+            throw new bare.BareError(offset, "invalid tag")
+        }
+    }
+}
+
+export function writeKvChangeOp(bc: bare.ByteCursor, x: KvChangeOp): void {
+    switch (x) {
+        case KvChangeOp.Put: {
+            bare.writeU8(bc, 0)
+            break
+        }
+        case KvChangeOp.Delete: {
+            bare.writeU8(bc, 1)
+            break
+        }
+        case KvChangeOp.Drop: {
+            bare.writeU8(bc, 2)
+            break
+        }
+    }
+}
+
+export type KvChange = {
+    readonly versionstamp: ArrayBuffer
+    readonly op: KvChangeOp
+    readonly key: KvKey
+}
+
+export function readKvChange(bc: bare.ByteCursor): KvChange {
+    return {
+        versionstamp: bare.readData(bc),
+        op: readKvChangeOp(bc),
+        key: readKvKey(bc),
+    }
+}
+
+export function writeKvChange(bc: bare.ByteCursor, x: KvChange): void {
+    bare.writeData(bc, x.versionstamp)
+    writeKvChangeOp(bc, x.op)
+    writeKvKey(bc, x.key)
+}
+
+/**
+ * Lists changes to keys under `prefix` committed after the `after` cursor, in commit order. A null
+ * `after` starts from the latest change.
+ *
+ * If there are no changes yet, waits up to `waitMs` for one before responding.
+ */
+export type KvChangesRequest = {
+    readonly prefix: KvKey
+    readonly after: ArrayBuffer | null
+    readonly limit: u64 | null
+    readonly waitMs: u64 | null
+}
+
+export function readKvChangesRequest(bc: bare.ByteCursor): KvChangesRequest {
+    return {
+        prefix: readKvKey(bc),
+        after: read16(bc),
+        limit: read2(bc),
+        waitMs: read2(bc),
+    }
+}
+
+export function writeKvChangesRequest(bc: bare.ByteCursor, x: KvChangesRequest): void {
+    writeKvKey(bc, x.prefix)
+    write16(bc, x.after)
+    write2(bc, x.limit)
+    write2(bc, x.waitMs)
+}
+
 /**
  * Response types
  */
@@ -338,6 +721,10 @@ export type KvListResponse = {
     readonly keys: readonly KvKey[]
     readonly values: readonly KvValue[]
     readonly metadata: readonly KvMetadata[]
+    /**
+     * Set if there are more entries to list.
+     */
+    readonly cursor: ArrayBuffer | null
 }
 
 export function readKvListResponse(bc: bare.ByteCursor): KvListResponse {
@@ -345,6 +732,7 @@ export function readKvListResponse(bc: bare.ByteCursor): KvListResponse {
         keys: read0(bc),
         values: read3(bc),
         metadata: read4(bc),
+        cursor: read16(bc),
     }
 }
 
@@ -352,6 +740,7 @@ export function writeKvListResponse(bc: bare.ByteCursor, x: KvListResponse): voi
     write0(bc, x.keys)
     write3(bc, x.values)
     write4(bc, x.metadata)
+    write16(bc, x.cursor)
 }
 
 export type KvPutResponse = null
@@ -360,6 +749,104 @@ export type KvDeleteResponse = null
 
 export type KvDropResponse = null
 
+/**
+ * False if any condition failed, in which case nothing was written.
+ */
+export type KvTransactResponse = {
+    readonly committed: boolean
+}
+
+export function readKvTransactResponse(bc: bare.ByteCursor): KvTransactResponse {
+    return {
+        committed: bare.readBool(bc),
+    }
+}
+
+export function writeKvTransactResponse(bc: bare.ByteCursor, x: KvTransactResponse): void {
+    bare.writeBool(bc, x.committed)
+}
+
+export type KvAtomicResponse = null
+
+/**
+ * `current` is the value of the key after the request was applied.
+ */
+export type KvCompareAndSwapResponse = {
+    readonly swapped: boolean
+    readonly current: KvValue | null
+}
+
+export function readKvCompareAndSwapResponse(bc: bare.ByteCursor): KvCompareAndSwapResponse {
+    return {
+        swapped: bare.readBool(bc),
+        current: read14(bc),
+    }
+}
+
+export function writeKvCompareAndSwapResponse(bc: bare.ByteCursor, x: KvCompareAndSwapResponse): void {
+    bare.writeBool(bc, x.swapped)
+    write14(bc, x.current)
+}
+
+/**
+ * Sizes include keys and values but not metadata.
+ */
+export type KvStatsResponse = {
+    readonly usedBytes: u64
+    readonly quotaBytes: u64
+}
+
+export function readKvStatsResponse(bc: bare.ByteCursor): KvStatsResponse {
+    return {
+        usedBytes: bare.readU64(bc),
+        quotaBytes: bare.readU64(bc),
+    }
+}
+
+export function writeKvStatsResponse(bc: bare.ByteCursor, x: KvStatsResponse): void {
+    bare.writeU64(bc, x.usedBytes)
+    bare.writeU64(bc, x.quotaBytes)
+}
+
+function read17(bc: bare.ByteCursor): readonly KvChange[] {
+    const len = bare.readUintSafe(bc)
+    if (len === 0) {
+        return []
+    }
+    const result = [readKvChange(bc)]
+    for (let i = 1; i < len; i++) {
+        result[i] = readKvChange(bc)
+    }
+    return result
+}
+
+function write17(bc: bare.ByteCursor, x: readonly KvChange[]): void {
+    bare.writeUintSafe(bc, x.length)
+    for (let i = 0; i < x.length; i++) {
+        writeKvChange(bc, x[i])
+    }
+}
+
+export type KvChangesResponse = {
+    readonly changes: readonly KvChange[]
+    /**
+     * Pass as `after` to resume from where this response ended.
+     */
+    readonly cursor: ArrayBuffer
+}
+
+export function readKvChangesResponse(bc: bare.ByteCursor): KvChangesResponse {
+    return {
+        changes: read17(bc),
+        cursor: bare.readData(bc),
+    }
+}
+
+export function writeKvChangesResponse(bc: bare.ByteCursor, x: KvChangesResponse): void {
+    write17(bc, x.changes)
+    bare.writeData(bc, x.cursor)
+}
+
 /**
  * Request/Response unions
  */
@@ -369,6 +856,11 @@ export type KvRequestData =
     | { readonly tag: "KvPutRequest"; readonly val: KvPutRequest }
     | { readonly tag: "KvDeleteRequest"; readonly val: KvDeleteRequest }
     | { readonly tag: "KvDropRequest"; readonly val: KvDropRequest }
+    | { readonly tag: "KvTransactRequest"; readonly val: KvTransactRequest }
+    | { readonly tag: "KvAtomicRequest"; readonly val: KvAtomicRequest }
+    | { readonly tag: "KvCompareAndSwapRequest"; readonly val: KvCompareAndSwapRequest }
+    | { readonly tag: "KvStatsRequest"; readonly val: KvStatsRequest }
+    | { readonly tag: "KvChangesRequest"; readonly val: KvChangesRequest }
 
 export function readKvRequestData(bc: bare.ByteCursor): KvRequestData {
     const offset = bc.offset
@@ -384,6 +876,16 @@ export function readKvRequestData(bc: bare.ByteCursor): KvRequestData {
             return { tag: "KvDeleteRequest", val: readKvDeleteRequest(bc) }
         case 4:
             return { tag: "KvDropRequest", val: null }
+        case 5:
+            return { tag: "KvTransactRequest", val: readKvTransactRequest(bc) }
+        case 6:
+            return { tag: "KvAtomicRequest", val: readKvAtomicRequest(bc) }
+        case 7:
+            return { tag: "KvCompareAndSwapRequest", val: readKvCompareAndSwapRequest(bc) }
+        case 8:
+            return { tag: "KvStatsRequest", val: null }
+        case 9:
+            return { tag: "KvChangesRequest", val: readKvChangesRequest(bc) }
         default: {
             bc.offset = offset
             throw new bare.BareError(offset, "invalid tag")
@@ -417,6 +919,30 @@ export function writeKvRequestData(bc: bare.ByteCursor, x: KvRequestData): void
             bare.writeU8(bc, 4)
             break
         }
+        case "KvTransactRequest": {
+            bare.writeU8(bc, 5)
+            writeKvTransactRequest(bc, x.val)
+            break
+        }
+        case "KvAtomicRequest": {
+            bare.writeU8(bc, 6)
+            writeKvAtomicRequest(bc, x.val)
+            break
+        }
+        case "KvCompareAndSwapRequest": {
+            bare.writeU8(bc, 7)
+            writeKvCompareAndSwapRequest(bc, x.val)
+            break
+        }
+        case "KvStatsRequest": {
+            bare.writeU8(bc, 8)
+            break
+        }
+        case "KvChangesRequest": {
+            bare.writeU8(bc, 9)
+            writeKvChangesRequest(bc, x.val)
+            break
+        }
     }
 }
 
@@ -427,6 +953,11 @@ export type KvResponseData =
     | { readonly tag: "KvPutResponse"; readonly val: KvPutResponse }
     | { readonly tag: "KvDeleteResponse"; readonly val: KvDeleteResponse }
     | { readonly tag: "KvDropResponse"; readonly val: KvDropResponse }
+    | { readonly tag: "KvTransactResponse"; readonly val: KvTransactResponse }
+    | { readonly tag: "KvAtomicResponse"; readonly val: KvAtomicResponse }
+    | { readonly tag: "KvCompareAndSwapResponse"; readonly val: KvCompareAndSwapResponse }
+    | { readonly tag: "KvStatsResponse"; readonly val: KvStatsResponse }
+    | { readonly tag: "KvChangesResponse"; readonly val: KvChangesResponse }
 
 export function readKvResponseData(bc: bare.ByteCursor): KvResponseData {
     const offset = bc.offset
@@ -444,6 +975,16 @@ export function readKvResponseData(bc: bare.ByteCursor): KvResponseData {
             return { tag: "KvDeleteResponse", val: null }
         case 5:
             return { tag: "KvDropResponse", val: null }
+        case 6:
+            return { tag: "KvTransactResponse", val: readKvTransactResponse(bc) }
+        case 7:
+            return { tag: "KvAtomicResponse", val: null }
+        case 8:
+            return { tag: "KvCompareAndSwapResponse", val: readKvCompareAndSwapResponse(bc) }
+        case 9:
+            return { tag: "KvStatsResponse", val: readKvStatsResponse(bc) }
+        case 10:
+            return { tag: "KvChangesResponse", val: readKvChangesResponse(bc) }
         default: {
             bc.offset = offset
             throw new bare.BareError(offset, "invalid tag")
@@ -480,6 +1021,30 @@ export function writeKvResponseData(bc: bare.ByteCursor, x: KvResponseData): voi
             bare.writeU8(bc, 5)
             break
         }
+        case "KvTransactResponse": {
+            bare.writeU8(bc, 6)
+            writeKvTransactResponse(bc, x.val)
+            break
+        }
+        case "KvAtomicResponse": {
+            bare.writeU8(bc, 7)
+            break
+        }
+        case "KvCompareAndSwapResponse": {
+            bare.writeU8(bc, 8)
+            writeKvCompareAndSwapResponse(bc, x.val)
+            break
+        }
+        case "KvStatsResponse": {
+            bare.writeU8(bc, 9)
+            writeKvStatsResponse(bc, x.val)
+            break
+        }
+        case "KvChangesResponse": {
+            bare.writeU8(bc, 10)
+            writeKvChangesResponse(bc, x.val)
+            break
+        }
     }
 }
 
@@ -8,7 +8,7 @@ import type { Logger } from "pino";
 import { setLogger, logger } from "./log.js";
 
 const KV_EXPIRE: number = 30_000;
-const PROTOCOL_VERSION: number = 1;
+const PROTOCOL_VERSION: number = 2;
 
 export interface ActorInstance {
 	actorId: string;
@@ -55,6 +55,58 @@ export interface RunnerConfig {
 export interface KvListOptions {
 	reverse?: boolean;
 	limit?: number;
+	/** Cursor from a previous page with the same query and direction. */
+	cursor?: Uint8Array;
+}
+
+export interface KvListPage {
+	entries: [Uint8Array, Uint8Array][];
+	/** Pass as `cursor` to fetch the next page. Null once the range is exhausted. */
+	cursor: Uint8Array | null;
+}
+
+export interface KvStats {
+	/** Bytes used by keys and values. */
+	usedBytes: bigint;
+	/** Maximum bytes the actor can store. */
+	quotaBytes: bigint;
+}
+
+export interface KvChangesOptions {
+	/** Cursor from a previous call. Starts from the latest change if omitted. */
+	after?: Uint8Array;
+	limit?: number;
+	/** Time to wait for a change if there are none yet, in milliseconds (max 25s). */
+	waitMs?: number;
+}
+
+export interface KvChange {
+	versionstamp: Uint8Array;
+	op: protocol.KvChangeOp;
+	/** Empty for `Drop`, which deletes every key. */
+	key: Uint8Array;
+}
+
+export interface KvChanges {
+	changes: KvChange[];
+	/** Pass as `after` to resume from where this call ended. */
+	cursor: Uint8Array;
+}
+
+export interface KvPutOptions {
+	/** Time to live in milliseconds. */
+	ttl?: number;
+}
+
+export type KvTransactCondition =
+	| { key: Uint8Array; exists: boolean }
+	| { key: Uint8Array; value: Uint8Array }
+	| { key: Uint8Array; createTs: bigint };
+
+export interface KvTransactOptions {
+	conditions?: KvTransactCondition[];
+	put?: [Uint8Array, Uint8Array][];
+	delete?: Uint8Array[];
 }
 
 interface KvRequestEntry {
@@ -982,22 +1034,48 @@ export class Runner {
 		return this.#parseGetResponseSimple(response, keys);
 	}
 
-	async kvListAll(
+	async kvListPage(
 		actorId: string,
+		query: protocol.KvListQuery,
 		options?: KvListOptions,
-	): Promise<[Uint8Array, Uint8Array][]> {
+	): Promise<KvListPage> {
 		const requestData: protocol.KvRequestData = {
 			tag: "KvListRequest",
 			val: {
-				query: { tag: "KvListAllQuery", val: null },
+				query,
 				reverse: options?.reverse || null,
 				limit:
 					options?.limit !== undefined ? BigInt(options.limit) : null,
+				cursor: options?.cursor
+					? (options.cursor.buffer.slice(
+							options.cursor.byteOffset,
+							options.cursor.byteOffset + options.cursor.byteLength,
+						) as ArrayBuffer)
+					: null,
 			},
 		};
 
-		const response = await this.#sendKvRequest(actorId, requestData);
-		return this.#parseListResponseSimple(response);
+		const response: protocol.KvListResponse = await this.#sendKvRequest(
+			actorId,
+			requestData,
+		);
+		return {
+			entries: this.#parseListResponseSimple(response),
+			cursor:
+				response.cursor !== null ? new Uint8Array(response.cursor) : null,
+		};
+	}
+
+	async kvListAll(
+		actorId: string,
+		options?: KvListOptions,
+	): Promise<[Uint8Array, Uint8Array][]> {
+		const page = await this.kvListPage(
+			actorId,
+			{ tag: "KvListAllQuery", val: null },
+			options,
+		);
+		return page.entries;
 	}
 
 	async kvListRange(
@@ -1016,25 +1094,19 @@ export class Runner {
 			end.byteOffset + end.byteLength,
 		) as ArrayBuffer;
 
-		const requestData: protocol.KvRequestData = {
-			tag: "KvListRequest",
-			val: {
-				query: {
-					tag: "KvListRangeQuery",
-					val: {
-						start: startKey,
-						end: endKey,
-						exclusive: exclusive || false,
-					},
+		const page = await this.kvListPage(
+			actorId,
+			{
+				tag: "KvListRangeQuery",
+				val: {
+					start: startKey,
+					end: endKey,
+					exclusive: exclusive || false,
 				},
-				reverse: options?.reverse || null,
-				limit:
-					options?.limit !== undefined ? BigInt(options.limit) : null,
 			},
-		};
-
-		const response = await this.#sendKvRequest(actorId, requestData);
-		return this.#parseListResponseSimple(response);
+			options,
+		);
+		return page.entries;
 	}
 
 	async kvListPrefix(
@@ -1047,26 +1119,21 @@ export class Runner {
 			prefix.byteOffset + prefix.byteLength,
 		) as ArrayBuffer;
 
-		const requestData: protocol.KvRequestData = {
-			tag: "KvListRequest",
-			val: {
-				query: {
-					tag: "KvListPrefixQuery",
-					val: { key: prefixKey },
-				},
-				reverse: options?.reverse || null,
-				limit:
-					options?.limit !== undefined ? BigInt(options.limit) : null,
+		const page = await this.kvListPage(
+			actorId,
+			{
+				tag: "KvListPrefixQuery",
+				val: { key: prefixKey },
 			},
-		};
-
-		const response = await this.#sendKvRequest(actorId, requestData);
-		return this.#parseListResponseSimple(response);
+			options,
+		);
+		return page.entries;
 	}
 
 	async kvPut(
 		actorId: string,
 		entries: [Uint8Array, Uint8Array][],
+		options?: KvPutOptions,
 	): Promise<void> {
 		const keys: protocol.KvKey[] = entries.map(
 			([key, _value]) =>
@@ -1085,7 +1152,11 @@ export class Runner {
 
 		const requestData: protocol.KvRequestData = {
 			tag: "KvPutRequest",
-			val: { keys, values },
+			val: {
+				keys,
+				values,
+				ttl: options?.ttl !== undefined ? BigInt(options.ttl) : null,
+			},
 		};
 
 		await this.#sendKvRequest(actorId, requestData);
@@ -1117,6 +1188,195 @@ export class Runner {
 		await this.#sendKvRequest(actorId, requestData);
 	}
 
+	/**
+	 * Checks all conditions and, if they pass, applies the deletes and puts
+	 * atomically. Returns false if any condition failed, in which case nothing
+	 * was written.
+	 */
+	async kvTransact(
+		actorId: string,
+		options: KvTransactOptions,
+	): Promise<boolean> {
+		const toBuffer = (data: Uint8Array): ArrayBuffer =>
+			data.buffer.slice(
+				data.byteOffset,
+				data.byteOffset + data.byteLength,
+			) as ArrayBuffer;
+
+		const conditions: protocol.KvCondition[] = (
+			options.conditions ?? []
+		).map((condition) => {
+			if ("exists" in condition) {
+				return {
+					tag: "KvConditionExists",
+					val: {
+						key: toBuffer(condition.key),
+						exists: condition.exists,
+					},
+				};
+			} else if ("value" in condition) {
+				return {
+					tag: "KvConditionValue",
+					val: {
+						key: toBuffer(condition.key),
+						value: toBuffer(condition.value),
+					},
+				};
+			} else {
+				return {
+					tag: "KvConditionCreateTs",
+					val: {
+						key: toBuffer(condition.key),
+						createTs: condition.createTs,
+					},
+				};
+			}
+		});
+
+		const requestData: protocol.KvRequestData = {
+			tag: "KvTransactRequest",
+			val: {
+				conditions,
+				putKeys: (options.put ?? []).map(([key, _value]) =>
+					toBuffer(key),
+				),
+				putValues: (options.put ?? []).map(([_key, value]) =>
+					toBuffer(value),
+				),
+				deleteKeys: (options.delete ?? []).map(toBuffer),
+			},
+		};
+
+		const response: protocol.KvTransactResponse =
+			await this.#sendKvRequest(actorId, requestData);
+		return response.committed;
+	}
+
+	/**
+	 * Applies an atomic operation to each entry. Add, Max and Min treat values
+	 * as little-endian integers of up to 8 bytes.
+	 */
+	async kvAtomic(
+		actorId: string,
+		op: protocol.KvAtomicOpType,
+		entries: [Uint8Array, Uint8Array][],
+	): Promise<void> {
+		const keys: protocol.KvKey[] = entries.map(
+			([key, _param]) =>
+				key.buffer.slice(
+					key.byteOffset,
+					key.byteOffset + key.byteLength,
+				) as ArrayBuffer,
+		);
+		const params: protocol.KvValue[] = entries.map(
+			([_key, param]) =>
+				param.buffer.slice(
+					param.byteOffset,
+					param.byteOffset + param.byteLength,
+				) as ArrayBuffer,
+		);
+
+		const requestData: protocol.KvRequestData = {
+			tag: "KvAtomicRequest",
+			val: { op, keys, params },
+		};
+
+		await this.#sendKvRequest(actorId, requestData);
+	}
+
+	/**
+	 * Sets `key` to `value` only if its current value equals `expected`. A null
+	 * `expected` requires the key to not exist and a null `value` deletes it.
+	 */
+	async kvCompareAndSwap(
+		actorId: string,
+		key: Uint8Array,
+		expected: Uint8Array | null,
+		value: Uint8Array | null,
+	): Promise<{ swapped: boolean; current: Uint8Array | null }> {
+		const toBuffer = (data: Uint8Array): ArrayBuffer =>
+			data.buffer.slice(
+				data.byteOffset,
+				data.byteOffset + data.byteLength,
+			) as ArrayBuffer;
+
+		const requestData: protocol.KvRequestData = {
+			tag: "KvCompareAndSwapRequest",
+			val: {
+				key: toBuffer(key),
+				expected: expected !== null ? toBuffer(expected) : null,
+				value: value !== null ? toBuffer(value) : null,
+			},
+		};
+
+		const response: protocol.KvCompareAndSwapResponse =
+			await this.#sendKvRequest(actorId, requestData);
+		return {
+			swapped: response.swapped,
+			current:
+				response.current !== null
+					? new Uint8Array(response.current)
+					: null,
+		};
+	}
+
+	async kvStats(actorId: string): Promise<KvStats> {
+		const requestData: protocol.KvRequestData = {
+			tag: "KvStatsRequest",
+			val: null,
+		};
+
+		const response: protocol.KvStatsResponse = await this.#sendKvRequest(
+			actorId,
+			requestData,
+		);
+		return {
+			usedBytes: response.usedBytes,
+			quotaBytes: response.quotaBytes,
+		};
+	}
+
+	/**
+	 * Lists changes to keys starting with `prefix` in commit order. Pass the
+	 * returned cursor as `after` to resume, including after reconnecting.
+	 */
+	async kvChanges(
+		actorId: string,
+		prefix: Uint8Array,
+		options?: KvChangesOptions,
+	): Promise<KvChanges> {
+		const toBuffer = (data: Uint8Array): ArrayBuffer =>
+			data.buffer.slice(
+				data.byteOffset,
+				data.byteOffset + data.byteLength,
+			) as ArrayBuffer;
+
+		const requestData: protocol.KvRequestData = {
+			tag: "KvChangesRequest",
+			val: {
+				prefix: toBuffer(prefix),
+				after: options?.after ? toBuffer(options.after) : null,
+				limit:
+					options?.limit !== undefined ? BigInt(options.limit) : null,
+				waitMs:
+					options?.waitMs !== undefined ? BigInt(options.waitMs) : null,
+			},
+		};
+
+		const response: protocol.KvChangesResponse = await this.#sendKvRequest(
+			actorId,
+			requestData,
+		);
+		return {
+			changes: response.changes.map((change) => ({
+				versionstamp: new Uint8Array(change.versionstamp),
+				op: change.op,
+				key: new Uint8Array(change.key),
+			})),
+			cursor: new Uint8Array(response.cursor),
+		};
+	}
+
 	// MARK: Alarm Operations
 	setAlarm(actorId: string, alarmTs: number | null, generation?: number) {
 		const actor = this.getActor(actorId, generation);